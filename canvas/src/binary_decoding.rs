@@ -0,0 +1,698 @@
+use crate::draw::*;
+use crate::namespace::*;
+use crate::binary_encoding::*;
+
+use serde::de;
+use serde::de::{Deserialize, DeserializeSeed, Visitor, IntoDeserializer};
+
+use futures::prelude::*;
+use futures::stream;
+use futures::task::{Poll};
+
+use std::fmt;
+use std::convert::{TryFrom};
+
+///
+/// Possible error from the binary decoder
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum BinaryDecoderError {
+    /// The data did not start with the binary drawing header
+    MissingHeader,
+
+    /// The data was encoded with a version of the binary format that this decoder does not support
+    UnsupportedVersion(u8),
+
+    /// An instruction ended before all of its data was read
+    UnexpectedEnd,
+
+    /// An instruction contained data that could not be decoded
+    InvalidData(String),
+
+    /// The decoder previously encountered an error and cannot continue
+    IsInErrorState,
+}
+
+impl fmt::Display for BinaryDecoderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryDecoderError::MissingHeader               => write!(f, "missing binary drawing header"),
+            BinaryDecoderError::UnsupportedVersion(version) => write!(f, "unsupported binary drawing version {}", version),
+            BinaryDecoderError::UnexpectedEnd               => write!(f, "unexpected end of instruction"),
+            BinaryDecoderError::InvalidData(msg)            => write!(f, "invalid data: {}", msg),
+            BinaryDecoderError::IsInErrorState              => write!(f, "decoder is in an error state"),
+        }
+    }
+}
+
+impl std::error::Error for BinaryDecoderError { }
+
+impl de::Error for BinaryDecoderError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        BinaryDecoderError::InvalidData(msg.to_string())
+    }
+}
+
+///
+/// Reads a variable-length quantity from the start of a slice, returning the value and the number of bytes it used (or None if the slice ends before the value)
+///
+#[inline]
+fn decode_varint(bytes: &[u8]) -> Option<Result<(u64, usize), BinaryDecoderError>> {
+    let mut val = 0u64;
+
+    for (idx, byte) in bytes.iter().enumerate() {
+        if idx >= 10 {
+            return Some(Err(BinaryDecoderError::InvalidData("number is too long".to_string())));
+        }
+
+        val |= ((byte & 0x7f) as u64) << (idx * 7);
+
+        if byte & 0x80 == 0 {
+            return Some(Ok((val, idx + 1)));
+        }
+    }
+
+    None
+}
+
+///
+/// Serde deserializer that reads values written by the binary serializer from a slice
+///
+struct BinaryDeserializer<'de> {
+    input: &'de [u8],
+}
+
+impl<'de> BinaryDeserializer<'de> {
+    #[inline]
+    fn take(&mut self, len: usize) -> Result<&'de [u8], BinaryDecoderError> {
+        if self.input.len() < len {
+            return Err(BinaryDecoderError::UnexpectedEnd);
+        }
+
+        let (taken, remaining)  = self.input.split_at(len);
+        self.input              = remaining;
+
+        Ok(taken)
+    }
+
+    #[inline]
+    fn byte(&mut self) -> Result<u8, BinaryDecoderError> {
+        Ok(self.take(1)?[0])
+    }
+
+    #[inline]
+    fn varint(&mut self) -> Result<u64, BinaryDecoderError> {
+        let (val, len) = decode_varint(self.input).ok_or(BinaryDecoderError::UnexpectedEnd)??;
+        self.input = &self.input[len..];
+
+        Ok(val)
+    }
+
+    #[inline]
+    fn signed(&mut self) -> Result<i64, BinaryDecoderError> {
+        let val = self.varint()?;
+        Ok(((val >> 1) as i64) ^ -((val & 1) as i64))
+    }
+
+    #[inline]
+    fn length(&mut self) -> Result<usize, BinaryDecoderError> {
+        let len = self.varint()? as usize;
+
+        // Every element takes up at least one byte except for unit values, so this is a reasonable sanity check for corrupt lengths
+        if len > self.input.len() {
+            Err(BinaryDecoderError::UnexpectedEnd)
+        } else {
+            Ok(len)
+        }
+    }
+
+    #[inline]
+    fn bytes(&mut self) -> Result<&'de [u8], BinaryDecoderError> {
+        let len = self.length()?;
+        self.take(len)
+    }
+
+    #[inline]
+    fn str(&mut self) -> Result<&'de str, BinaryDecoderError> {
+        let bytes = self.bytes()?;
+        std::str::from_utf8(bytes).map_err(|_| BinaryDecoderError::InvalidData("invalid UTF-8 string".to_string()))
+    }
+}
+
+///
+/// Provides the elements of a sequence, tuple or structure to a visitor
+///
+struct BinarySeqAccess<'a, 'de> {
+    deserializer:   &'a mut BinaryDeserializer<'de>,
+    remaining:      usize,
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for BinarySeqAccess<'a, 'de> {
+    type Error = BinaryDecoderError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, BinaryDecoderError> {
+        if self.remaining == 0 {
+            Ok(None)
+        } else {
+            self.remaining -= 1;
+            seed.deserialize(&mut *self.deserializer).map(Some)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'a, 'de> de::MapAccess<'de> for BinarySeqAccess<'a, 'de> {
+    type Error = BinaryDecoderError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, BinaryDecoderError> {
+        if self.remaining == 0 {
+            Ok(None)
+        } else {
+            self.remaining -= 1;
+            seed.deserialize(&mut *self.deserializer).map(Some)
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, BinaryDecoderError> {
+        seed.deserialize(&mut *self.deserializer)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut BinaryDeserializer<'de> {
+    type Error      = BinaryDecoderError;
+    type Variant    = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), BinaryDecoderError> {
+        let variant_index   = self.varint()? as u32;
+        let value           = seed.deserialize(variant_index.into_deserializer())?;
+
+        Ok((value, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut BinaryDeserializer<'de> {
+    type Error = BinaryDecoderError;
+
+    fn unit_variant(self) -> Result<(), BinaryDecoderError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, BinaryDecoderError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        visitor.visit_seq(BinarySeqAccess { deserializer: self, remaining: len })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        visitor.visit_seq(BinarySeqAccess { deserializer: self, remaining: fields.len() })
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut BinaryDeserializer<'de> {
+    type Error = BinaryDecoderError;
+
+    fn is_human_readable(&self) -> bool { false }
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        Err(BinaryDecoderError::InvalidData("the binary drawing format is not self-describing".to_string()))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        Err(BinaryDecoderError::InvalidData("the binary drawing format is not self-describing".to_string()))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        match self.byte()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            _ => Err(BinaryDecoderError::InvalidData("invalid boolean".to_string()))
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError>     { visitor.visit_i8(self.byte()? as i8) }
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError>    { visitor.visit_i64(self.signed()?) }
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError>    { visitor.visit_i64(self.signed()?) }
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError>    { visitor.visit_i64(self.signed()?) }
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError>     { visitor.visit_u8(self.byte()?) }
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError>    { visitor.visit_u64(self.varint()?) }
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError>    { visitor.visit_u64(self.varint()?) }
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError>    { visitor.visit_u64(self.varint()?) }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        let bytes = self.take(4)?;
+        visitor.visit_f32(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        let bytes = self.take(8)?;
+        visitor.visit_f64(f64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]]))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        let chr = self.varint()?;
+        let chr = u32::try_from(chr).ok().and_then(char::from_u32).ok_or_else(|| BinaryDecoderError::InvalidData("invalid character".to_string()))?;
+
+        visitor.visit_char(chr)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError>        { visitor.visit_borrowed_str(self.str()?) }
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError>     { visitor.visit_borrowed_str(self.str()?) }
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError>      { visitor.visit_borrowed_bytes(self.bytes()?) }
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError>   { visitor.visit_borrowed_bytes(self.bytes()?) }
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError> { visitor.visit_u64(self.varint()?) }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        match self.byte()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            _ => Err(BinaryDecoderError::InvalidData("invalid option".to_string()))
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        let len = self.length()?;
+        visitor.visit_seq(BinarySeqAccess { deserializer: self, remaining: len })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        visitor.visit_seq(BinarySeqAccess { deserializer: self, remaining: len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        visitor.visit_seq(BinarySeqAccess { deserializer: self, remaining: len })
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        let len = self.length()?;
+        visitor.visit_map(BinarySeqAccess { deserializer: self, remaining: len })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        visitor.visit_seq(BinarySeqAccess { deserializer: self, remaining: fields.len() })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, BinaryDecoderError> {
+        visitor.visit_enum(self)
+    }
+}
+
+///
+/// Decodes a single drawing instruction (as written by `encode_draw_binary()`, without its length)
+///
+fn decode_draw_binary(data: &[u8]) -> Result<Draw, BinaryDecoderError> {
+    let mut deserializer    = BinaryDeserializer { input: data };
+    let draw                = Draw::deserialize(&mut deserializer)?;
+
+    if !deserializer.input.is_empty() {
+        return Err(BinaryDecoderError::InvalidData("unexpected data after instruction".to_string()));
+    }
+
+    match draw {
+        // The local ID of a namespace is only meaningful in the process that encoded it, so look it up again from the global ID
+        Draw::Namespace(namespace_id)   => Ok(Draw::Namespace(NamespaceId::with_id(namespace_id.global_id()))),
+        other                           => Ok(other),
+    }
+}
+
+///
+/// Represents a (stateful) decoder for drawings encoded by `encode_drawing()`
+///
+/// Bytes can be passed to the decoder as they arrive, and it will return the drawing instructions as soon as they are complete.
+///
+pub struct BinaryCanvasDecoder {
+    /// Bytes that have been received but not decoded yet
+    buffer: Vec<u8>,
+
+    /// True once the header has been read
+    read_header: bool,
+
+    /// True if the decoder has encountered an error
+    failed: bool,
+}
+
+impl BinaryCanvasDecoder {
+    ///
+    /// Creates a new binary canvas decoder
+    ///
+    pub fn new() -> BinaryCanvasDecoder {
+        BinaryCanvasDecoder {
+            buffer:         vec![],
+            read_header:    false,
+            failed:         false,
+        }
+    }
+
+    ///
+    /// Decodes some more bytes, returning any drawing instructions that have been completed
+    ///
+    pub fn decode(&mut self, bytes: &[u8]) -> Result<Vec<Draw>, BinaryDecoderError> {
+        if self.failed {
+            return Err(BinaryDecoderError::IsInErrorState);
+        }
+
+        self.buffer.extend_from_slice(bytes);
+
+        let result = self.decode_buffer();
+        if result.is_err() {
+            self.failed = true;
+            self.buffer = vec![];
+        }
+
+        result
+    }
+
+    ///
+    /// Decodes as many instructions as possible from the buffer
+    ///
+    fn decode_buffer(&mut self) -> Result<Vec<Draw>, BinaryDecoderError> {
+        let mut pos     = 0;
+        let mut result  = vec![];
+
+        // Check the header
+        if !self.read_header {
+            let header_len = BINARY_DRAWING_MAGIC.len() + 1;
+            if self.buffer.len() < header_len {
+                // Wait for more bytes (but fail early if what we have can't be a header)
+                let available = self.buffer.len();
+                if self.buffer[..] != BINARY_DRAWING_MAGIC[..available.min(BINARY_DRAWING_MAGIC.len())] {
+                    return Err(BinaryDecoderError::MissingHeader);
+                }
+
+                return Ok(result);
+            }
+
+            if self.buffer[0..BINARY_DRAWING_MAGIC.len()] != BINARY_DRAWING_MAGIC {
+                return Err(BinaryDecoderError::MissingHeader);
+            }
+
+            let version = self.buffer[BINARY_DRAWING_MAGIC.len()];
+            if version != BINARY_DRAWING_VERSION {
+                return Err(BinaryDecoderError::UnsupportedVersion(version));
+            }
+
+            self.read_header    = true;
+            pos                 = header_len;
+        }
+
+        // Decode any instructions that have been completely received
+        while let Some(length) = decode_varint(&self.buffer[pos..]) {
+            let (length, length_len)    = length?;
+            let start                   = pos + length_len;
+            let end                     = start + length as usize;
+
+            if end > self.buffer.len() {
+                break;
+            }
+
+            result.push(decode_draw_binary(&self.buffer[start..end])?);
+            pos = end;
+        }
+
+        // Remove the bytes that have been decoded from the buffer
+        self.buffer.drain(0..pos);
+
+        Ok(result)
+    }
+}
+
+///
+/// Decodes a binary canvas drawing represented as an iterator of byte chunks. If there's an error in the data, it will be the last
+/// item decoded.
+///
+pub fn decode_binary_drawing<In: IntoIterator<Item=Vec<u8>>>(source: In) -> impl Iterator<Item=Result<Draw, BinaryDecoderError>> {
+    let mut decoder     = BinaryCanvasDecoder::new();
+    let mut seen_error  = false;
+
+    source.into_iter()
+        .flat_map(move |bytes| {
+            if seen_error {
+                return vec![];
+            }
+
+            match decoder.decode(&bytes) {
+                Ok(draw)    => draw.into_iter().map(Ok).collect(),
+                Err(err)    => {
+                    seen_error = true;
+                    vec![Err(err)]
+                }
+            }
+        })
+}
+
+///
+/// Error from either a binary decoder or the stream that's feeding it
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum BinaryStreamDecoderError<E> {
+    /// Error from the decoder
+    Decoder(BinaryDecoderError),
+
+    /// Error from the stream
+    Stream(E)
+}
+
+///
+/// Decodes a binary canvas drawing represented as a stream of byte chunks (for example, as read from a socket)
+///
+pub fn decode_binary_drawing_stream<In: Unpin+Stream<Item=Result<Vec<u8>, E>>, E>(source: In) -> impl Unpin+Stream<Item=Result<Draw, BinaryStreamDecoderError<E>>> {
+    let mut source      = source;
+    let mut decoder     = BinaryCanvasDecoder::new();
+    let mut seen_error  = false;
+    let mut pending     = vec![].into_iter();
+
+    stream::poll_fn(move |context| {
+        loop {
+            // Return any instructions that were decoded from the last set of bytes
+            if let Some(draw) = pending.next() {
+                return Poll::Ready(Some(Ok(draw)));
+            }
+
+            if seen_error {
+                // Only allow one error from the decoder (it remains in an error state after this)
+                return Poll::Ready(None);
+            }
+
+            match source.poll_next_unpin(context) {
+                Poll::Ready(None)               => { return Poll::Ready(None); },
+                Poll::Pending                   => { return Poll::Pending; },
+                Poll::Ready(Some(Ok(bytes)))    => {
+                    match decoder.decode(&bytes) {
+                        Ok(draw)    => { pending = draw.into_iter(); },
+                        Err(err)    => { seen_error = true; return Poll::Ready(Some(Err(BinaryStreamDecoderError::Decoder(err)))); }
+                    }
+                },
+
+                Poll::Ready(Some(Err(err)))     => { return Poll::Ready(Some(Err(BinaryStreamDecoderError::Stream(err)))); }
+            }
+        }
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::font::*;
+    use crate::path::*;
+    use crate::color::*;
+    use crate::sprite::*;
+    use crate::texture::*;
+    use crate::encoding::*;
+    use crate::gradient::*;
+    use crate::font_face::*;
+    use crate::transform2d::*;
+
+    use futures::executor;
+
+    use std::sync::*;
+
+    ///
+    /// Checks that a set of instructions survive a round trip through the binary encoding, whether the bytes arrive all at once
+    /// or one at a time
+    ///
+    fn check_round_trip(instructions: Vec<Draw>) {
+        let encoded = encode_drawing(&instructions);
+
+        let mut decoder = BinaryCanvasDecoder::new();
+        let decoded     = decoder.decode(&encoded).unwrap();
+        assert!(decoded == instructions, "{:?} != {:?}", decoded, instructions);
+
+        let mut decoder = BinaryCanvasDecoder::new();
+        let mut decoded = vec![];
+        for byte in encoded.iter() {
+            decoded.extend(decoder.decode(&[*byte]).unwrap());
+        }
+        assert!(decoded == instructions, "{:?} != {:?}", decoded, instructions);
+    }
+
+    #[test]
+    fn round_trip_every_draw_variant() {
+        let lato = CanvasFontFace::from_slice(include_bytes!("../test_data/Lato-Regular.ttf"));
+
+        check_round_trip(vec![
+            Draw::StartFrame,
+            Draw::ShowFrame,
+            Draw::ResetFrame,
+            Draw::Path(PathOp::NewPath),
+            Draw::Path(PathOp::Move(10.0, 15.0)),
+            Draw::Path(PathOp::Line(20.0, 42.0)),
+            Draw::Path(PathOp::BezierCurve(((1.0, 2.0), (3.0, 4.0)), (5.0, 6.0))),
+            Draw::Path(PathOp::ClosePath),
+            Draw::Fill,
+            Draw::Stroke,
+            Draw::LineWidth(23.0),
+            Draw::LineWidthPixels(43.0),
+            Draw::LineJoin(LineJoin::Bevel),
+            Draw::LineCap(LineCap::Round),
+            Draw::NewDashPattern,
+            Draw::DashLength(56.0),
+            Draw::DashOffset(13.0),
+            Draw::FillColor(Color::Rgba(0.2, 0.3, 0.4, 0.5)),
+            Draw::FillTexture(TextureId(23), (42.0, 43.0), (44.0, 45.0)),
+            Draw::FillGradient(GradientId(24), (42.0, 43.0), (44.0, 45.0)),
+            Draw::FillTransform(Transform2D::identity()),
+            Draw::StrokeColor(Color::Rgba(0.1, 0.2, 0.3, 0.4)),
+            Draw::WindingRule(WindingRule::NonZero),
+            Draw::BlendMode(BlendMode::Lighten),
+            Draw::IdentityTransform,
+            Draw::CanvasHeight(81.0),
+            Draw::CenterRegion((6.0, 7.0), (8.0, 9.0)),
+            Draw::MultiplyTransform(Transform2D([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]])),
+            Draw::Unclip,
+            Draw::Clip,
+            Draw::Store,
+            Draw::Restore,
+            Draw::FreeStoredBuffer,
+            Draw::PushState,
+            Draw::PopState,
+            Draw::ClearCanvas(Color::Rgba(0.1, 0.2, 0.3, 0.4)),
+            Draw::Layer(LayerId(21)),
+            Draw::LayerBlend(LayerId(22), BlendMode::Multiply),
+            Draw::LayerAlpha(LayerId(23), 0.5),
+            Draw::ClearLayer,
+            Draw::ClearAllLayers,
+            Draw::SwapLayers(LayerId(1), LayerId(2)),
+            Draw::Sprite(SpriteId(1000)),
+            Draw::MoveSpriteFrom(SpriteId(48)),
+            Draw::ClearSprite,
+            Draw::SpriteTransform(SpriteTransform::Identity),
+            Draw::SpriteTransform(SpriteTransform::Translate(4.0, 5.0)),
+            Draw::SpriteTransform(SpriteTransform::Scale(6.0, 7.0)),
+            Draw::SpriteTransform(SpriteTransform::Rotate(45.0)),
+            Draw::SpriteTransform(SpriteTransform::Transform2D(Transform2D::scale(3.0, 4.0))),
+            Draw::DrawSprite(SpriteId(1300)),
+            Draw::DrawSpriteWithFilters(SpriteId(10), vec![]),
+            Draw::DrawSpriteWithFilters(SpriteId(10), vec![TextureFilter::GaussianBlur(4.0), TextureFilter::AlphaBlend(0.5)]),
+
+            Draw::Texture(TextureId(42), TextureOp::Create(TextureSize(1024, 768), TextureFormat::Rgba)),
+            Draw::Texture(TextureId(42), TextureOp::Create(TextureSize(1024, 768), TextureFormat::RgbaPremultiplied)),
+            Draw::Texture(TextureId(43), TextureOp::Free),
+            Draw::Texture(TextureId(44), TextureOp::SetBytes(TexturePosition(2, 3), TextureSize(4, 5), Arc::new(vec![1,2,3,4,5]))),
+            Draw::Texture(TextureId(44), TextureOp::SetFromEncodedImage(Arc::new(vec![6,7,8,9]))),
+            Draw::Texture(TextureId(44), TextureOp::SetFromSprite(SpriteId(42), SpriteBounds(SpritePosition(20.0, 30.0), SpriteSize(40.0, 50.0)))),
+            Draw::Texture(TextureId(44), TextureOp::CreateDynamicSprite(SpriteId(42), SpriteBounds(SpritePosition(20.0, 30.0), SpriteSize(40.0, 50.0)), CanvasSize(60.0, 70.0))),
+            Draw::Texture(TextureId(45), TextureOp::FillTransparency(0.5)),
+            Draw::Texture(TextureId(45), TextureOp::SetWrapMode(TextureWrapMode::MirrorRepeat)),
+            Draw::Texture(TextureId(45), TextureOp::SetMipMaps(false)),
+            Draw::Texture(TextureId(46), TextureOp::Copy(TextureId(47))),
+            Draw::Texture(TextureId(47), TextureOp::Filter(TextureFilter::Mask(TextureId(48)))),
+            Draw::Texture(TextureId(47), TextureOp::Filter(TextureFilter::DisplacementMap(TextureId(48), 1.0, 2.0))),
+
+            Draw::Font(FontId(1), FontOp::UseFontDefinition(lato)),
+            Draw::Font(FontId(1), FontOp::FontSize(12.0)),
+            Draw::Font(FontId(1), FontOp::LayoutText("Layout".to_string())),
+            Draw::Font(FontId(1), FontOp::DrawGlyphs(vec![GlyphPosition { id: GlyphId(20), location: (2.0, 3.0), em_size: 12.0 }])),
+            Draw::BeginLineLayout(1.0, 2.0, TextAlignment::Center),
+            Draw::DrawLaidOutText,
+            Draw::DrawText(FontId(42), "Hello, world".to_string(), 100.0, 200.0),
+
+            Draw::Gradient(GradientId(42), GradientOp::Create(Color::Rgba(0.1, 0.2, 0.3, 0.4))),
+            Draw::Gradient(GradientId(44), GradientOp::AddStop(0.5, Color::Rgba(0.1, 0.2, 0.3, 0.4))),
+
+            Draw::Namespace(NamespaceId::default()),
+            Draw::Namespace(NamespaceId::new()),
+        ]);
+    }
+
+    #[test]
+    fn binary_is_smaller_than_text() {
+        let mut drawing = vec![Draw::Path(PathOp::NewPath)];
+        for idx in 0..100 {
+            drawing.push(Draw::Path(PathOp::Line(idx as f32, (idx * 2) as f32)));
+        }
+
+        let mut text = String::new();
+        drawing.encode_canvas(&mut text);
+
+        assert!(encode_drawing(&drawing).len() < text.len());
+    }
+
+    #[test]
+    fn reject_missing_header() {
+        let mut decoder = BinaryCanvasDecoder::new();
+
+        assert!(decoder.decode(b"NOPE") == Err(BinaryDecoderError::MissingHeader));
+        assert!(decoder.decode(&encode_drawing(&[Draw::Fill])) == Err(BinaryDecoderError::IsInErrorState));
+    }
+
+    #[test]
+    fn reject_unsupported_version() {
+        let mut encoded = encode_drawing(&[Draw::Fill]);
+        encoded[BINARY_DRAWING_MAGIC.len()] = BINARY_DRAWING_VERSION + 1;
+
+        let mut decoder = BinaryCanvasDecoder::new();
+        assert!(decoder.decode(&encoded) == Err(BinaryDecoderError::UnsupportedVersion(BINARY_DRAWING_VERSION + 1)));
+    }
+
+    #[test]
+    fn decode_iter_in_chunks() {
+        let drawing = vec![Draw::ClearCanvas(Color::Rgba(1.0, 1.0, 1.0, 1.0)), Draw::Path(PathOp::NewPath), Draw::Path(PathOp::Move(1.0, 2.0)), Draw::Fill];
+        let encoded = encode_drawing(&drawing);
+        let chunks  = encoded.chunks(3).map(|chunk| chunk.to_vec()).collect::<Vec<_>>();
+
+        let decoded = decode_binary_drawing(chunks).collect::<Vec<_>>();
+        let drawing = drawing.into_iter().map(|draw| Ok(draw)).collect::<Vec<_>>();
+
+        assert!(decoded == drawing);
+    }
+
+    #[test]
+    fn decode_stream() {
+        let drawing = vec![Draw::ClearCanvas(Color::Rgba(1.0, 1.0, 1.0, 1.0)), Draw::Path(PathOp::NewPath), Draw::Path(PathOp::Move(1.0, 2.0)), Draw::Fill];
+        let encoded = encode_drawing(&drawing);
+
+        let byte_stream = stream::iter(encoded.into_iter().map(|byte| -> Result<_, ()> { Ok(vec![byte]) }));
+        let mut decoder = decode_binary_drawing_stream(byte_stream);
+
+        executor::block_on(async {
+            let mut decoded = vec![];
+            while let Some(next) = decoder.next().await {
+                decoded.push(next);
+            }
+
+            let drawing = drawing.into_iter().map(|draw| Ok(draw)).collect::<Vec<_>>();
+            assert!(decoded == drawing);
+        });
+    }
+}
@@ -0,0 +1,379 @@
+use crate::draw::*;
+
+use serde::ser;
+use serde::ser::{Serialize};
+
+use std::fmt;
+
+///
+/// The bytes that begin a binary-encoded drawing
+///
+pub const BINARY_DRAWING_MAGIC: [u8; 4] = *b"FLOD";
+
+///
+/// The version of the binary encoding generated by `encode_drawing()`
+///
+/// Enum variants are written by their index, so new variants of `Draw` and the types it contains must only ever be added
+/// at the end of their enum: inserting, removing or reordering a variant changes the meaning of existing encoded drawings,
+/// and needs this version to be increased.
+///
+pub const BINARY_DRAWING_VERSION: u8 = 1;
+
+///
+/// Error generated when a value can't be serialized in the binary format
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct BinaryEncodingError(String);
+
+impl fmt::Display for BinaryEncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for BinaryEncodingError { }
+
+impl ser::Error for BinaryEncodingError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        BinaryEncodingError(msg.to_string())
+    }
+}
+
+///
+/// Appends a u64 value to a buffer as a variable-length quantity (7 bits per byte, high bit set if there are more bytes to follow)
+///
+#[inline]
+pub (crate) fn encode_varint(val: u64, append_to: &mut Vec<u8>) {
+    let mut val = val;
+
+    loop {
+        let seven_bits  = (val & 0x7f) as u8;
+        val             >>= 7;
+
+        if val != 0 {
+            append_to.push(seven_bits | 0x80);
+        } else {
+            append_to.push(seven_bits);
+            break;
+        }
+    }
+}
+
+///
+/// Serde serializer that writes values in a compact binary format
+///
+/// The format isn't self-describing: numbers are written as variable-length quantities, floating point values as little-endian
+/// bytes, and enum variants by index. Structures and tuples are written as their fields in order, and sequences, maps and strings
+/// are preceded by their length.
+///
+struct BinarySerializer<'a> {
+    output: &'a mut Vec<u8>,
+}
+
+///
+/// Serializes a sequence or map whose length is only known once all the elements have been written
+///
+struct BinarySeqSerializer<'a> {
+    /// Where the sequence should be written once it's complete
+    output: &'a mut Vec<u8>,
+
+    /// The elements serialized so far
+    elements: Vec<u8>,
+
+    /// The number of elements that have been serialized
+    count: u64,
+}
+
+impl<'a> BinarySerializer<'a> {
+    ///
+    /// Creates a serializer for a nested value that writes to the same output
+    ///
+    #[inline]
+    fn nested(&mut self) -> BinarySerializer<'_> {
+        BinarySerializer { output: self.output }
+    }
+}
+
+impl<'a> BinarySeqSerializer<'a> {
+    ///
+    /// Adds a new element to this sequence
+    ///
+    #[inline]
+    fn element<T: ?Sized+Serialize>(&mut self, value: &T) -> Result<(), BinaryEncodingError> {
+        value.serialize(BinarySerializer { output: &mut self.elements })
+    }
+
+    ///
+    /// Writes the length followed by the elements to the output
+    ///
+    #[inline]
+    fn finish(self) -> Result<(), BinaryEncodingError> {
+        encode_varint(self.count, self.output);
+        self.output.extend(self.elements);
+
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for BinarySerializer<'a> {
+    type Ok                     = ();
+    type Error                  = BinaryEncodingError;
+    type SerializeSeq           = BinarySeqSerializer<'a>;
+    type SerializeTuple         = Self;
+    type SerializeTupleStruct   = Self;
+    type SerializeTupleVariant  = Self;
+    type SerializeMap           = BinarySeqSerializer<'a>;
+    type SerializeStruct        = Self;
+    type SerializeStructVariant = Self;
+
+    fn is_human_readable(&self) -> bool { false }
+
+    fn serialize_bool(self, v: bool) -> Result<(), BinaryEncodingError>     { self.output.push(if v { 1 } else { 0 }); Ok(()) }
+    fn serialize_i8(self, v: i8) -> Result<(), BinaryEncodingError>         { self.output.push(v as u8); Ok(()) }
+    fn serialize_i16(self, v: i16) -> Result<(), BinaryEncodingError>       { self.serialize_i64(v as i64) }
+    fn serialize_i32(self, v: i32) -> Result<(), BinaryEncodingError>       { self.serialize_i64(v as i64) }
+    fn serialize_i64(self, v: i64) -> Result<(), BinaryEncodingError>       { encode_varint(((v << 1) ^ (v >> 63)) as u64, self.output); Ok(()) }
+    fn serialize_u8(self, v: u8) -> Result<(), BinaryEncodingError>         { self.output.push(v); Ok(()) }
+    fn serialize_u16(self, v: u16) -> Result<(), BinaryEncodingError>       { encode_varint(v as u64, self.output); Ok(()) }
+    fn serialize_u32(self, v: u32) -> Result<(), BinaryEncodingError>       { encode_varint(v as u64, self.output); Ok(()) }
+    fn serialize_u64(self, v: u64) -> Result<(), BinaryEncodingError>       { encode_varint(v, self.output); Ok(()) }
+    fn serialize_f32(self, v: f32) -> Result<(), BinaryEncodingError>       { self.output.extend(v.to_le_bytes()); Ok(()) }
+    fn serialize_f64(self, v: f64) -> Result<(), BinaryEncodingError>       { self.output.extend(v.to_le_bytes()); Ok(()) }
+    fn serialize_char(self, v: char) -> Result<(), BinaryEncodingError>     { encode_varint(v as u64, self.output); Ok(()) }
+    fn serialize_str(self, v: &str) -> Result<(), BinaryEncodingError>      { self.serialize_bytes(v.as_bytes()) }
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), BinaryEncodingError>   { encode_varint(v.len() as u64, self.output); self.output.extend_from_slice(v); Ok(()) }
+    fn serialize_none(self) -> Result<(), BinaryEncodingError>              { self.output.push(0); Ok(()) }
+    fn serialize_unit(self) -> Result<(), BinaryEncodingError>              { Ok(()) }
+
+    fn serialize_some<T: ?Sized+Serialize>(self, value: &T) -> Result<(), BinaryEncodingError> {
+        self.output.push(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), BinaryEncodingError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str) -> Result<(), BinaryEncodingError> {
+        encode_varint(variant_index as u64, self.output);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized+Serialize>(self, _name: &'static str, value: &T) -> Result<(), BinaryEncodingError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized+Serialize>(self, _name: &'static str, variant_index: u32, _variant: &'static str, value: &T) -> Result<(), BinaryEncodingError> {
+        encode_varint(variant_index as u64, self.output);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<BinarySeqSerializer<'a>, BinaryEncodingError> {
+        Ok(BinarySeqSerializer { output: self.output, elements: vec![], count: 0 })
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, BinaryEncodingError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, BinaryEncodingError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self, BinaryEncodingError> {
+        encode_varint(variant_index as u64, self.output);
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<BinarySeqSerializer<'a>, BinaryEncodingError> {
+        Ok(BinarySeqSerializer { output: self.output, elements: vec![], count: 0 })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, BinaryEncodingError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self, BinaryEncodingError> {
+        encode_varint(variant_index as u64, self.output);
+        Ok(self)
+    }
+}
+
+impl<'a> ser::SerializeSeq for BinarySeqSerializer<'a> {
+    type Ok     = ();
+    type Error  = BinaryEncodingError;
+
+    fn serialize_element<T: ?Sized+Serialize>(&mut self, value: &T) -> Result<(), BinaryEncodingError> {
+        self.count += 1;
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), BinaryEncodingError> { self.finish() }
+}
+
+impl<'a> ser::SerializeMap for BinarySeqSerializer<'a> {
+    type Ok     = ();
+    type Error  = BinaryEncodingError;
+
+    fn serialize_key<T: ?Sized+Serialize>(&mut self, key: &T) -> Result<(), BinaryEncodingError> {
+        self.count += 1;
+        self.element(key)
+    }
+
+    fn serialize_value<T: ?Sized+Serialize>(&mut self, value: &T) -> Result<(), BinaryEncodingError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), BinaryEncodingError> { self.finish() }
+}
+
+impl<'a> ser::SerializeTuple for BinarySerializer<'a> {
+    type Ok     = ();
+    type Error  = BinaryEncodingError;
+
+    fn serialize_element<T: ?Sized+Serialize>(&mut self, value: &T) -> Result<(), BinaryEncodingError> { value.serialize(self.nested()) }
+    fn end(self) -> Result<(), BinaryEncodingError> { Ok(()) }
+}
+
+impl<'a> ser::SerializeTupleStruct for BinarySerializer<'a> {
+    type Ok     = ();
+    type Error  = BinaryEncodingError;
+
+    fn serialize_field<T: ?Sized+Serialize>(&mut self, value: &T) -> Result<(), BinaryEncodingError> { value.serialize(self.nested()) }
+    fn end(self) -> Result<(), BinaryEncodingError> { Ok(()) }
+}
+
+impl<'a> ser::SerializeTupleVariant for BinarySerializer<'a> {
+    type Ok     = ();
+    type Error  = BinaryEncodingError;
+
+    fn serialize_field<T: ?Sized+Serialize>(&mut self, value: &T) -> Result<(), BinaryEncodingError> { value.serialize(self.nested()) }
+    fn end(self) -> Result<(), BinaryEncodingError> { Ok(()) }
+}
+
+impl<'a> ser::SerializeStruct for BinarySerializer<'a> {
+    type Ok     = ();
+    type Error  = BinaryEncodingError;
+
+    fn serialize_field<T: ?Sized+Serialize>(&mut self, _key: &'static str, value: &T) -> Result<(), BinaryEncodingError> { value.serialize(self.nested()) }
+    fn end(self) -> Result<(), BinaryEncodingError> { Ok(()) }
+}
+
+impl<'a> ser::SerializeStructVariant for BinarySerializer<'a> {
+    type Ok     = ();
+    type Error  = BinaryEncodingError;
+
+    fn serialize_field<T: ?Sized+Serialize>(&mut self, _key: &'static str, value: &T) -> Result<(), BinaryEncodingError> { value.serialize(self.nested()) }
+    fn end(self) -> Result<(), BinaryEncodingError> { Ok(()) }
+}
+
+///
+/// Appends the binary encoding of a single drawing instruction to a buffer (without the header that `encode_drawing()` generates)
+///
+/// Each instruction is written as its length followed by its data, so a decoder can tell when it has received a complete instruction.
+///
+pub fn encode_draw_binary(draw: &Draw, append_to: &mut Vec<u8>) -> Result<(), BinaryEncodingError> {
+    let mut data = vec![];
+    draw.serialize(BinarySerializer { output: &mut data })?;
+
+    encode_varint(data.len() as u64, append_to);
+    append_to.extend(data);
+
+    Ok(())
+}
+
+///
+/// Encodes a set of drawing instructions in a compact binary format
+///
+/// The result begins with a header (`BINARY_DRAWING_MAGIC` followed by `BINARY_DRAWING_VERSION`), and can be decoded using
+/// `BinaryCanvasDecoder` or `decode_binary_drawing_stream()`.
+///
+pub fn encode_drawing(drawing: &[Draw]) -> Vec<u8> {
+    let mut result = vec![];
+
+    result.extend(BINARY_DRAWING_MAGIC);
+    result.push(BINARY_DRAWING_VERSION);
+
+    for draw in drawing.iter() {
+        encode_draw_binary(draw, &mut result).expect("Drawing instructions can always be encoded");
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::path::*;
+    use crate::color::*;
+    use crate::sprite::*;
+    use crate::texture::*;
+
+    use std::sync::*;
+
+    ///
+    /// Checks that an instruction is encoded as a particular set of bytes (the length prefix is not included)
+    ///
+    fn check_encoding(draw: Draw, expected: &[u8]) {
+        let mut encoded = vec![];
+        encode_draw_binary(&draw, &mut encoded).unwrap();
+
+        assert!(encoded[0] as usize == expected.len(), "{:?}: {:?}", draw, encoded);
+        assert!(&encoded[1..] == expected, "{:?}: {:?} != {:?}", draw, &encoded[1..], expected);
+    }
+
+    #[test]
+    fn header() {
+        assert!(encode_drawing(&[]) == vec![b'F', b'L', b'O', b'D', 1]);
+    }
+
+    #[test]
+    fn encode_start_frame() {
+        check_encoding(Draw::StartFrame, &[0]);
+    }
+
+    #[test]
+    fn encode_fill() {
+        check_encoding(Draw::Fill, &[4]);
+    }
+
+    #[test]
+    fn encode_move() {
+        check_encoding(Draw::Path(PathOp::Move(1.0, 2.0)), &[3, 1, 0, 0, 128, 63, 0, 0, 0, 64]);
+    }
+
+    #[test]
+    fn encode_line_width() {
+        check_encoding(Draw::LineWidth(2.0), &[6, 0, 0, 0, 64]);
+    }
+
+    #[test]
+    fn encode_fill_color() {
+        check_encoding(Draw::FillColor(Color::Rgba(1.0, 0.5, 0.25, 1.0)), &[13, 0, 0, 0, 128, 63, 0, 0, 0, 63, 0, 0, 128, 62, 0, 0, 128, 63]);
+    }
+
+    #[test]
+    fn encode_sprite() {
+        check_encoding(Draw::Sprite(SpriteId(3)), &[38, 3]);
+    }
+
+    #[test]
+    fn encode_create_texture() {
+        check_encoding(Draw::Texture(TextureId(7), TextureOp::Create(TextureSize(16, 8), TextureFormat::Rgba)), &[44, 7, 0, 16, 8, 0]);
+    }
+
+    #[test]
+    fn encode_copy_texture() {
+        check_encoding(Draw::Texture(TextureId(7), TextureOp::Copy(TextureId(8))), &[44, 7, 7, 8]);
+    }
+
+    #[test]
+    fn encode_set_mipmaps() {
+        check_encoding(Draw::Texture(TextureId(7), TextureOp::SetMipMaps(false)), &[44, 7, 9, 0]);
+    }
+
+    #[test]
+    fn encode_set_from_encoded_image() {
+        check_encoding(Draw::Texture(TextureId(7), TextureOp::SetFromEncodedImage(Arc::new(vec![1, 2, 3]))), &[44, 7, 10, 3, 1, 2, 3]);
+    }
+}
@@ -6,16 +6,97 @@ use hsluv::*;
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum ColorFormat {
     Rgba,
-    Hsluv
+    LinearRgba,
+    Hsluv,
+    Hsl,
 }
 
 ///
 /// Representation of a colour
 ///
+/// `Rgba` colours are in the sRGB colour space: the components are gamma-encoded in the same way as colours in most image
+/// files and web pages. `LinearRgba` colours have components that are proportional to the intensity of the light, which is
+/// the space that physically-based blending and lighting calculations are usually done in. The hue of `Hsluv` and `Hsl`
+/// colours is in degrees, and the saturation and lightness range from 0 to 100.
+///
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Color {
     Rgba(f32, f32, f32, f32),
-    Hsluv(f32, f32, f32, f32)
+    Hsluv(f32, f32, f32, f32),
+    LinearRgba(f32, f32, f32, f32),
+    Hsl(f32, f32, f32, f32),
+}
+
+///
+/// Converts an sRGB component to a linear one
+///
+#[inline]
+fn srgb_to_linear(component: f32) -> f32 {
+    if component <= 0.04045 {
+        component / 12.92
+    } else {
+        ((component + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+///
+/// Converts a linear component to an sRGB one
+///
+#[inline]
+fn linear_to_srgb(component: f32) -> f32 {
+    if component <= 0.0031308 {
+        component * 12.92
+    } else {
+        1.055 * component.powf(1.0/2.4) - 0.055
+    }
+}
+
+///
+/// Converts HSL components (hue in degrees, saturation and lightness from 0-100) to RGB
+///
+fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (f32, f32, f32) {
+    let s       = (s / 100.0).clamp(0.0, 1.0);
+    let l       = (l / 100.0).clamp(0.0, 1.0);
+    let h       = h.rem_euclid(360.0) / 60.0;
+
+    let chroma  = (1.0 - (2.0*l - 1.0).abs()) * s;
+    let x       = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0   => (chroma, x, 0.0),
+        1   => (x, chroma, 0.0),
+        2   => (0.0, chroma, x),
+        3   => (0.0, x, chroma),
+        4   => (x, 0.0, chroma),
+        _   => (chroma, 0.0, x),
+    };
+
+    let m = l - chroma/2.0;
+    (r + m, g + m, b + m)
+}
+
+///
+/// Converts RGB components to HSL (hue in degrees, saturation and lightness from 0-100)
+///
+fn rgb_to_hsl(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max     = r.max(g).max(b);
+    let min     = r.min(g).min(b);
+    let chroma  = max - min;
+    let l       = (max + min) / 2.0;
+
+    if chroma <= 0.0 {
+        return (0.0, 0.0, l * 100.0);
+    }
+
+    let h = if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    let s = chroma / (1.0 - (2.0*l - 1.0).abs());
+
+    (h * 60.0, s * 100.0, l * 100.0)
 }
 
 impl PartialEq for Color {
@@ -26,6 +107,8 @@ impl PartialEq for Color {
         let distance = match (self, col) {
             (Rgba(r1, g1, b1, a1), Rgba(r2, g2, b2, a2))    => { (r1-r2)*(r1-r2) + (g1-g2)*(g1-g2) + (b1-b2)*(b1-b2) + (a1-a2)*(a1-a2) }
             (Hsluv(h1, s1, l1, a1), Hsluv(h2, s2, l2, a2))  => { (h1-h2)*(h1-h2) + (s1-s2)*(s1-s2) + (l1-l2)*(l1-l2) + (a1-a2)*(a1-a2) }
+            (LinearRgba(r1, g1, b1, a1), LinearRgba(r2, g2, b2, a2))    => { (r1-r2)*(r1-r2) + (g1-g2)*(g1-g2) + (b1-b2)*(b1-b2) + (a1-a2)*(a1-a2) }
+            (Hsl(h1, s1, l1, a1), Hsl(h2, s2, l2, a2))      => { (h1-h2)*(h1-h2) + (s1-s2)*(s1-s2) + (l1-l2)*(l1-l2) + (a1-a2)*(a1-a2) }
             _                                               => { return false; }
         };
 
@@ -35,7 +118,31 @@ impl PartialEq for Color {
 
 impl Color {
     ///
-    /// Returns this colour as RGBA components
+    /// Creates a colour from sRGB components (this is the same as `Color::Rgba`)
+    ///
+    pub fn from_srgb(r: f32, g: f32, b: f32, a: f32) -> Color {
+        Color::Rgba(r, g, b, a)
+    }
+
+    ///
+    /// Creates a colour from linear RGB components
+    ///
+    pub fn from_linear_rgb(r: f32, g: f32, b: f32, a: f32) -> Color {
+        Color::LinearRgba(r, g, b, a)
+    }
+
+    ///
+    /// Creates a colour from HSL components (the hue is in degrees, and the saturation and lightness range from 0 to 100)
+    ///
+    pub fn from_hsl(h: f32, s: f32, l: f32, a: f32) -> Color {
+        Color::Hsl(h, s, l, a)
+    }
+
+    ///
+    /// Returns this colour as RGBA components in the sRGB colour space
+    ///
+    /// These are the components that the renderers use: they blend colours in sRGB space, so this is also the value that
+    /// is sent to the GPU.
     ///
     pub fn to_rgba_components(&self) -> (f32, f32, f32, f32) {
         match self {
@@ -45,6 +152,42 @@ impl Color {
                 let (r, g, b) = hsluv_to_rgb((h as f64, s as f64, l as f64));
                 (r as f32, g as f32, b as f32, a)
             }
+
+            &Color::LinearRgba(r, g, b, a) => (linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), a),
+
+            &Color::Hsl(h, s, l, a) => {
+                let (r, g, b) = hsl_to_rgb(h, s, l);
+                (r, g, b, a)
+            }
+        }
+    }
+
+    ///
+    /// Returns this colour as RGBA components in the linear RGB colour space
+    ///
+    pub fn to_linear_rgba_components(&self) -> (f32, f32, f32, f32) {
+        match self {
+            &Color::LinearRgba(r, g, b, a) => (r, g, b, a),
+
+            other => {
+                let (r, g, b, a) = other.to_rgba_components();
+                (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+            }
+        }
+    }
+
+    ///
+    /// Returns this colour as HSL components
+    ///
+    pub fn to_hsl_components(&self) -> (f32, f32, f32, f32) {
+        match self {
+            &Color::Hsl(h, s, l, a) => (h, s, l, a),
+
+            other => {
+                let (r, g, b, a) = other.to_rgba_components();
+                let (h, s, l)    = rgb_to_hsl(r, g, b);
+                (h, s, l, a)
+            }
         }
     }
 
@@ -55,7 +198,8 @@ impl Color {
         match self {
             &Color::Hsluv(h, s, l, a) => (h, s, l, a),
 
-            &Color::Rgba(r, g, b, a) => {
+            other => {
+                let (r, g, b, a) = other.to_rgba_components();
                 let (h, s, l) = rgb_to_hsluv((r as f64, g as f64, b as f64));
                 let s = if l <= 0.0 { 100.0 } else { s };
                 (h as f32, s as f32, l as f32, a)
@@ -68,15 +212,11 @@ impl Color {
     ///
     #[inline]
     pub fn to_format(&self, format: ColorFormat) -> Color {
-        let (r, g, b, a) = self.to_rgba_components();
-
         match format {
-            ColorFormat::Rgba   => Color::Rgba(r, g, b, a),
-            ColorFormat::Hsluv  => {
-                let (h, s, l) = rgb_to_hsluv((r as f64, g as f64, b as f64));
-                let s = if l <= 0.0 { 100.0 } else { s };
-                Color::Hsluv(h as f32, s as f32, l as f32, a)
-            }
+            ColorFormat::Rgba       => { let (r, g, b, a) = self.to_rgba_components(); Color::Rgba(r, g, b, a) },
+            ColorFormat::LinearRgba => { let (r, g, b, a) = self.to_linear_rgba_components(); Color::LinearRgba(r, g, b, a) },
+            ColorFormat::Hsluv      => { let (h, s, l, a) = self.to_hsluv_components(); Color::Hsluv(h, s, l, a) },
+            ColorFormat::Hsl        => { let (h, s, l, a) = self.to_hsl_components(); Color::Hsl(h, s, l, a) },
         }
     }
 
@@ -86,7 +226,9 @@ impl Color {
     pub fn with_alpha(&self, new_alpha: f32) -> Color {
         match self {
             &Color::Rgba(r, g, b, _)    => Color::Rgba(r, g, b, new_alpha),
-            &Color::Hsluv(h, s, l, _)   => Color::Hsluv(h, s, l, new_alpha)
+            &Color::Hsluv(h, s, l, _)   => Color::Hsluv(h, s, l, new_alpha),
+            &Color::LinearRgba(r, g, b, _)  => Color::LinearRgba(r, g, b, new_alpha),
+            &Color::Hsl(h, s, l, _)     => Color::Hsl(h, s, l, new_alpha),
         }
     }
 }
@@ -135,4 +277,45 @@ mod test {
         assert!((b-0.38) < 0.1);
         assert!(a == 0.8);
     }
+
+    #[test]
+    fn srgb_linear_round_trip() {
+        for component in [0.0, 0.02, 0.2, 0.5, 0.8, 1.0] {
+            let linear          = Color::Rgba(component, component, component, 1.0).to_format(ColorFormat::LinearRgba);
+            let (r, g, b, a)    = linear.to_rgba_components();
+
+            assert!((r-component).abs() < 0.0001);
+            assert!((g-component).abs() < 0.0001);
+            assert!((b-component).abs() < 0.0001);
+            assert!(a == 1.0);
+        }
+    }
+
+    #[test]
+    fn srgb_mid_grey_is_darker_in_linear_space() {
+        let (r, _, _, _) = Color::Rgba(0.5, 0.5, 0.5, 1.0).to_linear_rgba_components();
+        assert!((r-0.214).abs() < 0.001);
+
+        let (r, _, _, _) = Color::LinearRgba(0.5, 0.5, 0.5, 1.0).to_rgba_components();
+        assert!((r-0.735).abs() < 0.001);
+    }
+
+    #[test]
+    fn can_convert_hsl_to_rgba() {
+        assert!(Color::Hsl(0.0, 100.0, 50.0, 1.0).to_format(ColorFormat::Rgba) == Color::Rgba(1.0, 0.0, 0.0, 1.0));
+        assert!(Color::Hsl(120.0, 100.0, 25.0, 1.0).to_format(ColorFormat::Rgba) == Color::Rgba(0.0, 0.5, 0.0, 1.0));
+        assert!(Color::Hsl(240.0, 0.0, 75.0, 0.5).to_format(ColorFormat::Rgba) == Color::Rgba(0.75, 0.75, 0.75, 0.5));
+    }
+
+    #[test]
+    fn rgba_hsl_round_trip() {
+        let rgb         = Color::Rgba(0.5, 0.7, 0.2, 0.9);
+        let hsl         = rgb.to_format(ColorFormat::Hsl);
+        let (h, s, l, _) = hsl.to_hsl_components();
+
+        assert!((h-84.0).abs() < 0.1);
+        assert!((s-55.6).abs() < 0.1);
+        assert!((l-45.0).abs() < 0.1);
+        assert!(hsl.to_format(ColorFormat::Rgba) == rgb);
+    }
 }
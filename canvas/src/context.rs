@@ -262,6 +262,11 @@ pub trait GraphicsContext {
         self.draw(Draw::Texture(texture_id, TextureOp::SetBytes(TexturePosition(x, y), TextureSize(width, height), bytes)));
     }
 
+    /// Sets a texture to the decoded contents of an encoded image file (eg, the bytes of a PNG or a JPEG file)
+    fn set_texture_from_encoded_image(&mut self, texture_id: TextureId, bytes: Arc<Vec<u8>>) {
+        self.draw(Draw::Texture(texture_id, TextureOp::SetFromEncodedImage(bytes)));
+    }
+
     /// Creates the texture bytes by drawing from a sprite
     fn set_texture_from_sprite(&mut self, texture_id: TextureId, sprite_id: SpriteId, sprite_x: f32, sprite_y: f32, sprite_width: f32, sprite_height: f32) {
         self.draw(Draw::Texture(texture_id, TextureOp::SetFromSprite(sprite_id, SpriteBounds(SpritePosition(sprite_x, sprite_y), SpriteSize(sprite_width, sprite_height)))));
@@ -282,6 +287,16 @@ pub trait GraphicsContext {
         self.draw(Draw::Texture(texture_id, TextureOp::FillTransparency(alpha)));
     }
 
+    /// Sets how a texture repeats when it's used as a fill
+    fn set_texture_wrap_mode(&mut self, texture_id: TextureId, wrap_mode: TextureWrapMode) {
+        self.draw(Draw::Texture(texture_id, TextureOp::SetWrapMode(wrap_mode)));
+    }
+
+    /// Sets whether or not mipmaps are generated for a texture (disabling them makes the texture sharper but more prone to aliasing when it's drawn at a reduced size)
+    fn set_texture_mipmaps(&mut self, texture_id: TextureId, enabled: bool) {
+        self.draw(Draw::Texture(texture_id, TextureOp::SetMipMaps(enabled)));
+    }
+
     /// Copies a texture from one ID to another
     fn copy_texture(&mut self, source_texture_id: TextureId, target_texture_id: TextureId) {
         self.draw(Draw::Texture(source_texture_id, TextureOp::Copy(target_texture_id)));
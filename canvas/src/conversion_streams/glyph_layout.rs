@@ -32,6 +32,7 @@ where
         let mut current_font        = None;
         let (mut x_pos, mut y_pos)  = (0.0, 0.0);
         let mut alignment           = TextAlignment::Left;
+        let mut layout_fill_color   = None;
 
         // Read from the drawing stream
         while let Some(draw) = draw_stream.next().await {
@@ -88,9 +89,10 @@ where
                     current_font    = None;
 
                     // Set up the layout for the next set of text
-                    x_pos           = x;
-                    y_pos           = y;
-                    alignment       = align;
+                    x_pos               = x;
+                    y_pos               = y;
+                    alignment           = align;
+                    layout_fill_color   = None;
                 }
 
                 Draw::Font(font_id, FontOp::LayoutText(text)) => {
@@ -105,7 +107,14 @@ where
                                 .map(|line: CanvasFontLineLayout| {
                                     line.continue_with_new_font(last_font, &new_font, font_size)
                                 }).or_else(|| {
-                                    Some(CanvasFontLineLayout::new(&new_font, font_size))
+                                    let mut line = CanvasFontLineLayout::new(&new_font, font_size);
+
+                                    // Any fill colour set since the layout began applies to the first glyphs
+                                    if let Some(fill_color) = layout_fill_color.take() {
+                                        line.draw(iter::once(Draw::FillColor(fill_color)));
+                                    }
+
+                                    Some(line)
                                 });
                             current_font = Some(font_id);
                         }
@@ -133,9 +142,11 @@ where
                 },
 
                 Draw::FillColor(fill_color) => {
-                    // This is added as a drawing instruction to the current layout
+                    // This is added as a drawing instruction to the current layout (or to the start of the next layout if no text has been laid out yet)
                     if let Some(current_line) = &mut current_line {
                         current_line.draw(iter::once(Draw::FillColor(fill_color.clone())));
+                    } else {
+                        layout_fill_color = Some(fill_color.clone());
                     }
 
                     yield_value(Draw::FillColor(fill_color)).await;
@@ -206,7 +217,9 @@ where
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::color::*;
     use crate::font_face::*;
+    use crate::primitives::*;
     use futures::stream;
     use futures::executor;
 
@@ -357,4 +370,40 @@ mod test {
             }
         });
     }
+
+    #[test]
+    fn layout_colored_text_runs() {
+        executor::block_on(async {
+            let lato            = CanvasFontFace::from_slice(include_bytes!("../../test_data/Lato-Regular.ttf"));
+            let red             = Color::Rgba(1.0, 0.0, 0.0, 1.0);
+            let blue            = Color::Rgba(0.0, 0.0, 1.0, 1.0);
+
+            let mut instructions = vec![Draw::Font(FontId(1), FontOp::UseFontDefinition(lato))];
+            instructions.draw_text_runs(500.0, 500.0, TextAlignment::Left, vec![
+                TextRun::new("Hello, ", FontId(1), 100.0, red),
+                TextRun::new("world", FontId(1), 50.0, blue),
+            ]);
+
+            let instructions    = stream::iter(instructions);
+            let instructions    = drawing_with_laid_out_text(instructions);
+            let instructions    = instructions.collect::<Vec<_>>().await;
+
+            // The laid out text is at the end of the drawing: each run should be preceded by its colour
+            let laid_out        = &instructions[instructions.len()-4..];
+            println!("{:?}", laid_out);
+
+            assert!(laid_out[0] == Draw::FillColor(red));
+            assert!(laid_out[2] == Draw::FillColor(blue));
+
+            if let (Draw::Font(FontId(1), FontOp::DrawGlyphs(hello)), Draw::Font(FontId(1), FontOp::DrawGlyphs(world))) = (&laid_out[1], &laid_out[3]) {
+                assert!(hello.len() == "Hello, ".len());
+                assert!(world.len() == "world".len());
+
+                assert!(hello.iter().all(|glyph| glyph.em_size == 100.0));
+                assert!(world.iter().all(|glyph| glyph.em_size == 50.0));
+            } else {
+                assert!(false);
+            }
+        });
+    }
 }
@@ -4,9 +4,11 @@ pub use self::path_stream::*;
 
 #[cfg(feature = "outline-fonts")] mod glyph_layout;
 #[cfg(feature = "outline-fonts")] mod outline_fonts;
+#[cfg(feature = "outline-fonts")] mod wrapped_text_layout;
 
 #[cfg(feature = "outline-fonts")] pub use self::glyph_layout::*;
 #[cfg(feature = "outline-fonts")] pub use self::outline_fonts::*;
+#[cfg(feature = "outline-fonts")] pub use self::wrapped_text_layout::*;
 
 mod dashed_lines;
 
@@ -1,6 +1,7 @@
 use crate::draw::*;
 use crate::path::*;
 use crate::font::*;
+use crate::color::*;
 use crate::namespace::*;
 
 use flo_stream::*;
@@ -82,6 +83,71 @@ impl<'a> ttf_parser::OutlineBuilder for FontOutliner<'a> {
     }
 }
 
+///
+/// Structure used to receive the layers of a color glyph from the COLR table
+///
+struct ColorGlyphPainter<'a> {
+    font:           &'a ttf_parser::Face<'a>,
+    drawing:        &'a mut Vec<Draw>,
+    scale_factor:   f32,
+    x_pos:          f32,
+    y_pos:          f32,
+    foreground:     Color,
+    outline:        Vec<Draw>,
+    changed_color:  bool,
+}
+
+impl<'a> ColorGlyphPainter<'a> {
+    ///
+    /// Fills the current outline as a new layer of the glyph
+    ///
+    fn fill_layer(&mut self, color: Option<Color>) {
+        self.drawing.push(Draw::Path(PathOp::NewPath));
+        self.drawing.extend(self.outline.iter().cloned());
+
+        match color {
+            Some(color) => {
+                self.drawing.push(Draw::FillColor(color));
+                self.changed_color = true;
+            }
+
+            None => {
+                // The foreground layers use the fill color that was set before the text was drawn
+                if self.changed_color {
+                    self.drawing.push(Draw::FillColor(self.foreground));
+                }
+            }
+        }
+
+        self.drawing.push(Draw::Fill);
+    }
+}
+
+impl<'a> ttf_parser::colr::Painter for ColorGlyphPainter<'a> {
+    fn outline(&mut self, glyph_id: ttf_parser::GlyphId) {
+        self.outline.clear();
+
+        let mut outliner = FontOutliner {
+            drawing:        &mut self.outline,
+            scale_factor:   self.scale_factor,
+            x_pos:          self.x_pos,
+            y_pos:          self.y_pos,
+            last:           (0.0, 0.0)
+        };
+
+        self.font.outline_glyph(glyph_id, &mut outliner);
+    }
+
+    fn paint_foreground(&mut self) {
+        self.fill_layer(None);
+    }
+
+    fn paint_color(&mut self, color: ttf_parser::RgbaColor) {
+        let color = Color::Rgba(color.red as f32 / 255.0, color.green as f32 / 255.0, color.blue as f32 / 255.0, color.alpha as f32 / 255.0);
+        self.fill_layer(Some(color));
+    }
+}
+
 ///
 /// Given a stream of drawing instructions (such as is returned by `Canvas::stream()`), turns any glyph drawing instructions 
 /// into the equivalent path drawing instructions.
@@ -90,6 +156,31 @@ impl<'a> ttf_parser::OutlineBuilder for FontOutliner<'a> {
 /// support of its own.
 ///
 pub fn drawing_with_text_as_paths<InStream>(draw_stream: InStream) -> impl Send+Unpin+Stream<Item=Draw> 
+where
+    InStream: 'static + Send + Unpin + Stream<Item=Draw>,
+{
+    text_as_paths(draw_stream, false)
+}
+
+///
+/// As for `drawing_with_text_as_paths`, except that glyphs from fonts with a color table (COLR/CPAL) are drawn as a series of
+/// filled layers using the colors from the font's first palette
+///
+/// Each color glyph is drawn between a `PushState` and a `PopState`, so the fill color is unchanged afterwards. Layers that
+/// use the text foreground color are filled with the most recent `FillColor`. Glyphs without color layers (including bitmap
+/// emoji from CBDT or sbix tables) are drawn as with `drawing_with_text_as_paths`.
+///
+pub fn drawing_with_color_text_as_paths<InStream>(draw_stream: InStream) -> impl Send+Unpin+Stream<Item=Draw> 
+where
+    InStream: 'static + Send + Unpin + Stream<Item=Draw>,
+{
+    text_as_paths(draw_stream, true)
+}
+
+///
+/// Implementation of the text to paths conversion, optionally drawing the layers of color glyphs
+///
+fn text_as_paths<InStream>(draw_stream: InStream, color_glyphs: bool) -> impl Send+Unpin+Stream<Item=Draw> 
 where
     InStream: 'static + Send + Unpin + Stream<Item=Draw>,
 {
@@ -97,6 +188,8 @@ where
         // Set up
         let mut namespace_id    = NamespaceId::default().local_id();
         let mut namespace_stack = vec![];
+        let mut fill_color      = Color::Rgba(0.0, 0.0, 0.0, 1.0);
+        let mut fill_stack      = vec![];
         let mut draw_stream     = draw_stream;
         let mut font_map        = HashMap::new();
 
@@ -105,7 +198,8 @@ where
             match draw {
                 Draw::ClearCanvas(_) => {
                     font_map.clear();
-                    namespace_id = NamespaceId::default().local_id();
+                    namespace_id    = NamespaceId::default().local_id();
+                    fill_color      = Color::Rgba(0.0, 0.0, 0.0, 1.0);
 
                     yield_value(draw).await;
                 }
@@ -115,8 +209,14 @@ where
                     yield_value(draw).await;
                 }
 
+                Draw::FillColor(color) => {
+                    fill_color = color;
+                    yield_value(draw).await;
+                }
+
                 Draw::PushState => {
                     namespace_stack.push(namespace_id);
+                    fill_stack.push(fill_color);
                     yield_value(draw).await;
                 }
 
@@ -124,6 +224,9 @@ where
                     if let Some(new_namespace) = namespace_stack.pop() {
                         namespace_id = new_namespace;
                     }
+                    if let Some(old_fill_color) = fill_stack.pop() {
+                        fill_color = old_fill_color;
+                    }
                     yield_value(draw).await;
                 }
 
@@ -140,12 +243,37 @@ where
                         let units_per_em    = ttf_font.units_per_em() as f32;
 
                         for glyph in glyphs {
-                            // Start rendering this glyph
-                            yield_value(Draw::Path(PathOp::NewPath)).await;
-
                             let GlyphId(glyph_id)   = glyph.id;
                             let glyph_id            = ttf_parser::GlyphId(glyph_id as _);
 
+                            // Color glyphs are drawn as a set of layers
+                            if color_glyphs && ttf_font.is_color_glyph(glyph_id) {
+                                let mut drawing     = vec![];
+                                let mut painter     = ColorGlyphPainter {
+                                    font:           ttf_font,
+                                    drawing:        &mut drawing,
+                                    scale_factor:   glyph.em_size / units_per_em,
+                                    x_pos:          glyph.location.0,
+                                    y_pos:          glyph.location.1,
+                                    foreground:     fill_color,
+                                    outline:        vec![],
+                                    changed_color:  false,
+                                };
+
+                                if ttf_font.paint_color_glyph(glyph_id, 0, &mut painter).is_some() {
+                                    yield_value(Draw::PushState).await;
+                                    for draw in drawing {
+                                        yield_value(draw).await;
+                                    }
+                                    yield_value(Draw::PopState).await;
+
+                                    continue;
+                                }
+                            }
+
+                            // Start rendering this glyph
+                            yield_value(Draw::Path(PathOp::NewPath)).await;
+
                             // Generate the outline
                             let mut drawing         = vec![];
                             let mut outliner        = FontOutliner { 
@@ -223,4 +351,58 @@ mod test {
             assert!(instructions.len() != 0);
         });
     }
+
+    #[test]
+    fn draw_color_glyph_layers() {
+        executor::block_on(async {
+            // Test font where 'A' is a red square, a blue inner square and an inner square in the foreground color
+            let colr_font       = CanvasFontFace::from_slice(include_bytes!("../../test_data/ColrTest.ttf"));
+            let glyph           = GlyphPosition { id: GlyphId(3), location: (100.0, 200.0), em_size: 10.0 };
+            let foreground      = Color::Rgba(0.0, 1.0, 0.0, 1.0);
+
+            let instructions    = vec![
+                Draw::Font(FontId(1), FontOp::UseFontDefinition(colr_font)), 
+                Draw::FillColor(foreground),
+                Draw::Font(FontId(1), FontOp::DrawGlyphs(vec![glyph])),
+            ];
+            let instructions    = stream::iter(instructions);
+            let instructions    = drawing_with_color_text_as_paths(instructions);
+
+            let instructions    = instructions.collect::<Vec<_>>().await;
+
+            // Each layer is filled with its own color, and the fill color is restored afterwards
+            let fill_colors     = instructions.iter()
+                .filter_map(|draw| if let Draw::FillColor(color) = draw { Some(*color) } else { None })
+                .collect::<Vec<_>>();
+            let num_fills       = instructions.iter().filter(|draw| **draw == Draw::Fill).count();
+
+            assert!(fill_colors == vec![foreground, Color::Rgba(1.0, 0.0, 0.0, 1.0), Color::Rgba(0.0, 0.0, 1.0, 1.0), foreground]);
+            assert!(num_fills == 3);
+            assert!(instructions.iter().filter(|draw| **draw == Draw::PushState).count() == 1);
+            assert!(instructions.last() == Some(&Draw::PopState));
+
+            // The layers are scaled to the em size and positioned at the glyph location
+            assert!(instructions.contains(&Draw::Path(PathOp::Move(100.0 + 2.5, 200.0 + 2.0))));
+        });
+    }
+
+    #[test]
+    fn color_glyphs_are_monochrome_by_default() {
+        executor::block_on(async {
+            let colr_font       = CanvasFontFace::from_slice(include_bytes!("../../test_data/ColrTest.ttf"));
+            let glyph           = GlyphPosition { id: GlyphId(3), location: (100.0, 200.0), em_size: 10.0 };
+
+            let instructions    = vec![
+                Draw::Font(FontId(1), FontOp::UseFontDefinition(colr_font)), 
+                Draw::Font(FontId(1), FontOp::DrawGlyphs(vec![glyph])),
+            ];
+            let instructions    = stream::iter(instructions);
+            let instructions    = drawing_with_text_as_paths(instructions);
+
+            let instructions    = instructions.collect::<Vec<_>>().await;
+
+            assert!(instructions.iter().filter(|draw| **draw == Draw::Fill).count() == 1);
+            assert!(!instructions.iter().any(|draw| matches!(draw, Draw::FillColor(_))));
+        });
+    }
 }
@@ -0,0 +1,280 @@
+use crate::draw::*;
+use crate::font::*;
+use crate::font_face::*;
+use crate::font_line_layout::*;
+
+use std::sync::*;
+
+///
+/// A single line of text generated by `layout_wrapped_text()`
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct WrappedTextLine {
+    /// The text on this line (the whitespace where the line was broken is not included)
+    pub text: String,
+
+    /// The position of the start of the baseline of this line, after alignment
+    pub baseline: (f32, f32),
+
+    /// The width of the text on this line
+    pub width: f32,
+
+    /// The drawing instructions that render the glyphs on this line
+    pub drawing: Vec<Draw>,
+}
+
+///
+/// A block of text that has been broken into lines by `layout_wrapped_text()`
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct WrappedTextLayout {
+    /// The lines that make up the text, from top to bottom
+    pub lines: Vec<WrappedTextLine>,
+
+    /// The total height of the text, from the ascender of the first line to the descender of the last line
+    pub height: f32,
+}
+
+impl WrappedTextLayout {
+    ///
+    /// Returns the drawing instructions to render all of the lines in this layout
+    ///
+    pub fn to_drawing(self) -> Vec<Draw> {
+        self.lines.into_iter()
+            .flat_map(|line| line.drawing)
+            .collect()
+    }
+}
+
+///
+/// Splits a single paragraph into lines at word boundaries, so that each line fits within `max_width` where possible
+///
+fn wrap_paragraph(font: &Arc<CanvasFontFace>, em_size: f32, paragraph: &str, max_width: f32) -> Vec<String> {
+    let mut lines           = vec![];
+    let mut current_line    = String::new();
+
+    for word in paragraph.split_whitespace() {
+        if current_line.is_empty() {
+            // Words always fit on an empty line (words that are too wide for a line are left to overflow)
+            current_line.push_str(word);
+        } else {
+            // Measure the line with the word added to it to see if it still fits
+            let candidate   = format!("{} {}", current_line, word);
+            let width       = measure_text(font, &candidate, em_size).pos.0 as f32;
+
+            if width <= max_width {
+                current_line = candidate;
+            } else {
+                lines.push(current_line);
+                current_line = word.to_string();
+            }
+        }
+    }
+
+    // Empty paragraphs still generate a (blank) line
+    lines.push(current_line);
+
+    lines
+}
+
+///
+/// Lays out a single line of text, adding `extra_spacing` to the width of each space between the words
+///
+fn layout_words(font: &Arc<CanvasFontFace>, em_size: f32, words: &[&str], extra_spacing: f32) -> CanvasFontLineLayout {
+    let mut layout = CanvasFontLineLayout::new(font, em_size);
+
+    for (idx, word) in words.iter().enumerate() {
+        if idx > 0 {
+            layout.add_text(" ");
+            layout.advance(extra_spacing, 0.0);
+        }
+
+        layout.add_text(word);
+    }
+
+    layout
+}
+
+///
+/// Lays out a block of text in a font, wrapping it at word boundaries so that each line fits within `max_width`. The
+/// `outline-fonts` feature must be enabled to use this function.
+///
+/// `(x, y)` is the origin of the block: `alignment` determines how each line is positioned horizontally relative to `x`, and
+/// `vertical_alignment` determines whether the top, middle or bottom of the block is at `y`. For `TextAlignment::Justify`, `x`
+/// is the left-hand side of the block and the spaces in each line are stretched so that it fills `max_width`, except for the
+/// last line of each paragraph, which is left-aligned.
+///
+/// Lines are broken at explicit newlines and wherever the next word would make the line wider than `max_width`: a word that
+/// is wider than `max_width` on its own is placed on a line by itself. The distance between the baselines of consecutive lines
+/// is the line height of the font plus `leading`.
+///
+/// The drawing instructions render the glyphs using `font_id`, which should be declared as `font` with a size of `em_size`.
+///
+pub fn layout_wrapped_text(font: &Arc<CanvasFontFace>, font_id: FontId, em_size: f32, text: &str, x: f32, y: f32, max_width: f32, leading: f32, alignment: TextAlignment, vertical_alignment: TextVerticalAlignment) -> WrappedTextLayout {
+    // Work out the line spacing from the font metrics
+    let (ascender, descender, line_gap) = font.font_metrics(em_size)
+        .map(|metrics| (metrics.ascender, metrics.descender, metrics.line_gap))
+        .unwrap_or((em_size, 0.0, 0.0));
+    let line_height                     = ascender - descender + line_gap + leading;
+
+    // Break the text into lines, noting which lines end a paragraph
+    let line_text = text.split('\n')
+        .flat_map(|paragraph| {
+            let lines       = wrap_paragraph(font, em_size, paragraph.trim_end_matches('\r'), max_width);
+            let last_line   = lines.len() - 1;
+
+            lines.into_iter()
+                .enumerate()
+                .map(move |(idx, line)| (line, idx == last_line))
+        })
+        .collect::<Vec<_>>();
+
+    // The height covers every line, from the top of the first to the bottom of the last
+    let height = (line_text.len() as f32 - 1.0) * line_height + ascender - descender;
+
+    // Position the first baseline according to the vertical alignment
+    let top = match vertical_alignment {
+        TextVerticalAlignment::Top      => y,
+        TextVerticalAlignment::Middle   => y + height/2.0,
+        TextVerticalAlignment::Bottom   => y + height,
+    };
+
+    // Lay out each line in turn
+    let mut lines       = vec![];
+    let mut baseline_y  = top - ascender;
+
+    for (text, ends_paragraph) in line_text {
+        let words       = text.split(' ').collect::<Vec<_>>();
+
+        // Justified lines are stretched to fill the width by adding space between the words
+        let justify     = alignment == TextAlignment::Justify && !ends_paragraph && words.len() > 1;
+        let mut layout  = if justify {
+            let natural_width   = layout_words(font, em_size, &words, 0.0).measure().pos.0 as f32;
+            let extra_spacing   = (max_width - natural_width).max(0.0) / ((words.len() - 1) as f32);
+
+            layout_words(font, em_size, &words, extra_spacing)
+        } else {
+            let mut layout = CanvasFontLineLayout::new(font, em_size);
+            layout.add_text(&text);
+            layout
+        };
+
+        let width       = layout.measure().pos.0 as f32;
+        let baseline_x  = match alignment {
+            TextAlignment::Left     => x,
+            TextAlignment::Right    => x - width,
+            TextAlignment::Center   => x - width/2.0,
+            TextAlignment::Justify  => x,
+        };
+
+        layout.align(baseline_x, baseline_y, TextAlignment::Left);
+
+        lines.push(WrappedTextLine {
+            text:       text,
+            baseline:   (baseline_x, baseline_y),
+            width:      width,
+            drawing:    layout.to_drawing(font_id),
+        });
+
+        baseline_y -= line_height;
+    }
+
+    WrappedTextLayout { lines, height }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wrap_at_word_boundaries() {
+        let lato        = CanvasFontFace::from_slice(include_bytes!("../../test_data/Lato-Regular.ttf"));
+        let text        = "The quick brown fox jumps over the lazy dog";
+        let max_width   = measure_text(&lato, "The quick brown", 20.0).pos.0 as f32 + 1.0;
+
+        let layout      = layout_wrapped_text(&lato, FontId(1), 20.0, text, 100.0, 500.0, max_width, 0.0, TextAlignment::Left, TextVerticalAlignment::Top);
+
+        // Every line should fit in the width, and the words should all be present in order
+        assert!(layout.lines.len() >= 3);
+        assert!(layout.lines[0].text == "The quick brown");
+        assert!(layout.lines.iter().all(|line| line.width <= max_width));
+        assert!(layout.lines.iter().map(|line| line.text.clone()).collect::<Vec<_>>().join(" ") == text);
+
+        // Lines move down the page
+        assert!(layout.lines[0].baseline.1 < 500.0);
+        assert!(layout.lines[1].baseline.1 < layout.lines[0].baseline.1);
+        assert!(layout.lines[2].baseline.1 < layout.lines[1].baseline.1);
+    }
+
+    #[test]
+    fn explicit_newlines_and_leading() {
+        let lato        = CanvasFontFace::from_slice(include_bytes!("../../test_data/Lato-Regular.ttf"));
+        let metrics     = lato.font_metrics(20.0).unwrap();
+        let line_height = metrics.ascender - metrics.descender + metrics.line_gap + 4.0;
+
+        let layout      = layout_wrapped_text(&lato, FontId(1), 20.0, "One\n\nTwo", 0.0, 0.0, 1000.0, 4.0, TextAlignment::Left, TextVerticalAlignment::Top);
+
+        // The blank line is preserved
+        assert!(layout.lines.iter().map(|line| line.text.as_str()).collect::<Vec<_>>() == vec!["One", "", "Two"]);
+        assert!(layout.lines[1].drawing.is_empty());
+
+        // Baselines are separated by the line height plus the leading
+        assert!((layout.lines[0].baseline.1 - (-metrics.ascender)).abs() < 0.01);
+        assert!((layout.lines[1].baseline.1 - layout.lines[0].baseline.1 + line_height).abs() < 0.01);
+        assert!((layout.lines[2].baseline.1 - layout.lines[1].baseline.1 + line_height).abs() < 0.01);
+        assert!((layout.height - (line_height * 2.0 + metrics.ascender - metrics.descender)).abs() < 0.01);
+    }
+
+    #[test]
+    fn align_lines_to_the_right() {
+        let lato        = CanvasFontFace::from_slice(include_bytes!("../../test_data/Lato-Regular.ttf"));
+        let layout      = layout_wrapped_text(&lato, FontId(1), 20.0, "Short\nA rather longer line", 300.0, 0.0, 1000.0, 0.0, TextAlignment::Right, TextVerticalAlignment::Top);
+
+        // Each line should end at the x position
+        assert!(layout.lines.len() == 2);
+        assert!(layout.lines.iter().all(|line| (line.baseline.0 + line.width - 300.0).abs() < 0.01));
+
+        // The drawing should contain the glyphs for every line
+        let drawing = layout.to_drawing();
+        assert!(drawing.len() == 2);
+        assert!(drawing.iter().all(|draw| match draw { Draw::Font(FontId(1), FontOp::DrawGlyphs(_)) => true, _ => false }));
+    }
+
+    #[test]
+    fn justify_stretches_all_but_the_last_line() {
+        let lato        = CanvasFontFace::from_slice(include_bytes!("../../test_data/Lato-Regular.ttf"));
+        let text        = "The quick brown fox jumps over the lazy dog";
+        let max_width   = measure_text(&lato, "The quick brown", 20.0).pos.0 as f32 + 10.0;
+
+        let layout      = layout_wrapped_text(&lato, FontId(1), 20.0, text, 100.0, 0.0, max_width, 0.0, TextAlignment::Justify, TextVerticalAlignment::Top);
+        let last_line   = layout.lines.last().unwrap();
+
+        // Every line except the last should fill the width exactly
+        assert!(layout.lines.len() >= 3);
+        assert!(layout.lines[0..layout.lines.len()-1].iter().all(|line| (line.width - max_width).abs() < 0.01));
+        assert!(layout.lines.iter().all(|line| line.baseline.0 == 100.0));
+
+        // The last line should be the natural width of the text
+        let natural_width = measure_text(&lato, &last_line.text, 20.0).pos.0 as f32;
+        assert!(last_line.width < max_width);
+        assert!((last_line.width - natural_width).abs() < 0.5);
+    }
+
+    #[test]
+    fn vertical_alignment() {
+        let lato        = CanvasFontFace::from_slice(include_bytes!("../../test_data/Lato-Regular.ttf"));
+        let metrics     = lato.font_metrics(20.0).unwrap();
+
+        let top         = layout_wrapped_text(&lato, FontId(1), 20.0, "One\nTwo", 0.0, 0.0, 1000.0, 0.0, TextAlignment::Left, TextVerticalAlignment::Top);
+        let middle      = layout_wrapped_text(&lato, FontId(1), 20.0, "One\nTwo", 0.0, 0.0, 1000.0, 0.0, TextAlignment::Left, TextVerticalAlignment::Middle);
+        let bottom      = layout_wrapped_text(&lato, FontId(1), 20.0, "One\nTwo", 0.0, 0.0, 1000.0, 0.0, TextAlignment::Left, TextVerticalAlignment::Bottom);
+
+        // The top of the block is at the origin for top alignment, and the bottom for bottom alignment
+        assert!((top.lines[0].baseline.1 + metrics.ascender).abs() < 0.01);
+        assert!((bottom.lines[1].baseline.1 + metrics.descender).abs() < 0.01);
+
+        // Middle alignment is half-way between the two
+        assert!((middle.lines[0].baseline.1 - (top.lines[0].baseline.1 + top.height/2.0)).abs() < 0.01);
+        assert!((bottom.lines[0].baseline.1 - (top.lines[0].baseline.1 + top.height)).abs() < 0.01);
+    }
+}
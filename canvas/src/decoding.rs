@@ -16,9 +16,13 @@ use futures::task::{Poll};
 use itertools::*;
 use uuid::*;
 
+use std::io;
+use std::fmt;
 use std::mem;
+use std::pin::*;
 use std::str::*;
 use std::sync::*;
+use std::io::{Read, BufReader};
 use std::result::Result;
 
 ///
@@ -427,9 +431,12 @@ enum DecoderState {
     TextureOp(DecodeTextureId),                                         // 'B<id>' (id, op)
     TextureOpCreate(TextureId, String),                                 // 'B<id>N' (w, h, format)
     TextureOpSetBytes(TextureId, String, DecodeBytes),                  // 'B<id>D' (x, y, w, h, bytes)
+    TextureOpSetFromEncodedImage(TextureId, DecodeBytes),               // 'B<id>E' (bytes)
     TextureOpSetFromSprite(TextureId, DecodeSpriteId, String),          // 'B<id>S' (sprite, x, y, w, h)
     TextureOpCreateDynamicSprite(TextureId, DecodeSpriteId, String),    // 'B<id>s' (sprite, x, y, w1, h1, w2, h2)
     TextureOpFillTransparency(TextureId, String),                       // 'B<id>t' (alpha)
+    TextureOpSetWrapMode(TextureId),                                    // 'B<id>W' (wrap mode)
+    TextureOpSetMipMaps(TextureId),                                     // 'B<id>M' (enabled)
     TextureOpCopy(TextureId, DecodeTextureId),                          // 'B<id>C' (texture)
     TextureOpFilter(TextureId, String),                                 // 'B<id>F' (filter)
 
@@ -565,9 +572,12 @@ impl CanvasDecoder {
             TextureOp(texture_id)                                   => Self::decode_texture_op(next_chr, texture_id)?,
             TextureOpCreate(texture_id, param)                      => Self::decode_texture_create(next_chr, texture_id, param)?,
             TextureOpSetBytes(texture_id, param, bytes)             => Self::decode_texture_set_bytes(next_chr, texture_id, param, bytes)?,
+            TextureOpSetFromEncodedImage(texture_id, bytes)         => Self::decode_texture_set_from_encoded_image(next_chr, texture_id, bytes)?,
             TextureOpSetFromSprite(texture_id, sprite, param)       => Self::decode_texture_set_from_sprite(next_chr, texture_id, sprite, param)?,
             TextureOpCreateDynamicSprite(texture_id, sprite, param) => Self::decode_texture_create_dynamic_sprite(next_chr, texture_id, sprite, param)?,
             TextureOpFillTransparency(texture_id, param)            => Self::decode_texture_fill_transparency(next_chr, texture_id, param)?,
+            TextureOpSetWrapMode(texture_id)                        => Self::decode_texture_set_wrap_mode(next_chr, texture_id)?,
+            TextureOpSetMipMaps(texture_id)                         => Self::decode_texture_set_mipmaps(next_chr, texture_id)?,
             TextureOpCopy(texture_id, param)                        => Self::decode_texture_copy(next_chr, texture_id, param)?,
             TextureOpFilter(texture_id, param)                      => Self::decode_texture_filter(next_chr, texture_id, param)?,
 
@@ -1450,6 +1460,7 @@ impl CanvasDecoder {
             Some('l')   => Ok(TextAlignment::Left),
             Some('r')   => Ok(TextAlignment::Right),
             Some('c')   => Ok(TextAlignment::Center),
+            Some('j')   => Ok(TextAlignment::Justify),
             Some(other) => Err(DecoderError::InvalidCharacter(other)),
             None        => Err(DecoderError::NotReady)
         }?;
@@ -1580,9 +1591,12 @@ impl CanvasDecoder {
             'N' => Ok((DecoderState::TextureOpCreate(texture_id, String::new()), None)),
             'X' => Ok((DecoderState::None, Some(Draw::Texture(texture_id, TextureOp::Free)))),
             'D' => Ok((DecoderState::TextureOpSetBytes(texture_id, String::new(), DecodeBytes::new()), None)),
+            'E' => Ok((DecoderState::TextureOpSetFromEncodedImage(texture_id, DecodeBytes::new()), None)),
             'S' => Ok((DecoderState::TextureOpSetFromSprite(texture_id, DecodeSpriteId::new(), String::new()), None)),
             's' => Ok((DecoderState::TextureOpCreateDynamicSprite(texture_id, DecodeSpriteId::new(), String::new()), None)),
             't' => Ok((DecoderState::TextureOpFillTransparency(texture_id, String::new()), None)),
+            'W' => Ok((DecoderState::TextureOpSetWrapMode(texture_id), None)),
+            'M' => Ok((DecoderState::TextureOpSetMipMaps(texture_id), None)),
             'C' => Ok((DecoderState::TextureOpCopy(texture_id, DecodeTextureId::new()), None)),
             'F' => Ok((DecoderState::TextureOpFilter(texture_id, String::new()), None)),
 
@@ -1609,6 +1623,7 @@ impl CanvasDecoder {
 
         let format      = match chars.next() {
             Some('r')   => TextureFormat::Rgba,
+            Some('p')   => TextureFormat::RgbaPremultiplied,
             Some(c)     => { return Err(DecoderError::InvalidCharacter(c)); }
             None        => { return Err(DecoderError::NotReady); }
         };
@@ -1643,6 +1658,19 @@ impl CanvasDecoder {
         Ok((DecoderState::None, Some(Draw::Texture(texture_id, TextureOp::SetBytes(TexturePosition(x, y), TextureSize(w, h), Arc::new(bytes.to_bytes()?))))))
     }
 
+    ///
+    /// Decodes a texture 'set from encoded image' operation
+    ///
+    fn decode_texture_set_from_encoded_image(chr: char, texture_id: TextureId, bytes: DecodeBytes) -> Result<(DecoderState, Option<Draw>), DecoderError> {
+        let bytes = bytes.decode(chr)?;
+
+        if bytes.ready() {
+            Ok((DecoderState::None, Some(Draw::Texture(texture_id, TextureOp::SetFromEncodedImage(Arc::new(bytes.to_bytes()?))))))
+        } else {
+            Ok((DecoderState::TextureOpSetFromEncodedImage(texture_id, bytes), None))
+        }
+    }
+
     ///
     /// Decodes a texture 'set from sprite' operation
     ///
@@ -1730,6 +1758,33 @@ impl CanvasDecoder {
         Ok((DecoderState::None, Some(Draw::Texture(texture_id, TextureOp::FillTransparency(alpha)))))
     }
 
+    ///
+    /// Decodes a texture 'set wrap mode'
+    ///
+    fn decode_texture_set_wrap_mode(chr: char, texture_id: TextureId) -> Result<(DecoderState, Option<Draw>), DecoderError> {
+        let wrap_mode = match chr {
+            'c' => TextureWrapMode::Clamp,
+            'r' => TextureWrapMode::Repeat,
+            'm' => TextureWrapMode::MirrorRepeat,
+            _   => { return Err(DecoderError::InvalidCharacter(chr)); }
+        };
+
+        Ok((DecoderState::None, Some(Draw::Texture(texture_id, TextureOp::SetWrapMode(wrap_mode)))))
+    }
+
+    ///
+    /// Decodes a texture 'set mipmaps'
+    ///
+    fn decode_texture_set_mipmaps(chr: char, texture_id: TextureId) -> Result<(DecoderState, Option<Draw>), DecoderError> {
+        let enabled = match chr {
+            'y' => true,
+            'n' => false,
+            _   => { return Err(DecoderError::InvalidCharacter(chr)); }
+        };
+
+        Ok((DecoderState::None, Some(Draw::Texture(texture_id, TextureOp::SetMipMaps(enabled)))))
+    }
+
     ///
     /// Decodes a texture copy
     ///
@@ -2096,6 +2151,290 @@ pub fn decode_drawing_stream<In: Unpin+Stream<Item=Result<char, E>>, E>(source:
     })
 }
 
+///
+/// The location of an error in a source being decoded by `decode_drawing_reader()` or `decode_drawing_async_reader()`
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecoderErrorLocation {
+    /// The offset in bytes from the start of the source of the character that caused the error
+    pub byte_offset: u64,
+
+    /// The line that the error is on (the first line is line 1)
+    pub line: u64,
+}
+
+///
+/// Error from decoding a drawing read from a byte source
+///
+#[derive(Debug)]
+pub enum ReadDecoderError {
+    /// An instruction could not be decoded
+    Decoder(DecoderError, DecoderErrorLocation),
+
+    /// The source contained a byte sequence that was not valid UTF-8
+    InvalidUtf8(DecoderErrorLocation),
+
+    /// The source could not be read
+    Io(io::Error),
+}
+
+impl fmt::Display for ReadDecoderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadDecoderError::Decoder(err, location)    => write!(f, "could not decode instruction at line {} (byte {}): {:?}", location.line, location.byte_offset, err),
+            ReadDecoderError::InvalidUtf8(location)     => write!(f, "invalid UTF-8 at line {} (byte {})", location.line, location.byte_offset),
+            ReadDecoderError::Io(err)                   => write!(f, "could not read drawing: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ReadDecoderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadDecoderError::Io(err)   => Some(err),
+            _                           => None,
+        }
+    }
+}
+
+///
+/// Decodes the bytes from a reader into drawing instructions, tracking where in the source each character came from
+///
+struct LocatedDecoder {
+    /// The decoder for the drawing instructions
+    decoder: CanvasDecoder,
+
+    /// The bytes of the UTF-8 character that is currently being read
+    utf8_bytes: [u8; 4],
+
+    /// The number of bytes in utf8_bytes
+    utf8_len: usize,
+
+    /// The number of bytes in the character that is being read
+    utf8_expected: usize,
+
+    /// The offset of the next byte to be read
+    byte_offset: u64,
+
+    /// The offset of the first byte of the character that is being read
+    char_offset: u64,
+
+    /// The line that is being read
+    line: u64,
+
+    /// After an error, characters are skipped until the start of the next line
+    skip_to_newline: bool,
+}
+
+impl LocatedDecoder {
+    ///
+    /// Creates a new decoder, positioned at the start of a source
+    ///
+    fn new() -> LocatedDecoder {
+        LocatedDecoder {
+            decoder:            CanvasDecoder::new(),
+            utf8_bytes:         [0; 4],
+            utf8_len:           0,
+            utf8_expected:      0,
+            byte_offset:        0,
+            char_offset:        0,
+            line:               1,
+            skip_to_newline:    false,
+        }
+    }
+
+    ///
+    /// The location of the character that is currently being decoded
+    ///
+    fn location(&self) -> DecoderErrorLocation {
+        DecoderErrorLocation {
+            byte_offset:    self.char_offset,
+            line:           self.line,
+        }
+    }
+
+    ///
+    /// Resets the decoder after an error so that decoding resumes with the instruction on the next line
+    ///
+    fn recover(&mut self, at_newline: bool) {
+        self.decoder            = CanvasDecoder::new();
+        self.skip_to_newline    = !at_newline;
+    }
+
+    ///
+    /// Decodes the next byte from the source, returning a result if it completes an instruction or causes an error
+    ///
+    fn decode_byte(&mut self, byte: u8) -> Option<Result<Draw, ReadDecoderError>> {
+        let offset = self.byte_offset;
+        self.byte_offset += 1;
+
+        if self.utf8_len > 0 && (byte & 0xc0) != 0x80 {
+            // The current character ended early: report an error and then treat this byte as the start of a new character
+            self.utf8_len = 0;
+            let location = self.location();
+            self.recover(false);
+
+            self.char_offset = offset;
+            if byte == b'\n' {
+                self.line               += 1;
+                self.skip_to_newline    = false;
+            }
+
+            return Some(Err(ReadDecoderError::InvalidUtf8(location)));
+        }
+
+        if self.utf8_len == 0 {
+            // Start of a new character
+            self.char_offset    = offset;
+            self.utf8_expected  = match byte {
+                0x00..=0x7f => { return self.decode_char(byte as char); }
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _           => {
+                    let location = self.location();
+                    self.recover(false);
+                    return Some(Err(ReadDecoderError::InvalidUtf8(location)));
+                }
+            };
+        }
+
+        // Part of a multi-byte character
+        self.utf8_bytes[self.utf8_len] = byte;
+        self.utf8_len += 1;
+
+        if self.utf8_len < self.utf8_expected {
+            return None;
+        }
+
+        let chr = from_utf8(&self.utf8_bytes[0..self.utf8_len]).ok().and_then(|chr| chr.chars().next());
+        self.utf8_len = 0;
+
+        match chr {
+            Some(chr)   => self.decode_char(chr),
+            None        => {
+                let location = self.location();
+                self.recover(false);
+                Some(Err(ReadDecoderError::InvalidUtf8(location)))
+            }
+        }
+    }
+
+    ///
+    /// Called at the end of the source, returning an error if it ended part way through a UTF-8 character
+    ///
+    fn finish(&mut self) -> Option<Result<Draw, ReadDecoderError>> {
+        if self.utf8_len > 0 {
+            self.utf8_len = 0;
+            Some(Err(ReadDecoderError::InvalidUtf8(self.location())))
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// Decodes a character from the source
+    ///
+    fn decode_char(&mut self, chr: char) -> Option<Result<Draw, ReadDecoderError>> {
+        let location = self.location();
+
+        if chr == '\n' {
+            self.line += 1;
+        }
+
+        if self.skip_to_newline {
+            self.skip_to_newline = chr != '\n';
+            return None;
+        }
+
+        match self.decoder.decode(chr) {
+            Ok(draw)    => draw.map(Ok),
+            Err(err)    => {
+                self.recover(chr == '\n');
+                Some(Err(ReadDecoderError::Decoder(err, location)))
+            }
+        }
+    }
+}
+
+///
+/// Decodes a canvas drawing read from a source of UTF-8 bytes, such as a file
+///
+/// Instructions are returned as they are decoded, so the whole drawing is never held in memory at once. When an instruction
+/// can't be decoded, the error and its location are returned, and then decoding resumes at the start of the next line (the
+/// encoding for a `Vec<Draw>` puts each instruction on its own line). Reading stops after an error from the source itself.
+///
+pub fn decode_drawing_reader<In: Read>(source: In) -> impl Iterator<Item=Result<Draw, ReadDecoderError>> {
+    let mut bytes       = BufReader::new(source).bytes();
+    let mut decoder     = LocatedDecoder::new();
+    let mut finished    = false;
+
+    std::iter::from_fn(move || {
+        while !finished {
+            match bytes.next() {
+                None            => { finished = true; return decoder.finish(); }
+                Some(Err(err))  => { finished = true; return Some(Err(ReadDecoderError::Io(err))); }
+                Some(Ok(byte))  => {
+                    if let Some(result) = decoder.decode_byte(byte) {
+                        return Some(result);
+                    }
+                }
+            }
+        }
+
+        None
+    })
+}
+
+///
+/// Decodes a canvas drawing read from an asynchronous source of UTF-8 bytes
+///
+/// This works in the same way as `decode_drawing_reader()`: instructions are returned as they are decoded, and decoding
+/// resumes on the next line after an instruction that can't be decoded.
+///
+pub fn decode_drawing_async_reader<In: Unpin+AsyncRead>(source: In) -> impl Unpin+Stream<Item=Result<Draw, ReadDecoderError>> {
+    let mut source      = ::futures::io::BufReader::new(source);
+    let mut decoder     = LocatedDecoder::new();
+    let mut finished    = false;
+
+    stream::poll_fn(move |context| {
+        while !finished {
+            let buffer = match Pin::new(&mut source).poll_fill_buf(context) {
+                Poll::Pending                                                   => { return Poll::Pending; }
+                Poll::Ready(Err(err)) if err.kind() == io::ErrorKind::Interrupted => { continue; }
+                Poll::Ready(Err(err))                                           => { finished = true; return Poll::Ready(Some(Err(ReadDecoderError::Io(err)))); }
+                Poll::Ready(Ok(buffer))                                         => buffer,
+            };
+
+            if buffer.is_empty() {
+                finished = true;
+                return Poll::Ready(decoder.finish());
+            }
+
+            // Decode bytes from the buffer until one produces a result
+            let mut consumed    = 0;
+            let mut result      = None;
+
+            for byte in buffer.iter() {
+                consumed += 1;
+                result = decoder.decode_byte(*byte);
+
+                if result.is_some() {
+                    break;
+                }
+            }
+
+            Pin::new(&mut source).consume(consumed);
+
+            if result.is_some() {
+                return Poll::Ready(result);
+            }
+        }
+
+        Poll::Ready(None)
+    })
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -2415,6 +2754,7 @@ mod test {
     #[test]
     fn decode_begin_line_layout() {
         check_round_trip_single(Draw::BeginLineLayout(1.0, 2.0, TextAlignment::Center));
+        check_round_trip_single(Draw::BeginLineLayout(1.0, 2.0, TextAlignment::Justify));
     }
 
     #[test]
@@ -2456,6 +2796,7 @@ mod test {
     #[test]
     fn decode_create_texture() {
         check_round_trip_single(Draw::Texture(TextureId(42), TextureOp::Create(TextureSize(100, 200), TextureFormat::Rgba)));
+        check_round_trip_single(Draw::Texture(TextureId(42), TextureOp::Create(TextureSize(100, 200), TextureFormat::RgbaPremultiplied)));
     }
 
     #[test]
@@ -2468,6 +2809,11 @@ mod test {
         check_round_trip_single(Draw::Texture(TextureId(44), TextureOp::SetBytes(TexturePosition(100, 200), TextureSize(300, 400), Arc::new(vec![240, 230, 220, 210, 200, 190]))));
     }
 
+    #[test]
+    fn decode_texture_set_from_encoded_image() {
+        check_round_trip_single(Draw::Texture(TextureId(44), TextureOp::SetFromEncodedImage(Arc::new(vec![0x89, b'P', b'N', b'G', 13, 10, 26, 10]))));
+    }
+
     #[test]
     fn decode_texture_set_from_sprite() {
         check_round_trip_single(Draw::Texture(TextureId(44), TextureOp::SetFromSprite(SpriteId(42), SpriteBounds(SpritePosition(20.0, 30.0), SpriteSize(40.0, 50.0)))));
@@ -2483,6 +2829,15 @@ mod test {
         check_round_trip_single(Draw::Texture(TextureId(45), TextureOp::FillTransparency(0.75)));
     }
 
+    #[test]
+    fn decode_texture_wrap_mode() {
+        check_round_trip_single(Draw::Texture(TextureId(45), TextureOp::SetWrapMode(TextureWrapMode::Clamp)));
+        check_round_trip_single(Draw::Texture(TextureId(45), TextureOp::SetWrapMode(TextureWrapMode::Repeat)));
+        check_round_trip_single(Draw::Texture(TextureId(45), TextureOp::SetWrapMode(TextureWrapMode::MirrorRepeat)));
+        check_round_trip_single(Draw::Texture(TextureId(45), TextureOp::SetMipMaps(true)));
+        check_round_trip_single(Draw::Texture(TextureId(45), TextureOp::SetMipMaps(false)));
+    }
+
     #[test]
     fn decode_gradient_new() {
         check_round_trip_single(Draw::Gradient(GradientId(42), GradientOp::Create(Color::Rgba(0.1, 0.2, 0.3, 0.4))));
@@ -2695,4 +3050,89 @@ mod test {
             assert!(all == decoded);
         });
     }
+
+    #[test]
+    fn decode_from_reader() {
+        let drawing = vec![
+            Draw::ClearCanvas(Color::Rgba(0.0, 0.0, 0.0, 1.0)),
+            Draw::Path(PathOp::NewPath),
+            Draw::Path(PathOp::Move(10.0, 20.0)),
+            Draw::Path(PathOp::Line(30.0, 40.0)),
+            Draw::Fill,
+        ];
+        let mut encoded = String::new();
+        drawing.encode_canvas(&mut encoded);
+
+        let decoded = decode_drawing_reader(encoded.as_bytes()).map(|draw| draw.unwrap()).collect::<Vec<_>>();
+        assert!(decoded == drawing);
+    }
+
+    #[test]
+    fn decode_from_async_reader() {
+        let drawing = vec![
+            Draw::ClearCanvas(Color::Rgba(0.0, 0.0, 0.0, 1.0)),
+            Draw::Path(PathOp::Move(10.0, 20.0)),
+            Draw::Stroke,
+        ];
+        let mut encoded = String::new();
+        drawing.encode_canvas(&mut encoded);
+
+        executor::block_on(async {
+            let decoded = decode_drawing_async_reader(encoded.as_bytes()).map(|draw| draw.unwrap()).collect::<Vec<_>>().await;
+            assert!(decoded == drawing);
+        });
+    }
+
+    #[test]
+    fn reader_reports_error_location_and_resumes() {
+        let decoded = decode_drawing_reader("F\nS\n  Nq12345\nF\n".as_bytes()).collect::<Vec<_>>();
+
+        assert!(decoded.len() == 4);
+        assert!(matches!(decoded[0], Ok(Draw::Fill)));
+        assert!(matches!(decoded[1], Ok(Draw::Stroke)));
+        assert!(matches!(decoded[2], Err(ReadDecoderError::Decoder(DecoderError::InvalidCharacter('q'), DecoderErrorLocation { byte_offset: 7, line: 3 }))));
+        assert!(matches!(decoded[3], Ok(Draw::Fill)));
+    }
+
+    #[test]
+    fn reader_reports_invalid_utf8() {
+        let decoded = decode_drawing_reader(&[b'F', b'\n', 0xc3, b'\n', b'S'][..]).collect::<Vec<_>>();
+
+        assert!(decoded.len() == 3);
+        assert!(matches!(decoded[0], Ok(Draw::Fill)));
+        assert!(matches!(decoded[1], Err(ReadDecoderError::InvalidUtf8(DecoderErrorLocation { byte_offset: 2, line: 2 }))));
+        assert!(matches!(decoded[2], Ok(Draw::Stroke)));
+    }
+
+    #[test]
+    fn reader_reports_utf8_truncated_at_end() {
+        let decoded = decode_drawing_reader(&[b'F', b'\n', 0xe2, 0x82][..]).collect::<Vec<_>>();
+
+        assert!(decoded.len() == 2);
+        assert!(matches!(decoded[0], Ok(Draw::Fill)));
+        assert!(matches!(decoded[1], Err(ReadDecoderError::InvalidUtf8(DecoderErrorLocation { byte_offset: 2, line: 2 }))));
+    }
+
+    #[test]
+    fn async_reader_reports_utf8_truncated_at_end() {
+        executor::block_on(async {
+            let decoded = decode_drawing_async_reader(&[b'F', b'\n', 0xe2, 0x82][..]).collect::<Vec<_>>().await;
+
+            assert!(decoded.len() == 2);
+            assert!(matches!(decoded[0], Ok(Draw::Fill)));
+            assert!(matches!(decoded[1], Err(ReadDecoderError::InvalidUtf8(DecoderErrorLocation { byte_offset: 2, line: 2 }))));
+        });
+    }
+
+    #[test]
+    fn read_decoder_error_display() {
+        let location = DecoderErrorLocation { byte_offset: 7, line: 3 };
+
+        assert!(ReadDecoderError::InvalidUtf8(location).to_string() == "invalid UTF-8 at line 3 (byte 7)");
+        assert!(ReadDecoderError::Decoder(DecoderError::InvalidCharacter('q'), location).to_string() == "could not decode instruction at line 3 (byte 7): InvalidCharacter('q')");
+
+        let io_error = ReadDecoderError::Io(io::Error::other("disk on fire"));
+        assert!(io_error.to_string() == "could not read drawing: disk on fire");
+        assert!(std::error::Error::source(&io_error).is_some());
+    }
 }
@@ -76,7 +76,7 @@ pub enum WindingRule {
 /// If a layer is cleared, other entities (such as sprites) are not affected, whereas `ClearCanvas` will
 /// remove all entities from the canvas.
 ///
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct LayerId(pub u64);
 
 ///
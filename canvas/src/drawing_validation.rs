@@ -0,0 +1,429 @@
+use crate::draw::*;
+use crate::path::*;
+use crate::font::*;
+use crate::color::*;
+use crate::sprite::*;
+use crate::texture::*;
+use crate::gradient::*;
+use crate::namespace::*;
+use crate::transform2d::*;
+
+use std::collections::{HashMap, HashSet};
+
+///
+/// Describes a change made to a drawing by `validate_drawing_with_report()`
+///
+#[derive(Clone, PartialEq, Debug)]
+pub enum DrawingFix {
+    /// An instruction with a NaN or infinite value was removed
+    RemovedNonFiniteValue(Draw),
+
+    /// A `CanvasHeight` of zero or less was replaced with a height of 1
+    ClampedCanvasHeight(f32),
+
+    /// A `PopState` with no matching `PushState` was removed
+    RemovedUnbalancedPopState,
+
+    /// An instruction that would make a sprite draw itself (directly or via other sprites) was removed
+    RemovedRecursiveSprite(SpriteId),
+}
+
+///
+/// Tracks the state needed to validate a drawing
+///
+struct DrawingValidator {
+    /// The number of `PushState` instructions that have not been popped yet
+    state_depth: usize,
+
+    /// The current namespace ID
+    namespace_id: usize,
+
+    /// The sprite that is being drawn, if any
+    current_sprite: Option<(usize, SpriteId)>,
+
+    /// The sprites drawn by each sprite
+    sprite_contents: HashMap<(usize, SpriteId), HashSet<(usize, SpriteId)>>,
+}
+
+///
+/// Passes through a drawing, removing or clamping any instructions that might cause a renderer to fail
+///
+/// See `validate_drawing_with_report()` for the checks that are performed.
+///
+pub fn validate_drawing(drawing: impl IntoIterator<Item=Draw>) -> impl Iterator<Item=Draw> {
+    validate_drawing_with_report(drawing, |_| { })
+}
+
+///
+/// Passes through a drawing, removing or clamping any instructions that might cause a renderer to fail, and reporting each
+/// change that was made to a callback
+///
+/// Instructions with NaN or infinite values are removed, as are `PopState` instructions with no matching `PushState` and any
+/// `DrawSprite` that would make a sprite draw itself. Zero and negative canvas heights are clamped to 1. This is intended for
+/// use with drawings from untrusted sources, such as a network connection.
+///
+pub fn validate_drawing_with_report(drawing: impl IntoIterator<Item=Draw>, report: impl FnMut(DrawingFix)) -> impl Iterator<Item=Draw> {
+    let mut report      = report;
+    let mut validator   = DrawingValidator {
+        state_depth:        0,
+        namespace_id:       NamespaceId::default().local_id(),
+        current_sprite:     None,
+        sprite_contents:    HashMap::new(),
+    };
+
+    drawing.into_iter()
+        .filter_map(move |draw| validator.validate(draw, &mut report))
+}
+
+impl DrawingValidator {
+    ///
+    /// Validates a single instruction, returning the instruction to pass on (if any)
+    ///
+    fn validate(&mut self, draw: Draw, report: &mut impl FnMut(DrawingFix)) -> Option<Draw> {
+        if !is_finite(&draw) {
+            report(DrawingFix::RemovedNonFiniteValue(draw));
+            return None;
+        }
+
+        match draw {
+            Draw::CanvasHeight(height) if height <= 0.0 => {
+                report(DrawingFix::ClampedCanvasHeight(height));
+                Some(Draw::CanvasHeight(1.0))
+            }
+
+            Draw::PushState => {
+                self.state_depth += 1;
+                Some(draw)
+            }
+
+            Draw::PopState => {
+                if self.state_depth == 0 {
+                    report(DrawingFix::RemovedUnbalancedPopState);
+                    None
+                } else {
+                    self.state_depth -= 1;
+                    Some(draw)
+                }
+            }
+
+            Draw::ClearCanvas(_) => {
+                self.namespace_id   = NamespaceId::default().local_id();
+                self.current_sprite = None;
+                self.sprite_contents.clear();
+                Some(draw)
+            }
+
+            Draw::Namespace(namespace_id) => {
+                self.namespace_id = namespace_id.local_id();
+                Some(draw)
+            }
+
+            Draw::Layer(_) => {
+                self.current_sprite = None;
+                Some(draw)
+            }
+
+            Draw::Sprite(sprite_id) => {
+                self.current_sprite = Some((self.namespace_id, sprite_id));
+                Some(draw)
+            }
+
+            Draw::ClearSprite => {
+                if let Some(current_sprite) = self.current_sprite {
+                    self.sprite_contents.remove(&current_sprite);
+                }
+                Some(draw)
+            }
+
+            Draw::MoveSpriteFrom(sprite_id) => {
+                let source_sprite = (self.namespace_id, sprite_id);
+
+                if let Some(current_sprite) = self.current_sprite {
+                    if self.sprite_draws(source_sprite, current_sprite) {
+                        report(DrawingFix::RemovedRecursiveSprite(sprite_id));
+                        return None;
+                    }
+
+                    let contents = self.sprite_contents.remove(&source_sprite).unwrap_or_default();
+                    self.sprite_contents.insert(current_sprite, contents);
+                }
+
+                Some(draw)
+            }
+
+            Draw::DrawSprite(sprite_id) | Draw::DrawSpriteWithFilters(sprite_id, _) => {
+                let drawn_sprite = (self.namespace_id, sprite_id);
+
+                if let Some(current_sprite) = self.current_sprite {
+                    if self.sprite_draws(drawn_sprite, current_sprite) {
+                        report(DrawingFix::RemovedRecursiveSprite(sprite_id));
+                        return None;
+                    }
+
+                    self.sprite_contents.entry(current_sprite).or_default().insert(drawn_sprite);
+                }
+
+                Some(draw)
+            }
+
+            _ => Some(draw)
+        }
+    }
+
+    ///
+    /// True if drawing the `from` sprite will also draw the `to` sprite (or if they are the same sprite)
+    ///
+    fn sprite_draws(&self, from: (usize, SpriteId), to: (usize, SpriteId)) -> bool {
+        let mut visited     = HashSet::new();
+        let mut to_visit    = vec![from];
+
+        while let Some(sprite) = to_visit.pop() {
+            if sprite == to {
+                return true;
+            }
+
+            if visited.insert(sprite) {
+                if let Some(contents) = self.sprite_contents.get(&sprite) {
+                    to_visit.extend(contents.iter().cloned());
+                }
+            }
+        }
+
+        false
+    }
+}
+
+///
+/// True if all of the values in a set of floats are finite
+///
+#[inline]
+fn all_finite(values: &[f32]) -> bool {
+    values.iter().all(|value| value.is_finite())
+}
+
+#[inline]
+fn transform_is_finite(Transform2D(matrix): &Transform2D) -> bool {
+    matrix.iter().all(|row| all_finite(row))
+}
+
+#[inline]
+fn color_is_finite(color: &Color) -> bool {
+    match color {
+        Color::Rgba(r, g, b, a)     => all_finite(&[*r, *g, *b, *a]),
+        Color::Hsluv(h, s, l, a)    => all_finite(&[*h, *s, *l, *a]),
+        Color::LinearRgba(r, g, b, a) => all_finite(&[*r, *g, *b, *a]),
+        Color::Hsl(h, s, l, a)      => all_finite(&[*h, *s, *l, *a]),
+    }
+}
+
+#[inline]
+fn sprite_transform_is_finite(transform: &SpriteTransform) -> bool {
+    match transform {
+        SpriteTransform::Identity               => true,
+        SpriteTransform::Translate(x, y)        => all_finite(&[*x, *y]),
+        SpriteTransform::Scale(x, y)            => all_finite(&[*x, *y]),
+        SpriteTransform::Rotate(angle)          => angle.is_finite(),
+        SpriteTransform::Transform2D(transform) => transform_is_finite(transform),
+    }
+}
+
+#[inline]
+fn filter_is_finite(filter: &TextureFilter) -> bool {
+    match filter {
+        TextureFilter::GaussianBlur(radius)                 => radius.is_finite(),
+        TextureFilter::AlphaBlend(alpha)                    => alpha.is_finite(),
+        TextureFilter::Mask(_)                              => true,
+        TextureFilter::DisplacementMap(_, x_off, y_off)     => all_finite(&[*x_off, *y_off]),
+    }
+}
+
+///
+/// True if none of the values in a drawing instruction are NaN or infinite
+///
+fn is_finite(draw: &Draw) -> bool {
+    use self::Draw::*;
+
+    match draw {
+        Path(PathOp::Move(x, y))                                    => all_finite(&[*x, *y]),
+        Path(PathOp::Line(x, y))                                    => all_finite(&[*x, *y]),
+        Path(PathOp::BezierCurve(((x1, y1), (x2, y2)), (x3, y3)))   => all_finite(&[*x1, *y1, *x2, *y2, *x3, *y3]),
+
+        LineWidth(width)                                            => width.is_finite(),
+        LineWidthPixels(width)                                      => width.is_finite(),
+        DashLength(length)                                          => length.is_finite(),
+        DashOffset(offset)                                          => offset.is_finite(),
+        FillColor(color)                                            => color_is_finite(color),
+        StrokeColor(color)                                          => color_is_finite(color),
+        FillTexture(_, (x1, y1), (x2, y2))                          => all_finite(&[*x1, *y1, *x2, *y2]),
+        FillGradient(_, (x1, y1), (x2, y2))                         => all_finite(&[*x1, *y1, *x2, *y2]),
+        FillTransform(transform)                                    => transform_is_finite(transform),
+        CanvasHeight(height)                                        => height.is_finite(),
+        CenterRegion((x1, y1), (x2, y2))                            => all_finite(&[*x1, *y1, *x2, *y2]),
+        MultiplyTransform(transform)                                => transform_is_finite(transform),
+        ClearCanvas(color)                                          => color_is_finite(color),
+        LayerAlpha(_, alpha)                                        => alpha.is_finite(),
+
+        SpriteTransform(transform)                                  => sprite_transform_is_finite(transform),
+        DrawSpriteWithFilters(_, filters)                           => filters.iter().all(filter_is_finite),
+
+        Texture(_, TextureOp::SetFromSprite(_, SpriteBounds(SpritePosition(x, y), SpriteSize(w, h))))   => all_finite(&[*x, *y, *w, *h]),
+        Texture(_, TextureOp::CreateDynamicSprite(_, SpriteBounds(SpritePosition(x, y), SpriteSize(w, h)), CanvasSize(canvas_w, canvas_h)))
+                                                                    => all_finite(&[*x, *y, *w, *h, *canvas_w, *canvas_h]),
+        Texture(_, TextureOp::FillTransparency(alpha))              => alpha.is_finite(),
+        Texture(_, TextureOp::Filter(filter))                       => filter_is_finite(filter),
+
+        Font(_, FontOp::FontSize(size))                             => size.is_finite(),
+        Font(_, FontOp::DrawGlyphs(glyphs))                         => glyphs.iter().all(|glyph| all_finite(&[glyph.location.0, glyph.location.1, glyph.em_size])),
+        BeginLineLayout(x, y, _)                                    => all_finite(&[*x, *y]),
+        DrawText(_, _, x, y)                                        => all_finite(&[*x, *y]),
+
+        Gradient(_, GradientOp::Create(color))                      => color_is_finite(color),
+        Gradient(_, GradientOp::AddStop(pos, color))                => pos.is_finite() && color_is_finite(color),
+
+        _                                                           => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn removes_nan_coordinates() {
+        let drawing = vec![
+            Draw::Path(PathOp::NewPath),
+            Draw::Path(PathOp::Move(0.0, 0.0)),
+            Draw::Path(PathOp::Line(f32::NAN, 10.0)),
+            Draw::Path(PathOp::Line(10.0, 10.0)),
+            Draw::Fill,
+        ];
+
+        let mut fixes   = vec![];
+        let validated   = validate_drawing_with_report(drawing, |fix| fixes.push(fix)).collect::<Vec<_>>();
+
+        assert!(validated == vec![
+            Draw::Path(PathOp::NewPath),
+            Draw::Path(PathOp::Move(0.0, 0.0)),
+            Draw::Path(PathOp::Line(10.0, 10.0)),
+            Draw::Fill,
+        ]);
+        assert!(fixes.len() == 1);
+    }
+
+    #[test]
+    fn clamps_canvas_height() {
+        let validated = validate_drawing(vec![Draw::CanvasHeight(-100.0), Draw::CanvasHeight(f32::INFINITY)]).collect::<Vec<_>>();
+
+        assert!(validated == vec![Draw::CanvasHeight(1.0)]);
+    }
+
+    #[test]
+    fn removes_unbalanced_pop_state() {
+        let validated = validate_drawing(vec![Draw::PushState, Draw::PopState, Draw::PopState]).collect::<Vec<_>>();
+
+        assert!(validated == vec![Draw::PushState, Draw::PopState]);
+    }
+
+    #[test]
+    fn removes_sprite_drawing_itself() {
+        let drawing = vec![
+            Draw::Sprite(SpriteId(1)),
+            Draw::DrawSprite(SpriteId(1)),
+            Draw::Layer(LayerId(0)),
+            Draw::DrawSprite(SpriteId(1)),
+        ];
+
+        let mut fixes   = vec![];
+        let validated   = validate_drawing_with_report(drawing, |fix| fixes.push(fix)).collect::<Vec<_>>();
+
+        assert!(validated == vec![Draw::Sprite(SpriteId(1)), Draw::Layer(LayerId(0)), Draw::DrawSprite(SpriteId(1))]);
+        assert!(fixes == vec![DrawingFix::RemovedRecursiveSprite(SpriteId(1))]);
+    }
+
+    #[test]
+    fn removes_indirect_sprite_recursion() {
+        let drawing = vec![
+            Draw::Sprite(SpriteId(1)),
+            Draw::DrawSprite(SpriteId(2)),
+            Draw::Sprite(SpriteId(2)),
+            Draw::DrawSprite(SpriteId(3)),
+            Draw::Sprite(SpriteId(3)),
+            Draw::DrawSprite(SpriteId(1)),
+        ];
+
+        let validated = validate_drawing(drawing).collect::<Vec<_>>();
+
+        assert!(validated.len() == 5);
+        assert!(validated.last() == Some(&Draw::Sprite(SpriteId(3))));
+    }
+
+    #[test]
+    fn sprites_in_other_namespaces_are_distinct() {
+        let drawing = vec![
+            Draw::Sprite(SpriteId(1)),
+            Draw::Namespace(NamespaceId::new()),
+            Draw::DrawSprite(SpriteId(1)),
+        ];
+
+        let validated = validate_drawing(drawing.clone()).collect::<Vec<_>>();
+
+        assert!(validated == drawing);
+    }
+
+    #[test]
+    fn fuzz_random_drawings() {
+        // Simple xorshift generator so the test is repeatable
+        let mut seed    = 0x2545f491u32;
+        let mut random  = move || { seed ^= seed << 13; seed ^= seed >> 17; seed ^= seed << 5; seed };
+
+        let values      = [0.0, 1.0, -1.0, 1e30, -1e30, f32::NAN, f32::INFINITY, f32::NEG_INFINITY];
+
+        for _ in 0..100 {
+            let mut drawing = vec![];
+
+            for _ in 0..200 {
+                let val     = values[(random() as usize) % values.len()];
+                let sprite  = SpriteId((random() % 4) as _);
+
+                let draw = match random() % 12 {
+                    0   => Draw::Path(PathOp::Move(val, 1.0)),
+                    1   => Draw::Path(PathOp::BezierCurve(((1.0, val), (2.0, 2.0)), (3.0, 3.0))),
+                    2   => Draw::CanvasHeight(val),
+                    3   => Draw::CenterRegion((0.0, 0.0), (val, val)),
+                    4   => Draw::PushState,
+                    5   => Draw::PopState,
+                    6   => Draw::Sprite(sprite),
+                    7   => Draw::DrawSprite(sprite),
+                    8   => Draw::Layer(LayerId(0)),
+                    9   => Draw::SpriteTransform(SpriteTransform::Scale(val, val)),
+                    10  => Draw::MultiplyTransform(Transform2D::scale(val, 1.0)),
+                    _   => Draw::LineWidth(val),
+                };
+
+                drawing.push(draw);
+            }
+
+            // The validated drawing should only have finite values, positive canvas heights and balanced state pops
+            let validated   = validate_drawing(drawing).collect::<Vec<_>>();
+            let mut depth   = 0i64;
+
+            for draw in validated.iter() {
+                assert!(is_finite(draw));
+
+                match draw {
+                    Draw::CanvasHeight(height)  => { assert!(*height > 0.0); }
+                    Draw::PushState             => { depth += 1; }
+                    Draw::PopState              => { depth -= 1; assert!(depth >= 0); }
+                    _                           => { }
+                }
+            }
+
+            // Validating a second time should change nothing
+            let mut fixes = vec![];
+            let revalidated = validate_drawing_with_report(validated.clone(), |fix| fixes.push(fix)).collect::<Vec<_>>();
+
+            assert!(fixes.is_empty(), "{:?}", fixes);
+            assert!(revalidated == validated);
+        }
+    }
+}
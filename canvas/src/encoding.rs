@@ -304,7 +304,20 @@ impl CanvasEncoding<String> for &TextureFormat {
         use self::TextureFormat::*;
 
         match self {
-            Rgba => 'r'.encode_canvas(append_to)
+            Rgba                => 'r'.encode_canvas(append_to),
+            RgbaPremultiplied   => 'p'.encode_canvas(append_to),
+        }
+    }
+}
+
+impl CanvasEncoding<String> for &TextureWrapMode {
+    fn encode_canvas(&self, append_to: &mut String) {
+        use self::TextureWrapMode::*;
+
+        match self {
+            Clamp           => 'c'.encode_canvas(append_to),
+            Repeat          => 'r'.encode_canvas(append_to),
+            MirrorRepeat    => 'm'.encode_canvas(append_to),
         }
     }
 }
@@ -317,9 +330,12 @@ impl<'a> CanvasEncoding<String> for &'a TextureOp {
             Create(TextureSize(width, height), format)                                      => ('N', *width, *height, format).encode_canvas(append_to), 
             Free                                                                            => ('X').encode_canvas(append_to),
             SetBytes(TexturePosition(x, y), TextureSize(width, height), bytes)              => ('D', *x, *y, *width, *height, &**bytes).encode_canvas(append_to),
+            SetFromEncodedImage(bytes)                                                      => ('E', &**bytes).encode_canvas(append_to),
             SetFromSprite(sprite_id, SpriteBounds(SpritePosition(x, y), SpriteSize(w, h)))  => ('S', sprite_id, *x, *y, *w, *h).encode_canvas(append_to),
             CreateDynamicSprite(sprite_id, SpriteBounds(SpritePosition(x, y), SpriteSize(sprite_w, sprite_h)), CanvasSize(canvas_w, canvas_h))  => ('s', sprite_id, (*x, *y, *sprite_w, *sprite_h), (*canvas_w, *canvas_h)).encode_canvas(append_to),
             FillTransparency(alpha)                                                         => ('t', *alpha).encode_canvas(append_to),
+            SetWrapMode(wrap_mode)                                                          => ('W', wrap_mode).encode_canvas(append_to),
+            SetMipMaps(enabled)                                                             => ('M', if *enabled { 'y' } else { 'n' }).encode_canvas(append_to),
             Copy(target_texture)                                                            => ('C', target_texture).encode_canvas(append_to),
             Filter(filter)                                                                  => ('F', filter).encode_canvas(append_to),
         }
@@ -379,6 +395,7 @@ impl<'a> CanvasEncoding<String> for &TextAlignment {
             Left    => { 'l'.encode_canvas(append_to); }
             Right   => { 'r'.encode_canvas(append_to); }
             Center  => { 'c'.encode_canvas(append_to); }
+            Justify => { 'j'.encode_canvas(append_to); }
         }
     }
 }
@@ -1,3 +1,5 @@
+use super::draw::*;
+use super::color::*;
 use super::font_face::*;
 
 use flo_curves::geo::*;
@@ -56,7 +58,26 @@ impl FontProperties {
 pub enum TextAlignment {
     Left,
     Right,
-    Center
+    Center,
+
+    /// Stretches the spaces between words so that each line fills the width of the text block (except for the last line in
+    /// a paragraph). Only has an effect where the text is wrapped to a width: single lines of text are aligned to the left.
+    Justify
+}
+
+///
+/// Determines how a block of text is positioned vertically relative to its alignment's origin point
+///
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum TextVerticalAlignment {
+    /// The top of the first line of text is at the origin
+    Top,
+
+    /// The block of text is centered on the origin
+    Middle,
+
+    /// The bottom of the last line of text is at the origin
+    Bottom
 }
 
 ///
@@ -148,6 +169,24 @@ pub struct TextLayoutMetrics {
     pub pos: Coord2
 }
 
+///
+/// The size of a string of text in a particular font, as returned by `measure_text_metrics()`
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TextMetrics {
+    /// The distance from the start of the text to the point where any following text would be placed
+    pub advance_width: f32,
+
+    /// The ascent of the font above the baseline
+    pub ascent: f32,
+
+    /// The descent of the font below the baseline (this is negative, matching `FontMetrics::descender`)
+    pub descent: f32,
+
+    /// The bounds of the glyph outlines as (min, max), relative to the start of the baseline (None if none of the glyphs have an outline)
+    pub bounding_box: Option<((f32, f32), (f32, f32))>,
+}
+
 ///
 /// ID for a glyph within a font
 ///
@@ -168,3 +207,35 @@ pub struct GlyphPosition {
     /// The number of canvas units that map to one em in font units
     pub em_size: f32
 }
+
+///
+/// A span of text with its own font, size and colour, for drawing styled text with `GraphicsPrimitives::draw_text_runs()`
+///
+#[derive(Clone, PartialEq, Debug)]
+pub struct TextRun {
+    /// The text in this run
+    pub text: String,
+
+    /// The font to lay out this run in
+    pub font_id: FontId,
+
+    /// The size of the font for this run
+    pub font_size: f32,
+
+    /// The colour to fill the glyphs in this run with
+    pub color: Color,
+}
+
+impl TextRun {
+    ///
+    /// Creates a new text run
+    ///
+    pub fn new(text: impl Into<String>, font_id: FontId, font_size: f32, color: Color) -> TextRun {
+        TextRun {
+            text:       text.into(),
+            font_id:    font_id,
+            font_size:  font_size,
+            color:      color,
+        }
+    }
+}
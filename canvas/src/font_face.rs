@@ -163,6 +163,45 @@ mod canvas_font_face {
         layout.measure()
     }
 
+    ///
+    /// Measures the advance width, ascent, descent and the bounds of the glyph outlines for some text in this font
+    ///
+    /// This lays out the text in the same way as it's laid out when it's drawn, so the measurements match the rendered text.
+    ///
+    #[cfg(feature = "outline-fonts")]
+    pub fn measure_text_metrics(font: &Arc<CanvasFontFace>, text: &str, em_size: f32) -> TextMetrics {
+        // Lay out the text to find the glyph positions
+        let mut layout      = CanvasFontLineLayout::new(font, em_size);
+        layout.add_text(text);
+
+        let advance_width   = layout.measure().pos.0 as f32;
+        let glyphs          = layout.to_glyphs();
+
+        // Ascent and descent come from the font
+        let ttf_font        = font.ttf_font();
+        let scale_factor    = em_size / (ttf_font.units_per_em() as f32);
+        let ascent          = (ttf_font.ascender() as f32) * scale_factor;
+        let descent         = (ttf_font.descender() as f32) * scale_factor;
+
+        // The bounding box is the union of the bounds of the glyphs at their laid out positions
+        let bounding_box    = glyphs.iter()
+            .flat_map(|glyph| {
+                let bounds  = ttf_font.glyph_bounding_box(ttf_parser::GlyphId(glyph.id.0 as _))?;
+                let (x, y)  = glyph.location;
+
+                Some(((x + (bounds.x_min as f32) * scale_factor, y + (bounds.y_min as f32) * scale_factor),
+                    (x + (bounds.x_max as f32) * scale_factor, y + (bounds.y_max as f32) * scale_factor)))
+            })
+            .fold(None, |total: Option<((f32, f32), (f32, f32))>, ((min_x, min_y), (max_x, max_y))| {
+                match total {
+                    None                                    => Some(((min_x, min_y), (max_x, max_y))),
+                    Some(((x1, y1), (x2, y2)))              => Some(((x1.min(min_x), y1.min(min_y)), (x2.max(max_x), y2.max(max_y)))),
+                }
+            });
+
+        TextMetrics { advance_width, ascent, descent, bounding_box }
+    }
+
     #[cfg(feature = "outline-fonts")]
     impl CanvasFontFace {
         ///
@@ -336,6 +375,37 @@ mod test {
         font.allsorts_font();
     }
 
+    #[cfg(feature = "outline-fonts")]
+    #[test]
+    fn measured_width_is_sum_of_glyph_advances() {
+        let font            = CanvasFontFace::from_slice(include_bytes!("../test_data/Lato-Regular.ttf"));
+        let metrics         = measure_text_metrics(&font, "Hello, world", 100.0);
+
+        // Lay out the same text and add up the advances of the glyphs
+        let mut layout      = crate::font_line_layout::CanvasFontLineLayout::new(&font, 100.0);
+        layout.add_text("Hello, world");
+        let glyphs          = layout.to_glyphs();
+
+        let ttf_font        = font.ttf_font();
+        let scale_factor    = 100.0 / (ttf_font.units_per_em() as f32);
+        let last_glyph      = glyphs.last().unwrap();
+        let last_advance    = ttf_font.glyph_hor_advance(ttf_parser::GlyphId(last_glyph.id.0 as _)).unwrap() as f32 * scale_factor;
+        let advances        = glyphs.windows(2).map(|pair| pair[1].location.0 - pair[0].location.0).sum::<f32>() + last_advance;
+
+        assert!((metrics.advance_width - advances).abs() < 0.01);
+
+        // The glyphs should fit within the ascent and descent of the font, and start close to the origin
+        let ((min_x, min_y), (max_x, max_y)) = metrics.bounding_box.unwrap();
+        assert!(min_y >= metrics.descent && max_y <= metrics.ascent);
+        assert!(min_x >= 0.0 && min_x < 10.0);
+        assert!(max_x <= metrics.advance_width);
+
+        // Whitespace has an advance but no outline
+        let space           = measure_text_metrics(&font, "   ", 100.0);
+        assert!(space.advance_width > 0.0);
+        assert!(space.bounding_box.is_none());
+    }
+
     #[test]
     fn serialize_deserialize_font_face() {
         let font    = CanvasFontFace::from_slice(include_bytes!("../test_data/Lato-Regular.ttf"));
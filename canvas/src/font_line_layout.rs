@@ -150,7 +150,8 @@ impl CanvasFontLineLayout {
         let x_offset = match align {
             TextAlignment::Left     => x,
             TextAlignment::Right    => x - max_x,
-            TextAlignment::Center   => x - (max_x+min_x)/2.0
+            TextAlignment::Center   => x - (max_x+min_x)/2.0,
+            TextAlignment::Justify  => x,
         };
 
         // Move all of the glyph positions
@@ -199,7 +200,8 @@ impl CanvasFontLineLayout {
         let x_offset = match align {
             TextAlignment::Left     => x,
             TextAlignment::Right    => x - max_x,
-            TextAlignment::Center   => x - (max_x+min_x)/2.0
+            TextAlignment::Center   => x - (max_x+min_x)/2.0,
+            TextAlignment::Justify  => x,
         };
 
         // Add transform instructions at the start of the drawing, then restore the previous state at the end
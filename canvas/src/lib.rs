@@ -42,6 +42,7 @@ extern crate desync;
 extern crate hsluv;
 
 mod draw;
+mod svg;
 mod path;
 mod font;
 mod color;
@@ -51,10 +52,14 @@ mod context;
 mod texture;
 mod encoding;
 mod decoding;
+mod binary_encoding;
+mod binary_decoding;
 mod gradient;
 mod namespace;
 mod font_face;
 mod primitives;
+mod path_builder;
+mod drawing_validation;
 mod transform2d;
 mod draw_stream;
 mod draw_resource;
@@ -62,9 +67,11 @@ mod drawing_target;
 mod conversion_streams;
 
 #[cfg(feature = "outline-fonts")] mod font_line_layout;
+#[cfg(feature = "image-loading")] mod texture_loading;
 #[cfg(feature = "scenery")] pub mod scenery;
 
 pub use self::draw::*;
+pub use self::svg::*;
 pub use self::path::*;
 pub use self::font::*;
 pub use self::color::*;
@@ -74,16 +81,21 @@ pub use self::context::*;
 pub use self::texture::*;
 pub use self::encoding::*;
 pub use self::decoding::*;
+pub use self::binary_encoding::*;
+pub use self::binary_decoding::*;
 pub use self::gradient::*;
 pub use self::namespace::*;
 pub use self::font_face::*;
 pub use self::primitives::*;
+pub use self::path_builder::*;
+pub use self::drawing_validation::*;
 pub use self::transform2d::*;
 pub use self::draw_stream::*;
 pub use self::drawing_target::*;
 pub use self::conversion_streams::*;
 
 #[cfg(feature = "outline-fonts")] pub use self::font_line_layout::*;
+#[cfg(feature = "image-loading")] pub use self::texture_loading::*;
 
 pub use flo_curves as curves;
 pub use flo_curves::geo::{Coordinate2D, Coord2};
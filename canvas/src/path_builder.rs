@@ -0,0 +1,268 @@
+use crate::draw::*;
+use crate::path::*;
+use crate::context::*;
+
+use flo_curves::geo::*;
+use flo_curves::bezier::path::*;
+
+use std::mem;
+
+///
+/// The ways that two paths can be combined by a `PathBuilder`
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PathCombineOp {
+    /// The result covers the area covered by either path
+    Union,
+
+    /// The result covers the area covered by both paths
+    Intersect,
+
+    /// The result covers the area covered by the first path but not the second
+    Subtract,
+}
+
+///
+/// Collects the path instructions sent to it as a graphics context, so that the resulting paths can be combined with other paths
+///
+/// Any of the path primitives (`rect()`, `circle()`, `bezier_curve_to()`, etc) can be used to build up the path. Instructions
+/// that don't define a path are ignored, and `new_path()` discards anything built so far. The result of combining two paths
+/// can be drawn with `draw_list(path.to_drawing())`, followed by a `fill()` or a `stroke()`.
+///
+#[derive(Clone, Debug, Default)]
+pub struct PathBuilder {
+    /// The subpaths that have been completed so far
+    paths: Vec<SimpleBezierPath>,
+
+    /// The subpath that is currently being built
+    current_path: Option<SimpleBezierPath>,
+}
+
+impl PathBuilder {
+    ///
+    /// Creates a new, empty path builder
+    ///
+    pub fn new() -> PathBuilder {
+        PathBuilder {
+            paths:          vec![],
+            current_path:   None,
+        }
+    }
+
+    ///
+    /// Creates a path builder containing a set of existing subpaths
+    ///
+    pub fn from_paths(paths: Vec<SimpleBezierPath>) -> PathBuilder {
+        PathBuilder {
+            paths:          paths,
+            current_path:   None,
+        }
+    }
+
+    ///
+    /// Returns the subpaths that make up the path that has been built so far
+    ///
+    pub fn paths(&self) -> Vec<SimpleBezierPath> {
+        self.paths.iter()
+            .chain(self.current_path.iter())
+            .filter(|(_, curves)| !curves.is_empty())
+            .cloned()
+            .collect()
+    }
+
+    ///
+    /// Combines this path with another path, returning the result as a new path
+    ///
+    /// The accuracy is the distance below which points are considered to be the same (0.01 is a reasonable value for paths
+    /// measured in pixels)
+    ///
+    pub fn combine(&self, op: PathCombineOp, other: &PathBuilder, accuracy: f64) -> PathBuilder {
+        let path1 = self.paths();
+        let path2 = other.paths();
+
+        let result: Vec<SimpleBezierPath> = match op {
+            PathCombineOp::Union        => path_add(&path1, &path2, accuracy),
+            PathCombineOp::Intersect    => path_intersect(&path1, &path2, accuracy),
+            PathCombineOp::Subtract     => path_sub(&path1, &path2, accuracy),
+        };
+
+        PathBuilder::from_paths(result)
+    }
+
+    ///
+    /// Returns the path covering the area of either this path or another path
+    ///
+    pub fn union(&self, other: &PathBuilder, accuracy: f64) -> PathBuilder {
+        self.combine(PathCombineOp::Union, other, accuracy)
+    }
+
+    ///
+    /// Returns the path covering the area shared by this path and another path
+    ///
+    pub fn intersect(&self, other: &PathBuilder, accuracy: f64) -> PathBuilder {
+        self.combine(PathCombineOp::Intersect, other, accuracy)
+    }
+
+    ///
+    /// Returns the path covering the area of this path with the area of another path cut out of it
+    ///
+    pub fn subtract(&self, other: &PathBuilder, accuracy: f64) -> PathBuilder {
+        self.combine(PathCombineOp::Subtract, other, accuracy)
+    }
+
+    ///
+    /// Returns the drawing instructions that define this path (each subpath is closed)
+    ///
+    pub fn to_drawing(&self) -> Vec<Draw> {
+        let mut drawing = vec![];
+
+        for (start_point, curves) in self.paths() {
+            drawing.push(Draw::Path(PathOp::Move(start_point.x() as _, start_point.y() as _)));
+
+            for (cp1, cp2, end_point) in curves {
+                drawing.push(Draw::Path(PathOp::BezierCurve(((cp1.x() as _, cp1.y() as _), (cp2.x() as _, cp2.y() as _)), (end_point.x() as _, end_point.y() as _))));
+            }
+
+            drawing.push(Draw::Path(PathOp::ClosePath));
+        }
+
+        drawing
+    }
+
+    ///
+    /// Adds a straight line to the current subpath
+    ///
+    fn line_to(&mut self, end_point: Coord2) {
+        if let Some((start_point, curves)) = &mut self.current_path {
+            let last_point  = curves.last().map(|(_, _, end_point)| *end_point).unwrap_or(*start_point);
+            let cp1         = last_point + (end_point - last_point) * (1.0/3.0);
+            let cp2         = last_point + (end_point - last_point) * (2.0/3.0);
+
+            curves.push((cp1, cp2, end_point));
+        }
+    }
+}
+
+impl GraphicsContext for PathBuilder {
+    fn draw(&mut self, drawing: Draw) {
+        use self::PathOp::*;
+
+        match drawing {
+            Draw::Path(NewPath)                                 => {
+                self.paths          = vec![];
+                self.current_path   = None;
+            }
+
+            Draw::Path(Move(x, y))                              => {
+                if let Some(path) = self.current_path.take() {
+                    self.paths.push(path);
+                }
+
+                self.current_path = Some((Coord2(x as _, y as _), vec![]));
+            }
+
+            Draw::Path(Line(x, y))                              => {
+                self.line_to(Coord2(x as _, y as _));
+            }
+
+            Draw::Path(BezierCurve(((cp1x, cp1y), (cp2x, cp2y)), (x, y))) => {
+                if let Some((_, curves)) = &mut self.current_path {
+                    curves.push((Coord2(cp1x as _, cp1y as _), Coord2(cp2x as _, cp2y as _), Coord2(x as _, y as _)));
+                }
+            }
+
+            Draw::Path(ClosePath)                               => {
+                // Close the path with a line back to the start (the path arithmetic functions only work with closed paths)
+                if let Some((start_point, curves)) = &self.current_path {
+                    let start_point = *start_point;
+                    let last_point  = curves.last().map(|(_, _, end_point)| *end_point);
+
+                    if last_point != Some(start_point) {
+                        self.line_to(start_point);
+                    }
+
+                    let path = mem::take(&mut self.current_path);
+                    self.paths.extend(path);
+                }
+            }
+
+            // Other instructions do not affect the path
+            _ => { }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::primitives::*;
+
+    fn num_subpaths(drawing: &[Draw]) -> usize {
+        drawing.iter().filter(|draw| matches!(draw, Draw::Path(PathOp::Move(_, _)))).count()
+    }
+
+    #[test]
+    fn build_rectangle() {
+        let mut path = PathBuilder::new();
+        path.rect(0.0, 0.0, 100.0, 100.0);
+
+        let paths = path.paths();
+        assert!(paths.len() == 1);
+        assert!(paths[0].0 == Coord2(0.0, 0.0));
+        assert!(paths[0].1.len() == 4);
+    }
+
+    #[test]
+    fn new_path_clears_builder() {
+        let mut path = PathBuilder::new();
+        path.rect(0.0, 0.0, 100.0, 100.0);
+        path.new_path();
+        path.rect(10.0, 10.0, 20.0, 20.0);
+
+        assert!(path.paths().len() == 1);
+        assert!(path.paths()[0].0 == Coord2(10.0, 10.0));
+    }
+
+    #[test]
+    fn subtract_cuts_hole() {
+        let mut base    = PathBuilder::new();
+        let mut cutter  = PathBuilder::new();
+        base.rect(0.0, 0.0, 100.0, 100.0);
+        cutter.rect(25.0, 25.0, 75.0, 75.0);
+
+        // Result is the outer rectangle and the hole
+        let result = base.subtract(&cutter, 0.01);
+        assert!(num_subpaths(&result.to_drawing()) == 2);
+    }
+
+    #[test]
+    fn intersect_overlapping_rectangles() {
+        let mut rect1   = PathBuilder::new();
+        let mut rect2   = PathBuilder::new();
+        rect1.rect(0.0, 0.0, 100.0, 100.0);
+        rect2.rect(50.0, 50.0, 150.0, 150.0);
+
+        let result = rect1.intersect(&rect2, 0.01);
+        let paths  = result.paths();
+        assert!(paths.len() == 1);
+
+        // All of the points should be in the overlapping region
+        for (start_point, curves) in paths.iter() {
+            for point in curves.iter().map(|(_, _, end_point)| end_point).chain(Some(start_point)) {
+                assert!(point.x() > 49.9 && point.x() < 100.1);
+                assert!(point.y() > 49.9 && point.y() < 100.1);
+            }
+        }
+    }
+
+    #[test]
+    fn union_disjoint_rectangles() {
+        let mut rect1   = PathBuilder::new();
+        let mut rect2   = PathBuilder::new();
+        rect1.rect(0.0, 0.0, 100.0, 100.0);
+        rect2.rect(200.0, 0.0, 300.0, 100.0);
+
+        let result = rect1.union(&rect2, 0.01);
+        assert!(num_subpaths(&result.to_drawing()) == 2);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::draw::*;
 use crate::path::*;
+use crate::font::*;
 use crate::context::*;
 use crate::transform2d::*;
 use crate::conversion_streams::*;
@@ -10,13 +11,13 @@ use flo_curves::bezier::{BezierCurve};
 use flo_curves::bezier::path::{BezierPath};
 
 use std::iter;
+use std::f64::consts::{PI, FRAC_PI_2};
 use smallvec::*;
 
 #[cfg(feature = "image-loading")] use super::texture::*;
-#[cfg(feature = "image-loading")] use image;
+#[cfg(feature = "image-loading")] use super::texture_loading::*;
 #[cfg(feature = "image-loading")] use image::io::Reader as ImageReader;
 #[cfg(feature = "image-loading")] use std::io;
-#[cfg(feature = "image-loading")] use std::sync::*;
 
 ///
 /// GraphicsPrimitives adds new primitives that can be built directly from a graphics context
@@ -40,6 +41,56 @@ pub trait GraphicsPrimitives : GraphicsContext {
         }
     }
 
+    ///
+    /// Draws an ellipse with its axes aligned to the x and y axes
+    ///
+    fn ellipse(&mut self, center_x: f32, center_y: f32, radius_x: f32, radius_y: f32) {
+        for d in draw_ellipse(center_x, center_y, radius_x, radius_y) {
+            self.draw(d);
+        }
+    }
+
+    ///
+    /// Draws a rectangle with rounded corners
+    ///
+    fn rounded_rect(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) {
+        for d in draw_rounded_rect(x1, y1, x2, y2, radius) {
+            self.draw(d);
+        }
+    }
+
+    ///
+    /// Draws a rectangle with a different radius for each corner
+    ///
+    /// The radii are for the corners at `(x1, y1)`, `(x2, y1)`, `(x2, y2)` and `(x1, y2)`, in that order.
+    ///
+    fn rounded_rect_corners(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radii: [f32; 4]) {
+        for d in draw_rounded_rect_corners(x1, y1, x2, y2, radii) {
+            self.draw(d);
+        }
+    }
+
+    ///
+    /// Moves to the start of an arc and draws it as a series of bezier curves
+    ///
+    /// Angles are in radians, measured anticlockwise from the x axis. The arc goes from `start_angle` to `end_angle`, and is
+    /// clamped to a full circle if the two angles are more than 2π apart.
+    ///
+    fn arc(&mut self, center_x: f32, center_y: f32, radius: f32, start_angle: f32, end_angle: f32) {
+        for d in draw_arc(center_x, center_y, radius, start_angle, end_angle) {
+            self.draw(d);
+        }
+    }
+
+    ///
+    /// Draws a closed polygon through a set of points
+    ///
+    fn polygon(&mut self, points: impl IntoIterator<Item=(f32, f32)>) {
+        for d in draw_polygon(points) {
+            self.draw(d);
+        }
+    }
+
     ///
     /// Draws a bezier path
     ///
@@ -146,6 +197,24 @@ pub trait GraphicsPrimitives : GraphicsContext {
         }
     }
 
+    ///
+    /// Lays out a line of text made up of runs with their own font, size and colour
+    ///
+    /// This generates a line layout, so it's rendered by `drawing_with_laid_out_text()` (each run's glyphs are drawn after
+    /// its fill colour). The font size and fill colour of the last run remain set after the text is drawn.
+    ///
+    fn draw_text_runs(&mut self, x: f32, y: f32, align: TextAlignment, runs: impl IntoIterator<Item=TextRun>) {
+        self.begin_line_layout(x, y, align);
+
+        for run in runs {
+            self.set_font_size(run.font_id, run.font_size);
+            self.fill_color(run.color);
+            self.layout_text(run.font_id, run.text);
+        }
+
+        self.draw_text_layout();
+    }
+
     ///
     /// Loads an image from an IO stream into a texture, returning the size (or None if the image can't be read for any reason)
     ///
@@ -155,18 +224,8 @@ pub trait GraphicsPrimitives : GraphicsContext {
         let img         = ImageReader::new(data).with_guessed_format().ok()?;
         let img         = img.decode().ok()?;
 
-        // Convert to 8-bit RGBA
-        let img         = img.into_rgba8();
-        let width       = img.width();
-        let height      = img.height();
-
-        // Load the texture
-        let raw_pixels  = Arc::new(img.into_raw());
-        self.create_texture(texture_id, width, height, TextureFormat::Rgba);
-        self.set_texture_bytes(texture_id, 0, 0, width, height, raw_pixels);
-
-        // Result is the image size
-        Some((width as _, height as _))
+        // Load the texture (result is the image size)
+        Some(upload_image_texture(self, texture_id, img))
     }
 }
 
@@ -210,6 +269,156 @@ pub fn draw_circle(center_x: f32, center_y: f32, radius: f32) -> Vec<Draw> {
     path.collect()
 }
 
+///
+/// Returns bezier curves approximating an elliptical arc, starting at the point on the ellipse at `start_angle`
+///
+/// Each curve covers at most a quarter turn, which keeps the approximation within the usual bounds for the 4-segment circle.
+///
+fn elliptical_arc_curves(center_x: f64, center_y: f64, radius_x: f64, radius_y: f64, start_angle: f64, end_angle: f64) -> Vec<Draw> {
+    // Sweep at most one full turn, in either direction (allowing for angles that have been rounded to f32)
+    let sweep           = (end_angle - start_angle).clamp(-2.0*PI, 2.0*PI);
+    let num_segments    = ((sweep.abs() / FRAC_PI_2) - 1e-6).ceil().max(1.0) as usize;
+    let segment_sweep   = sweep / (num_segments as f64);
+
+    // Distance of the control points along the tangent for a unit circle
+    let kappa           = (4.0/3.0) * (segment_sweep / 4.0).tan();
+
+    (0..num_segments)
+        .map(|segment| {
+            let angle1          = start_angle + segment_sweep * (segment as f64);
+            let angle2          = angle1 + segment_sweep;
+
+            let (sin1, cos1)    = angle1.sin_cos();
+            let (sin2, cos2)    = angle2.sin_cos();
+
+            let cp1             = (cos1 - kappa * sin1, sin1 + kappa * cos1);
+            let cp2             = (cos2 + kappa * sin2, sin2 - kappa * cos2);
+            let end             = (cos2, sin2);
+
+            let to_ellipse      = |(x, y): (f64, f64)| ((center_x + x * radius_x) as f32, (center_y + y * radius_y) as f32);
+
+            Draw::Path(PathOp::BezierCurve((to_ellipse(cp1), to_ellipse(cp2)), to_ellipse(end)))
+        })
+        .collect()
+}
+
+///
+/// Returns the drawing commands for an ellipse
+///
+pub fn draw_ellipse(center_x: f32, center_y: f32, radius_x: f32, radius_y: f32) -> Vec<Draw> {
+    use self::Draw::*;
+    use self::PathOp::*;
+
+    let curves = elliptical_arc_curves(center_x as f64, center_y as f64, radius_x as f64, radius_y as f64, 0.0, 2.0*PI);
+
+    iter::once(Path(Move(center_x + radius_x, center_y)))
+        .chain(curves)
+        .chain(iter::once(Path(ClosePath)))
+        .collect()
+}
+
+///
+/// Returns the drawing commands for a rectangle with rounded corners
+///
+pub fn draw_rounded_rect(x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) -> Vec<Draw> {
+    draw_rounded_rect_corners(x1, y1, x2, y2, [radius, radius, radius, radius])
+}
+
+///
+/// Returns the drawing commands for a rectangle with a different radius for each corner
+///
+/// The radii are for the corners at `(x1, y1)`, `(x2, y1)`, `(x2, y2)` and `(x1, y2)`, in that order. If the radii on any
+/// side add up to more than the length of that side, they're all scaled down so that the corners just meet.
+///
+pub fn draw_rounded_rect_corners(x1: f32, y1: f32, x2: f32, y2: f32, radii: [f32; 4]) -> Vec<Draw> {
+    use self::Draw::*;
+    use self::PathOp::*;
+
+    let (x1, y1, x2, y2)    = (x1 as f64, y1 as f64, x2 as f64, y2 as f64);
+    let (dx, dy)            = (if x2 < x1 { -1.0 } else { 1.0 }, if y2 < y1 { -1.0 } else { 1.0 });
+    let (width, height)     = ((x2-x1).abs(), (y2-y1).abs());
+
+    // Scale the radii down if they overlap
+    let radii               = radii.map(|radius| (radius as f64).max(0.0));
+    let scale               = [
+        width / (radii[0] + radii[1]),
+        height / (radii[1] + radii[2]),
+        width / (radii[2] + radii[3]),
+        height / (radii[3] + radii[0]),
+    ].iter().fold(1.0f64, |scale, side_scale| if side_scale.is_finite() { scale.min(*side_scale) } else { scale });
+    let radii               = radii.map(|radius| radius * scale);
+
+    // Each corner is drawn by moving along the edge to the start of its arc, then curving around to the next edge
+    let corners             = [(x1, y1), (x2, y1), (x2, y2), (x1, y2)];
+    let directions          = [(dx, dy), (-dx, dy), (-dx, -dy), (dx, -dy)];
+
+    // The arcs go anticlockwise unless the rectangle is mirrored
+    let sweep               = if dx * dy < 0.0 { -FRAC_PI_2 } else { FRAC_PI_2 };
+
+    let mut drawing         = vec![Path(Move((x1 + dx*radii[0]) as f32, y1 as f32))];
+
+    for corner_idx in [1, 2, 3, 0] {
+        let (x, y)          = corners[corner_idx];
+        let (dir_x, dir_y)  = directions[corner_idx];
+        let radius          = radii[corner_idx];
+
+        // Corners 1 and 3 are approached along a horizontal edge, and corners 0 and 2 along a vertical edge
+        let start_point     = match corner_idx {
+            1 | 3   => (x + dir_x*radius, y),
+            _       => (x, y + dir_y*radius),
+        };
+        drawing.push(Path(Line(start_point.0 as f32, start_point.1 as f32)));
+
+        if radius > 0.0 {
+            let center      = (x + dir_x*radius, y + dir_y*radius);
+            let start_angle = (start_point.1 - center.1).atan2(start_point.0 - center.0);
+
+            drawing.extend(elliptical_arc_curves(center.0, center.1, radius, radius, start_angle, start_angle + sweep));
+        }
+    }
+
+    drawing.push(Path(ClosePath));
+    drawing
+}
+
+///
+/// Returns the drawing commands for an arc, starting with a move to the start of the arc
+///
+/// Angles are in radians, measured anticlockwise from the x axis. Arcs covering more than a full circle are clamped to a
+/// single turn.
+///
+pub fn draw_arc(center_x: f32, center_y: f32, radius: f32, start_angle: f32, end_angle: f32) -> Vec<Draw> {
+    use self::Draw::*;
+    use self::PathOp::*;
+
+    let (start_sin, start_cos)  = (start_angle as f64).sin_cos();
+    let start_point             = (center_x as f64 + start_cos * radius as f64, center_y as f64 + start_sin * radius as f64);
+    let curves                  = elliptical_arc_curves(center_x as f64, center_y as f64, radius as f64, radius as f64, start_angle as f64, end_angle as f64);
+
+    iter::once(Path(Move(start_point.0 as f32, start_point.1 as f32)))
+        .chain(curves)
+        .collect()
+}
+
+///
+/// Returns the drawing commands for a closed polygon (or no commands if there are no points)
+///
+pub fn draw_polygon(points: impl IntoIterator<Item=(f32, f32)>) -> Vec<Draw> {
+    use self::Draw::*;
+    use self::PathOp::*;
+
+    let mut points  = points.into_iter();
+    let mut drawing = vec![];
+
+    if let Some((x, y)) = points.next() {
+        drawing.push(Path(Move(x, y)));
+        drawing.extend(points.map(|(x, y)| Path(Line(x, y))));
+        drawing.push(Path(ClosePath));
+    }
+
+    drawing
+}
+
 impl<'a, Curve: BezierCurve> From<&'a Curve> for Draw
 where Curve::Point: Coordinate2D {
     fn from(curve: &'a Curve) -> Draw {
@@ -237,3 +446,131 @@ where T: GraphicsContext {
 impl<'a> GraphicsPrimitives for dyn 'a+GraphicsContext {
 
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    ///
+    /// Returns the points along the curves in a drawing, along with the point where each curve starts
+    ///
+    fn curve_points(drawing: &[Draw]) -> Vec<(f64, f64)> {
+        let mut last_point  = (0.0, 0.0);
+        let mut points      = vec![];
+
+        for draw in drawing {
+            match draw {
+                Draw::Path(PathOp::Move(x, y))          => { last_point = (*x as f64, *y as f64); }
+                Draw::Path(PathOp::Line(x, y))          => { last_point = (*x as f64, *y as f64); }
+                Draw::Path(PathOp::BezierCurve(((cp1x, cp1y), (cp2x, cp2y)), (x, y))) => {
+                    let (x0, y0)    = last_point;
+                    let (x1, y1)    = (*cp1x as f64, *cp1y as f64);
+                    let (x2, y2)    = (*cp2x as f64, *cp2y as f64);
+                    let (x3, y3)    = (*x as f64, *y as f64);
+
+                    for step in 0..=100 {
+                        let t       = (step as f64) / 100.0;
+                        let u       = 1.0 - t;
+                        let x       = u*u*u*x0 + 3.0*u*u*t*x1 + 3.0*u*t*t*x2 + t*t*t*x3;
+                        let y       = u*u*u*y0 + 3.0*u*u*t*y1 + 3.0*u*t*t*y2 + t*t*t*y3;
+
+                        points.push((x, y));
+                    }
+
+                    last_point = (x3, y3);
+                }
+                _ => { }
+            }
+        }
+
+        points
+    }
+
+    fn num_curves(drawing: &[Draw]) -> usize {
+        drawing.iter().filter(|draw| matches!(draw, Draw::Path(PathOp::BezierCurve(_, _)))).count()
+    }
+
+    #[test]
+    fn ellipse_is_within_kappa_error_bound() {
+        let drawing = draw_ellipse(100.0, 50.0, 40.0, 20.0);
+        let points  = curve_points(&drawing);
+
+        assert!(num_curves(&drawing) == 4);
+        assert!(drawing.last() == Some(&Draw::Path(PathOp::ClosePath)));
+
+        // The 4-segment approximation of a circle has a radial error of about 0.027%
+        for (x, y) in points {
+            let (dx, dy)    = ((x - 100.0) / 40.0, (y - 50.0) / 20.0);
+            let radius      = (dx*dx + dy*dy).sqrt();
+
+            assert!((radius - 1.0).abs() < 0.0003, "{:?} has radius {}", (x, y), radius);
+        }
+    }
+
+    #[test]
+    fn arc_across_quadrants() {
+        let drawing = draw_arc(0.0, 0.0, 10.0, 0.25*PI as f32, 1.25*PI as f32);
+        let points  = curve_points(&drawing);
+
+        // Half a circle needs two curves
+        assert!(num_curves(&drawing) == 2);
+
+        for (x, y) in points.iter() {
+            assert!(((x*x + y*y).sqrt() - 10.0).abs() < 0.003);
+        }
+
+        let (start_x, start_y)  = points[0];
+        let (end_x, end_y)      = points[points.len()-1];
+        let expected            = 10.0 * (0.25*PI).cos();
+
+        assert!((start_x - expected).abs() < 0.001 && (start_y - expected).abs() < 0.001);
+        assert!((end_x + expected).abs() < 0.001 && (end_y + expected).abs() < 0.001);
+    }
+
+    #[test]
+    fn arc_clamps_to_full_circle() {
+        let drawing = draw_arc(0.0, 0.0, 10.0, 0.0, 10.0*PI as f32);
+        assert!(num_curves(&drawing) == 4);
+
+        let drawing = draw_arc(0.0, 0.0, 10.0, 0.0, -10.0*PI as f32);
+        assert!(num_curves(&drawing) == 4);
+    }
+
+    #[test]
+    fn rounded_rect_stays_inside_bounds() {
+        let drawing = draw_rounded_rect_corners(10.0, 20.0, 110.0, 70.0, [5.0, 10.0, 15.0, 0.0]);
+        let points  = curve_points(&drawing);
+
+        // The square corner has no curve
+        assert!(num_curves(&drawing) == 3);
+
+        for (x, y) in points {
+            assert!((9.999..=110.001).contains(&x) && (19.999..=70.001).contains(&y), "{:?} is outside the rectangle", (x, y));
+        }
+    }
+
+    #[test]
+    fn rounded_rect_scales_large_radii() {
+        let drawing = draw_rounded_rect(0.0, 0.0, 100.0, 40.0, 50.0);
+
+        // Radii are scaled to 20 to fit the height, so the path starts 20 units along the bottom edge
+        assert!(drawing[0] == Draw::Path(PathOp::Move(20.0, 0.0)));
+
+        for (x, y) in curve_points(&drawing) {
+            assert!((-0.001..=100.001).contains(&x) && (-0.001..=40.001).contains(&y));
+        }
+    }
+
+    #[test]
+    fn polygon_is_closed() {
+        let drawing = draw_polygon(vec![(0.0, 0.0), (10.0, 0.0), (5.0, 10.0)]);
+
+        assert!(drawing == vec![
+            Draw::Path(PathOp::Move(0.0, 0.0)),
+            Draw::Path(PathOp::Line(10.0, 0.0)),
+            Draw::Path(PathOp::Line(5.0, 10.0)),
+            Draw::Path(PathOp::ClosePath),
+        ]);
+        assert!(draw_polygon(vec![]).is_empty());
+    }
+}
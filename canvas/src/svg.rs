@@ -0,0 +1,581 @@
+use crate::draw::*;
+use crate::path::*;
+use crate::color::*;
+use crate::sprite::*;
+use crate::gradient::*;
+use crate::transform2d::*;
+
+use std::collections::{HashMap, BTreeMap};
+use std::fmt::Write;
+
+///
+/// The drawing state that can be saved and restored with `PushState` and `PopState`
+///
+#[derive(Clone)]
+struct SvgDrawState {
+    /// The transform applied to paths as they're drawn
+    transform:      Transform2D,
+
+    /// The transform applied to sprites as they're drawn
+    sprite_matrix:  Transform2D,
+
+    /// The paint attributes to use for fills
+    fill:           String,
+
+    /// The colour to use for strokes
+    stroke_color:   Color,
+
+    /// The line width, and true if it's specified in pixels
+    line_width:     (f32, bool),
+
+    /// The line join style
+    line_join:      LineJoin,
+
+    /// The line cap style
+    line_cap:       LineCap,
+
+    /// The dash pattern to use for strokes
+    dash_pattern:   Vec<f32>,
+
+    /// The offset for the dash pattern
+    dash_offset:    f32,
+
+    /// The winding rule to use when filling
+    winding_rule:   WindingRule,
+
+    /// The blend mode for new elements
+    blend_mode:     BlendMode,
+
+    /// The ID of the clip path that's currently in effect
+    clip:           Option<usize>,
+}
+
+///
+/// The contents of a layer in the SVG file
+///
+#[derive(Clone, Default)]
+struct SvgLayer {
+    /// The elements in this layer
+    elements:   Vec<String>,
+
+    /// The opacity of this layer
+    alpha:      Option<f32>,
+
+    /// The blend mode for this layer
+    blend_mode: Option<BlendMode>,
+}
+
+///
+/// Converts a stream of drawing instructions into SVG elements
+///
+struct SvgWriter {
+    /// The current drawing state
+    state:              SvgDrawState,
+
+    /// The states saved by `PushState`
+    state_stack:        Vec<SvgDrawState>,
+
+    /// The path that's being built up, as SVG path data
+    path:               String,
+
+    /// The layers that have been drawn to
+    layers:             BTreeMap<u64, SvgLayer>,
+
+    /// The sprites that have been drawn to
+    sprites:            HashMap<SpriteId, Vec<String>>,
+
+    /// The current version of each sprite, and whether or not it has been changed since it was last written to the defs section
+    sprite_versions:    HashMap<SpriteId, (usize, bool)>,
+
+    /// The layer that's being drawn to
+    current_layer:      LayerId,
+
+    /// The sprite that's being drawn to, if any
+    current_sprite:     Option<SpriteId>,
+
+    /// The transform that was active before the current sprite was selected
+    layer_transform:    Transform2D,
+
+    /// The stops for each gradient
+    gradients:          HashMap<GradientId, Vec<(f32, Color)>>,
+
+    /// The contents of the `<defs>` section
+    defs:               Vec<String>,
+
+    /// The number of definitions generated so far (used to generate unique IDs)
+    next_def_id:        usize,
+
+    /// The contents of the current layer, as it was when 'Store' was last used
+    stored:             Option<Vec<String>>,
+
+    /// The background colour set by the last `ClearCanvas` instruction
+    background:         Option<Color>,
+}
+
+impl Default for SvgDrawState {
+    fn default() -> SvgDrawState {
+        SvgDrawState {
+            transform:      Transform2D::identity(),
+            sprite_matrix:  Transform2D::identity(),
+            fill:           svg_color_attributes("fill", &Color::Rgba(0.0, 0.0, 0.0, 1.0)),
+            stroke_color:   Color::Rgba(0.0, 0.0, 0.0, 1.0),
+            line_width:     (1.0, false),
+            line_join:      LineJoin::Round,
+            line_cap:       LineCap::Butt,
+            dash_pattern:   vec![],
+            dash_offset:    0.0,
+            winding_rule:   WindingRule::NonZero,
+            blend_mode:     BlendMode::SourceOver,
+            clip:           None,
+        }
+    }
+}
+
+///
+/// Formats a transform as the value of an SVG 'transform' attribute
+///
+fn svg_matrix(transform: &Transform2D) -> String {
+    let Transform2D(m) = transform;
+
+    format!("matrix({} {} {} {} {} {})", m[0][0], m[1][0], m[0][1], m[1][1], m[0][2], m[1][2])
+}
+
+///
+/// Generates the SVG attributes for a colour (eg, `fill="rgb(...)" fill-opacity="..."`)
+///
+fn svg_color_attributes(attribute: &str, color: &Color) -> String {
+    let (r, g, b, a)    = color.to_rgba_components();
+    let to_byte         = |component: f32| (component.max(0.0).min(1.0) * 255.0).round() as u8;
+
+    if a >= 1.0 {
+        format!("{}=\"rgb({},{},{})\"", attribute, to_byte(r), to_byte(g), to_byte(b))
+    } else {
+        format!("{}=\"rgb({},{},{})\" {}-opacity=\"{}\"", attribute, to_byte(r), to_byte(g), to_byte(b), attribute, a.max(0.0))
+    }
+}
+
+///
+/// Returns the CSS name for a blend mode (or None for modes that can't be represented in SVG)
+///
+fn svg_blend_mode(blend_mode: BlendMode) -> Option<&'static str> {
+    match blend_mode {
+        BlendMode::Multiply     => Some("multiply"),
+        BlendMode::Screen       => Some("screen"),
+        BlendMode::Darken       => Some("darken"),
+        BlendMode::Lighten      => Some("lighten"),
+        _                       => None
+    }
+}
+
+impl SvgWriter {
+    ///
+    /// Creates a new SVG writer with an empty canvas
+    ///
+    fn new() -> SvgWriter {
+        SvgWriter {
+            state:              SvgDrawState::default(),
+            state_stack:        vec![],
+            path:               String::new(),
+            layers:             BTreeMap::new(),
+            sprites:            HashMap::new(),
+            sprite_versions:    HashMap::new(),
+            current_layer:      LayerId(0),
+            current_sprite:     None,
+            layer_transform:    Transform2D::identity(),
+            gradients:          HashMap::new(),
+            defs:               vec![],
+            next_def_id:        0,
+            stored:             None,
+            background:         None,
+        }
+    }
+
+    ///
+    /// Returns a new ID for an item in the defs section
+    ///
+    fn def_id(&mut self) -> usize {
+        let id = self.next_def_id;
+        self.next_def_id += 1;
+        id
+    }
+
+    ///
+    /// Returns the list of elements that are currently being drawn to
+    ///
+    fn current_elements(&mut self) -> &mut Vec<String> {
+        if let Some(sprite_id) = self.current_sprite {
+            self.sprite_versions.entry(sprite_id).or_insert((0, true)).1 = true;
+            self.sprites.entry(sprite_id).or_insert_with(|| vec![])
+        } else {
+            &mut self.layers.entry(self.current_layer.0).or_insert_with(|| SvgLayer::default()).elements
+        }
+    }
+
+    ///
+    /// Adds an element to the current layer or sprite, applying the current clipping path and blend mode
+    ///
+    fn add_element(&mut self, element: String) {
+        let element = if let Some(blend_mode) = svg_blend_mode(self.state.blend_mode) {
+            format!("<g style=\"mix-blend-mode:{}\">{}</g>", blend_mode, element)
+        } else {
+            element
+        };
+
+        let element = if let Some(clip) = self.state.clip {
+            format!("<g clip-path=\"url(#clip{})\">{}</g>", clip, element)
+        } else {
+            element
+        };
+
+        self.current_elements().push(element);
+    }
+
+    ///
+    /// Applies a path operation to the current path
+    ///
+    fn path_op(&mut self, path_op: PathOp) {
+        match path_op {
+            PathOp::NewPath                                     => { self.path.clear(); }
+            PathOp::Move(x, y)                                  => { write!(self.path, "M{} {} ", x, y).ok(); }
+            PathOp::Line(x, y)                                  => { write!(self.path, "L{} {} ", x, y).ok(); }
+            PathOp::BezierCurve(((x1, y1), (x2, y2)), (x, y))   => { write!(self.path, "C{} {} {} {} {} {} ", x1, y1, x2, y2, x, y).ok(); }
+            PathOp::ClosePath                                   => { self.path.push_str("Z "); }
+        }
+    }
+
+    ///
+    /// Returns the 'fill-rule' value for the current winding rule
+    ///
+    fn fill_rule(&self) -> &'static str {
+        match self.state.winding_rule {
+            WindingRule::NonZero    => "nonzero",
+            WindingRule::EvenOdd    => "evenodd",
+        }
+    }
+
+    ///
+    /// Fills the current path
+    ///
+    fn fill(&mut self) {
+        let element = format!("<path d=\"{}\" {} fill-rule=\"{}\" transform=\"{}\"/>", self.path.trim_end(), self.state.fill, self.fill_rule(), svg_matrix(&self.state.transform));
+        self.add_element(element);
+    }
+
+    ///
+    /// Strokes the current path
+    ///
+    fn stroke(&mut self) {
+        let state       = &self.state;
+        let mut element = format!("<path d=\"{}\" fill=\"none\" {} stroke-width=\"{}\"", self.path.trim_end(), svg_color_attributes("stroke", &state.stroke_color), state.line_width.0);
+
+        let line_join = match state.line_join {
+            LineJoin::Miter => "miter",
+            LineJoin::Round => "round",
+            LineJoin::Bevel => "bevel",
+        };
+        let line_cap = match state.line_cap {
+            LineCap::Butt   => "butt",
+            LineCap::Round  => "round",
+            LineCap::Square => "square",
+        };
+        write!(element, " stroke-linejoin=\"{}\" stroke-linecap=\"{}\"", line_join, line_cap).ok();
+
+        if state.line_width.1 {
+            // Pixel widths are relative to the size of the SVG rather than the canvas coordinates
+            element.push_str(" vector-effect=\"non-scaling-stroke\"");
+        }
+
+        if !state.dash_pattern.is_empty() {
+            let dash_pattern = state.dash_pattern.iter().map(|len| len.to_string()).collect::<Vec<_>>().join(" ");
+            write!(element, " stroke-dasharray=\"{}\" stroke-dashoffset=\"{}\"", dash_pattern, state.dash_offset).ok();
+        }
+
+        write!(element, " transform=\"{}\"/>", svg_matrix(&state.transform)).ok();
+        self.add_element(element);
+    }
+
+    ///
+    /// Intersects the clipping region with the current path
+    ///
+    fn clip(&mut self) {
+        let clip_id     = self.def_id();
+        let parent_clip = self.state.clip.map(|parent| format!(" clip-path=\"url(#clip{})\"", parent)).unwrap_or_default();
+        let clip_path   = format!("<clipPath id=\"clip{}\"{}><path d=\"{}\" clip-rule=\"{}\" transform=\"{}\"/></clipPath>", clip_id, parent_clip, self.path.trim_end(), self.fill_rule(), svg_matrix(&self.state.transform));
+
+        self.defs.push(clip_path);
+        self.state.clip = Some(clip_id);
+    }
+
+    ///
+    /// Sets the fill to use a linear gradient
+    ///
+    fn fill_gradient(&mut self, gradient_id: GradientId, (x1, y1): (f32, f32), (x2, y2): (f32, f32)) {
+        let def_id  = self.def_id();
+        let stops   = self.gradients.get(&gradient_id).cloned().unwrap_or_default();
+        let stops   = stops.into_iter()
+            .map(|(pos, color)| format!("<stop offset=\"{}\" {}/>", pos, svg_color_attributes("stop-color", &color)))
+            .collect::<String>();
+
+        self.defs.push(format!("<linearGradient id=\"gradient{}\" gradientUnits=\"userSpaceOnUse\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\">{}</linearGradient>", def_id, x1, y1, x2, y2, stops));
+        self.state.fill = format!("fill=\"url(#gradient{})\"", def_id);
+    }
+
+    ///
+    /// Updates the transform that's applied to sprites when they're drawn
+    ///
+    fn sprite_transform(&mut self, transform: SpriteTransform) {
+        match transform {
+            SpriteTransform::Identity   => { self.state.sprite_matrix = Transform2D::identity(); }
+            other                       => { self.state.sprite_matrix = Transform2D::from(other) * self.state.sprite_matrix; }
+        }
+    }
+
+    ///
+    /// Draws a sprite at the current position
+    ///
+    fn draw_sprite(&mut self, sprite_id: SpriteId) {
+        // Write out the sprite definition if it's changed since it was last drawn
+        let (version, changed) = self.sprite_versions.get(&sprite_id).cloned().unwrap_or((0, true));
+
+        let version = if changed {
+            let version     = version + 1;
+            let contents    = self.sprites.get(&sprite_id).map(|elements| elements.concat()).unwrap_or_default();
+
+            self.defs.push(format!("<g id=\"sprite{}_{}\">{}</g>", sprite_id.0, version, contents));
+            self.sprite_versions.insert(sprite_id, (version, false));
+
+            version
+        } else {
+            version
+        };
+
+        let transform = self.state.transform * self.state.sprite_matrix;
+        self.add_element(format!("<use href=\"#sprite{}_{}\" transform=\"{}\"/>", sprite_id.0, version, svg_matrix(&transform)));
+    }
+
+    ///
+    /// Processes a single drawing instruction
+    ///
+    fn draw(&mut self, draw: Draw) {
+        use self::Draw::*;
+
+        match draw {
+            StartFrame | ShowFrame | ResetFrame     => { }
+            Namespace(_)                            => { }
+
+            Path(path_op)                           => { self.path_op(path_op); }
+            Fill                                    => { self.fill(); }
+            Stroke                                  => { self.stroke(); }
+
+            LineWidth(width)                        => { self.state.line_width = (width, false); }
+            LineWidthPixels(width)                  => { self.state.line_width = (width, true); }
+            LineJoin(join)                          => { self.state.line_join = join; }
+            LineCap(cap)                            => { self.state.line_cap = cap; }
+            NewDashPattern                          => { self.state.dash_pattern = vec![]; }
+            DashLength(length)                      => { self.state.dash_pattern.push(length); }
+            DashOffset(offset)                      => { self.state.dash_offset = offset; }
+            FillColor(color)                        => { self.state.fill = svg_color_attributes("fill", &color); }
+            FillGradient(gradient, start, end)      => { self.fill_gradient(gradient, start, end); }
+            FillTexture(_, _, _)                    => { }
+            FillTransform(_)                        => { }
+            StrokeColor(color)                      => { self.state.stroke_color = color; }
+            WindingRule(rule)                       => { self.state.winding_rule = rule; }
+            BlendMode(blend_mode)                   => { self.state.blend_mode = blend_mode; }
+
+            IdentityTransform                       => { self.state.transform = Transform2D::identity(); }
+            CanvasHeight(height)                    => {
+                let scale               = 2.0 / f32::max(1.0, height);
+                self.state.transform    = Transform2D::scale(scale, scale);
+            }
+            CenterRegion((x1, y1), (x2, y2))        => {
+                let inverse             = self.state.transform.invert().unwrap_or_else(|| Transform2D::identity());
+                let (cx, cy)            = inverse.transform_point(0.0, 0.0);
+                let (new_x, new_y)      = ((x1+x2)/2.0, (y1+y2)/2.0);
+                self.state.transform    = self.state.transform * Transform2D::translate(-(new_x - cx), -(new_y - cy));
+            }
+            MultiplyTransform(transform)            => { self.state.transform = self.state.transform * transform; }
+
+            Unclip                                  => { self.state.clip = None; }
+            Clip                                    => { self.clip(); }
+
+            Store                                   => { self.stored = Some(self.current_elements().clone()); }
+            Restore                                 => {
+                if let Some(stored) = self.stored.clone() {
+                    *self.current_elements() = stored;
+                }
+            }
+            FreeStoredBuffer                        => { self.stored = None; }
+
+            PushState                               => { self.state_stack.push(self.state.clone()); }
+            PopState                                => {
+                if let Some(state) = self.state_stack.pop() {
+                    self.state = state;
+                }
+            }
+
+            ClearCanvas(color)                      => {
+                let gradients   = self.gradients.clone();
+                *self           = SvgWriter::new();
+                self.gradients  = gradients;
+                self.background = Some(color);
+            }
+
+            Layer(layer_id)                         => {
+                if self.current_sprite.take().is_some() {
+                    self.state.transform = self.layer_transform;
+                }
+                self.current_layer = layer_id;
+            }
+            LayerBlend(layer_id, blend_mode)        => { self.layers.entry(layer_id.0).or_insert_with(|| SvgLayer::default()).blend_mode = Some(blend_mode); }
+            LayerAlpha(layer_id, alpha)             => { self.layers.entry(layer_id.0).or_insert_with(|| SvgLayer::default()).alpha = Some(alpha); }
+            ClearLayer                              => { self.current_elements().clear(); }
+            ClearAllLayers                          => { self.layers.clear(); }
+            SwapLayers(layer1, layer2)              => {
+                let content1 = self.layers.remove(&layer1.0).unwrap_or_default();
+                let content2 = self.layers.remove(&layer2.0).unwrap_or_default();
+
+                self.layers.insert(layer1.0, content2);
+                self.layers.insert(layer2.0, content1);
+            }
+
+            Sprite(sprite_id)                       => {
+                // Sprites are drawn with their own coordinate scheme, so they're unaffected by the canvas transform
+                if self.current_sprite.is_none() {
+                    self.layer_transform = self.state.transform;
+                }
+                self.current_sprite     = Some(sprite_id);
+                self.state.transform    = Transform2D::identity();
+            }
+            MoveSpriteFrom(source_id)               => {
+                let contents = self.sprites.remove(&source_id).unwrap_or_default();
+                self.sprite_versions.remove(&source_id);
+                *self.current_elements() = contents;
+            }
+            ClearSprite                             => { self.current_elements().clear(); }
+            SpriteTransform(transform)              => { self.sprite_transform(transform); }
+            DrawSprite(sprite_id)                   => { self.draw_sprite(sprite_id); }
+            DrawSpriteWithFilters(sprite_id, _)     => { self.draw_sprite(sprite_id); }
+
+            Texture(_, _)                           => { }
+            Font(_, _)                              => { }
+            BeginLineLayout(_, _, _)                => { }
+            DrawLaidOutText                         => { }
+            DrawText(_, _, _, _)                    => { }
+
+            Gradient(gradient_id, GradientOp::Create(color))        => { self.gradients.insert(gradient_id, vec![(0.0, color)]); }
+            Gradient(gradient_id, GradientOp::AddStop(pos, color))  => { self.gradients.entry(gradient_id).or_insert_with(|| vec![]).push((pos, color)); }
+        }
+    }
+
+    ///
+    /// Generates the SVG document for the drawing
+    ///
+    fn to_svg(&self, width: f32, height: f32) -> String {
+        let mut svg = String::new();
+
+        write!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">", width, height, width, height).ok();
+
+        if let Some(background) = &self.background {
+            write!(svg, "<rect width=\"{}\" height=\"{}\" {}/>", width, height, svg_color_attributes("fill", background)).ok();
+        }
+
+        if !self.defs.is_empty() {
+            write!(svg, "<defs>{}</defs>", self.defs.concat()).ok();
+        }
+
+        // Canvas coordinates have (0,0) at the center of the window, the y axis pointing upwards and a height of 2.0
+        write!(svg, "<g transform=\"matrix({} 0 0 {} {} {})\">", height/2.0, -height/2.0, width/2.0, height/2.0).ok();
+
+        for layer in self.layers.values() {
+            svg.push_str("<g");
+            if let Some(alpha) = layer.alpha {
+                write!(svg, " opacity=\"{}\"", alpha).ok();
+            }
+            if let Some(blend_mode) = layer.blend_mode.and_then(svg_blend_mode) {
+                write!(svg, " style=\"mix-blend-mode:{}\"", blend_mode).ok();
+            }
+            svg.push('>');
+
+            svg.push_str(&layer.elements.concat());
+            svg.push_str("</g>");
+        }
+
+        svg.push_str("</g></svg>");
+
+        svg
+    }
+}
+
+///
+/// Converts a set of drawing instructions to an SVG document
+///
+/// The width and height are the size of the SVG document: the canvas coordinates are mapped onto this in the same way
+/// that they would be mapped onto a window of the same size. Each layer becomes a group in the output, and sprites are
+/// written to the `<defs>` section and referenced with `<use>`.
+///
+/// Textures and text are not included in the output: use `drawing_with_laid_out_text()` and `drawing_with_text_as_paths()`
+/// (with the `outline-fonts` feature) to convert any text into paths first.
+///
+pub fn draw_to_svg<DrawIter: IntoIterator<Item=Draw>>(drawing: DrawIter, width: f32, height: f32) -> String {
+    let mut writer = SvgWriter::new();
+
+    for draw in drawing {
+        writer.draw(draw);
+    }
+
+    writer.to_svg(width, height)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::context::*;
+    use crate::primitives::*;
+
+    #[test]
+    fn fill_rectangle() {
+        let mut drawing = vec![];
+        drawing.fill_color(Color::Rgba(1.0, 0.0, 0.0, 1.0));
+        drawing.new_path();
+        drawing.rect(0.0, 0.0, 100.0, 100.0);
+        drawing.fill();
+
+        let svg = draw_to_svg(drawing, 400.0, 200.0);
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("fill=\"rgb(255,0,0)\""));
+        assert!(svg.contains("M0 0 L0 100 L100 100 L100 0 L0 0 Z"));
+        assert!(svg.contains("matrix(100 0 0 -100 200 100)"));
+    }
+
+    #[test]
+    fn pop_state_restores_transform() {
+        let mut drawing = vec![];
+        drawing.canvas_height(100.0);
+        drawing.push_state();
+        drawing.transform(Transform2D::translate(10.0, 0.0));
+        drawing.pop_state();
+        drawing.new_path();
+        drawing.move_to(0.0, 0.0);
+        drawing.line_to(1.0, 1.0);
+        drawing.stroke();
+
+        let svg = draw_to_svg(drawing, 100.0, 100.0);
+
+        assert!(svg.contains("transform=\"matrix(0.02 0 0 0.02 0 0)\""));
+    }
+
+    #[test]
+    fn layers_become_groups() {
+        let mut drawing = vec![];
+        drawing.layer(LayerId(1));
+        drawing.layer_alpha(LayerId(1), 0.5);
+        drawing.new_path();
+        drawing.rect(0.0, 0.0, 1.0, 1.0);
+        drawing.fill();
+
+        let svg = draw_to_svg(drawing, 100.0, 100.0);
+
+        assert!(svg.contains("<g opacity=\"0.5\"><path"));
+    }
+}
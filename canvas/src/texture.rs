@@ -27,8 +27,28 @@ pub struct TextureSize(pub u32, pub u32);
 ///
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum TextureFormat {
-    /// Every pixel is 4 bytes specifying the red, green, blue and alpha values for the pixel
-    Rgba
+    /// Every pixel is 4 bytes specifying the red, green, blue and alpha values for the pixel (the color values are not multiplied by the alpha value).
+    /// Renderers that store textures with premultiplied alpha convert the pixels when they're written to the texture.
+    Rgba,
+
+    /// Every pixel is 4 bytes specifying the red, green, blue and alpha values for the pixel, where the color values have already been
+    /// multiplied by the alpha value
+    RgbaPremultiplied,
+}
+
+///
+/// How a texture fill is addressed outside of the region defined by its corner points
+///
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum TextureWrapMode {
+    /// The pixels at the edge of the texture are extended indefinitely
+    Clamp,
+
+    /// The texture is tiled across the fill region
+    Repeat,
+
+    /// The texture is tiled across the fill region, with every other tile mirrored so that the edges meet seamlessly
+    MirrorRepeat,
 }
 
 ///
@@ -75,6 +95,9 @@ pub enum TextureOp {
     Free,
 
     /// Sets a region of a texture (specified as minx, miny, width, height) to the specified bitmap
+    ///
+    /// The bytes are interpreted according to the format the texture was created with: straight alpha for `TextureFormat::Rgba`
+    /// (the default) or premultiplied alpha for `TextureFormat::RgbaPremultiplied`.
     SetBytes(TexturePosition, TextureSize, Arc<Vec<u8>>),
 
     /// Renders the specified sprite to the texture (mapping the supplied bounds to the coordinates in the texture)
@@ -88,6 +111,9 @@ pub enum TextureOp {
     /// Sets the transparency to use when rendering a texture
     FillTransparency(f32),
 
+    /// Sets how the texture is addressed when it's used as a fill (textures repeat by default)
+    SetWrapMode(TextureWrapMode),
+
     /// Copies this texture to another texture
     Copy(TextureId),
 
@@ -95,4 +121,13 @@ pub enum TextureOp {
     /// For dynamic textures, any measurements (eg: gaussian blur radius) are in sprite units, but for static textures, measurements
     /// are in pixels.
     Filter(TextureFilter),
+
+    /// Sets whether or not mipmaps are generated for the texture (they are by default). Mipmaps are used to smooth the texture
+    /// when it's drawn at a smaller size than it was defined at. This takes effect the next time that the texture is written to.
+    SetMipMaps(bool),
+
+    /// Replaces the texture with the decoded contents of an encoded image file (eg, a PNG or a JPEG). The texture takes on
+    /// the size of the image. Renderers need the `image-formats` feature to decode these: if the image can't be decoded,
+    /// the texture is left empty.
+    SetFromEncodedImage(Arc<Vec<u8>>),
 }
@@ -0,0 +1,249 @@
+use crate::context::*;
+use crate::texture::*;
+
+use image::{DynamicImage, ImageError, ImageFormat};
+
+use std::fs;
+use std::fmt;
+use std::path::{Path};
+use std::sync::*;
+
+///
+/// Errors that can occur while loading an image into a texture
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextureLoadError {
+    /// The format of the image could not be determined, or is not one that can be decoded
+    UnsupportedFormat,
+
+    /// The image is in a supported format but could not be decoded
+    DecodeFailed(String),
+
+    /// The image data could not be read
+    ReadFailed(String),
+}
+
+impl fmt::Display for TextureLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureLoadError::UnsupportedFormat => write!(f, "unsupported image format"),
+            TextureLoadError::DecodeFailed(msg) => write!(f, "could not decode image: {}", msg),
+            TextureLoadError::ReadFailed(msg)   => write!(f, "could not read image: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TextureLoadError { }
+
+impl From<ImageError> for TextureLoadError {
+    fn from(err: ImageError) -> TextureLoadError {
+        match err {
+            ImageError::Unsupported(_)  => TextureLoadError::UnsupportedFormat,
+            ImageError::IoError(err)    => TextureLoadError::ReadFailed(err.to_string()),
+            other                       => TextureLoadError::DecodeFailed(other.to_string()),
+        }
+    }
+}
+
+///
+/// Reads the EXIF orientation tag from a JPEG file, if there is one
+///
+fn jpeg_orientation(bytes: &[u8]) -> Option<u16> {
+    // JPEG files start with an SOI marker, followed by a series of segments
+    if bytes.get(0..2)? != [0xff, 0xd8] { return None; }
+
+    let mut pos = 2;
+    loop {
+        let marker  = bytes.get(pos..pos+2)?;
+        let len     = u16::from_be_bytes([*bytes.get(pos+2)?, *bytes.get(pos+3)?]) as usize;
+        if marker[0] != 0xff || len < 2 { return None; }
+
+        // The start of the scan data means there's no more metadata
+        if marker[1] == 0xda { return None; }
+
+        // APP1 segments containing 'Exif' have the orientation tag
+        let segment = bytes.get(pos+4..pos+2+len)?;
+        if marker[1] == 0xe1 && segment.starts_with(b"Exif\0\0") {
+            return tiff_orientation(&segment[6..]);
+        }
+
+        pos += 2 + len;
+    }
+}
+
+///
+/// Reads the orientation tag from the first IFD of a TIFF structure
+///
+fn tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    let big_endian  = match tiff.get(0..2)? {
+        b"MM"   => true,
+        b"II"   => false,
+        _       => return None,
+    };
+    let read_u16    = |pos: usize| -> Option<u16> { let bytes = [*tiff.get(pos)?, *tiff.get(pos+1)?]; Some(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }) };
+    let read_u32    = |pos: usize| -> Option<u32> { let bytes = [*tiff.get(pos)?, *tiff.get(pos+1)?, *tiff.get(pos+2)?, *tiff.get(pos+3)?]; Some(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }) };
+
+    let ifd_offset  = read_u32(4)? as usize;
+    let num_entries = read_u16(ifd_offset)? as usize;
+
+    (0..num_entries)
+        .map(|entry_num| ifd_offset + 2 + entry_num*12)
+        .find(|entry_pos| read_u16(*entry_pos) == Some(0x0112))
+        .and_then(|entry_pos| read_u16(entry_pos + 8))
+}
+
+///
+/// Rotates and flips an image so that it has the orientation described by an EXIF orientation tag
+///
+fn apply_orientation(img: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+///
+/// Decodes an encoded image (eg, a PNG or a JPEG file) into 8-bit straight-alpha RGBA pixels, returning the width, height and pixels
+///
+/// JPEG files are rotated according to their EXIF orientation tag, so the result is the right way up.
+///
+pub fn decode_image_rgba(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), TextureLoadError> {
+    let format  = image::guess_format(bytes)?;
+    let img     = image::load_from_memory_with_format(bytes, format)?;
+    let img     = match (format, jpeg_orientation(bytes)) {
+        (ImageFormat::Jpeg, Some(orientation))  => apply_orientation(img, orientation),
+        _                                       => img,
+    };
+
+    let img     = img.into_rgba8();
+    Ok((img.width(), img.height(), img.into_raw()))
+}
+
+///
+/// Creates a texture from a decoded image, returning the size of the texture
+///
+pub (crate) fn upload_image_texture<Gc: ?Sized+GraphicsContext>(gc: &mut Gc, texture_id: TextureId, img: DynamicImage) -> (usize, usize) {
+    // Convert to 8-bit RGBA
+    let img         = img.into_rgba8();
+    let width       = img.width();
+    let height      = img.height();
+
+    // Load the texture
+    let raw_pixels  = Arc::new(img.into_raw());
+    gc.create_texture(texture_id, width, height, TextureFormat::Rgba);
+    gc.set_texture_bytes(texture_id, 0, 0, width, height, raw_pixels);
+
+    (width as _, height as _)
+}
+
+///
+/// Decodes an encoded image (eg, a PNG, JPEG or BMP file) and loads it into a texture, returning the size of the texture
+///
+/// The format of the image is determined from its contents. `TextureLoadError::UnsupportedFormat` is returned if the format can't be
+/// recognised. JPEG files are rotated according to their EXIF orientation tag.
+///
+pub fn load_texture_from_bytes<Gc: ?Sized+GraphicsContext>(gc: &mut Gc, texture_id: TextureId, bytes: &[u8]) -> Result<(usize, usize), TextureLoadError> {
+    let (width, height, pixels) = decode_image_rgba(bytes)?;
+
+    gc.create_texture(texture_id, width, height, TextureFormat::Rgba);
+    gc.set_texture_bytes(texture_id, 0, 0, width, height, Arc::new(pixels));
+
+    Ok((width as _, height as _))
+}
+
+///
+/// Reads an image file and loads it into a texture, returning the size of the texture
+///
+/// As for `load_texture_from_bytes()`, the format is determined from the contents of the file rather than its extension.
+///
+pub fn load_texture_from_path<Gc: ?Sized+GraphicsContext, P: AsRef<Path>>(gc: &mut Gc, texture_id: TextureId, path: P) -> Result<(usize, usize), TextureLoadError> {
+    let bytes = fs::read(path).map_err(|err| TextureLoadError::ReadFailed(err.to_string()))?;
+
+    load_texture_from_bytes(gc, texture_id, &bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::draw::*;
+
+    use image::{RgbaImage, ImageOutputFormat};
+
+    use std::io::{Cursor};
+
+    fn encoded_image(format: ImageOutputFormat) -> Vec<u8> {
+        let img         = RgbaImage::from_fn(2, 3, |x, y| image::Rgba([x as u8, y as u8, 128, 255]));
+        let mut bytes   = Cursor::new(vec![]);
+
+        DynamicImage::ImageRgba8(img).write_to(&mut bytes, format).unwrap();
+
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn load_png() {
+        let mut drawing = vec![];
+        let size        = load_texture_from_bytes(&mut drawing, TextureId(1), &encoded_image(ImageOutputFormat::Png));
+
+        assert!(size == Ok((2, 3)));
+        assert!(drawing[0] == Draw::Texture(TextureId(1), TextureOp::Create(TextureSize(2, 3), TextureFormat::Rgba)));
+        assert!(drawing[1] == Draw::Texture(TextureId(1), TextureOp::SetBytes(TexturePosition(0, 0), TextureSize(2, 3), Arc::new(vec![
+            0, 0, 128, 255,     1, 0, 128, 255,
+            0, 1, 128, 255,     1, 1, 128, 255,
+            0, 2, 128, 255,     1, 2, 128, 255,
+        ]))));
+    }
+
+    #[test]
+    fn load_bmp() {
+        let mut drawing = vec![];
+        let size        = load_texture_from_bytes(&mut drawing, TextureId(1), &encoded_image(ImageOutputFormat::Bmp));
+
+        assert!(size == Ok((2, 3)));
+        assert!(drawing.len() == 2);
+    }
+
+    #[test]
+    fn unsupported_format() {
+        let mut drawing = vec![];
+        let size        = load_texture_from_bytes(&mut drawing, TextureId(1), b"Not an image");
+
+        assert!(size == Err(TextureLoadError::UnsupportedFormat));
+        assert!(drawing.is_empty());
+    }
+
+    #[test]
+    fn jpeg_exif_orientation() {
+        // A 3x2 JPEG with an EXIF segment that says it should be rotated 90 degrees clockwise
+        let img         = RgbaImage::from_pixel(3, 2, image::Rgba([200, 100, 50, 255]));
+        let mut jpeg    = Cursor::new(vec![]);
+        DynamicImage::ImageRgba8(img).to_rgb8().write_to(&mut jpeg, ImageOutputFormat::Jpeg(90)).unwrap();
+        let jpeg        = jpeg.into_inner();
+
+        let tiff        = vec![b'M', b'M', 0, 42, 0, 0, 0, 8, 0, 1, 0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0, 0, 0, 0, 0, 0, 0];
+        let mut app1    = vec![0xff, 0xe1, 0, (2 + 6 + tiff.len()) as u8];
+        app1.extend(b"Exif\0\0");
+        app1.extend(tiff);
+
+        let rotated     = jpeg[0..2].iter().cloned().chain(app1).chain(jpeg[2..].iter().cloned()).collect::<Vec<_>>();
+
+        assert!(decode_image_rgba(&jpeg).unwrap().0 == 3);
+        let (width, height, pixels) = decode_image_rgba(&rotated).unwrap();
+        assert!((width, height) == (2, 3));
+        assert!(pixels.len() == 2*3*4);
+    }
+
+    #[test]
+    fn missing_file() {
+        let mut drawing = vec![];
+        let size        = load_texture_from_path(&mut drawing, TextureId(1), "does/not/exist.png");
+
+        assert!(matches!(size, Err(TextureLoadError::ReadFailed(_))));
+    }
+}
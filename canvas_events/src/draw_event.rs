@@ -2,6 +2,7 @@ use crate::key::*;
 use crate::pointer_event::*;
 
 use flo_canvas::*;
+use flo_render::RenderInitError;
 use flo_scene::*;
 
 ///
@@ -21,6 +22,13 @@ pub enum DrawEvent {
     /// Window has a new size
     Resize(f64, f64),
 
+    /// The rendering device was lost and has been recreated. Any resources created by earlier render actions have been lost and
+    /// need to be sent again (canvas windows do this automatically by redrawing the canvas)
+    DeviceReset,
+
+    /// The renderer for the window could not be created, so nothing will be drawn to it
+    RenderError(RenderInitError),
+
     /// Canvas transformation for the window has changed (this will convert between window coordinates and canvas coordinates)
     CanvasTransform(Transform2D),
 
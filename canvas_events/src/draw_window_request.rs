@@ -2,6 +2,7 @@ use super::render_request::*;
 use super::draw_event_request::*;
 
 use flo_scene::*;
+use flo_canvas::{Transform2D, TextureId, SpriteId, NamespaceId};
 use flo_canvas::scenery::*;
 
 ///
@@ -16,6 +17,21 @@ pub enum MousePointer {
     SystemDefault
 }
 
+///
+/// How a window presents new frames to the display
+///
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PresentMode {
+    /// Wait for the vertical blank before presenting a frame (no tearing, frame rate limited to the display rate)
+    Vsync,
+
+    /// Replace any frame waiting to be displayed with the newest one, without waiting (no tearing, lower latency). Falls back to vsync if this isn't available.
+    LowLatency,
+
+    /// Present frames as soon as they're ready (lowest latency, but may tear)
+    Immediate,
+}
+
 ///
 /// Messages that can be sent to a flo_draw window that can generate events
 ///
@@ -38,6 +54,15 @@ pub enum EventWindowRequest {
 
     /// Sets the mouse pointer to display for the window
     SetMousePointer(MousePointer),
+
+    /// Sets how the window presents new frames
+    SetPresentMode(PresentMode),
+
+    /// Sets the number of samples per pixel used to antialias the window
+    SetMultisampling(u32),
+
+    /// Sets the maximum number of frames per second the window presents (0 for no limit)
+    SetMaxFrameRate(u32),
 }
 
 
@@ -66,6 +91,36 @@ pub enum DrawingWindowRequest {
 
     /// Sets the mouse pointer to display for the window
     SetMousePointer(MousePointer),
+
+    /// Sets how the window presents new frames
+    SetPresentMode(PresentMode),
+
+    /// Sets the number of samples per pixel used to antialias the window
+    SetMultisampling(u32),
+
+    /// Sets the maximum number of frames per second the window presents (0 for no limit)
+    SetMaxFrameRate(u32),
+
+    /// Renders the current contents of the window once any frame in progress is finished, and sends the result as a `CapturedFrame` to the specified program
+    CaptureFrame(SubProgramId),
+
+    /// Reads back the pixels of a texture once any frame in progress is finished, and sends the result as a `CapturedTexture` to the specified program
+    CaptureTexture(TextureId, SubProgramId),
+
+    /// Sets an extra transform to apply when rendering the canvas, in normalized window coordinates (used to zoom or pan a view without changing the drawing)
+    SetViewTransform(Transform2D),
+
+    /// Sends the transforms in effect after any drawing received so far as a `CanvasTransforms` message to the specified program
+    QueryTransforms(SubProgramId),
+
+    /// Sends the bounds of a sprite in a namespace after any drawing received so far as a `SpriteBoundsResult` message to the specified program
+    QuerySpriteBounds(NamespaceId, SpriteId, SubProgramId),
+
+    /// Discards everything the renderer has cached (tessellations, vertex buffers and textures) and redraws the whole canvas from scratch
+    RedrawAll,
+
+    /// Sends a `FramePresented` message to the specified program once the drawing received so far has been presented on screen
+    NotifyWhenPresented(SubProgramId),
 }
 
 ///
@@ -93,11 +148,91 @@ pub enum RenderWindowRequest {
 
     /// Sets the mouse pointer to display for the window
     SetMousePointer(MousePointer),
+
+    /// Sets how the window presents new frames
+    SetPresentMode(PresentMode),
+
+    /// Sets the number of samples per pixel used to antialias the window
+    SetMultisampling(u32),
+
+    /// Sets the maximum number of frames per second the window presents (0 for no limit)
+    SetMaxFrameRate(u32),
 }
 
+///
+/// The contents of a window, as captured by `DrawingWindowRequest::CaptureFrame`
+///
+#[derive(Clone, PartialEq, Debug)]
+pub struct CapturedFrame {
+    /// The width of the frame in pixels
+    pub width: usize,
+
+    /// The height of the frame in pixels
+    pub height: usize,
+
+    /// The pixels of the frame, as RGBA values with straight alpha, starting from the top-left corner (this is empty if the frame could not be rendered)
+    pub pixels: Vec<u8>,
+}
+
+///
+/// The pixels of a texture, as captured by `DrawingWindowRequest::CaptureTexture`
+///
+#[derive(Clone, PartialEq, Debug)]
+pub struct CapturedTexture {
+    /// The texture that was captured
+    pub texture_id: TextureId,
+
+    /// The width of the texture in pixels
+    pub width: usize,
+
+    /// The height of the texture in pixels
+    pub height: usize,
+
+    /// The pixels of the texture, as straight-alpha RGBA values in the same order as `TextureOp::SetBytes` (this is empty if the texture does not exist)
+    pub pixels: Vec<u8>,
+}
+
+///
+/// The transforms in effect for a window's canvas, as queried by `DrawingWindowRequest::QueryTransforms`
+///
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CanvasTransforms {
+    /// The transform set by the drawing (via `CanvasHeight`, `CenterRegion`, `MultiplyTransform`, etc), mapping canvas coordinates to normalized window coordinates
+    pub current_transform: Transform2D,
+
+    /// The transform mapping canvas coordinates to pixels in the window's viewport, including the view transform
+    pub viewport_transform: Transform2D,
+}
+
+///
+/// The bounds of a sprite, as queried by `DrawingWindowRequest::QuerySpriteBounds`
+///
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SpriteBoundsResult {
+    /// The namespace of the sprite that was queried
+    pub namespace_id: NamespaceId,
+
+    /// The sprite that was queried
+    pub sprite_id: SpriteId,
+
+    /// The bounds of the sprite in its own coordinates, as `((min_x, min_y), (max_x, max_y))` (`None` if the sprite is empty or not defined)
+    pub bounds: Option<((f64, f64), (f64, f64))>,
+}
+
+///
+/// Sent by a drawing window once a frame has been presented on screen, as requested by `DrawingWindowRequest::NotifyWhenPresented`
+///
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FramePresented;
+
 impl SceneMessage for EventWindowRequest { }
 impl SceneMessage for RenderWindowRequest { }
 impl SceneMessage for DrawingWindowRequest { }
+impl SceneMessage for CapturedFrame { }
+impl SceneMessage for CanvasTransforms { }
+impl SceneMessage for SpriteBoundsResult { }
+impl SceneMessage for CapturedTexture { }
+impl SceneMessage for FramePresented { }
 
 impl From<RenderRequest> for RenderWindowRequest {
     fn from(req: RenderRequest) -> RenderWindowRequest {
@@ -120,6 +255,9 @@ impl From<EventWindowRequest> for RenderWindowRequest {
             EventWindowRequest::SetFullScreen(fullscreen)       => RenderWindowRequest::SetFullScreen(fullscreen),
             EventWindowRequest::SetHasDecorations(decorations)  => RenderWindowRequest::SetHasDecorations(decorations),
             EventWindowRequest::SetMousePointer(mouse_pointer)  => RenderWindowRequest::SetMousePointer(mouse_pointer),
+            EventWindowRequest::SetPresentMode(present_mode)    => RenderWindowRequest::SetPresentMode(present_mode),
+            EventWindowRequest::SetMultisampling(samples)       => RenderWindowRequest::SetMultisampling(samples),
+            EventWindowRequest::SetMaxFrameRate(frame_rate)     => RenderWindowRequest::SetMaxFrameRate(frame_rate),
         }
     }
 }
@@ -133,6 +271,9 @@ impl From<EventWindowRequest> for DrawingWindowRequest {
             EventWindowRequest::SetFullScreen(fullscreen)       => DrawingWindowRequest::SetFullScreen(fullscreen),
             EventWindowRequest::SetHasDecorations(decorations)  => DrawingWindowRequest::SetHasDecorations(decorations),
             EventWindowRequest::SetMousePointer(mouse_pointer)  => DrawingWindowRequest::SetMousePointer(mouse_pointer),
+            EventWindowRequest::SetPresentMode(present_mode)    => DrawingWindowRequest::SetPresentMode(present_mode),
+            EventWindowRequest::SetMultisampling(samples)       => DrawingWindowRequest::SetMultisampling(samples),
+            EventWindowRequest::SetMaxFrameRate(frame_rate)     => DrawingWindowRequest::SetMaxFrameRate(frame_rate),
         }
     }
 }
@@ -3,9 +3,6 @@ use flo_canvas::*;
 
 use rand::*;
 
-use std::thread;
-use std::time::{Duration};
-
 struct Ball {
     col: Color,
     radius: f64,
@@ -58,34 +55,30 @@ impl Ball {
 pub fn main() {
     // 'with_2d_graphics' is used to support operating systems that can't run event loops anywhere other than the main thread
     with_2d_graphics(|| {
-        // Create a window with a canvas to draw on
-        let canvas = create_drawing_window("Bouncing balls");
-
         // Generate some random balls
         let mut balls = (0..256).into_iter().map(|_| Ball::random()).collect::<Vec<_>>();
 
-        // Animate them
-        loop {
+        // Create a window and animate the balls at 60fps
+        let animation = create_drawing_window_with_frames("Bouncing balls", 60.0, move |gc, _frame_info| {
             // Update the balls for this frame
             for ball in balls.iter_mut() {
                 ball.update();
             }
 
             // Render the frame
-            canvas.draw(|gc| {
-                gc.clear_canvas(Color::Rgba(0.6, 0.7, 0.8, 1.0));
-                gc.canvas_height(1000.0);
-                gc.center_region(0.0, 0.0, 1000.0, 1000.0);
+            gc.clear_canvas(Color::Rgba(0.6, 0.7, 0.8, 1.0));
+            gc.canvas_height(1000.0);
+            gc.center_region(0.0, 0.0, 1000.0, 1000.0);
 
-                for ball in balls.iter() {
-                    gc.circle(ball.x as f32, ball.y as f32, ball.radius as f32);
-                    gc.fill_color(ball.col);
-                    gc.fill();
-                }
-            });
+            for ball in balls.iter() {
+                gc.circle(ball.x as f32, ball.y as f32, ball.radius as f32);
+                gc.fill_color(ball.col);
+                gc.fill();
+            }
 
-            // Wait for the next frame
-            thread::sleep(Duration::from_nanos(1_000_000_000 / 60));
-        }
+            FrameControl::Continue
+        });
+
+        animation.join().unwrap();
     });
 }
@@ -70,7 +70,7 @@ pub fn main() {
                 CreateMipMaps(TextureId(2)),
                 RenderAction::SetTransform(Matrix([[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 1.0], [0.0, 0.0, 0.0, 1.0]])),
                 RenderAction::BlendMode(render::BlendMode::SourceOver),
-                RenderAction::UseShader(ShaderType::Texture { texture: TextureId(2), texture_transform: transform_to_matrix(&canvas::Transform2D::translate(0.5, 0.5)), repeat: false, alpha: 1.0, clip_texture: None }),
+                RenderAction::UseShader(ShaderType::Texture { texture: TextureId(2), texture_transform: transform_to_matrix(&canvas::Transform2D::translate(0.5, 0.5)), wrap_mode: TextureWrapMode::Clamp, alpha: 1.0, clip_texture: None }),
 
                 CreateVertex2DBuffer(VertexBufferId(2), vec![
                     Vertex2D::with_pos(-0.5, -0.5).with_color(0.0, 0.0, 1.0, 1.0),
@@ -0,0 +1,39 @@
+use flo_draw::*;
+use flo_draw::canvas::*;
+
+use futures::executor;
+
+///
+/// Animation that is clocked by the display: each step is only simulated once the previous frame has been presented
+///
+pub fn main() {
+    with_2d_graphics(|| {
+        // Create a window for a canvas, keeping the view so we can find out when frames are presented
+        let (canvas, view, _events) = create_canvas_window_with_view("Self-clocking animation");
+
+        // The animation runs one step per presented frame, so it runs at the display rate and never gets ahead of it
+        let mut p = 0.0f32;
+        loop {
+            p += 0.02;
+
+            canvas.draw(|gc| {
+                gc.clear_canvas(Color::Rgba(1.0, 1.0, 1.0, 1.0));
+
+                gc.canvas_height(1000.0);
+
+                let x = p.sin() * 500.0;
+                let y = (p*3.0).cos() * 200.0;
+
+                gc.new_path();
+                gc.circle(x, y, 50.0);
+                gc.fill_color(Color::Rgba(0.0, 0.4, 0.8, 1.0));
+                gc.fill();
+            });
+
+            // Wait for the frame to reach the screen before simulating the next one (stop if the window is closed)
+            if executor::block_on(view.next_frame_presented()).is_none() {
+                break;
+            }
+        }
+    });
+}
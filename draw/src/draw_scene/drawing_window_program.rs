@@ -1,4 +1,5 @@
 use futures::prelude::*;
+use futures::executor;
 use futures::channel::oneshot;
 use futures::{pin_mut};
 use futures::task::{Poll, Context};
@@ -8,11 +9,14 @@ use flo_canvas::*;
 use flo_canvas::scenery::*;
 use flo_canvas_events::*;
 use flo_render_canvas::*;
+use flo_render::{initialize_offscreen_rendering, OffscreenRenderContext, OffscreenRenderTarget};
 
 use once_cell::sync::{Lazy};
 
+use std::mem;
 use std::pin::*;
 use std::sync::*;
+use std::thread;
 
 ///
 /// Combines rendering and event messages into one enum
@@ -133,8 +137,15 @@ where
                 let redraw = state.renderer.draw(vec![].into_iter()).collect::<Vec<_>>().await;
                 send_render_actions(redraw).await;
 
+                // Notify the subscribers if the transform has changed (eg, due to a resize)
+                let old_transform       = state.window_transform;
                 let window_transform    = state.update_window_transform();
-                vec![DrawEvent::CanvasTransform(window_transform)]
+
+                if old_transform != Some(window_transform) {
+                    vec![DrawEvent::CanvasTransform(window_transform)]
+                } else {
+                    vec![]
+                }
             },
 
             DrawEvent::Scale(new_scale)         => {
@@ -163,6 +174,8 @@ where
             }
 
             DrawEvent::NewFrame                 => { vec![] }
+            DrawEvent::DeviceReset              => { vec![] }
+            DrawEvent::RenderError(_)           => { vec![] }
             DrawEvent::Closed                   => { vec![] }
             DrawEvent::CanvasTransform(_)       => { vec![] }
             DrawEvent::Pointer(_, _, _)         => { vec![] }
@@ -172,6 +185,125 @@ where
     }
 }
 
+///
+/// Sends an event to a list of subscribers, removing any subscribers that are no longer accepting events
+///
+async fn send_to_subscribers<TSubscriber>(subscribers: &mut Vec<TSubscriber>, event: &DrawEvent)
+where
+    TSubscriber: Unpin + Sink<DrawEvent>,
+{
+    for idx in (0..subscribers.len()).rev() {
+        let target = &mut subscribers[idx];
+
+        if target.send(event.clone()).await.is_err() {
+            subscribers.remove(idx);
+        }
+    }
+}
+
+///
+/// A rendering job for the offscreen capture thread
+///
+enum OffscreenJob {
+    /// Renders a drawing with the specified viewport and view transform, and returns the pixels
+    Frame { drawing: Vec<Draw>, width: usize, height: usize, scale: f32, view_transform: Transform2D, result: oneshot::Sender<Option<Vec<u8>>> },
+
+    /// Renders a drawing and reads back the pixels of one of its textures
+    Texture { drawing: Vec<Draw>, texture_id: flo_canvas::TextureId, width: usize, height: usize, scale: f32, result: oneshot::Sender<Option<(usize, usize, Vec<u8>)>> },
+}
+
+///
+/// Sends jobs to the thread that renders the frame and texture captures for every window
+///
+static OFFSCREEN_JOBS: Lazy<Mutex<mpsc::Sender<OffscreenJob>>> = Lazy::new(|| {
+    let (send_jobs, recv_jobs) = mpsc::channel();
+    thread::spawn(move || run_offscreen_jobs(recv_jobs));
+
+    Mutex::new(send_jobs)
+});
+
+///
+/// Runs the offscreen capture thread
+///
+/// Offscreen render contexts can't generally be moved between threads, so a single context is created here and used for every
+/// capture. If it can't be created, it's tried again for the next job (every capture fails until a device is available).
+///
+fn run_offscreen_jobs(jobs: mpsc::Receiver<OffscreenJob>) {
+    let mut context = None;
+
+    while let Ok(job) = jobs.recv() {
+        if context.is_none() {
+            context = initialize_offscreen_rendering().ok();
+        }
+
+        match job {
+            OffscreenJob::Frame { drawing, width, height, scale, view_transform, result } => {
+                let pixels = context.as_mut().map(|context| {
+                    let mut render_target   = context.create_render_target(width, height);
+                    let mut renderer        = CanvasRenderer::new();
+
+                    // Render with the same viewport and view transform as the window
+                    renderer.set_viewport(0.0..(width as f32), 0.0..(height as f32), width as f32, height as f32, scale);
+                    renderer.set_view_transform(view_transform);
+
+                    let rendering = executor::block_on(renderer.draw(drawing.into_iter()).collect::<Vec<_>>());
+                    render_target.render(rendering);
+                    render_target.realize()
+                });
+
+                result.send(pixels).ok();
+            }
+
+            OffscreenJob::Texture { drawing, texture_id, width, height, scale, result } => {
+                let pixels = context.as_mut().and_then(|context| {
+                    executor::block_on(render_texture_offscreen(context, width, height, scale, &drawing, texture_id))
+                });
+
+                result.send(pixels).ok();
+            }
+        }
+    }
+}
+
+///
+/// Renders a drawing offscreen at the size of the window, returning the captured frame
+///
+/// Re-rendering the drawing means that frames can be captured even if the window is hidden or minimised.
+///
+async fn capture_frame(drawing: Vec<Draw>, state: &RendererState) -> CapturedFrame {
+    let width                       = state.width.max(1.0) as usize;
+    let height                      = state.height.max(1.0) as usize;
+    let scale                       = state.scale as f32;
+    let view_transform              = state.renderer.get_view_transform();
+    let (send_pixels, recv_pixels)  = oneshot::channel();
+
+    OFFSCREEN_JOBS.lock().unwrap().send(OffscreenJob::Frame { drawing, width, height, scale, view_transform, result: send_pixels }).ok();
+
+    match recv_pixels.await {
+        Ok(Some(pixels))    => CapturedFrame { width, height, pixels },
+        _                   => CapturedFrame { width: 0, height: 0, pixels: vec![] },
+    }
+}
+
+///
+/// Renders a drawing offscreen and reads back the pixels of one of its textures
+///
+/// Dynamic textures are rendered at the size they'd have in the window.
+///
+async fn capture_texture(drawing: Vec<Draw>, texture_id: flo_canvas::TextureId, state: &RendererState) -> CapturedTexture {
+    let width                       = state.width.max(1.0) as usize;
+    let height                      = state.height.max(1.0) as usize;
+    let scale                       = state.scale as f32;
+    let (send_pixels, recv_pixels)  = oneshot::channel();
+
+    OFFSCREEN_JOBS.lock().unwrap().send(OffscreenJob::Texture { drawing, texture_id, width, height, scale, result: send_pixels }).ok();
+
+    match recv_pixels.await {
+        Ok(Some((width, height, pixels)))   => CapturedTexture { texture_id, width, height, pixels },
+        _                                   => CapturedTexture { texture_id, width: 0, height: 0, pixels: vec![] },
+    }
+}
+
 impl RendererState {
     ///
     /// Updates the window transform for this state
@@ -189,6 +321,26 @@ impl RendererState {
         window_transform
     }
 
+    ///
+    /// Replaces the canvas renderer with a new one with the same viewport, so anything cached by the old renderer is discarded
+    ///
+    /// Returns the render actions that free the resources the old renderer created on the render target
+    ///
+    fn reset_renderer(&mut self) -> Vec<RenderAction> {
+        let view_transform  = self.renderer.get_view_transform();
+        let width           = self.width as f32;
+        let height          = self.height as f32;
+        let scale           = self.scale as f32;
+
+        let free_actions    = self.renderer.free_resources();
+
+        self.renderer = CanvasRenderer::new();
+        self.renderer.set_viewport(0.0..width, 0.0..height, width, height, scale);
+        self.renderer.set_view_transform(view_transform);
+
+        free_actions
+    }
+
     ///
     /// Performs a drawing action and passes it on to the render target
     ///
@@ -198,10 +350,151 @@ impl RendererState {
     }
 }
 
+///
+/// Where a drawing window finds the whole of the drawing it's displaying, when it needs to render it again
+///
+enum DrawingHistory {
+    /// The drawing comes from a canvas, which already stores it. The program that sends the drawing restarts its stream from the canvas when it
+    /// receives a `RedrawAll` request
+    Canvas(Canvas, SubProgramId),
+
+    /// The drawing comes from a stream, so the window keeps its own copy of it
+    Recorded(Canvas),
+}
+
+impl DrawingHistory {
+    ///
+    /// Retrieves the drawing instructions that will recreate the current drawing
+    ///
+    fn get_drawing(&self) -> Vec<Draw> {
+        match self {
+            DrawingHistory::Canvas(canvas, _)   => canvas.get_drawing(),
+            DrawingHistory::Recorded(canvas)    => canvas.get_drawing(),
+        }
+    }
+}
+
+///
+/// Tracks the programs that are waiting to be told when the drawing they were sent after has been presented
+///
+struct PresentNotifications {
+    /// Programs waiting for the drawing before their request to be shown
+    waiting_for_frame: Vec<SubProgramId>,
+
+    /// Programs whose drawing has been shown, and which will be notified by the next 'NewFrame' event
+    in_flight: Vec<SubProgramId>,
+}
+
+impl PresentNotifications {
+    ///
+    /// Creates a new set of present notifications with nothing waiting
+    ///
+    fn new() -> PresentNotifications {
+        PresentNotifications {
+            waiting_for_frame:  vec![],
+            in_flight:          vec![],
+        }
+    }
+
+    ///
+    /// Adds a program to notify once the drawing that was processed before it has been presented
+    ///
+    fn notify_when_presented(&mut self, target_program: SubProgramId) {
+        self.waiting_for_frame.push(target_program);
+    }
+
+    ///
+    /// Indicates that the drawing so far has been shown, so the next 'NewFrame' event will present it
+    ///
+    fn frame_shown(&mut self) {
+        self.in_flight.extend(self.waiting_for_frame.drain(..));
+    }
+
+    ///
+    /// Indicates that a 'NewFrame' event has arrived, returning the programs that should be told that their frame has been presented
+    ///
+    fn frame_presented(&mut self) -> Vec<SubProgramId> {
+        self.in_flight.drain(..).collect()
+    }
+}
+
+///
+/// The drawing gathered from a set of requests, which is sent to the renderer as a single frame
+///
+struct FrameBatch {
+    /// Drawing that's waiting to be sent to the renderer
+    drawing: Vec<Arc<Vec<Draw>>>,
+}
+
+impl FrameBatch {
+    ///
+    /// Creates a batch that begins with some 'StartFrame' instructions, so that nothing is displayed until it's finished
+    ///
+    fn new(num_start_frames: usize) -> FrameBatch {
+        FrameBatch {
+            drawing: vec![Arc::new(vec![Draw::StartFrame; num_start_frames])],
+        }
+    }
+
+    ///
+    /// Adds some drawing to this batch
+    ///
+    fn push(&mut self, drawing: Arc<Vec<Draw>>) {
+        self.drawing.push(drawing);
+    }
+
+    ///
+    /// Takes the drawing that's waiting to be sent, so the renderer can process it before the batch is finished
+    ///
+    fn take_drawing(&mut self) -> Vec<Arc<Vec<Draw>>> {
+        mem::take(&mut self.drawing)
+    }
+
+    ///
+    /// Discards the drawing that's waiting to be sent after the renderer has been replaced
+    ///
+    /// The new renderer is sent the 'StartFrame' instructions that the old one was still waiting to match with a 'ShowFrame',
+    /// including any that were already sent to it from this batch.
+    ///
+    fn restart(&mut self, suspended_frames: usize) {
+        self.drawing = vec![Arc::new(vec![Draw::StartFrame; suspended_frames])];
+    }
+
+    ///
+    /// Finishes the batch, returning the drawing to send to the renderer
+    ///
+    fn finish(self) -> Vec<Arc<Vec<Draw>>> {
+        let mut drawing = self.drawing;
+        drawing.push(Arc::new(vec![Draw::ShowFrame]));
+
+        drawing
+    }
+}
+
 ///
 /// Creates a drawing window that sends render requests to the specified target
 ///
+/// The window keeps a copy of the drawing it receives, so that it can redraw it from scratch (for instance, if the graphics device is reset)
+///
 pub fn create_drawing_window_program(scene: &Arc<Scene>, program_id: SubProgramId, render_target_program: SubProgramId) -> Result<(), ConnectionError> {
+    create_drawing_window_program_with_history(scene, program_id, render_target_program, DrawingHistory::Recorded(Canvas::new()))
+}
+
+///
+/// Creates a drawing window that displays the contents of a canvas, sending render requests to the specified target
+///
+/// The drawing is sent to the window by the `source_program`, which should pass on any `DrawingWindowRequest` it receives after the drawing
+/// that was sent before it. The window reads the canvas when capturing frames and doesn't keep a copy of the drawing: when everything needs to
+/// be drawn again, it sends `RedrawAll` to the source program, which should restart its stream from the canvas and pass the request back.
+///
+pub fn create_drawing_window_program_for_canvas(scene: &Arc<Scene>, program_id: SubProgramId, render_target_program: SubProgramId, canvas: Canvas, source_program: SubProgramId) -> Result<(), ConnectionError> {
+    create_drawing_window_program_with_history(scene, program_id, render_target_program, DrawingHistory::Canvas(canvas, source_program))
+}
+
+///
+/// Creates a drawing window program that uses the specified drawing history
+///
+fn create_drawing_window_program_with_history(scene: &Arc<Scene>, program_id: SubProgramId, render_target_program: SubProgramId, drawing_history: DrawingHistory) -> Result<(), ConnectionError> {
     // Create an ingress program for the drawing window requests
     // This will pass on its input stream to the main program, so it's possible to block 
     let drawing_window_ingress_program              = SubProgramId::new();
@@ -220,6 +513,7 @@ pub fn create_drawing_window_program(scene: &Arc<Scene>, program_id: SubProgramI
         100);
 
     // Create the window in the scene
+    let window_scene = Arc::clone(scene);
     scene.add_subprogram(
         program_id, 
         move |drawing_window_requests, context| async move {
@@ -255,6 +549,15 @@ pub fn create_drawing_window_program(scene: &Arc<Scene>, program_id: SubProgramI
             let mut drawing_since_last_frame    = false;
             let mut closed                      = false;
 
+            // The drawing history is used to capture frames by re-rendering them offscreen
+            let drawing_history                 = drawing_history;
+            let mut frame_depth                 = 0usize;
+            let mut pending_captures            = vec![];
+            let mut pending_texture_captures    = vec![];
+
+            // Programs waiting to hear when a frame has been presented
+            let mut present_notifications       = PresentNotifications::new();
+
             // Pause the drawing using a start frame event
             render_state.draw(vec![Draw::StartFrame].iter(), &mut render_target).await;
 
@@ -263,20 +566,98 @@ pub fn create_drawing_window_program(scene: &Arc<Scene>, program_id: SubProgramI
             while let Some(message) = messages.next().await {
                 match message {
                     DrawingOrEvent::Drawing(drawing_list) => {
-                        // Perform all the actions in a single frame
-                        let mut combined_list   = vec![Arc::new(vec![Draw::StartFrame])];
-
                         // If we've rendered something and 'NewFrame' hasn't yet been generated, add an extra 'StartFrame' to suspend rendering until the last frame is finished
+                        let mut num_start_frames = 1;
                         if waiting_for_new_frame.is_some() && !drawing_since_last_frame {
                             drawing_since_last_frame = true;
-                            combined_list.push(Arc::new(vec![Draw::StartFrame]));
+                            num_start_frames += 1;
                         }
 
+                        // Perform all the actions in a single frame
+                        let mut frame_batch = FrameBatch::new(num_start_frames);
+
                         for draw_msg in drawing_list {
                             match draw_msg {
                                 DrawingWindowRequest::Draw(DrawingRequest::Draw(drawing)) => {
+                                    // Track whether or not the drawing is in the middle of a frame (captures wait for the frame to finish)
+                                    for draw in drawing.iter() {
+                                        match draw {
+                                            Draw::StartFrame        => { frame_depth += 1; }
+                                            Draw::ShowFrame         => { frame_depth = frame_depth.saturating_sub(1); }
+                                            Draw::ClearCanvas(_)    => { frame_depth = 0; }
+                                            Draw::ResetFrame        => { frame_depth = 0; }
+                                            _                       => { }
+                                        }
+                                    }
+
                                     // Send the drawing to the renderer
-                                    combined_list.push(drawing);
+                                    if let DrawingHistory::Recorded(history) = &drawing_history {
+                                        history.write((*drawing).clone());
+                                    }
+                                    frame_batch.push(drawing);
+                                }
+
+                                DrawingWindowRequest::CaptureFrame(target_program) => {
+                                    pending_captures.push(target_program);
+                                }
+
+                                DrawingWindowRequest::CaptureTexture(texture_id, target_program) => {
+                                    pending_texture_captures.push((texture_id, target_program));
+                                }
+
+                                DrawingWindowRequest::SetViewTransform(view_transform) => {
+                                    // Takes effect when the frame is rendered below
+                                    render_state.renderer.set_view_transform(view_transform);
+                                }
+
+                                DrawingWindowRequest::QueryTransforms(target_program) => {
+                                    // Process the drawing received before the query so the transforms reflect its state (the frame is still suspended, so nothing is displayed yet)
+                                    let drawing = frame_batch.take_drawing();
+                                    render_state.draw(drawing.iter().flat_map(|item| item.iter()), &mut render_target).await;
+
+                                    let transforms = CanvasTransforms {
+                                        current_transform:  render_state.renderer.get_active_transform(),
+                                        viewport_transform: render_state.renderer.get_viewport_transform(),
+                                    };
+
+                                    if let Ok(mut target) = context.send::<CanvasTransforms>(target_program) {
+                                        target.send(transforms).await.ok();
+                                    }
+                                }
+
+                                DrawingWindowRequest::QuerySpriteBounds(namespace_id, sprite_id, target_program) => {
+                                    // As for the transforms, the drawing received before the query needs to be processed first so the sprite is up to date
+                                    let drawing = frame_batch.take_drawing();
+                                    render_state.draw(drawing.iter().flat_map(|item| item.iter()), &mut render_target).await;
+
+                                    let sprite_bounds = SpriteBoundsResult {
+                                        namespace_id:   namespace_id,
+                                        sprite_id:      sprite_id,
+                                        bounds:         render_state.renderer.get_sprite_bounds(namespace_id, sprite_id),
+                                    };
+
+                                    if let Ok(mut target) = context.send::<SpriteBoundsResult>(target_program) {
+                                        target.send(sprite_bounds).await.ok();
+                                    }
+                                }
+
+                                DrawingWindowRequest::NotifyWhenPresented(target_program) => {
+                                    present_notifications.notify_when_presented(target_program);
+                                }
+
+                                DrawingWindowRequest::RedrawAll => {
+                                    // Start again with a new renderer, and discard the drawing so far (it's replaced by the whole drawing)
+                                    let free_actions = render_state.reset_renderer();
+                                    render_target.send(RenderWindowRequest::Render(RenderRequest::Render(free_actions))).await.ok();
+
+                                    // The new renderer needs to be suspended in the same way as the old one: by this batch, by the initial frame if the window isn't ready yet, and by the frame waiting for 'NewFrame'
+                                    let suspended_frames = 1 + (!ready_to_render as usize) + (drawing_since_last_frame as usize);
+                                    frame_batch.restart(suspended_frames);
+
+                                    // Recorded drawings are replayed here: when the drawing is from a canvas, the source program has restarted its stream, which will send the whole drawing next
+                                    if let DrawingHistory::Recorded(history) = &drawing_history {
+                                        frame_batch.push(Arc::new(history.get_drawing()));
+                                    }
                                 }
 
                                 DrawingWindowRequest::CloseWindow => {
@@ -294,18 +675,54 @@ pub fn create_drawing_window_program(scene: &Arc<Scene>, program_id: SubProgramI
                                 DrawingWindowRequest::SetFullScreen(fullscreen)         => { render_target.send(RenderWindowRequest::SetFullScreen(fullscreen)).await.ok(); },
                                 DrawingWindowRequest::SetHasDecorations(decorations)    => { render_target.send(RenderWindowRequest::SetHasDecorations(decorations)).await.ok(); },
                                 DrawingWindowRequest::SetMousePointer(mouse_pointer)    => { render_target.send(RenderWindowRequest::SetMousePointer(mouse_pointer)).await.ok(); },
+                                DrawingWindowRequest::SetPresentMode(present_mode)      => { render_target.send(RenderWindowRequest::SetPresentMode(present_mode)).await.ok(); },
+                                DrawingWindowRequest::SetMultisampling(samples)         => { render_target.send(RenderWindowRequest::SetMultisampling(samples)).await.ok(); },
+                                DrawingWindowRequest::SetMaxFrameRate(frame_rate)       => { render_target.send(RenderWindowRequest::SetMaxFrameRate(frame_rate)).await.ok(); },
                             }
                         }
 
                         // Commit the frame. We'll add backpressure to new drawing events by not accepting them.
                         waiting_for_new_frame = Some(ingress_blocker.block());
 
-                        combined_list.push(Arc::new(vec![Draw::ShowFrame]));
-                        render_state.draw(combined_list.iter()
+                        let drawing = frame_batch.finish();
+                        render_state.draw(drawing.iter()
                             .flat_map(|item| item.iter()), &mut render_target).await;
 
                         // Update the window transform according to the drawing actions we processed
-                        render_state.update_window_transform();
+                        let old_transform       = render_state.window_transform;
+                        let window_transform    = render_state.update_window_transform();
+
+                        // Let the subscribers know if the canvas transform has changed, so they can keep converting pointer events to canvas coordinates
+                        if old_transform != Some(window_transform) {
+                            send_to_subscribers(&mut subscribers, &DrawEvent::CanvasTransform(window_transform)).await;
+                        }
+
+                        // If the drawing was shown by this commit, the next 'NewFrame' event indicates that it has been presented
+                        if frame_depth == 0 && !drawing_since_last_frame {
+                            present_notifications.frame_shown();
+                        }
+
+                        // Capture the frame if there are any requests waiting and the drawing isn't in the middle of a frame
+                        if frame_depth == 0 && !pending_captures.is_empty() {
+                            let captured_frame = capture_frame(drawing_history.get_drawing(), &render_state).await;
+
+                            for target_program in pending_captures.drain(..) {
+                                if let Ok(mut target) = context.send::<CapturedFrame>(target_program) {
+                                    target.send(captured_frame.clone()).await.ok();
+                                }
+                            }
+                        }
+
+                        // Texture captures also wait for the frame to finish
+                        if frame_depth == 0 && !pending_texture_captures.is_empty() {
+                            for (texture_id, target_program) in pending_texture_captures.drain(..) {
+                                let captured_texture = capture_texture(drawing_history.get_drawing(), texture_id, &render_state).await;
+
+                                if let Ok(mut target) = context.send::<CapturedTexture>(target_program) {
+                                    target.send(captured_texture).await.ok();
+                                }
+                            }
+                        }
                     }
 
                     DrawingOrEvent::Event(event_list) => {
@@ -340,6 +757,30 @@ pub fn create_drawing_window_program(scene: &Arc<Scene>, program_id: SubProgramI
                                     }
                                 },
 
+                                DrawEvent::DeviceReset => {
+                                    // The render window has lost all of its resources, so replay the drawing using a new canvas renderer (there's nothing left to free)
+                                    render_state.reset_renderer();
+
+                                    match &drawing_history {
+                                        DrawingHistory::Recorded(history) => {
+                                            let drawing = history.get_drawing();
+                                            render_state.draw(drawing.iter(), &mut render_target).await;
+                                        }
+
+                                        DrawingHistory::Canvas(_, source_program) => {
+                                            // Ask the source program to send the whole drawing again (from another program, as the source program may be waiting for us to accept its drawing)
+                                            let source_program = *source_program;
+                                            window_scene.add_subprogram(SubProgramId::new(),
+                                                move |_: InputStream<()>, context| async move {
+                                                    if let Ok(mut source) = context.send::<DrawingWindowRequest>(source_program) {
+                                                        source.send(DrawingWindowRequest::RedrawAll).await.ok();
+                                                    }
+                                                },
+                                                0);
+                                        }
+                                    }
+                                }
+
                                 DrawEvent::Closed => {
                                     // Close events terminate the loop (after we've finshed processing the events)
                                     closed = true;
@@ -349,11 +790,22 @@ pub fn create_drawing_window_program(scene: &Arc<Scene>, program_id: SubProgramI
                                     // A new frame was displayed
                                     waiting_for_new_frame = None;
 
+                                    for target_program in present_notifications.frame_presented() {
+                                        if let Ok(mut target) = context.send::<FramePresented>(target_program) {
+                                            target.send(FramePresented).await.ok();
+                                        }
+                                    }
+
                                     if drawing_since_last_frame {
                                         // Finalize any drawing that occurred while we were waiting for the new frame to display
                                         waiting_for_new_frame = Some(ingress_blocker.block());
                                         render_state.draw(vec![Draw::ShowFrame].iter(), &mut render_target).await;
                                         drawing_since_last_frame = false;
+
+                                        // The deferred drawing will be presented with the next frame
+                                        if frame_depth == 0 {
+                                            present_notifications.frame_shown();
+                                        }
                                     }
                                 }
 
@@ -361,17 +813,11 @@ pub fn create_drawing_window_program(scene: &Arc<Scene>, program_id: SubProgramI
                             }
 
                             // Publish the event to any subscribers
-                            for idx in (0..subscribers.len()).rev() {
-                                let target = &mut subscribers[idx];
-
-                                if target.send(evt_message.clone()).await.is_err() {
-                                    subscribers.remove(idx);
-                                }
-                            }
+                            send_to_subscribers(&mut subscribers, &evt_message).await;
 
                             // Handle the next message
-                            let context = &context;
-                            handle_window_event(&mut render_state, evt_message, &mut move |render_actions| {
+                            let context         = &context;
+                            let extra_events    = handle_window_event(&mut render_state, evt_message, &mut move |render_actions| {
                                 let render_target = context.send::<RenderWindowRequest>(render_target_program);
 
                                 async move {
@@ -380,6 +826,11 @@ pub fn create_drawing_window_program(scene: &Arc<Scene>, program_id: SubProgramI
                                     }
                                 }
                             }).await;
+
+                            // Publish any events that were generated while handling the event
+                            for extra_event in extra_events.iter() {
+                                send_to_subscribers(&mut subscribers, extra_event).await;
+                            }
                         }
 
                         // The entity stops when the window is closed
@@ -393,6 +844,19 @@ pub fn create_drawing_window_program(scene: &Arc<Scene>, program_id: SubProgramI
             // Shut down
             render_target.send(RenderWindowRequest::CloseWindow).await.ok();
 
+            // Captures that are still waiting for a frame to finish will never be made, so send empty results for them
+            for target_program in pending_captures.drain(..) {
+                if let Ok(mut target) = context.send::<CapturedFrame>(target_program) {
+                    target.send(CapturedFrame { width: 0, height: 0, pixels: vec![] }).await.ok();
+                }
+            }
+
+            for (texture_id, target_program) in pending_texture_captures.drain(..) {
+                if let Ok(mut target) = context.send::<CapturedTexture>(target_program) {
+                    target.send(CapturedTexture { texture_id, width: 0, height: 0, pixels: vec![] }).await.ok();
+                }
+            }
+
             use std::mem;
 
             // Drop the receivers
@@ -412,3 +876,60 @@ pub fn create_drawing_window_program(scene: &Arc<Scene>, program_id: SubProgramI
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn present_is_notified_after_frame_is_shown() {
+        let mut present_notifications   = PresentNotifications::new();
+        let target_program              = SubProgramId::new();
+
+        present_notifications.notify_when_presented(target_program);
+
+        // A 'NewFrame' for an earlier frame doesn't present the drawing the request was waiting for
+        assert!(present_notifications.frame_presented().is_empty());
+
+        // Once the drawing is shown, the next 'NewFrame' presents it
+        present_notifications.frame_shown();
+        assert!(present_notifications.frame_presented() == vec![target_program]);
+        assert!(present_notifications.frame_presented().is_empty());
+    }
+
+    #[test]
+    fn request_after_frame_is_shown_waits_for_following_frame() {
+        let mut present_notifications   = PresentNotifications::new();
+        let first_program               = SubProgramId::new();
+        let second_program              = SubProgramId::new();
+
+        present_notifications.notify_when_presented(first_program);
+        present_notifications.frame_shown();
+
+        // Arrives while the first frame is waiting to be presented
+        present_notifications.notify_when_presented(second_program);
+
+        assert!(present_notifications.frame_presented() == vec![first_program]);
+
+        present_notifications.frame_shown();
+        assert!(present_notifications.frame_presented() == vec![second_program]);
+    }
+
+    #[test]
+    fn redraw_after_query_suspends_new_renderer() {
+        let mut frame_batch = FrameBatch::new(1);
+        frame_batch.push(Arc::new(vec![Draw::Fill]));
+
+        // A query sends the drawing so far to the renderer, including the 'StartFrame' for the batch
+        let sent_drawing = frame_batch.take_drawing().iter().flat_map(|item| item.iter()).cloned().collect::<Vec<_>>();
+        assert!(sent_drawing == vec![Draw::StartFrame, Draw::Fill], "{:?}", sent_drawing);
+
+        // Redrawing replaces the renderer, which needs to be suspended again (here by the batch and the window's initial frame)
+        frame_batch.push(Arc::new(vec![Draw::Stroke]));
+        frame_batch.restart(2);
+        frame_batch.push(Arc::new(vec![Draw::Fill, Draw::Stroke]));
+
+        let sent_drawing = frame_batch.finish().iter().flat_map(|item| item.iter()).cloned().collect::<Vec<_>>();
+        assert!(sent_drawing == vec![Draw::StartFrame, Draw::StartFrame, Draw::Fill, Draw::Stroke, Draw::ShowFrame], "{:?}", sent_drawing);
+    }
+}
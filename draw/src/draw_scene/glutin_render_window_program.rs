@@ -31,6 +31,9 @@ pub fn create_glutin_render_window_program(scene: &Arc<Scene>, program_id: SubPr
             let fullscreen          = bind(false);
             let has_decorations     = bind(true);
             let mouse_pointer       = bind(MousePointer::SystemDefault);
+            let present_mode        = bind(PresentMode::Vsync);
+            let multisampling       = bind(4);
+            let max_frame_rate      = bind(0);
             let size                = bind(initial_size);
 
             let window_properties   = WindowProperties { 
@@ -38,6 +41,9 @@ pub fn create_glutin_render_window_program(scene: &Arc<Scene>, program_id: SubPr
                 fullscreen:         BindRef::from(fullscreen.clone()), 
                 has_decorations:    BindRef::from(has_decorations.clone()), 
                 mouse_pointer:      BindRef::from(mouse_pointer.clone()), 
+                present_mode:       BindRef::from(present_mode.clone()),
+                multisampling:      BindRef::from(multisampling.clone()),
+                max_frame_rate:     BindRef::from(max_frame_rate.clone()),
                 size:               BindRef::from(size.clone()),
             };
             let mut event_publisher = Publisher::new(1000);
@@ -111,6 +117,9 @@ pub fn create_glutin_render_window_program(scene: &Arc<Scene>, program_id: SubPr
                         RenderWindowRequest::SetFullScreen(new_fullscreen)      => { fullscreen.set(new_fullscreen); },
                         RenderWindowRequest::SetHasDecorations(new_decorations) => { has_decorations.set(new_decorations); },
                         RenderWindowRequest::SetMousePointer(new_mouse_pointer) => { mouse_pointer.set(new_mouse_pointer); },
+                        RenderWindowRequest::SetPresentMode(new_present_mode)   => { present_mode.set(new_present_mode); },
+                        RenderWindowRequest::SetMultisampling(new_samples)     => { multisampling.set(new_samples); },
+                        RenderWindowRequest::SetMaxFrameRate(new_frame_rate)   => { max_frame_rate.set(new_frame_rate); },
                     }
                 }
             }
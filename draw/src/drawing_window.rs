@@ -7,14 +7,22 @@ use flo_canvas::*;
 use flo_canvas::scenery::*;
 use flo_binding::*;
 use flo_scene::*;
+use flo_render::RenderInitError;
 
 use futures::prelude::*;
+use futures::future;
+use futures::future::{Either};
+use futures::stream;
+use futures::stream::{BoxStream};
+use futures::executor;
 use futures::channel::mpsc;
+use futures::channel::oneshot;
 use futures::task::{Poll, Context};
 
 use std::mem;
 use std::pin::*;
 use std::sync::*;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 
 const MAX_BATCH_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
@@ -36,6 +44,31 @@ where
 /// Creates a drawing target that will render to a window, along with a stream of events from that window
 ///
 pub fn create_drawing_window_with_events<'a, TProperties>(window_properties: TProperties) -> (DrawingTarget, impl Send + Stream<Item=DrawEvent>) 
+where
+    TProperties: 'a + FloWindowProperties,
+{
+    let (target, _view, events) = create_drawing_window_with_view(window_properties);
+
+    (target, events)
+}
+
+///
+/// Creates a drawing target that will render to a window, along with a stream of events from that window and a way to capture the contents of the window
+///
+pub fn create_drawing_window_with_capture<'a, TProperties>(window_properties: TProperties) -> (DrawingTarget, impl Send + Stream<Item=DrawEvent>, WindowFrameCapture) 
+where
+    TProperties: 'a + FloWindowProperties,
+{
+    let (target, view, events) = create_drawing_window_with_view(window_properties);
+
+    (target, events, view.frame_capture())
+}
+
+///
+/// Creates a drawing target that will render to a window, along with a view that can be used to query or change how the window displays
+/// the drawing, and a stream of events from that window
+///
+pub fn create_drawing_window_with_view<'a, TProperties>(window_properties: TProperties) -> (DrawingTarget, WindowView, impl Send + Stream<Item=DrawEvent>) 
 where
     TProperties: 'a + FloWindowProperties,
 {
@@ -51,17 +84,18 @@ where
     });
 
     // Get the stream of drawing instructions (and gather them into batches)
+    let flush_frame         = Arc::new(AtomicBool::new(false));
     let target_stream       = stream;
     let target_stream       = drawing_without_dashed_lines(target_stream);
     let target_stream       = drawing_with_laid_out_text(target_stream);
     let target_stream       = drawing_with_text_as_paths(target_stream);
-    let target_stream       = BatchedStream { stream: Some(target_stream), frame_count: 0, waiting: vec![] };
+    let target_stream       = BatchedStream::new(target_stream, Arc::clone(&flush_frame));
 
     // Create the events stream
-    let events              = create_drawing_window_from_stream(target_stream, window_properties);
+    let (events, capture)   = create_drawing_window_from_source(target_stream.boxed(), None, flush_frame, window_properties);
 
     // Return the result
-    (target, events)
+    (target, capture.window_view(), events)
 }
 
 ///
@@ -81,6 +115,31 @@ pub fn create_canvas_window<'a, TProperties: 'a+FloWindowProperties>(window_prop
 /// Creates a drawing target that will render to a window, along with a stream of events from that window
 ///
 pub fn create_canvas_window_with_events<'a, TProperties>(window_properties: TProperties) -> (Canvas, impl Send + Sync + Stream<Item=DrawEvent>) 
+where
+    TProperties: 'a + FloWindowProperties,
+{
+    let (canvas, events, _capture) = create_canvas_window_with_capture(window_properties);
+
+    (canvas, events)
+}
+
+///
+/// Creates a canvas that will render to a window, along with a stream of events from that window and a way to capture the contents of the window
+///
+pub fn create_canvas_window_with_capture<'a, TProperties>(window_properties: TProperties) -> (Canvas, impl Send + Sync + Stream<Item=DrawEvent>, WindowFrameCapture) 
+where
+    TProperties: 'a + FloWindowProperties,
+{
+    let (canvas, view, events) = create_canvas_window_with_view(window_properties);
+
+    (canvas, events, view.frame_capture())
+}
+
+///
+/// Creates a canvas that will render to a window, along with a view that can be used to query or change how the window displays the
+/// canvas, and a stream of events from that window
+///
+pub fn create_canvas_window_with_view<'a, TProperties>(window_properties: TProperties) -> (Canvas, WindowView, impl Send + Sync + Stream<Item=DrawEvent>) 
 where
     TProperties: 'a + FloWindowProperties,
 {
@@ -95,18 +154,96 @@ where
         gc.center_region(0.0, 0.0, width as _, height as _);
     });
 
-    // Get the stream of drawing instructions (and gather them into batches)
+    // Create the events stream
+    let flush_frame         = Arc::new(AtomicBool::new(false));
+    let (events, capture)   = create_drawing_window_from_source(canvas_drawing_stream(&canvas, &flush_frame), Some(canvas.clone()), flush_frame, window_properties);
+
+    // Return the result
+    (canvas, capture.window_view(), events)
+}
+
+///
+/// Creates a canvas that will render to a window, returning an error if the window's renderer can't be started
+///
+/// This is the same as `create_canvas_window()`, except that it waits for the window to display its first frame, so it
+/// can report problems such as there being no GPU available instead of leaving a window that never draws anything.
+///
+pub fn try_create_canvas_window<'a, TProperties: 'a+FloWindowProperties>(window_properties: TProperties) -> Result<Canvas, RenderInitError> {
+    let (canvas, _events) = try_create_canvas_window_with_events(window_properties)?;
+
+    // Dropping the events will stop the window from blocking when they're not handled
+    Ok(canvas)
+}
+
+///
+/// Creates a canvas that will render to a window along with a stream of events from that window, returning an error if the
+/// window's renderer can't be started
+///
+/// The events that arrive while waiting for the first frame are still returned in the event stream.
+///
+pub fn try_create_canvas_window_with_events<'a, TProperties>(window_properties: TProperties) -> Result<(Canvas, impl Send + Sync + Stream<Item=DrawEvent>), RenderInitError> 
+where
+    TProperties: 'a + FloWindowProperties,
+{
+    let (canvas, events)    = create_canvas_window_with_events(window_properties);
+    let mut events          = Box::pin(events);
+    let initial_events      = executor::block_on(wait_for_first_frame(&mut events))?;
+
+    Ok((canvas, stream::iter(initial_events).chain(events)))
+}
+
+///
+/// Waits for the first frame to be displayed (or for the renderer to report that it couldn't start), returning the events that arrived up to that point
+///
+async fn wait_for_first_frame(events: &mut (impl Unpin + Stream<Item=DrawEvent>)) -> Result<Vec<DrawEvent>, RenderInitError> {
+    let mut initial_events = vec![];
+
+    loop {
+        match events.next().await {
+            Some(DrawEvent::RenderError(err))   => { return Err(err); }
+            Some(DrawEvent::Closed) | None      => { return Err(RenderInitError::WindowClosed); }
+            Some(DrawEvent::NewFrame)           => { initial_events.push(DrawEvent::NewFrame); break; }
+            Some(other_event)                   => { initial_events.push(other_event); }
+        }
+    }
+
+    Ok(initial_events)
+}
+
+///
+/// Creates an extra window that displays the contents of an existing canvas
+///
+/// This can be used to show the same canvas in several windows. The returned `WindowView` can be used to zoom or pan
+/// the view shown in the new window without changing the canvas itself.
+///
+/// Windows share a GPU device where the adapter allows it, but each window still has its own renderer: the canvas
+/// is tessellated separately for each window and its vertex buffers and textures are uploaded once per window.
+///
+pub fn create_window_for_canvas<'a, TProperties>(canvas: &Canvas, window_properties: TProperties) -> (WindowView, impl Send + Sync + Stream<Item=DrawEvent>) 
+where
+    TProperties: 'a + FloWindowProperties,
+{
+    // Create the events stream
+    let flush_frame         = Arc::new(AtomicBool::new(false));
+    let (events, capture)   = create_drawing_window_from_source(canvas_drawing_stream(canvas, &flush_frame), Some(canvas.clone()), flush_frame, window_properties);
+
+    (capture.window_view(), events)
+}
+
+///
+/// Returns the stream of drawing instructions for a window showing a canvas, gathered into batches
+///
+/// The stream begins with the whole of the drawing on the canvas so far. Drawing inside a frame is held back until the frame is
+/// shown, unless the `flush_frame` flag is set.
+///
+fn canvas_drawing_stream(canvas: &Canvas, flush_frame: &Arc<AtomicBool>) -> BoxStream<'static, Vec<Draw>> {
     let canvas_stream       = canvas.stream();
     let canvas_stream       = drawing_without_dashed_lines(canvas_stream);
     let canvas_stream       = drawing_with_laid_out_text(canvas_stream);
     let canvas_stream       = drawing_with_text_as_paths(canvas_stream);
-    let canvas_stream       = BatchedStream { stream: Some(canvas_stream), frame_count: 0, waiting: vec![] };
-
-    // Create the events stream
-    let events              = create_drawing_window_from_stream(canvas_stream, window_properties);
+    let canvas_stream       = BatchedStream::new(canvas_stream, Arc::clone(flush_frame));
 
-    // Return the result
-    (canvas, events)
+    canvas_stream.boxed()
 }
 
 ///
@@ -116,16 +253,46 @@ pub fn create_drawing_window_from_stream<'a, DrawStream, TProperties>(canvas_str
 where
     DrawStream:  'static + Send + Unpin + Stream<Item=Vec<Draw>>,
     TProperties: 'a + FloWindowProperties,
+{
+    let (events, _capture) = create_drawing_window_from_stream_with_capture(canvas_stream, window_properties);
+
+    events
+}
+
+///
+/// Creates a drawing window that will render a stream of drawing instructions, along with a way to capture the contents of the window
+///
+pub fn create_drawing_window_from_stream_with_capture<'a, DrawStream, TProperties>(canvas_stream: DrawStream, window_properties: TProperties) -> (impl Send + Stream<Item=DrawEvent>, WindowFrameCapture)
+where
+    DrawStream:  'static + Send + Unpin + Stream<Item=Vec<Draw>>,
+    TProperties: 'a + FloWindowProperties,
+{
+    create_drawing_window_from_source(canvas_stream.boxed(), None, Arc::new(AtomicBool::new(false)), window_properties)
+}
+
+///
+/// Creates a drawing window for a stream of drawing instructions, which can be restarted from a canvas if one is supplied
+///
+/// If there's no canvas, the window keeps its own copy of the drawing so that it can be redrawn from scratch. `flush_frame` is the
+/// flag used to make the canvas stream send a frame that's still in progress, so queries can be sent after the drawing before them.
+///
+fn create_drawing_window_from_source<'a, TProperties>(canvas_stream: BoxStream<'static, Vec<Draw>>, source_canvas: Option<Canvas>, flush_frame: Arc<AtomicBool>, window_properties: TProperties) -> (impl Send + Stream<Item=DrawEvent>, WindowFrameCapture)
+where
+    TProperties: 'a + FloWindowProperties,
 {
     let properties              = WindowProperties::from(&window_properties);
 
     // Create a new render window entity
     let render_window_program   = SubProgramId::new();
     let drawing_window_program  = SubProgramId::new();
+    let processing_subprogram   = SubProgramId::new();
     let scene_context           = flo_draw_scene_context();
 
     create_render_window_sub_program(&scene_context, render_window_program, window_properties.size().get()).unwrap();
-    create_drawing_window_program(&scene_context, drawing_window_program, render_window_program).unwrap();
+    match &source_canvas {
+        Some(canvas)    => create_drawing_window_program_for_canvas(&scene_context, drawing_window_program, render_window_program, canvas.clone(), processing_subprogram).unwrap(),
+        None            => create_drawing_window_program(&scene_context, drawing_window_program, render_window_program).unwrap(),
+    }
 
     // Use a channel to get the events out of the program
     let (send_events, recv_events)  = mpsc::channel(20);
@@ -151,10 +318,10 @@ where
         0);
 
     // Pass events from the render stream onto the window using another entity (potentially this could be a background task for the render window entity?)
-    let processing_subprogram = SubProgramId::new();
-    scene_context.add_subprogram(processing_subprogram, move |_: InputStream<()>, context| {
+    scene_context.add_subprogram(processing_subprogram, move |requests: InputStream<DrawingWindowRequest>, context| {
         async move {
             let mut canvas_stream   = canvas_stream;
+            let mut requests        = requests;
             let mut drawing_channel = context.send::<DrawingWindowRequest>(drawing_window_program).unwrap();
 
             // Send the window properties to the window
@@ -163,11 +330,46 @@ where
             // Request event actions from the renderer to the relay program (which sends them on to the stream returned from this function)
             drawing_channel.send(DrawingWindowRequest::SendEvents(event_relay_program)).await.ok();
 
-            // Main loop passes on the render actions (we don't process messages directed at this entity)
-            while let Some(drawing_actions) = canvas_stream.next().await {
-                let maybe_err = drawing_channel.send(DrawingWindowRequest::Draw(DrawingRequest::Draw(Arc::new(drawing_actions)))).await;
+            // Main loop passes on the render actions, and any requests sent to this program (drawing that's ready is always sent first)
+            loop {
+                let next_item = match future::select(canvas_stream.next(), requests.next()).await {
+                    Either::Left((drawing, _))  => Either::Left(drawing),
+                    Either::Right((request, _)) => Either::Right(request),
+                };
+
+                let next_request = match next_item {
+                    Either::Left(Some(drawing_actions)) => DrawingWindowRequest::Draw(DrawingRequest::Draw(Arc::new(drawing_actions))),
+
+                    Either::Left(None) => {
+                        // A frame that was in progress when the drawing stopped can never be finished, so stop waiting for it (requests are still passed on)
+                        canvas_stream = stream::pending().boxed();
+                        DrawingWindowRequest::Draw(DrawingRequest::Draw(Arc::new(vec![Draw::ResetFrame])))
+                    }
+
+                    Either::Right(Some(DrawingWindowRequest::RedrawAll)) => {
+                        // Canvases are redrawn by restarting the stream, which begins with the whole of the drawing
+                        if let Some(source_canvas) = &source_canvas {
+                            canvas_stream = canvas_drawing_stream(source_canvas, &flush_frame);
+                        }
+
+                        DrawingWindowRequest::RedrawAll
+                    }
+
+                    Either::Right(Some(request)) => {
+                        // Queries are answered with the state after the drawing sent before them, so that's sent first (even if it's in the middle of a frame)
+                        if waits_for_drawing(&request) {
+                            for drawing in take_ready_drawing(&mut canvas_stream, &flush_frame) {
+                                drawing_channel.send(drawing).await.ok();
+                            }
+                        }
+
+                        request
+                    }
+
+                    Either::Right(None)                 => { break; }
+                };
 
-                if maybe_err.is_err() {
+                if drawing_channel.send(next_request).await.is_err() {
                     // Stop if the request doesn't go through
                     break;
                 }
@@ -175,8 +377,310 @@ where
         }
     }, 0);
 
-    // The events stream is the result
-    recv_events
+    // The events stream and the capture object are the result
+    (recv_events, WindowFrameCapture { drawing_window_program, source_program: processing_subprogram })
+}
+
+///
+/// True if a request reads the state of the window, and so needs to be sent after the drawing that was sent before it
+///
+fn waits_for_drawing(request: &DrawingWindowRequest) -> bool {
+    match request {
+        DrawingWindowRequest::QueryTransforms(_)            |
+        DrawingWindowRequest::QuerySpriteBounds(_, _, _)    |
+        DrawingWindowRequest::CaptureFrame(_)               |
+        DrawingWindowRequest::CaptureTexture(_, _)          |
+        DrawingWindowRequest::NotifyWhenPresented(_)        => true,
+        _                                                   => false,
+    }
+}
+
+///
+/// Takes the drawing that's ready to be sent from a drawing stream, including any frame that's still in progress
+///
+/// If the stream has finished, this replaces it with an empty one and returns a `ResetFrame`, the same as when the end of the
+/// stream is reached normally.
+///
+fn take_ready_drawing(canvas_stream: &mut BoxStream<'static, Vec<Draw>>, flush_frame: &AtomicBool) -> Vec<DrawingWindowRequest> {
+    let mut ready_drawing = vec![];
+
+    // The batched stream holds back the drawing for a frame until it's shown, unless it's asked to flush it
+    flush_frame.store(true, Ordering::Release);
+
+    while let Some(drawing) = canvas_stream.next().now_or_never() {
+        match drawing {
+            Some(drawing_actions) => {
+                ready_drawing.push(DrawingWindowRequest::Draw(DrawingRequest::Draw(Arc::new(drawing_actions))));
+            }
+
+            None => {
+                *canvas_stream = stream::pending().boxed();
+                ready_drawing.push(DrawingWindowRequest::Draw(DrawingRequest::Draw(Arc::new(vec![Draw::ResetFrame]))));
+                break;
+            }
+        }
+    }
+
+    flush_frame.store(false, Ordering::Release);
+
+    ready_drawing
+}
+
+///
+/// Captures the contents of a drawing window
+///
+/// This is returned by `create_canvas_window_with_capture()` and `create_drawing_window_with_capture()`, and can also be retrieved from
+/// a `WindowView` by calling `frame_capture()`.
+///
+#[derive(Clone, Copy, Debug)]
+pub struct WindowFrameCapture {
+    /// The program that processes the drawing requests for the window
+    drawing_window_program: SubProgramId,
+
+    /// The program that sends the drawing to the window
+    source_program: SubProgramId,
+}
+
+///
+/// Changes how a drawing window displays its canvas, and queries what it's showing
+///
+/// This is returned by `create_canvas_window_with_view()`, `create_drawing_window_with_view()` and `create_window_for_canvas()`.
+///
+#[derive(Clone, Copy, Debug)]
+pub struct WindowView {
+    /// The program that processes the drawing requests for the window
+    drawing_window_program: SubProgramId,
+
+    /// The program that sends the drawing to the window
+    source_program: SubProgramId,
+}
+
+impl WindowView {
+    ///
+    /// Sets a transform to apply to the canvas when it's displayed in this window
+    ///
+    /// This is applied after the canvas transform, in normalized coordinates where the window runs from -1 to 1 vertically
+    /// and 0,0 is the center of the window: for example, `Transform2D::scale(2.0, 2.0)` will zoom in on the center of the
+    /// window. Pointer events will report canvas coordinates that take this transform into account.
+    ///
+    pub fn set_view_transform(&self, view_transform: Transform2D) {
+        let drawing_window_program = self.drawing_window_program;
+
+        flo_draw_scene_context().add_subprogram(SubProgramId::new(), 
+            move |_: InputStream<()>, context| async move {
+                if let Ok(mut drawing_window) = context.send::<DrawingWindowRequest>(drawing_window_program) {
+                    drawing_window.send(DrawingWindowRequest::SetViewTransform(view_transform)).await.ok();
+                }
+            },
+            0);
+    }
+
+    ///
+    /// Redraws the whole canvas in this window from scratch
+    ///
+    /// The renderer only tessellates and uploads the parts of the canvas that have changed since the last frame, reusing what
+    /// it generated before for everything else. This discards everything it has kept and regenerates it from the drawing.
+    ///
+    pub fn redraw_all(&self) {
+        // Sent via the source program, which restarts the drawing from the canvas
+        let source_program = self.source_program;
+
+        flo_draw_scene_context().add_subprogram(SubProgramId::new(), 
+            move |_: InputStream<()>, context| async move {
+                if let Ok(mut drawing_window) = context.send::<DrawingWindowRequest>(source_program) {
+                    drawing_window.send(DrawingWindowRequest::RedrawAll).await.ok();
+                }
+            },
+            0);
+    }
+
+    ///
+    /// Requests the transforms in effect for the canvas in this window
+    ///
+    /// These are answered by the window once it has processed all the drawing sent before the request, so any `PushState`,
+    /// `PopState` or transform instructions that were sent are taken into account. The result is `None` if the window has
+    /// been closed.
+    ///
+    pub fn request_transforms(&self) -> impl Send + Future<Output=Option<CanvasTransforms>> {
+        // Sent via the source program so the request arrives after the drawing
+        let source_program                      = self.source_program;
+        let query_program                       = SubProgramId::new();
+        let (send_transforms, recv_transforms)  = oneshot::channel();
+
+        // Create a program to query the transforms and wait for the result
+        flo_draw_scene_context().add_subprogram(query_program,
+            move |mut transforms: InputStream<CanvasTransforms>, context| async move {
+                if let Ok(mut drawing_window) = context.send::<DrawingWindowRequest>(source_program) {
+                    drawing_window.send(DrawingWindowRequest::QueryTransforms(query_program)).await.ok();
+
+                    let canvas_transforms = transforms.next().await;
+                    send_transforms.send(canvas_transforms).ok();
+                }
+            },
+            0);
+
+        async move {
+            recv_transforms.await.ok().flatten()
+        }
+    }
+
+    ///
+    /// Requests the transform set by the drawing, which maps canvas coordinates to normalized window coordinates
+    ///
+    pub fn current_transform(&self) -> impl Send + Future<Output=Option<Transform2D>> {
+        let transforms = self.request_transforms();
+        async move { transforms.await.map(|transforms| transforms.current_transform) }
+    }
+
+    ///
+    /// Requests the transform that maps canvas coordinates to pixels in the window's viewport
+    ///
+    pub fn viewport_transform(&self) -> impl Send + Future<Output=Option<Transform2D>> {
+        let transforms = self.request_transforms();
+        async move { transforms.await.map(|transforms| transforms.viewport_transform) }
+    }
+
+    ///
+    /// Requests the bounds of a sprite that has been drawn in this window, in the sprite's own coordinates
+    ///
+    /// This is answered once the window has processed all the drawing sent before the request, so it includes anything that
+    /// was drawn into the sprite up to that point. Sprites drawn without selecting a namespace are in `NamespaceId::default()`.
+    /// The result is `None` if the window has been closed, or if the sprite is empty or has not been defined.
+    ///
+    pub fn sprite_bounds(&self, namespace_id: NamespaceId, sprite_id: SpriteId) -> impl Send + Future<Output=Option<((f64, f64), (f64, f64))>> {
+        // Sent via the source program so the request arrives after the drawing
+        let source_program              = self.source_program;
+        let query_program               = SubProgramId::new();
+        let (send_bounds, recv_bounds)  = oneshot::channel();
+
+        // Create a program to query the bounds and wait for the result
+        flo_draw_scene_context().add_subprogram(query_program,
+            move |mut sprite_bounds: InputStream<SpriteBoundsResult>, context| async move {
+                if let Ok(mut drawing_window) = context.send::<DrawingWindowRequest>(source_program) {
+                    drawing_window.send(DrawingWindowRequest::QuerySpriteBounds(namespace_id, sprite_id, query_program)).await.ok();
+
+                    let bounds = sprite_bounds.next().await;
+                    send_bounds.send(bounds).ok();
+                }
+            },
+            0);
+
+        async move {
+            recv_bounds.await.ok()
+                .flatten()
+                .and_then(|sprite_bounds| sprite_bounds.bounds)
+        }
+    }
+
+    ///
+    /// Waits until the drawing sent to this window so far has been presented on screen
+    ///
+    /// If a frame is in progress (a `StartFrame` without a matching `ShowFrame`), this waits for the frame to be finished and
+    /// presented. Waiting on this after each frame makes it possible to drive an animation in lockstep with the display, so it
+    /// never runs ahead of what has actually been shown. The result is `None` if the window has been closed.
+    ///
+    pub fn next_frame_presented(&self) -> impl Send + Future<Output=Option<FramePresented>> {
+        // Sent via the source program so the request arrives after the drawing
+        let source_program                  = self.source_program;
+        let notify_program                  = SubProgramId::new();
+        let (send_present, recv_present)    = oneshot::channel();
+
+        // Create a program to request the notification and wait for it to arrive
+        flo_draw_scene_context().add_subprogram(notify_program,
+            move |mut presented_frames: InputStream<FramePresented>, context| async move {
+                if let Ok(mut drawing_window) = context.send::<DrawingWindowRequest>(source_program) {
+                    drawing_window.send(DrawingWindowRequest::NotifyWhenPresented(notify_program)).await.ok();
+
+                    let presented = presented_frames.next().await;
+                    send_present.send(presented).ok();
+                }
+            },
+            0);
+
+        async move {
+            recv_present.await.ok().flatten()
+        }
+    }
+
+    ///
+    /// Returns an object that can be used to capture the contents of this window
+    ///
+    pub fn frame_capture(&self) -> WindowFrameCapture {
+        WindowFrameCapture { drawing_window_program: self.drawing_window_program, source_program: self.source_program }
+    }
+}
+
+impl WindowFrameCapture {
+    ///
+    /// Returns the view for the window that this captures
+    ///
+    fn window_view(&self) -> WindowView {
+        WindowView { drawing_window_program: self.drawing_window_program, source_program: self.source_program }
+    }
+
+    ///
+    /// Requests a capture of the current contents of the window
+    ///
+    /// The drawing is re-rendered offscreen at the size of the window, so this works even if the window is hidden. If a frame is
+    /// in progress (`StartFrame` has been sent without a matching `ShowFrame`), the capture is made once it has finished, or once
+    /// the drawing stream ends. The result is `None` if the window is closed first or the frame could not be rendered.
+    ///
+    pub fn request_frame_capture(&self) -> impl Send + Future<Output=Option<CapturedFrame>> {
+        // Sent via the source program so the request arrives after the drawing
+        let source_program                  = self.source_program;
+        let capture_program                 = SubProgramId::new();
+        let (send_capture, recv_capture)    = oneshot::channel();
+
+        // Create a program to request the capture and wait for the result
+        flo_draw_scene_context().add_subprogram(capture_program, 
+            move |mut captured_frames: InputStream<CapturedFrame>, context| async move {
+                if let Ok(mut drawing_window) = context.send::<DrawingWindowRequest>(source_program) {
+                    drawing_window.send(DrawingWindowRequest::CaptureFrame(capture_program)).await.ok();
+
+                    let captured_frame = captured_frames.next().await;
+                    send_capture.send(captured_frame).ok();
+                }
+            },
+            0);
+
+        async move {
+            recv_capture.await.ok()
+                .flatten()
+                .filter(|captured_frame| !captured_frame.pixels.is_empty())
+        }
+    }
+
+    ///
+    /// Requests the pixels of a texture that has been defined in the window's drawing
+    ///
+    /// The texture is read back once any frame in progress has finished, after all of the operations on it (copies, filters, etc)
+    /// have been applied. Dynamic textures are rendered at the size they would have in the window. The result is `None` if the
+    /// window has been closed or the texture does not exist.
+    ///
+    pub fn request_texture_capture(&self, texture_id: TextureId) -> impl Send + Future<Output=Option<CapturedTexture>> {
+        // Sent via the source program so the request arrives after the drawing
+        let source_program                  = self.source_program;
+        let capture_program                 = SubProgramId::new();
+        let (send_capture, recv_capture)    = oneshot::channel();
+
+        // Create a program to request the capture and wait for the result
+        flo_draw_scene_context().add_subprogram(capture_program,
+            move |mut captured_textures: InputStream<CapturedTexture>, context| async move {
+                if let Ok(mut drawing_window) = context.send::<DrawingWindowRequest>(source_program) {
+                    drawing_window.send(DrawingWindowRequest::CaptureTexture(texture_id, capture_program)).await.ok();
+
+                    let captured_texture = captured_textures.next().await;
+                    send_capture.send(captured_texture).ok();
+                }
+            },
+            0);
+
+        async move {
+            recv_capture.await.ok()
+                .flatten()
+                .filter(|captured_texture| !captured_texture.pixels.is_empty())
+        }
+    }
 }
 
 ///
@@ -190,10 +694,28 @@ where TStream: Stream<Item=Draw> {
     /// The number of times StartFrame has been called
     frame_count: usize,
 
+    /// Set to send the drawing for a frame that's in progress instead of waiting for it to be shown
+    flush_frame: Arc<AtomicBool>,
+
     // Stream of individual draw events
     stream: Option<TStream>
 }
 
+impl<TStream> BatchedStream<TStream>
+where TStream: Stream<Item=Draw> {
+    ///
+    /// Creates a new batched stream, which flushes any frame in progress when the `flush_frame` flag is set
+    ///
+    fn new(stream: TStream, flush_frame: Arc<AtomicBool>) -> BatchedStream<TStream> {
+        BatchedStream {
+            waiting:        vec![],
+            frame_count:    0,
+            flush_frame:    flush_frame,
+            stream:         Some(stream),
+        }
+    }
+}
+
 impl<TStream> Stream for BatchedStream<TStream>
 where TStream: Unpin+Stream<Item=Draw> {
     type Item = Vec<TStream::Item>;
@@ -264,8 +786,8 @@ where TStream: Unpin+Stream<Item=Draw> {
                     Poll::Pending
                 } else {
                     // Batched up some drawing commands
-                    if *frame_count == 0 {
-                        // Not paused on a frame
+                    if *frame_count == 0 || this.flush_frame.load(Ordering::Acquire) {
+                        // Not paused on a frame, or the frame so far needs to be sent
                         Poll::Ready(Some(batch))
                     } else {
                         // Draw everything up until the most recent 'ShowFrame'
@@ -339,3 +861,104 @@ where
         Poll::Pending
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn drawing_in(requests: &[DrawingWindowRequest]) -> Vec<Vec<Draw>> {
+        requests.iter()
+            .map(|request| match request {
+                DrawingWindowRequest::Draw(DrawingRequest::Draw(drawing))   => (**drawing).clone(),
+                _                                                           => panic!("Not a drawing request"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn drawing_is_taken_before_query() {
+        let (send_drawing, recv_drawing)    = mpsc::unbounded();
+        let flush_frame                     = Arc::new(AtomicBool::new(false));
+        let mut canvas_stream               = BatchedStream::new(recv_drawing, Arc::clone(&flush_frame)).boxed();
+
+        send_drawing.unbounded_send(Draw::Path(PathOp::NewPath)).unwrap();
+        send_drawing.unbounded_send(Draw::Fill).unwrap();
+
+        let ready_drawing = take_ready_drawing(&mut canvas_stream, &flush_frame);
+
+        assert!(drawing_in(&ready_drawing) == vec![vec![Draw::Path(PathOp::NewPath), Draw::Fill]], "{:?}", drawing_in(&ready_drawing));
+        assert!(take_ready_drawing(&mut canvas_stream, &flush_frame).is_empty());
+    }
+
+    #[test]
+    fn frame_in_progress_is_taken_before_query() {
+        let (send_drawing, recv_drawing)    = mpsc::unbounded();
+        let flush_frame                     = Arc::new(AtomicBool::new(false));
+        let mut canvas_stream               = BatchedStream::new(recv_drawing, Arc::clone(&flush_frame)).boxed();
+
+        send_drawing.unbounded_send(Draw::StartFrame).unwrap();
+        send_drawing.unbounded_send(Draw::Path(PathOp::NewPath)).unwrap();
+
+        // The frame is held back until it's shown
+        assert!(canvas_stream.next().now_or_never().is_none());
+
+        // ... unless a query needs to go after it
+        let ready_drawing = take_ready_drawing(&mut canvas_stream, &flush_frame);
+        assert!(drawing_in(&ready_drawing) == vec![vec![Draw::StartFrame, Draw::Path(PathOp::NewPath)]], "{:?}", drawing_in(&ready_drawing));
+        assert!(!flush_frame.load(Ordering::Acquire));
+
+        // The rest of the frame is sent when it's shown
+        send_drawing.unbounded_send(Draw::Fill).unwrap();
+        assert!(canvas_stream.next().now_or_never().is_none());
+
+        send_drawing.unbounded_send(Draw::ShowFrame).unwrap();
+        assert!(canvas_stream.next().now_or_never() == Some(Some(vec![Draw::Fill, Draw::ShowFrame])));
+    }
+
+    #[test]
+    fn finished_stream_resets_frame() {
+        let (send_drawing, recv_drawing)    = mpsc::unbounded();
+        let flush_frame                     = Arc::new(AtomicBool::new(false));
+        let mut canvas_stream               = BatchedStream::new(recv_drawing, Arc::clone(&flush_frame)).boxed();
+
+        send_drawing.unbounded_send(Draw::StartFrame).unwrap();
+        mem::drop(send_drawing);
+
+        let ready_drawing = take_ready_drawing(&mut canvas_stream, &flush_frame);
+        assert!(drawing_in(&ready_drawing) == vec![vec![Draw::StartFrame], vec![Draw::ResetFrame]], "{:?}", drawing_in(&ready_drawing));
+    }
+
+    #[test]
+    fn requests_that_read_the_window_wait_for_drawing() {
+        assert!(waits_for_drawing(&DrawingWindowRequest::QueryTransforms(SubProgramId::new())));
+        assert!(waits_for_drawing(&DrawingWindowRequest::QuerySpriteBounds(NamespaceId::default(), SpriteId(0), SubProgramId::new())));
+        assert!(waits_for_drawing(&DrawingWindowRequest::CaptureFrame(SubProgramId::new())));
+        assert!(waits_for_drawing(&DrawingWindowRequest::NotifyWhenPresented(SubProgramId::new())));
+
+        assert!(!waits_for_drawing(&DrawingWindowRequest::CloseWindow));
+        assert!(!waits_for_drawing(&DrawingWindowRequest::RedrawAll));
+    }
+
+    #[test]
+    fn render_error_before_first_frame_is_returned() {
+        let mut events = stream::iter(vec![DrawEvent::Resize(100.0, 100.0), DrawEvent::RenderError(RenderInitError::CouldNotCreateContext), DrawEvent::NewFrame]);
+
+        assert!(executor::block_on(wait_for_first_frame(&mut events)) == Err(RenderInitError::CouldNotCreateContext));
+    }
+
+    #[test]
+    fn window_closed_before_first_frame_is_an_error() {
+        let mut events = stream::iter(vec![DrawEvent::Resize(100.0, 100.0), DrawEvent::Closed]);
+
+        assert!(executor::block_on(wait_for_first_frame(&mut events)) == Err(RenderInitError::WindowClosed));
+    }
+
+    #[test]
+    fn events_before_first_frame_are_kept() {
+        let mut events = stream::iter(vec![DrawEvent::Resize(100.0, 100.0), DrawEvent::Scale(2.0), DrawEvent::NewFrame, DrawEvent::Redraw]);
+
+        let initial_events = executor::block_on(wait_for_first_frame(&mut events));
+        assert!(initial_events == Ok(vec![DrawEvent::Resize(100.0, 100.0), DrawEvent::Scale(2.0), DrawEvent::NewFrame]), "{:?}", initial_events);
+        assert!(executor::block_on(events.next()) == Some(DrawEvent::Redraw));
+    }
+}
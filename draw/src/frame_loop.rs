@@ -0,0 +1,378 @@
+use crate::drawing_window::*;
+use crate::window_properties::*;
+
+use flo_canvas::*;
+
+use futures::executor;
+use futures_timer::{Delay};
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+///
+/// Information about the frame that a frame callback is generating
+///
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FrameInfo {
+    /// The number of frames that have been generated before this one
+    pub frame_number: u64,
+
+    /// The time since the first frame was generated
+    pub elapsed: Duration,
+
+    /// The time since the previous frame was generated
+    pub delta: Duration,
+
+    /// The total number of frames that have been skipped because the callback took longer than a frame to run
+    pub dropped_frames: u64,
+}
+
+///
+/// Value returned by a frame callback to indicate whether or not to generate more frames
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FrameControl {
+    /// Generate another frame
+    Continue,
+
+    /// Stop the frame loop
+    Stop,
+}
+
+///
+/// Calls a frame callback for each frame displayed by a window, sending the drawing it generates for each frame to a drawing target
+///
+/// The output of each callback is wrapped in `StartFrame`/`ShowFrame` so that each frame is displayed in one go. The loop is clocked
+/// by the window: the callback isn't called for the next frame until the previous one has been presented (see
+/// `WindowView::next_frame_presented()`), so there's never more than one frame waiting to be rendered. With a `frames_per_second`
+/// of 0, a frame is generated for every frame the window presents. Otherwise, frames are limited to that rate, and if the callback
+/// or the renderer falls behind, the frames that were missed are skipped rather than queued up, and are counted in
+/// `FrameInfo::dropped_frames`.
+///
+/// This blocks until the callback returns `FrameControl::Stop` or the window is closed.
+///
+pub fn run_frame_loop<TCallback>(target: &DrawingTarget, view: &WindowView, frames_per_second: f64, callback: TCallback)
+where
+    TCallback: FnMut(&mut Vec<Draw>, FrameInfo) -> FrameControl,
+{
+    run_frame_loop_with_output(frames_per_second, callback, 
+        |drawing| target.write(drawing),
+        || executor::block_on(view.next_frame_presented()).is_some());
+}
+
+///
+/// Runs a frame loop, passing the drawing for each frame to an output function
+///
+/// `wait_for_present` is called after each frame is sent, and should block until it has been presented. It returns false if
+/// the frame will never be presented (eg, because the window was closed), which stops the loop.
+///
+fn run_frame_loop_with_output<TCallback, TOutput, TWaitForPresent>(frames_per_second: f64, callback: TCallback, output: TOutput, wait_for_present: TWaitForPresent)
+where
+    TCallback:          FnMut(&mut Vec<Draw>, FrameInfo) -> FrameControl,
+    TOutput:            FnMut(Vec<Draw>),
+    TWaitForPresent:    FnMut() -> bool,
+{
+    let mut callback            = callback;
+    let mut output              = output;
+    let mut wait_for_present    = wait_for_present;
+    let frame_duration          = if frames_per_second > 0.0 { Some(Duration::from_secs_f64(1.0 / frames_per_second)) } else { None };
+
+    let start_time              = Instant::now();
+    let mut last_frame_time     = start_time;
+    let mut frame_number        = 0;
+    let mut frame_slot          = 0;
+    let mut dropped_frames      = 0;
+
+    loop {
+        // Generate the drawing for this frame
+        let now         = Instant::now();
+        let frame_info  = FrameInfo {
+            frame_number:   frame_number,
+            elapsed:        now - start_time,
+            delta:          now - last_frame_time,
+            dropped_frames: dropped_frames,
+        };
+
+        let mut drawing = vec![Draw::StartFrame];
+        let control     = callback(&mut drawing, frame_info);
+        drawing.push(Draw::ShowFrame);
+
+        output(drawing);
+
+        if control == FrameControl::Stop {
+            break;
+        }
+
+        // Wait for the window to present the frame before generating the next one, so frames can't pile up in front of the renderer
+        if !wait_for_present() {
+            break;
+        }
+
+        last_frame_time = now;
+        frame_number    += 1;
+
+        if let Some(frame_duration) = frame_duration {
+            // Skip any frames that were missed while the callback was running or the frame was being presented
+            frame_slot      += 1;
+
+            let elapsed     = start_time.elapsed();
+            let next_slot   = (elapsed.as_secs_f64() / frame_duration.as_secs_f64()).ceil() as u64;
+
+            if next_slot > frame_slot {
+                dropped_frames  += next_slot - frame_slot;
+                frame_slot      = next_slot;
+            }
+
+            // Don't generate frames faster than the requested rate
+            let next_frame_time = start_time + Duration::from_secs_f64(frame_duration.as_secs_f64() * (frame_slot as f64));
+            let now             = Instant::now();
+            if next_frame_time > now {
+                executor::block_on(Delay::new(next_frame_time - now));
+            }
+        }
+    }
+}
+
+///
+/// Creates a window and animates its contents by calling a frame callback for each frame on a background thread
+///
+/// See `run_frame_loop()` for how the frames are generated. The window's event stream is discarded, as with
+/// `create_drawing_window()`. The returned handle can be used to wait for the callback to return `FrameControl::Stop`
+/// or for the window to close.
+///
+pub fn create_drawing_window_with_frames<'a, TProperties, TCallback>(window_properties: TProperties, frames_per_second: f64, callback: TCallback) -> thread::JoinHandle<()>
+where
+    TProperties:    'a + FloWindowProperties,
+    TCallback:      'static + Send + FnMut(&mut Vec<Draw>, FrameInfo) -> FrameControl,
+{
+    // Dropping the events will stop the window from blocking when they're not handled
+    let (target, view, _events) = create_drawing_window_with_view(window_properties);
+
+    thread::spawn(move || {
+        run_frame_loop(&target, &view, frames_per_second, callback);
+    })
+}
+
+///
+/// Limits the rate at which a window presents frames
+///
+#[cfg(any(feature="render-opengl", feature="render-wgpu"))]
+pub (crate) struct FrameLimiter {
+    /// The time that the last frame was presented at
+    last_frame_time: Option<Instant>,
+}
+
+#[cfg(any(feature="render-opengl", feature="render-wgpu"))]
+impl FrameLimiter {
+    ///
+    /// Creates a new frame limiter
+    ///
+    pub fn new() -> FrameLimiter {
+        FrameLimiter {
+            last_frame_time: None,
+        }
+    }
+
+    ///
+    /// Waits until it's time to present the next frame at the specified maximum frame rate (0 for no limit)
+    ///
+    pub async fn wait_for_next_frame(&mut self, max_frame_rate: u32) {
+        if let Some(delay) = self.schedule_frame(Instant::now(), max_frame_rate) {
+            Delay::new(delay).await;
+        }
+    }
+
+    ///
+    /// Schedules the next frame, returning how long to wait before presenting it (or None if it can be presented immediately)
+    ///
+    /// Frames are spaced from when the previous frame was due rather than when it was actually presented so the frame rate doesn't
+    /// drift. A frame that's already late is presented immediately and the next frame is spaced from it, so the frames that were
+    /// missed are skipped rather than being presented in a burst to catch up.
+    ///
+    fn schedule_frame(&mut self, now: Instant, max_frame_rate: u32) -> Option<Duration> {
+        if let (Some(last_frame_time), true) = (self.last_frame_time, max_frame_rate > 0) {
+            let next_frame_time = last_frame_time + Duration::from_secs_f64(1.0 / max_frame_rate as f64);
+
+            if next_frame_time > now {
+                self.last_frame_time = Some(next_frame_time);
+                return Some(next_frame_time - now);
+            }
+        }
+
+        self.last_frame_time = Some(now);
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::cell::*;
+
+    #[test]
+    fn each_frame_is_wrapped_in_start_and_show_frame() {
+        let mut frames      = vec![];
+        let mut frame_infos = vec![];
+
+        run_frame_loop_with_output(1000.0, |drawing, frame_info| {
+            frame_infos.push(frame_info);
+            drawing.new_path();
+
+            if frame_info.frame_number < 2 { FrameControl::Continue } else { FrameControl::Stop }
+        }, |drawing| frames.push(drawing), || true);
+
+        assert!(frames.len() == 3);
+        assert!(frames.iter().all(|frame| frame == &vec![Draw::StartFrame, Draw::Path(PathOp::NewPath), Draw::ShowFrame]), "{:?}", frames);
+        assert!(frame_infos.iter().map(|info| info.frame_number).collect::<Vec<_>>() == vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn stop_sends_the_final_frame_and_ends_the_loop() {
+        let mut frames      = vec![];
+        let mut num_calls   = 0;
+
+        run_frame_loop_with_output(60.0, |drawing, _frame_info| {
+            num_calls += 1;
+            drawing.new_path();
+
+            FrameControl::Stop
+        }, |drawing| frames.push(drawing), || true);
+
+        assert!(num_calls == 1);
+        assert!(frames == vec![vec![Draw::StartFrame, Draw::Path(PathOp::NewPath), Draw::ShowFrame]], "{:?}", frames);
+    }
+
+    #[test]
+    fn slow_callback_drops_frames() {
+        let mut frames      = vec![];
+        let mut frame_infos = vec![];
+
+        // 10ms frames, where the first frame takes 35ms to generate
+        run_frame_loop_with_output(100.0, |drawing, frame_info| {
+            frame_infos.push(frame_info);
+            drawing.new_path();
+
+            if frame_info.frame_number == 0 {
+                thread::sleep(Duration::from_millis(35));
+            }
+
+            if frame_info.frame_number < 2 { FrameControl::Continue } else { FrameControl::Stop }
+        }, |drawing| frames.push(drawing), || true);
+
+        // The slow frame is still sent, but the frames that were missed while it was generated are skipped rather than queued up
+        assert!(frames.len() == 3);
+        assert!(frames.iter().all(|frame| frame.first() == Some(&Draw::StartFrame) && frame.last() == Some(&Draw::ShowFrame)));
+
+        assert!(frame_infos[0].dropped_frames == 0);
+        assert!(frame_infos[1].dropped_frames >= 2, "{:?}", frame_infos);
+        assert!(frame_infos[1].frame_number == 1);
+        assert!(frame_infos[1].delta >= Duration::from_millis(35), "{:?}", frame_infos);
+    }
+
+    #[test]
+    fn slow_renderer_holds_back_frames_and_drops_them() {
+        let frames_sent         = Cell::new(0);
+        let frames_presented    = Cell::new(0);
+        let mut frame_infos     = vec![];
+
+        // 10ms frames, where the renderer takes 35ms to present each frame
+        run_frame_loop_with_output(100.0, |drawing, frame_info| {
+            // The previous frame must have been presented before the next one is generated
+            assert!(frames_sent.get() == frames_presented.get(), "{} {}", frames_sent.get(), frames_presented.get());
+
+            frame_infos.push(frame_info);
+            drawing.new_path();
+
+            if frame_info.frame_number < 2 { FrameControl::Continue } else { FrameControl::Stop }
+        }, |_drawing| {
+            frames_sent.set(frames_sent.get() + 1);
+        }, || {
+            thread::sleep(Duration::from_millis(35));
+            frames_presented.set(frames_presented.get() + 1);
+            true
+        });
+
+        // Only one frame is generated per presented frame, and the frames that were missed while waiting are skipped
+        assert!(frames_sent.get() == 3);
+        assert!(frames_presented.get() == 2);
+        assert!(frame_infos[1].dropped_frames >= 2, "{:?}", frame_infos);
+        assert!(frame_infos[2].dropped_frames >= 4, "{:?}", frame_infos);
+    }
+
+    #[test]
+    fn display_rate_generates_a_frame_per_presented_frame() {
+        let mut frame_infos     = vec![];
+        let mut num_presented   = 0;
+
+        run_frame_loop_with_output(0.0, |drawing, frame_info| {
+            frame_infos.push(frame_info);
+            drawing.new_path();
+
+            if frame_info.frame_number < 3 { FrameControl::Continue } else { FrameControl::Stop }
+        }, |_drawing| { }, || {
+            thread::sleep(Duration::from_millis(5));
+            num_presented += 1;
+            true
+        });
+
+        // Without a frame rate, the presented frames are the clock, so no frames are ever dropped
+        assert!(num_presented == 3);
+        assert!(frame_infos.len() == 4);
+        assert!(frame_infos.iter().all(|info| info.dropped_frames == 0), "{:?}", frame_infos);
+    }
+
+    #[test]
+    fn closing_the_window_stops_the_loop() {
+        let mut num_calls = 0;
+
+        run_frame_loop_with_output(60.0, |drawing, _frame_info| {
+            num_calls += 1;
+            drawing.new_path();
+
+            FrameControl::Continue
+        }, |_drawing| { }, || false);
+
+        assert!(num_calls == 1);
+    }
+
+    #[cfg(any(feature="render-opengl", feature="render-wgpu"))]
+    #[test]
+    fn frame_limiter_spaces_frames_at_the_frame_rate() {
+        let mut limiter = FrameLimiter::new();
+        let start       = Instant::now();
+
+        // The first frame is presented immediately, then each frame is due 10ms after the one before
+        assert!(limiter.schedule_frame(start, 100) == None);
+        assert!(limiter.schedule_frame(start, 100) == Some(Duration::from_millis(10)));
+
+        // The next frame is spaced from when the last one was due, not when it was requested
+        assert!(limiter.schedule_frame(start + Duration::from_millis(12), 100) == Some(Duration::from_millis(8)));
+    }
+
+    #[cfg(any(feature="render-opengl", feature="render-wgpu"))]
+    #[test]
+    fn frame_limiter_skips_missed_frames() {
+        let mut limiter = FrameLimiter::new();
+        let start       = Instant::now();
+
+        assert!(limiter.schedule_frame(start, 100) == None);
+
+        // 35ms later, three frames have been missed: present straight away rather than catching up
+        let late_frame = start + Duration::from_millis(35);
+        assert!(limiter.schedule_frame(late_frame, 100) == None);
+
+        // The following frame is spaced from the late frame
+        assert!(limiter.schedule_frame(late_frame, 100) == Some(Duration::from_millis(10)));
+    }
+
+    #[cfg(any(feature="render-opengl", feature="render-wgpu"))]
+    #[test]
+    fn frame_limiter_does_not_wait_without_a_frame_rate() {
+        let mut limiter = FrameLimiter::new();
+        let start       = Instant::now();
+
+        assert!(limiter.schedule_frame(start, 0) == None);
+        assert!(limiter.schedule_frame(start, 0) == None);
+    }
+}
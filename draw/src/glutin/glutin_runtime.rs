@@ -9,20 +9,22 @@ use super::glutin_thread_event::*;
 use flo_stream::*;
 use flo_binding::*;
 
-use glutin::config::{ConfigTemplateBuilder, GlConfig};
-use glutin::context::{ContextApi, ContextAttributesBuilder, Version};
+use glutin::config::{Config, ConfigTemplateBuilder, GlConfig};
+use glutin::context::{ContextApi, ContextAttributesBuilder, NotCurrentContext, Version};
 use glutin::display::{GetGlDisplay, GlDisplay};
 use glutin_winit::{DisplayBuilder};
 use winit::event::{DeviceId, Event, WindowEvent, ElementState};
 use winit::event_loop::{ControlFlow, EventLoopWindowTarget};
-use winit::window::{WindowId, Fullscreen}; 
+use winit::window::{Window, WindowBuilder, WindowId, Fullscreen}; 
 use winit::keyboard::{PhysicalKey, NativeKeyCode};
 use raw_window_handle::{HasRawWindowHandle};
+use flo_render::{RenderInitError};
 
 use futures::task;
 use futures::prelude::*;
 use futures::future::{LocalBoxFuture};
 
+use std::panic;
 use std::sync::*;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::collections::{HashMap};
@@ -152,7 +154,7 @@ impl GlutinRuntime {
             Touch(_touch)                                                   => vec![],
             Ime(_)                                                          => vec![],
             Occluded(_)                                                     => vec![],
-            ScaleFactorChanged { scale_factor, inner_size_writer: _ }       => vec![DrawEvent::Scale(scale_factor)],
+            ScaleFactorChanged { scale_factor, inner_size_writer: _ }       => vec![DrawEvent::Scale(scale_factor), DrawEvent::Redraw],
             ThemeChanged(_theme)                                            => vec![],
 
             RedrawRequested                                                 => {
@@ -337,44 +339,27 @@ impl GlutinRuntime {
                     .with_inner_size(winit::dpi::LogicalSize::new(size_x as f64, size_y as _))
                     .with_fullscreen(fullscreen)
                     .with_decorations(decorations);
-                let display_builder     = DisplayBuilder::new()
-                    .with_window_builder(Some(window_builder));
-                let template            = ConfigTemplateBuilder::new()
-                    .prefer_hardware_accelerated(Some(true))
-                    .with_alpha_size(8);
-
-                let (window, gl_config) = display_builder
-                    .build(window_target, template, |configs| configs.reduce(|a, b| {
-                        if a.num_samples() > b.num_samples() {
-                            a
-                        } else {
-                            b
-                        }
-                    }).unwrap())
-                    .unwrap();
-                let window = window.unwrap();
-
-                let raw_window_handle           = Some(window.raw_window_handle());
-                let gl_display                  = gl_config.display();
-                let context_attributes          = ContextAttributesBuilder::new().build(raw_window_handle);
-                let fallback_context_attributes = ContextAttributesBuilder::new().with_context_api(ContextApi::Gles(None)).build(raw_window_handle);
-                let legacy_context_attributes   = ContextAttributesBuilder::new().with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3)))).build(raw_window_handle);
-                let windowed_context            = unsafe {
-                    gl_display.create_context(&gl_config, &context_attributes).unwrap_or_else(|_| {
-                        gl_display.create_context(&gl_config, &fallback_context_attributes).unwrap_or_else(
-                            |_| {
-                                gl_display
-                                    .create_context(&gl_config, &legacy_context_attributes)
-                                    .expect("failed to create context")
-                            },
-                        )
-                    })
-                };
 
-                // Finalize the window (might be unsafe under operating systems like Android, but adding this to the window itself requires considerable extra state...)
-                let window_builder = winit::window::WindowBuilder::new();
-                glutin_winit::finalize_window(window_target, window_builder, &gl_config)
-                    .unwrap();
+                let (window, gl_config, windowed_context) = match create_gl_window(window_builder, window_target) {
+                    Ok(gl_window)   => gl_window,
+                    Err(err)        => {
+                        // Report the error to the window's events, and acknowledge the frames so the drawing doesn't stall
+                        let mut events  = events;
+                        let mut actions = actions;
+
+                        self.run_process(async move {
+                            events.publish(DrawEvent::RenderError(err)).await;
+
+                            while let Some(render_actions) = actions.next().await {
+                                if !render_actions.is_empty() {
+                                    events.publish(DrawEvent::NewFrame).await;
+                                }
+                            }
+                        });
+
+                        return;
+                    }
+                };
 
                 // Store the window context in a new glutin window
                 let mut suspend_resume          = Publisher::new(1);
@@ -487,3 +472,49 @@ impl task::ArcWake for GlutinFutureWaker {
         }
     }
 }
+
+///
+/// Creates a window along with an OpenGL context to render to it
+///
+fn create_gl_window(window_builder: WindowBuilder, window_target: &EventLoopWindowTarget<GlutinThreadEvent>) -> Result<(Window, Config, NotCurrentContext), RenderInitError> {
+    let display_builder     = DisplayBuilder::new()
+        .with_window_builder(Some(window_builder));
+    let template            = ConfigTemplateBuilder::new()
+        .prefer_hardware_accelerated(Some(true))
+        .with_alpha_size(8);
+
+    // The config picker has no way to report that there are no configs, so it panics instead and we turn that into an error here
+    let build_display       = panic::AssertUnwindSafe(|| display_builder
+        .build(window_target, template, |configs| configs.reduce(|a, b| {
+            if a.num_samples() > b.num_samples() {
+                a
+            } else {
+                b
+            }
+        }).expect("No OpenGL configurations available")));
+
+    let (window, gl_config) = match panic::catch_unwind(build_display) {
+        Ok(Ok(display))     => display,
+        _                   => { return Err(RenderInitError::CouldNotConfigureDisplay); }
+    };
+    let window              = window.ok_or(RenderInitError::CouldNotCreateSurface)?;
+
+    let raw_window_handle           = Some(window.raw_window_handle());
+    let gl_display                  = gl_config.display();
+    let context_attributes          = ContextAttributesBuilder::new().build(raw_window_handle);
+    let fallback_context_attributes = ContextAttributesBuilder::new().with_context_api(ContextApi::Gles(None)).build(raw_window_handle);
+    let legacy_context_attributes   = ContextAttributesBuilder::new().with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3)))).build(raw_window_handle);
+    let windowed_context            = unsafe {
+        gl_display.create_context(&gl_config, &context_attributes)
+            .or_else(|_| gl_display.create_context(&gl_config, &fallback_context_attributes))
+            .or_else(|_| gl_display.create_context(&gl_config, &legacy_context_attributes))
+            .map_err(|_| RenderInitError::CouldNotCreateContext)?
+    };
+
+    // Finalize the window (might be unsafe under operating systems like Android, but adding this to the window itself requires considerable extra state...)
+    let window_builder = winit::window::WindowBuilder::new();
+    glutin_winit::finalize_window(window_target, window_builder, &gl_config)
+        .map_err(|_| RenderInitError::CouldNotCreateSurface)?;
+
+    Ok((window, gl_config, windowed_context))
+}
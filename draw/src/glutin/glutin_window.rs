@@ -1,4 +1,5 @@
 use crate::events::*;
+use crate::frame_loop::*;
 use crate::window_properties::*;
 
 use flo_stream::*;
@@ -7,7 +8,7 @@ use flo_binding::*;
 
 use glutin::context::{NotCurrentContext, PossiblyCurrentGlContext, NotCurrentGlContext};
 use glutin::display::{GetGlDisplay, GlDisplay};
-use glutin::surface::{Surface, SurfaceTypeTrait};
+use glutin::surface::{Surface, SurfaceTypeTrait, SwapInterval};
 use glutin::prelude::{GlConfig, GlSurface};
 use glutin_winit::GlWindow;
 use winit::dpi::{LogicalSize};
@@ -18,6 +19,7 @@ use gl;
 
 use std::pin::*;
 use std::ffi::{CString};
+use std::num::{NonZeroU32};
 
 ///
 /// Message indicating that the application has been suspended or resumed
@@ -44,6 +46,15 @@ where
     /// The surface for the window
     surface: Option<<TConfig::Target as GlDisplay>::WindowSurface>,
 
+    /// The size in pixels that the surface was last rendered at
+    surface_size: Option<(u32, u32)>,
+
+    /// The present mode requested for this window
+    present_mode: PresentMode,
+
+    /// True if the swap interval for the surface needs to be updated to match the present mode
+    swap_interval_changed: bool,
+
     /// The window the context is attached to
     window: Option<Window>,
 
@@ -60,11 +71,14 @@ where
     ///
     pub fn new(context: NotCurrentContext, gl_config: TConfig, window: Window) -> GlutinWindow<TConfig> {
         GlutinWindow {
-            context:            Some(context),
-            gl_config:          gl_config,
-            surface:            None,
-            window:             Some(window),
-            renderer:           None
+            context:                Some(context),
+            gl_config:              gl_config,
+            surface:                None,
+            surface_size:           None,
+            present_mode:           PresentMode::Vsync,
+            swap_interval_changed:  true,
+            window:                 Some(window),
+            renderer:               None
         }
     }
 }
@@ -84,6 +98,9 @@ where
     // Read events from the render actions list
     let mut window          = window;
     let mut events          = events;
+    let max_frame_rate      = window_properties.max_frame_rate.clone();
+    let mut frame_limiter   = FrameLimiter::new();
+    let mut render_failed   = false;
     let mut window_actions  = WindowUpdateStream { 
         suspend_resume:     suspend_resume,
         render_stream:      render_actions, 
@@ -91,17 +108,28 @@ where
         size:               follow(window_properties.size),
         fullscreen:         follow(window_properties.fullscreen),
         has_decorations:    follow(window_properties.has_decorations),
-        mouse_pointer:      follow(window_properties.mouse_pointer)
+        mouse_pointer:      follow(window_properties.mouse_pointer),
+        present_mode:       follow(window_properties.present_mode),
     };
 
     while let Some(next_action) = window_actions.next().await {
         match next_action {
             WindowUpdate::Resumed => {
                 // Create surface
-                let surface_attributes  = window.window.as_ref().unwrap().build_surface_attributes(<_>::default());
-                window.surface          = unsafe {
-                    Some(window.gl_config.display().create_window_surface(&window.gl_config, &surface_attributes).unwrap())
-                };
+                let surface_attributes  = window.window.as_ref().map(|winit_window| winit_window.build_surface_attributes(<_>::default()));
+                let surface             = surface_attributes.and_then(|surface_attributes| unsafe {
+                    window.gl_config.display().create_window_surface(&window.gl_config, &surface_attributes).ok()
+                });
+
+                // Report the error to the window's events if the surface couldn't be created
+                if surface.is_none() && !render_failed {
+                    render_failed = true;
+                    events.publish(DrawEvent::RenderError(RenderInitError::CouldNotCreateSurface)).await;
+                }
+
+                window.surface                  = surface;
+                window.surface_size             = None;
+                window.swap_interval_changed    = true;
             }
 
             WindowUpdate::Suspended => {
@@ -122,25 +150,63 @@ where
                     next_action.iter().any(|item| item == &RenderAction::ShowFrameBuffer)
                 };
 
-                // TODO: report errors if we can't set the context rather than just stopping mysteriously
+                // Hold back frames that are too soon after the last one (before the context is made current, as other windows can run while this waits)
+                if show_frame_buffer {
+                    frame_limiter.wait_for_next_frame(max_frame_rate.get()).await;
+                }
+
+                // Once the renderer has failed to start, the render actions are discarded (but frames are still acknowledged so the drawing doesn't stall)
+                if render_failed {
+                    events.publish(DrawEvent::NewFrame).await;
+                    continue;
+                }
+
+                // Get informtion about the window
+                let size            = if let Some(winit_window) = &window.window { winit_window.inner_size() } else { continue; };
 
                 // Fetch the surface, if one has been created (won't be available if we haven't resumed)
                 let current_surface = window.surface.take();
                 let current_surface = if let Some(current_surface) = current_surface { current_surface } else { continue; };
 
                 // Make the current context current
-                let current_context = window.context.take().expect("Window context");
-
-                let current_context = current_context.make_current(&current_surface);
-                let current_context = if let Ok(context) = current_context { context } else { break; };
+                let current_context = window.context.take().map(|context| context.make_current(&current_surface));
+                let current_context = match current_context {
+                    Some(Ok(context))   => context,
+                    _                   => {
+                        // The context is lost if it can't be made current, so the window can't render any more
+                        render_failed = true;
+                        events.publish(DrawEvent::RenderError(RenderInitError::ContextDidNotStart)).await;
+                        events.publish(DrawEvent::NewFrame).await;
+                        continue;
+                    }
+                };
 
                 let display         = window.gl_config.display();
 
                 // Get informtion about the current context
-                let size            = window.window.as_ref().unwrap().inner_size();
                 let width           = size.width as usize;
                 let height          = size.height as usize;
 
+                // Resize the surface if the window has changed size (eg, due to a change in scale factor when moving between monitors)
+                if window.surface_size != Some((size.width, size.height)) {
+                    if let (Some(surface_width), Some(surface_height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
+                        current_surface.resize(&current_context, surface_width, surface_height);
+                    }
+
+                    window.surface_size = Some((size.width, size.height));
+                }
+
+                // Update the swap interval if the present mode has changed (OpenGL has no equivalent to a mailbox mode, so low latency is the same as vsync here)
+                if window.swap_interval_changed {
+                    let swap_interval = match window.present_mode {
+                        PresentMode::Vsync | PresentMode::LowLatency    => SwapInterval::Wait(NonZeroU32::new(1).unwrap()),
+                        PresentMode::Immediate                          => SwapInterval::DontWait,
+                    };
+
+                    current_surface.set_swap_interval(&current_context, swap_interval).ok();
+                    window.swap_interval_changed = false;
+                }
+
                 // Create the renderer (needs the OpenGL functions to be loaded)
                 if window.renderer.is_none() {
                     // Load the functions for the current context
@@ -168,10 +234,17 @@ where
                 }
 
                 // Release the current context
-                let context     = current_context.make_not_current();
-                let context     = if let Ok(context) = context { context } else { break; };
-                window.context  = Some(context);
-                window.surface  = Some(current_surface);
+                match current_context.make_not_current() {
+                    Ok(context) => {
+                        window.context  = Some(context);
+                        window.surface  = Some(current_surface);
+                    }
+
+                    Err(_) => {
+                        render_failed = true;
+                        events.publish(DrawEvent::RenderError(RenderInitError::ContextDidNotStart)).await;
+                    }
+                }
 
                 // Notify that a new frame has been drawn
                 events.publish(DrawEvent::NewFrame).await;
@@ -201,6 +274,14 @@ where
             WindowUpdate::SetMousePointer(MousePointer::SystemDefault) => {
                 window.window.as_ref().map(|ctxt| ctxt.set_cursor_visible(true));
             }
+
+            WindowUpdate::SetPresentMode(present_mode) => {
+                // The swap interval can only be changed while the context is current, so this is applied on the next render
+                if window.present_mode != present_mode {
+                    window.present_mode             = present_mode;
+                    window.swap_interval_changed    = true;
+                }
+            }
         }
     }
 
@@ -219,23 +300,25 @@ enum WindowUpdate {
     SetSize((u64, u64)),
     SetFullscreen(bool),
     SetHasDecorations(bool),
-    SetMousePointer(MousePointer)
+    SetMousePointer(MousePointer),
+    SetPresentMode(PresentMode),
 }
 
 ///
 /// Stream that merges the streams from the window properties and the renderer into a single stream
 ///
-struct WindowUpdateStream<TSuspendResumeStream, TRenderStream, TTitleStream, TSizeStream, TFullscreenStream, TDecorationStream, TMousePointerStream> {
+struct WindowUpdateStream<TSuspendResumeStream, TRenderStream, TTitleStream, TSizeStream, TFullscreenStream, TDecorationStream, TMousePointerStream, TPresentModeStream> {
     suspend_resume:     TSuspendResumeStream,
     render_stream:      TRenderStream,
     title_stream:       TTitleStream,
     size:               TSizeStream,
     fullscreen:         TFullscreenStream,
     has_decorations:    TDecorationStream,
-    mouse_pointer:      TMousePointerStream
+    mouse_pointer:      TMousePointerStream,
+    present_mode:       TPresentModeStream,
 }
 
-impl<TSuspendResumeStream, TRenderStream, TTitleStream, TSizeStream, TFullscreenStream, TDecorationStream, TMousePointerStream> Stream for WindowUpdateStream<TSuspendResumeStream, TRenderStream, TTitleStream, TSizeStream, TFullscreenStream, TDecorationStream, TMousePointerStream>
+impl<TSuspendResumeStream, TRenderStream, TTitleStream, TSizeStream, TFullscreenStream, TDecorationStream, TMousePointerStream, TPresentModeStream> Stream for WindowUpdateStream<TSuspendResumeStream, TRenderStream, TTitleStream, TSizeStream, TFullscreenStream, TDecorationStream, TMousePointerStream, TPresentModeStream>
 where
     TSuspendResumeStream:   Unpin + Stream<Item=SuspendResume>,
     TRenderStream:          Unpin + Stream<Item=Vec<RenderAction>>,
@@ -243,7 +326,8 @@ where
     TSizeStream:            Unpin + Stream<Item=(u64, u64)>,
     TFullscreenStream:      Unpin + Stream<Item=bool>,
     TDecorationStream:      Unpin + Stream<Item=bool>,
-    TMousePointerStream:    Unpin + Stream<Item=MousePointer> 
+    TMousePointerStream:    Unpin + Stream<Item=MousePointer>,
+    TPresentModeStream:     Unpin + Stream<Item=PresentMode>,
 {
     type Item = WindowUpdate;
 
@@ -296,6 +380,12 @@ where
             Poll::Pending           => { }
         }
 
+        match self.present_mode.poll_next_unpin(context) {
+            Poll::Ready(Some(item)) => { return Poll::Ready(Some(WindowUpdate::SetPresentMode(item))); }
+            Poll::Ready(None)       => { return Poll::Ready(None); }
+            Poll::Pending           => { }
+        }
+
         // No stream matched anything
         Poll::Pending
     }
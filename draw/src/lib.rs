@@ -31,7 +31,9 @@
 //! Start your application by calling `with_2d_graphics(|| {})` with a function to perform whatever drawing operations you want. 
 //! In that function, `let canvas = create_drawing_window("Canvas window");` will create a window with a 2D graphics canvas that 
 //! you can draw on using `canvas.draw(|gc| { });`. Finally, `create_drawing_window_with_events()` is a way to create a graphics
-//! window that supplies events allowing interactivity. 
+//! window that supplies events allowing interactivity. `create_drawing_window_with_view()` and `create_canvas_window_with_view()` also
+//! return a `WindowView`, which can query the transforms and sprite bounds in the window, wait for frames to be presented and capture
+//! what the window is showing.
 //!
 //! The documentation for [flo_canvas](canvas) shows what can be done in a drawing routine.
 //!
@@ -62,12 +64,14 @@ pub use flo_render_canvas as render_canvas;
 pub use flo_binding as binding;
 pub use flo_scene as scene;
 
-pub use flo_render::{initialize_offscreen_rendering};
+pub use flo_render::{initialize_offscreen_rendering, RenderInitError};
 pub use flo_render_canvas::{render_canvas_offscreen};
 
 mod render_window;
 mod drawing_window;
+mod frame_loop;
 mod window_properties;
+mod window_transform;
 
 /// The 'glutin' module provides an OpenGL implementation of the canvas using glutin for window management
 #[cfg(feature="render-opengl")]
@@ -86,7 +90,12 @@ pub use self::wgpu::{with_2d_graphics};
 /// The 'Scene' API provides a framework for building more complex software out of message-passing components
 pub mod draw_scene;
 
+/// The 'scene_graph' module provides a retained tree of drawing instructions that generates only the instructions needed to update a canvas
+pub mod scene_graph;
+
 pub use self::events::*;
 pub use self::render_window::*;
 pub use self::drawing_window::*;
+pub use self::frame_loop::*;
 pub use self::window_properties::*;
+pub use self::window_transform::*;
@@ -45,18 +45,27 @@ where
                 let fullscreen      = follow(window_properties.fullscreen);
                 let has_decorations = follow(window_properties.has_decorations);
                 let mouse_pointer   = follow(window_properties.mouse_pointer);
+                let present_mode    = follow(window_properties.present_mode);
+                let multisampling   = follow(window_properties.multisampling);
+                let max_frame_rate  = follow(window_properties.max_frame_rate);
 
                 // Each one generates an event when it changes
                 let title           = title.map(|new_title| EventWindowRequest::SetTitle(new_title));
                 let fullscreen      = fullscreen.map(|fullscreen| EventWindowRequest::SetFullScreen(fullscreen));
                 let has_decorations = has_decorations.map(|has_decorations| EventWindowRequest::SetHasDecorations(has_decorations));
                 let mouse_pointer   = mouse_pointer.map(|mouse_pointer| EventWindowRequest::SetMousePointer(mouse_pointer));
+                let present_mode    = present_mode.map(|present_mode| EventWindowRequest::SetPresentMode(present_mode));
+                let multisampling   = multisampling.map(|samples| EventWindowRequest::SetMultisampling(samples));
+                let max_frame_rate  = max_frame_rate.map(|frame_rate| EventWindowRequest::SetMaxFrameRate(frame_rate));
 
                 let mut requests    = stream::select_all(vec![
                     title.boxed(),
                     fullscreen.boxed(),
                     has_decorations.boxed(),
                     mouse_pointer.boxed(),
+                    present_mode.boxed(),
+                    multisampling.boxed(),
+                    max_frame_rate.boxed(),
                 ]);
 
                 // Pass the requests on to the underlying window
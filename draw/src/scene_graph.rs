@@ -0,0 +1,668 @@
+//!
+//! # Retained scene graph
+//!
+//! The `SceneGraph` type keeps a tree of nodes, each of which holds some drawing instructions, a transform and a list of
+//! child nodes. Changing a node marks it as dirty, and `diff_to_draw()` generates the drawing instructions needed to bring
+//! a canvas up to date with the changes made since the last call.
+//!
+//! Each top-level node is drawn on its own layer, and only the layers containing a changed node are redrawn. Nodes can be
+//! marked as sprites: these are drawn once as a sprite and then drawn with `DrawSprite`, which is useful for subtrees that
+//! appear in more than one place or that rarely change. As this only generates standard canvas instructions, it works with
+//! any renderer.
+//!
+//! ```
+//! # use flo_draw::canvas::*;
+//! # use flo_draw::scene_graph::*;
+//! let mut scene   = SceneGraph::new();
+//! let background  = scene.create_node();
+//! let shape       = scene.create_node();
+//!
+//! scene.set_drawing(background, vec![Draw::FillColor(Color::Rgba(0.0, 0.0, 0.4, 1.0))]);
+//! scene.set_drawing(shape, vec![Draw::Path(PathOp::NewPath), Draw::Path(PathOp::Move(0.0, 0.0)), Draw::Path(PathOp::Line(100.0, 100.0)), Draw::Stroke]);
+//! scene.add_top_level(background);
+//! scene.add_top_level(shape);
+//!
+//! // The first diff draws everything
+//! let drawing = scene.diff_to_draw();
+//!
+//! // Moving the shape only redraws its layer
+//! scene.set_transform(shape, Transform2D::translate(10.0, 0.0));
+//! let drawing = scene.diff_to_draw();
+//! ```
+//!
+
+use flo_canvas::*;
+
+use std::collections::{HashMap, HashSet};
+
+///
+/// Identifies a node in a `SceneGraph`
+///
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SceneNodeId(u64);
+
+///
+/// A node in the scene graph
+///
+#[derive(Clone, Debug)]
+struct SceneNode {
+    /// The drawing instructions for this node (drawn before any of the child nodes)
+    drawing: Vec<Draw>,
+
+    /// The transform to apply to this node and its children
+    transform: Transform2D,
+
+    /// The child nodes, in the order that they're drawn
+    children: Vec<SceneNodeId>,
+
+    /// If this node is drawn as a sprite, the ID of the sprite
+    sprite: Option<SpriteId>,
+}
+
+///
+/// A retained set of drawing instructions that can generate the instructions needed to update a canvas when it changes
+///
+/// Top-level nodes are drawn on the layers starting at the base layer ID (0 by default), and sprites are allocated starting
+/// from the base sprite ID (0 by default), so these should not be used for other drawing on the same canvas.
+///
+#[derive(Clone, Debug)]
+pub struct SceneGraph {
+    /// The nodes in this graph
+    nodes: HashMap<SceneNodeId, SceneNode>,
+
+    /// The ID to assign to the next node
+    next_node_id: u64,
+
+    /// The nodes that are drawn on their own layers
+    top_level: Vec<SceneNodeId>,
+
+    /// The top-level nodes as of the last time the drawing was generated
+    drawn_top_level: Vec<SceneNodeId>,
+
+    /// The ID of the layer used for the first top-level node
+    base_layer_id: u64,
+
+    /// The ID to assign to the next sprite
+    next_sprite_id: u64,
+
+    /// Sprite IDs that are no longer used by any node and can be reassigned
+    free_sprites: Vec<SpriteId>,
+
+    /// Sprites that need to be cleared the next time the drawing is generated
+    released_sprites: Vec<SpriteId>,
+
+    /// Nodes whose drawing instructions or children have changed since the drawing was last generated
+    changed_content: HashSet<SceneNodeId>,
+
+    /// Nodes whose transform has changed since the drawing was last generated
+    changed_transform: HashSet<SceneNodeId>,
+}
+
+impl SceneGraph {
+    ///
+    /// Creates a new, empty scene graph which draws to the layers and sprites starting at ID 0
+    ///
+    pub fn new() -> SceneGraph {
+        SceneGraph::with_base_ids(LayerId(0), SpriteId(0))
+    }
+
+    ///
+    /// Creates a new, empty scene graph which draws to the layers and sprites starting at the specified IDs
+    ///
+    pub fn with_base_ids(base_layer: LayerId, base_sprite: SpriteId) -> SceneGraph {
+        SceneGraph {
+            nodes:              HashMap::new(),
+            next_node_id:       0,
+            top_level:          vec![],
+            drawn_top_level:    vec![],
+            base_layer_id:      base_layer.0,
+            next_sprite_id:     base_sprite.0,
+            free_sprites:       vec![],
+            released_sprites:   vec![],
+            changed_content:    HashSet::new(),
+            changed_transform:  HashSet::new(),
+        }
+    }
+
+    ///
+    /// Creates a new node with no drawing instructions, an identity transform and no children
+    ///
+    /// The node isn't drawn until it's added as a top-level node or as the child of a node that's drawn.
+    ///
+    pub fn create_node(&mut self) -> SceneNodeId {
+        let node_id = SceneNodeId(self.next_node_id);
+        self.next_node_id += 1;
+
+        self.nodes.insert(node_id, SceneNode {
+            drawing:    vec![],
+            transform:  Transform2D::default(),
+            children:   vec![],
+            sprite:     None,
+        });
+        self.changed_content.insert(node_id);
+
+        node_id
+    }
+
+    ///
+    /// Removes a node from the graph, along with any references to it from its parents or the list of top-level nodes
+    ///
+    /// The children of the node are not removed.
+    ///
+    pub fn remove_node(&mut self, node_id: SceneNodeId) {
+        if let Some(node) = self.nodes.remove(&node_id) {
+            if let Some(sprite_id) = node.sprite {
+                self.release_sprite(sprite_id);
+            }
+
+            // Remove any references to this node
+            self.top_level.retain(|top_level_id| *top_level_id != node_id);
+
+            for (parent_id, parent) in self.nodes.iter_mut() {
+                if parent.children.contains(&node_id) {
+                    parent.children.retain(|child_id| *child_id != node_id);
+                    self.changed_content.insert(*parent_id);
+                }
+            }
+
+            self.changed_content.remove(&node_id);
+            self.changed_transform.remove(&node_id);
+        }
+    }
+
+    ///
+    /// Sets the drawing instructions for a node
+    ///
+    /// These are drawn before the children of the node. The canvas state (fill colour, line width, etc) is saved before the node
+    /// is drawn and restored afterwards, so changes to it don't affect any other node.
+    ///
+    pub fn set_drawing(&mut self, node_id: SceneNodeId, drawing: Vec<Draw>) {
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            node.drawing = drawing;
+            self.changed_content.insert(node_id);
+        }
+    }
+
+    ///
+    /// Sets the transform that's applied to a node and its children (on top of the transform of its parent node)
+    ///
+    pub fn set_transform(&mut self, node_id: SceneNodeId, transform: Transform2D) {
+        if let Some(node) = self.nodes.get_mut(&node_id) {
+            if node.transform != transform {
+                node.transform = transform;
+                self.changed_transform.insert(node_id);
+            }
+        }
+    }
+
+    ///
+    /// Adds a child node to the end of the list of children for a node
+    ///
+    /// Nodes can have more than one parent. Returns false if either node doesn't exist, or if the child node contains the parent
+    /// node (which would create a cycle).
+    ///
+    pub fn add_child(&mut self, parent_id: SceneNodeId, child_id: SceneNodeId) -> bool {
+        if !self.nodes.contains_key(&child_id) || self.contains_node(child_id, parent_id) {
+            return false;
+        }
+
+        if let Some(parent) = self.nodes.get_mut(&parent_id) {
+            parent.children.push(child_id);
+            self.changed_content.insert(parent_id);
+
+            true
+        } else {
+            false
+        }
+    }
+
+    ///
+    /// Removes a child node from a node (the child node itself is left in the graph)
+    ///
+    pub fn remove_child(&mut self, parent_id: SceneNodeId, child_id: SceneNodeId) {
+        if let Some(parent) = self.nodes.get_mut(&parent_id) {
+            if parent.children.contains(&child_id) {
+                parent.children.retain(|existing_id| *existing_id != child_id);
+                self.changed_content.insert(parent_id);
+            }
+        }
+    }
+
+    ///
+    /// Returns the children of a node
+    ///
+    pub fn children(&self, node_id: SceneNodeId) -> Vec<SceneNodeId> {
+        self.nodes.get(&node_id)
+            .map(|node| node.children.clone())
+            .unwrap_or_default()
+    }
+
+    ///
+    /// Sets whether or not a node is drawn as a sprite
+    ///
+    /// A sprite node is only redrawn when it or one of its children changes, and changing its transform only moves the sprite,
+    /// so this is useful for subtrees that are reused in several places or that are expensive to draw.
+    ///
+    pub fn set_sprite(&mut self, node_id: SceneNodeId, is_sprite: bool) {
+        let has_sprite = if let Some(node) = self.nodes.get(&node_id) { node.sprite.is_some() } else { return; };
+
+        if is_sprite && !has_sprite {
+            let sprite_id = self.allocate_sprite();
+            self.nodes.get_mut(&node_id).unwrap().sprite = Some(sprite_id);
+            self.changed_content.insert(node_id);
+        } else if !is_sprite && has_sprite {
+            let sprite_id = self.nodes.get_mut(&node_id).unwrap().sprite.take().unwrap();
+            self.release_sprite(sprite_id);
+            self.changed_content.insert(node_id);
+        }
+    }
+
+    ///
+    /// Adds a node to the end of the list of top-level nodes, which are each drawn on their own layer
+    ///
+    pub fn add_top_level(&mut self, node_id: SceneNodeId) {
+        if self.nodes.contains_key(&node_id) && !self.top_level.contains(&node_id) {
+            self.top_level.push(node_id);
+        }
+    }
+
+    ///
+    /// Removes a node from the list of top-level nodes (the node itself is left in the graph)
+    ///
+    pub fn remove_top_level(&mut self, node_id: SceneNodeId) {
+        self.top_level.retain(|top_level_id| *top_level_id != node_id);
+    }
+
+    ///
+    /// Returns the top-level nodes, in the order of the layers they're drawn on
+    ///
+    pub fn top_level(&self) -> Vec<SceneNodeId> {
+        self.top_level.clone()
+    }
+
+    ///
+    /// Marks everything as needing to be redrawn (eg, after the canvas has been cleared)
+    ///
+    pub fn invalidate_all(&mut self) {
+        self.drawn_top_level = vec![];
+        self.changed_content.extend(self.nodes.keys().copied());
+    }
+
+    ///
+    /// Generates the drawing instructions needed to update a canvas with the changes made since the last time this was called
+    ///
+    /// The canvas is assumed to contain the drawing generated by the previous calls to this function, and the layers and sprites
+    /// used by this graph should not be changed by anything else. If any sprites are drawn, the instructions finish by selecting
+    /// a layer, so drawing that follows them doesn't end up in a sprite.
+    ///
+    pub fn diff_to_draw(&mut self) -> Vec<Draw> {
+        let mut drawing         = vec![];
+        let mut layer_selected  = true;
+
+        // Work out which subtrees need to be redrawn
+        let mut dirty_subtrees  = HashMap::new();
+        let node_ids            = self.nodes.keys().copied().collect::<Vec<_>>();
+        for node_id in node_ids.iter() {
+            self.subtree_is_dirty(*node_id, &mut dirty_subtrees);
+        }
+
+        // Clear any sprites that are no longer in use
+        for sprite_id in self.released_sprites.drain(..) {
+            layer_selected = false;
+            drawing.push(Draw::Sprite(sprite_id));
+            drawing.push(Draw::ClearSprite);
+        }
+
+        // Redefine any sprite whose content has changed (moving a sprite doesn't change its definition)
+        for node_id in node_ids.iter() {
+            let node = &self.nodes[node_id];
+
+            if let Some(sprite_id) = node.sprite {
+                let content_changed = self.changed_content.contains(node_id)
+                    || node.children.iter().any(|child_id| dirty_subtrees.get(child_id) == Some(&true));
+
+                if content_changed {
+                    layer_selected = false;
+                    drawing.push(Draw::Sprite(sprite_id));
+                    drawing.push(Draw::ClearSprite);
+                    drawing.push(Draw::PushState);
+                    self.draw_content(*node_id, &mut drawing);
+                    drawing.push(Draw::PopState);
+                }
+            }
+        }
+
+        // Redraw any layer whose node has changed or which contains a changed node
+        for (idx, node_id) in self.top_level.iter().enumerate() {
+            let layer_changed = self.drawn_top_level.get(idx) != Some(node_id)
+                || dirty_subtrees.get(node_id) == Some(&true);
+
+            if layer_changed {
+                layer_selected = true;
+                drawing.push(Draw::Layer(LayerId(self.base_layer_id + idx as u64)));
+                drawing.push(Draw::ClearLayer);
+                self.draw_node(*node_id, &mut drawing);
+            }
+        }
+
+        // Clear any layers that are no longer in use
+        for idx in self.top_level.len()..self.drawn_top_level.len() {
+            layer_selected = true;
+            drawing.push(Draw::Layer(LayerId(self.base_layer_id + idx as u64)));
+            drawing.push(Draw::ClearLayer);
+        }
+
+        // If only sprites were drawn, the canvas would be left drawing to the last of them
+        if !layer_selected {
+            drawing.push(Draw::Layer(LayerId(self.base_layer_id)));
+        }
+
+        // Everything is now up to date
+        self.drawn_top_level = self.top_level.clone();
+        self.changed_content.clear();
+        self.changed_transform.clear();
+
+        drawing
+    }
+
+    ///
+    /// Draws a node, including its transform
+    ///
+    fn draw_node(&self, node_id: SceneNodeId, drawing: &mut Vec<Draw>) {
+        if let Some(node) = self.nodes.get(&node_id) {
+            drawing.push(Draw::PushState);
+
+            if node.transform != Transform2D::default() {
+                drawing.push(Draw::MultiplyTransform(node.transform));
+            }
+
+            if let Some(sprite_id) = node.sprite {
+                drawing.push(Draw::DrawSprite(sprite_id));
+            } else {
+                self.draw_content(node_id, drawing);
+            }
+
+            drawing.push(Draw::PopState);
+        }
+    }
+
+    ///
+    /// Draws the drawing instructions and children for a node
+    ///
+    fn draw_content(&self, node_id: SceneNodeId, drawing: &mut Vec<Draw>) {
+        if let Some(node) = self.nodes.get(&node_id) {
+            drawing.extend(node.drawing.iter().cloned());
+
+            for child_id in node.children.iter() {
+                self.draw_node(*child_id, drawing);
+            }
+        }
+    }
+
+    ///
+    /// Returns true if a node or any of its children have changed since the drawing was last generated
+    ///
+    fn subtree_is_dirty(&self, node_id: SceneNodeId, dirty_subtrees: &mut HashMap<SceneNodeId, bool>) -> bool {
+        if let Some(is_dirty) = dirty_subtrees.get(&node_id) {
+            return *is_dirty;
+        }
+
+        let mut is_dirty = self.changed_content.contains(&node_id) || self.changed_transform.contains(&node_id);
+
+        if let Some(node) = self.nodes.get(&node_id) {
+            for child_id in node.children.iter() {
+                // Every child is visited so that the result for the whole tree is cached
+                is_dirty = self.subtree_is_dirty(*child_id, dirty_subtrees) || is_dirty;
+            }
+        }
+
+        dirty_subtrees.insert(node_id, is_dirty);
+        is_dirty
+    }
+
+    ///
+    /// Returns true if the `containing_id` node is the `node_id` node or has it as a descendant
+    ///
+    fn contains_node(&self, containing_id: SceneNodeId, node_id: SceneNodeId) -> bool {
+        if containing_id == node_id {
+            return true;
+        }
+
+        self.nodes.get(&containing_id)
+            .map(|node| node.children.iter().any(|child_id| self.contains_node(*child_id, node_id)))
+            .unwrap_or(false)
+    }
+
+    ///
+    /// Allocates a sprite ID for a node
+    ///
+    fn allocate_sprite(&mut self) -> SpriteId {
+        if let Some(sprite_id) = self.free_sprites.pop() {
+            // Reusing a sprite ID, so it doesn't need to be cleared any more (it'll be redefined instead)
+            self.released_sprites.retain(|released_id| *released_id != sprite_id);
+            sprite_id
+        } else {
+            let sprite_id = SpriteId(self.next_sprite_id);
+            self.next_sprite_id += 1;
+            sprite_id
+        }
+    }
+
+    ///
+    /// Releases a sprite ID that's no longer in use
+    ///
+    fn release_sprite(&mut self, sprite_id: SpriteId) {
+        self.free_sprites.push(sprite_id);
+        self.released_sprites.push(sprite_id);
+    }
+}
+
+impl Default for SceneGraph {
+    fn default() -> SceneGraph {
+        SceneGraph::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fill_rect(x: f32) -> Vec<Draw> {
+        vec![Draw::Path(PathOp::NewPath), Draw::Path(PathOp::Move(x, 0.0)), Draw::Path(PathOp::Line(x + 10.0, 10.0)), Draw::Fill]
+    }
+
+    fn layers_selected(drawing: &[Draw]) -> Vec<LayerId> {
+        drawing.iter()
+            .filter_map(|draw| if let Draw::Layer(layer_id) = draw { Some(*layer_id) } else { None })
+            .collect()
+    }
+
+    fn sprites_cleared(drawing: &[Draw]) -> Vec<SpriteId> {
+        drawing.windows(2)
+            .filter_map(|draws| match draws {
+                [Draw::Sprite(sprite_id), Draw::ClearSprite] => Some(*sprite_id),
+                _                                            => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn first_diff_draws_every_layer() {
+        let mut scene   = SceneGraph::new();
+        let first       = scene.create_node();
+        let second      = scene.create_node();
+
+        scene.set_drawing(first, fill_rect(0.0));
+        scene.set_drawing(second, fill_rect(20.0));
+        scene.add_top_level(first);
+        scene.add_top_level(second);
+
+        let drawing = scene.diff_to_draw();
+
+        let mut expected = vec![Draw::Layer(LayerId(0)), Draw::ClearLayer, Draw::PushState];
+        expected.extend(fill_rect(0.0));
+        expected.extend(vec![Draw::PopState, Draw::Layer(LayerId(1)), Draw::ClearLayer, Draw::PushState]);
+        expected.extend(fill_rect(20.0));
+        expected.push(Draw::PopState);
+
+        assert!(drawing == expected, "{:?}", drawing);
+    }
+
+    #[test]
+    fn unchanged_scene_draws_nothing() {
+        let mut scene   = SceneGraph::new();
+        let node        = scene.create_node();
+
+        scene.set_drawing(node, fill_rect(0.0));
+        scene.add_top_level(node);
+        scene.diff_to_draw();
+
+        assert!(scene.diff_to_draw().is_empty());
+    }
+
+    #[test]
+    fn changed_child_only_redraws_its_layer() {
+        let mut scene   = SceneGraph::new();
+        let first       = scene.create_node();
+        let second      = scene.create_node();
+        let child       = scene.create_node();
+
+        scene.set_drawing(child, fill_rect(0.0));
+        scene.add_child(second, child);
+        scene.add_top_level(first);
+        scene.add_top_level(second);
+        scene.diff_to_draw();
+
+        // Moving the child dirties the layer of its top-level node, but not the other layer
+        scene.set_transform(child, Transform2D::translate(10.0, 0.0));
+        let drawing = scene.diff_to_draw();
+
+        assert!(layers_selected(&drawing) == vec![LayerId(1)], "{:?}", drawing);
+        assert!(drawing.contains(&Draw::MultiplyTransform(Transform2D::translate(10.0, 0.0))));
+        assert!(drawing.contains(&Draw::Fill));
+    }
+
+    #[test]
+    fn removed_top_level_clears_unused_layer() {
+        let mut scene   = SceneGraph::with_base_ids(LayerId(4), SpriteId(0));
+        let first       = scene.create_node();
+        let second      = scene.create_node();
+
+        scene.set_drawing(first, fill_rect(0.0));
+        scene.set_drawing(second, fill_rect(20.0));
+        scene.add_top_level(first);
+        scene.add_top_level(second);
+        scene.diff_to_draw();
+
+        // The second node moves down to the first layer, and the layer it was on is cleared
+        scene.remove_top_level(first);
+        let drawing = scene.diff_to_draw();
+
+        assert!(drawing.first() == Some(&Draw::Layer(LayerId(4))), "{:?}", drawing);
+        assert!(drawing.contains(&Draw::Path(PathOp::Move(20.0, 0.0))));
+        assert!(drawing.ends_with(&[Draw::Layer(LayerId(5)), Draw::ClearLayer]), "{:?}", drawing);
+    }
+
+    #[test]
+    fn removed_node_clears_its_layer() {
+        let mut scene   = SceneGraph::new();
+        let node        = scene.create_node();
+
+        scene.set_drawing(node, fill_rect(0.0));
+        scene.add_top_level(node);
+        scene.diff_to_draw();
+
+        scene.remove_node(node);
+
+        assert!(scene.top_level().is_empty());
+        assert!(scene.diff_to_draw() == vec![Draw::Layer(LayerId(0)), Draw::ClearLayer]);
+    }
+
+    #[test]
+    fn sprite_is_only_redefined_when_content_changes() {
+        let mut scene   = SceneGraph::new();
+        let node        = scene.create_node();
+
+        scene.set_drawing(node, fill_rect(0.0));
+        scene.set_sprite(node, true);
+        scene.add_top_level(node);
+
+        let drawing = scene.diff_to_draw();
+        assert!(sprites_cleared(&drawing) == vec![SpriteId(0)], "{:?}", drawing);
+        assert!(drawing.contains(&Draw::DrawSprite(SpriteId(0))));
+
+        // Moving the sprite redraws the layer without redefining the sprite
+        scene.set_transform(node, Transform2D::translate(10.0, 0.0));
+        let drawing = scene.diff_to_draw();
+        assert!(sprites_cleared(&drawing).is_empty(), "{:?}", drawing);
+        assert!(drawing.contains(&Draw::DrawSprite(SpriteId(0))));
+    }
+
+    #[test]
+    fn redefined_sprite_finishes_on_a_layer() {
+        let mut scene   = SceneGraph::new();
+        let node        = scene.create_node();
+
+        scene.set_drawing(node, fill_rect(0.0));
+        scene.set_sprite(node, true);
+        scene.diff_to_draw();
+
+        // The node isn't on a layer, so only the sprite is drawn: the canvas should still be left drawing to a layer
+        scene.set_drawing(node, fill_rect(20.0));
+        let drawing = scene.diff_to_draw();
+
+        assert!(sprites_cleared(&drawing) == vec![SpriteId(0)], "{:?}", drawing);
+        assert!(drawing.last() == Some(&Draw::Layer(LayerId(0))), "{:?}", drawing);
+    }
+
+    #[test]
+    fn released_sprite_is_cleared_and_reused() {
+        let mut scene   = SceneGraph::new();
+        let first       = scene.create_node();
+        let second      = scene.create_node();
+
+        scene.set_sprite(first, true);
+        scene.set_sprite(second, true);
+        scene.diff_to_draw();
+
+        // Releasing a sprite clears it (and leaves the canvas on a layer)
+        scene.set_sprite(first, false);
+        let drawing = scene.diff_to_draw();
+
+        assert!(sprites_cleared(&drawing) == vec![SpriteId(0)], "{:?}", drawing);
+        assert!(drawing.last() == Some(&Draw::Layer(LayerId(0))), "{:?}", drawing);
+
+        // The next sprite reuses the free ID
+        let third = scene.create_node();
+        scene.set_sprite(third, true);
+        scene.diff_to_draw();
+
+        let fourth = scene.create_node();
+        scene.set_sprite(fourth, true);
+
+        assert!(scene.nodes[&third].sprite == Some(SpriteId(0)));
+        assert!(scene.nodes[&fourth].sprite == Some(SpriteId(2)));
+    }
+
+    #[test]
+    fn reused_sprite_is_not_cleared_after_redefinition() {
+        let mut scene   = SceneGraph::new();
+        let first       = scene.create_node();
+        let second      = scene.create_node();
+
+        scene.set_drawing(first, fill_rect(0.0));
+        scene.set_sprite(first, true);
+        scene.diff_to_draw();
+
+        // The sprite is released and reallocated before the next diff, so it's just redefined with the new content
+        scene.remove_node(first);
+        scene.set_drawing(second, fill_rect(20.0));
+        scene.set_sprite(second, true);
+
+        let drawing = scene.diff_to_draw();
+
+        assert!(sprites_cleared(&drawing) == vec![SpriteId(0)], "{:?}", drawing);
+        assert!(drawing.contains(&Draw::Path(PathOp::Move(20.0, 0.0))));
+        assert!(!drawing.contains(&Draw::Path(PathOp::Move(0.0, 0.0))));
+    }
+}
@@ -3,8 +3,11 @@ mod winit_window;
 mod winit_thread;
 mod winit_runtime;
 mod winit_thread_event;
+mod window_canvas_renderer;
 
 pub (crate) use self::winit_thread::*;
 pub (crate) use self::winit_thread_event::*;
 
 pub use self::winit_thread::{with_2d_graphics};
+pub use self::winit_window::{set_wgpu_adapter_options};
+pub use self::window_canvas_renderer::*;
@@ -0,0 +1,148 @@
+use flo_canvas::*;
+use flo_render::*;
+use flo_render_canvas::*;
+
+use wgpu;
+use raw_window_handle::{HasRawWindowHandle, HasRawDisplayHandle};
+use futures::prelude::*;
+
+use std::sync::*;
+
+///
+/// Renders canvas drawing instructions to a window that is owned by another part of the application
+///
+/// This is created by `canvas_renderer_for_window()`. Drawing instructions are sent using `draw()`, and the window is
+/// updated when `present()` is called. The window is not resized automatically: the application should call `resize()`
+/// whenever the size or scale factor of its window changes.
+///
+pub struct WindowCanvasRenderer {
+    /// The renderer that converts render actions to wgpu instructions
+    renderer: WgpuRenderer,
+
+    /// The canvas renderer that converts drawing instructions into render actions
+    canvas_renderer: CanvasRenderer,
+
+    /// The size of the window in pixels
+    size: (u32, u32),
+
+    /// The scale factor of the window
+    scale: f64,
+}
+
+///
+/// Creates a renderer that can draw canvas instructions to an existing window
+///
+/// The window can be anything that provides a raw window handle, for example a winit or SDL2 window. The size is the
+/// size of the window in pixels, and the scale is the ratio of pixels to logical window coordinates (1.0 for most
+/// displays, 2.0 for 'retina' displays).
+///
+/// An error is returned if a surface can't be created for the window, or if no suitable adapter or device is available.
+///
+/// # Safety
+///
+/// The window must remain valid for the lifetime of the returned renderer.
+///
+pub async unsafe fn canvas_renderer_for_window<TWindow>(window: &TWindow, size: (u32, u32), scale: f64) -> Result<WindowCanvasRenderer, RenderInitError>
+where
+    TWindow: HasRawWindowHandle + HasRawDisplayHandle,
+{
+    canvas_renderer_for_window_with_options(window, size, scale, AdapterOptions::default()).await
+}
+
+///
+/// Creates a renderer that can draw canvas instructions to an existing window, choosing the adapter according to a set of options
+///
+/// This is the same as `canvas_renderer_for_window()`, except that the adapter is chosen using the supplied options.
+///
+/// # Safety
+///
+/// The window must remain valid for the lifetime of the returned renderer.
+///
+pub async unsafe fn canvas_renderer_for_window_with_options<TWindow>(window: &TWindow, size: (u32, u32), scale: f64, options: AdapterOptions) -> Result<WindowCanvasRenderer, RenderInitError>
+where
+    TWindow: HasRawWindowHandle + HasRawDisplayHandle,
+{
+    // Create a new WGPU instance, surface and adapter
+    let backend         = options.backends_or(wgpu::Backends::PRIMARY);
+    let instance        = wgpu::Instance::new(wgpu::InstanceDescriptor { backends: backend, ..Default::default() });
+    let surface         = instance.create_surface(window).map_err(|_| RenderInitError::CouldNotCreateSurface)?;
+    let adapter         = request_adapter_with_options(&instance, Some(&surface), &options).await?;
+    check_surface_supported(&surface, &adapter)?;
+
+    // Fetch the device and the queue
+    let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor {
+        label:      None,
+        features:   wgpu::Features::empty(),
+        limits:     options.device_limits(&adapter, default_device_limits(&adapter))
+    }, None).await.map_err(|err| no_device_error(&adapter, err))?;
+
+    // Create the renderers
+    let device              = Arc::new(device);
+    let queue               = Arc::new(queue);
+    let surface             = Arc::new(surface);
+    let adapter             = Arc::new(adapter);
+    let renderer            = WgpuRenderer::from_surface(device, queue, surface, adapter);
+    let canvas_renderer     = CanvasRenderer::new();
+
+    let mut window_renderer = WindowCanvasRenderer { renderer, canvas_renderer, size, scale };
+    window_renderer.resize(size, scale);
+
+    Ok(window_renderer)
+}
+
+impl WindowCanvasRenderer {
+    ///
+    /// Updates the size (in pixels) and scale factor of the window that is being rendered to
+    ///
+    pub fn resize(&mut self, size: (u32, u32), scale: f64) {
+        let width   = size.0 as f32;
+        let height  = size.1 as f32;
+
+        self.size   = size;
+        self.scale  = scale;
+        self.canvas_renderer.set_viewport(0.0..width, 0.0..height, width, height, scale as f32);
+    }
+
+    ///
+    /// Returns the size (in pixels) and scale factor of the window that is being rendered to
+    ///
+    pub fn size(&self) -> ((u32, u32), f64) {
+        (self.size, self.scale)
+    }
+
+    ///
+    /// Sets the present mode to use for the window
+    ///
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
+        self.renderer.set_present_mode(present_mode);
+    }
+
+    ///
+    /// Updates the canvas with some drawing instructions
+    ///
+    /// The window is not updated until `present()` is called.
+    ///
+    pub async fn draw<DrawIter: Iterator<Item=Draw>>(&mut self, drawing: DrawIter) {
+        self.canvas_renderer.process_drawing(drawing).await;
+    }
+
+    ///
+    /// Renders the current state of the canvas and presents it to the window
+    ///
+    pub async fn present(&mut self) {
+        // Nothing to render if the window has no area
+        if self.size.0 == 0 || self.size.1 == 0 {
+            return;
+        }
+
+        // Drawing nothing will generate the render actions for the current state of the canvas
+        let render_actions = self.canvas_renderer.draw(vec![].into_iter()).collect::<Vec<_>>().await;
+
+        // Render to the surface and present the result
+        self.renderer.prepare_to_render(self.size.0, self.size.1);
+
+        if let Some(surface_texture) = self.renderer.render_to_surface(render_actions) {
+            surface_texture.present();
+        }
+    }
+}
@@ -2,6 +2,7 @@ use super::winit_thread::*;
 use super::winit_thread_event::*;
 
 use crate::events::*;
+use crate::frame_loop::*;
 use crate::window_properties::*;
 
 use flo_stream::*;
@@ -14,6 +15,7 @@ use winit::window::{Window, Fullscreen};
 use futures::prelude::*;
 use futures::channel::oneshot;
 use futures::task::{Poll, Context};
+use once_cell::sync::{Lazy};
 
 use std::pin::*;
 use std::sync::*;
@@ -37,10 +39,43 @@ pub struct WinitWindow {
     device: Option<Arc<wgpu::Device>>,
 
     /// The WGPU instance used by this window
-    instance: Option<wgpu::Instance>,
+    instance: Option<Arc<wgpu::Instance>>,
 
     /// The renderer for this window (or none if there isn't one yet)
-    renderer: Option<WgpuRenderer>
+    renderer: Option<WgpuRenderer>,
+
+    /// The present mode that the renderer should use for this window
+    present_mode: wgpu::PresentMode,
+
+    /// The number of samples per pixel that the renderer should use for multisampled render targets
+    multisample_count: u32,
+}
+
+///
+/// The WGPU instance and device that are shared between windows
+///
+#[derive(Clone)]
+struct SharedDevice {
+    instance:   Arc<wgpu::Instance>,
+    adapter:    Arc<wgpu::Adapter>,
+    device:     Arc<wgpu::Device>,
+    queue:      Arc<wgpu::Queue>,
+}
+
+/// Windows use the same device where possible, so that the GPU resources are not created multiple times
+static SHARED_DEVICE: Lazy<Mutex<Option<SharedDevice>>> = Lazy::new(|| Mutex::new(None));
+
+/// The options used to choose the adapter when a device is created for a window
+static ADAPTER_OPTIONS: Lazy<Mutex<AdapterOptions>> = Lazy::new(|| Mutex::new(AdapterOptions::default()));
+
+///
+/// Sets the options used to choose the adapter (GPU) that renders to windows
+///
+/// Windows share a device where possible, so this only affects windows created after it's called, and only when a new device is
+/// needed (usually this should be called before the first window is created).
+///
+pub fn set_wgpu_adapter_options(options: AdapterOptions) {
+    *ADAPTER_OPTIONS.lock().unwrap() = options;
 }
 
 impl WinitWindow {
@@ -49,12 +84,78 @@ impl WinitWindow {
     ///
     pub fn new(window: Arc<Window>) -> WinitWindow {
         WinitWindow {
-            window:     Some(window),
-            device:     None,
-            instance:   None,
-            renderer:   None,
+            window:             Some(window),
+            device:             None,
+            instance:           None,
+            renderer:           None,
+            present_mode:       wgpu::PresentMode::AutoVsync,
+            multisample_count:  4,
+        }
+    }
+}
+
+///
+/// Creates a renderer for a window, using the shared device if it can render to the window
+///
+async fn create_renderer(winit_window: &Window, present_mode: wgpu::PresentMode, multisample_count: u32) -> Result<(Arc<wgpu::Instance>, Arc<wgpu::Device>, WgpuRenderer), RenderInitError> {
+    // Create a surface using the shared WGPU instance (or a new instance if this is the first window)
+    let shared_device   = SHARED_DEVICE.lock().unwrap().clone();
+    let adapter_options = ADAPTER_OPTIONS.lock().unwrap().clone();
+
+    let instance        = shared_device.as_ref().map(|shared_device| Arc::clone(&shared_device.instance)).unwrap_or_else(|| {
+        let backend = adapter_options.backends_or(wgpu::Backends::PRIMARY);
+        Arc::new(wgpu::Instance::new(wgpu::InstanceDescriptor { backends: backend, ..Default::default() }))
+    });
+    let surface         = unsafe { instance.create_surface(winit_window).map_err(|_| RenderInitError::CouldNotCreateSurface)? };
+
+    // Re-use the shared device if it can render to this window, otherwise create a new adapter and device
+    let (adapter, device, queue) = match shared_device {
+        Some(shared_device) if shared_device.adapter.is_surface_supported(&surface) => {
+            (shared_device.adapter, shared_device.device, shared_device.queue)
+        }
+
+        _ => {
+            let adapter         = request_adapter_with_options(&instance, Some(&surface), &adapter_options).await?;
+
+            // Fetch the device and the queue (sample counts other than 4 need the adapter-specific texture format features)
+            let features        = adapter.features() & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+            #[cfg(feature="wgpu-profiler")] let features = features | GpuProfiler::ALL_WGPU_TIMER_FEATURES;
+            let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor {
+                label:      None,
+                features:   features,
+                limits:     adapter_options.device_limits(&adapter, default_device_limits(&adapter))
+            }, None).await.map_err(|err| no_device_error(&adapter, err))?;
+
+            let adapter         = Arc::new(adapter);
+            let device          = Arc::new(device);
+            let queue           = Arc::new(queue);
+
+            // The first device that's created is shared with any other windows
+            let mut shared_device = SHARED_DEVICE.lock().unwrap();
+            if shared_device.is_none() {
+                *shared_device = Some(SharedDevice { instance: Arc::clone(&instance), adapter: Arc::clone(&adapter), device: Arc::clone(&device), queue: Arc::clone(&queue) });
+            }
+
+            (adapter, device, queue)
         }
+    };
+
+    check_surface_supported(&surface, &adapter)?;
+
+    // Create the WGPU renderer
+    let surface         = Arc::new(surface);
+    let mut renderer    = WgpuRenderer::from_surface(Arc::clone(&device), Arc::clone(&queue), Arc::clone(&surface), Arc::clone(&adapter));
+    renderer.set_present_mode(present_mode);
+    renderer.set_multisample_count(multisample_count);
+
+    // Compile the commonly used pipelines in the background so that they're less likely to cause a delay when they're first used
+    let size            = winit_window.inner_size();
+    if size.width > 0 && size.height > 0 {
+        renderer.prepare_to_render(size.width, size.height);
     }
+    renderer.prewarm_common_pipelines();
+
+    Ok((instance, device, renderer))
 }
 
 ///
@@ -71,15 +172,20 @@ where
     // Read events from the render actions list
     let mut window          = window;
     let mut events          = events;
+    let max_frame_rate      = window_properties.max_frame_rate.clone();
+    let mut frame_limiter   = FrameLimiter::new();
     let window_actions      = WindowUpdateStream { 
         render_stream:      render_actions, 
         title_stream:       follow(window_properties.title),
         size:               follow(window_properties.size),
         fullscreen:         follow(window_properties.fullscreen),
         has_decorations:    follow(window_properties.has_decorations),
-        mouse_pointer:      follow(window_properties.mouse_pointer)
+        mouse_pointer:      follow(window_properties.mouse_pointer),
+        present_mode:       follow(window_properties.present_mode),
+        multisampling:      follow(window_properties.multisampling),
     };
     let mut window_actions  = window_actions.ready_chunks(100);
+    let mut render_failed   = false;
 
     while let Some(next_action_set) = window_actions.next().await {
         let mut send_new_frame = false;
@@ -93,39 +199,27 @@ where
                         continue;
                     }
 
+                    // Once the renderer has failed to start, the render actions are discarded (but frames are still acknowledged so the drawing doesn't stall)
+                    if render_failed {
+                        send_new_frame = true;
+                        continue;
+                    }
+
                     // Create the renderer if it doesn't already exist
                     if let (Some(winit_window), None) = (&window.window, &window.renderer) {
-                        // Create a new WGPU instance, surface and adapter
-                        let winit_window    = &**winit_window;
-
-                        let backend         = wgpu::util::backend_bits_from_env().unwrap_or_else(|| wgpu::Backends::PRIMARY);
-                        let instance        = wgpu::Instance::new(wgpu::InstanceDescriptor { backends: backend, ..Default::default() });
-                        let surface         = unsafe { instance.create_surface(winit_window).expect("wgpu surface") };
-                        let adapter         = instance.request_adapter(&wgpu::RequestAdapterOptions {
-                            power_preference:       wgpu::PowerPreference::default(),
-                            force_fallback_adapter: false,
-                            compatible_surface:     Some(&surface),
-                        }).await.expect("Could not acquire an adapter for winit/wgpu");
-
-                        // Fetch the device and the queue
-                        let features        = wgpu::Features::empty();
-                        #[cfg(feature="wgpu-profiler")] let features = features | GpuProfiler::ALL_WGPU_TIMER_FEATURES;
-                        let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor {
-                            label:      None,
-                            features:   features,
-                            limits:     wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits())
-                        }, None).await.expect("Create WGPU device and queue");
-
-                        // Create the WGPU renderer
-                        let device          = Arc::new(device);
-                        let queue           = Arc::new(queue);
-                        let surface         = Arc::new(surface);
-                        let adapter         = Arc::new(adapter);
-                        let renderer        = WgpuRenderer::from_surface(Arc::clone(&device), Arc::clone(&queue), Arc::clone(&surface), Arc::clone(&adapter));
-
-                        window.device       = Some(device);
-                        window.instance     = Some(instance);
-                        window.renderer     = Some(renderer);
+                        match create_renderer(&**winit_window, window.present_mode, window.multisample_count).await {
+                            Ok((instance, device, renderer)) => {
+                                window.device       = Some(device);
+                                window.instance     = Some(instance);
+                                window.renderer     = Some(renderer);
+                            }
+
+                            Err(err) => {
+                                // Report the error to the window's events
+                                render_failed = true;
+                                events.publish(DrawEvent::RenderError(err)).await;
+                            }
+                        }
 
                         // First frame has been displayed
                         send_new_frame = true;
@@ -142,8 +236,29 @@ where
                         // Send the commands to the renderer
                         let maybe_next_frame = renderer.render_to_surface(next_action);
 
+                        // If the device has been lost, discard the renderer so that a new one is created, and ask for the drawing to be sent again
+                        if renderer.is_device_lost() {
+                            if let Some(device) = &window.device {
+                                let mut shared_device = SHARED_DEVICE.lock().unwrap();
+
+                                if shared_device.as_ref().map(|shared_device| Arc::ptr_eq(&shared_device.device, device)).unwrap_or(false) {
+                                    *shared_device = None;
+                                }
+                            }
+
+                            window.renderer = None;
+                            window.device   = None;
+                            window.instance = None;
+
+                            events.publish(DrawEvent::DeviceReset).await;
+                            continue;
+                        }
+
                         // Notify that a new frame has been drawn if show_frame_buffer is set
                         if let Some(next_frame) = maybe_next_frame {
+                            // Hold the frame back if it's too soon after the last one
+                            frame_limiter.wait_for_next_frame(max_frame_rate.get()).await;
+
                             #[cfg(feature="profile")]
                             let start_time = Instant::now();
 
@@ -201,6 +316,28 @@ where
                         winit_window.set_cursor_visible(true);
                     }
                 }
+
+                WindowUpdate::SetPresentMode(present_mode) => {
+                    // Low latency uses mailbox mode where it's available (the renderer falls back to vsync if it isn't)
+                    window.present_mode = match present_mode {
+                        PresentMode::Vsync      => wgpu::PresentMode::AutoVsync,
+                        PresentMode::LowLatency => wgpu::PresentMode::Mailbox,
+                        PresentMode::Immediate  => wgpu::PresentMode::AutoNoVsync,
+                    };
+
+                    if let Some(renderer) = &mut window.renderer {
+                        renderer.set_present_mode(window.present_mode);
+                    }
+                }
+
+                WindowUpdate::SetMultisampling(samples) => {
+                    // The canvas renderer creates its render targets at the start of each frame, so this applies from the next frame
+                    window.multisample_count = samples;
+
+                    if let Some(renderer) = &mut window.renderer {
+                        renderer.set_multisample_count(samples);
+                    }
+                }
             }
         }
 
@@ -224,7 +361,9 @@ enum WindowUpdate {
     SetSize((u64, u64)),
     SetFullscreen(bool),
     SetHasDecorations(bool),
-    SetMousePointer(MousePointer)
+    SetMousePointer(MousePointer),
+    SetPresentMode(PresentMode),
+    SetMultisampling(u32),
 }
 
 impl fmt::Debug for WindowUpdate {
@@ -238,6 +377,8 @@ impl fmt::Debug for WindowUpdate {
             SetFullscreen(val)          => write!(f, "SetFullscreen({:?})", val),
             SetHasDecorations(val)      => write!(f, "SetHasDecorations({:?})", val),
             SetMousePointer(ptr)        => write!(f, "SetMousePointer({:?})", ptr),
+            SetPresentMode(mode)        => write!(f, "SetPresentMode({:?})", mode),
+            SetMultisampling(samples)   => write!(f, "SetMultisampling({:?})", samples),
         }
     }
 }
@@ -245,23 +386,27 @@ impl fmt::Debug for WindowUpdate {
 ///
 /// Stream that merges the streams from the window properties and the renderer into a single stream
 ///
-struct WindowUpdateStream<TRenderStream, TTitleStream, TSizeStream, TFullscreenStream, TDecorationStream, TMousePointerStream> {
+struct WindowUpdateStream<TRenderStream, TTitleStream, TSizeStream, TFullscreenStream, TDecorationStream, TMousePointerStream, TPresentModeStream, TMultisamplingStream> {
     render_stream:      TRenderStream,
     title_stream:       TTitleStream,
     size:               TSizeStream,
     fullscreen:         TFullscreenStream,
     has_decorations:    TDecorationStream,
-    mouse_pointer:      TMousePointerStream
+    mouse_pointer:      TMousePointerStream,
+    present_mode:       TPresentModeStream,
+    multisampling:      TMultisamplingStream,
 }
 
-impl<TRenderStream, TTitleStream, TSizeStream, TFullscreenStream, TDecorationStream, TMousePointerStream> Stream for WindowUpdateStream<TRenderStream, TTitleStream, TSizeStream, TFullscreenStream, TDecorationStream, TMousePointerStream>
+impl<TRenderStream, TTitleStream, TSizeStream, TFullscreenStream, TDecorationStream, TMousePointerStream, TPresentModeStream, TMultisamplingStream> Stream for WindowUpdateStream<TRenderStream, TTitleStream, TSizeStream, TFullscreenStream, TDecorationStream, TMousePointerStream, TPresentModeStream, TMultisamplingStream>
 where
     TRenderStream:          Unpin + Stream<Item=Vec<RenderAction>>,
     TTitleStream:           Unpin + Stream<Item=String>,
     TSizeStream:            Unpin + Stream<Item=(u64, u64)>,
     TFullscreenStream:      Unpin + Stream<Item=bool>,
     TDecorationStream:      Unpin + Stream<Item=bool>,
-    TMousePointerStream:    Unpin + Stream<Item=MousePointer> 
+    TMousePointerStream:    Unpin + Stream<Item=MousePointer>,
+    TPresentModeStream:     Unpin + Stream<Item=PresentMode>,
+    TMultisamplingStream:   Unpin + Stream<Item=u32>,
 {
     type Item = WindowUpdate;
 
@@ -306,6 +451,18 @@ where
             Poll::Pending           => { }
         }
 
+        match self.present_mode.poll_next_unpin(context) {
+            Poll::Ready(Some(item)) => { return Poll::Ready(Some(WindowUpdate::SetPresentMode(item))); }
+            Poll::Ready(None)       => { return Poll::Ready(None); }
+            Poll::Pending           => { }
+        }
+
+        match self.multisampling.poll_next_unpin(context) {
+            Poll::Ready(Some(item)) => { return Poll::Ready(Some(WindowUpdate::SetMultisampling(item))); }
+            Poll::Ready(None)       => { return Poll::Ready(None); }
+            Poll::Pending           => { }
+        }
+
         // No stream matched anything
         Poll::Pending
     }
@@ -32,6 +32,37 @@ pub trait FloWindowProperties {
     /// The mouse pointer to show for a window
     ///
     fn mouse_pointer(&self) -> BindRef<MousePointer>;
+
+    ///
+    /// How the window should present new frames (windows wait for vsync by default)
+    ///
+    fn present_mode(&self) -> BindRef<PresentMode> {
+        BindRef::from(bind(PresentMode::Vsync))
+    }
+
+    ///
+    /// The number of samples per pixel used to antialias the window (4 by default)
+    ///
+    /// Higher values produce smoother edges but use more graphics memory: each sample adds about 8MB for a 1920x1080 window. If
+    /// the graphics device doesn't support the requested count, the nearest supported count is used. Changes take effect from the
+    /// next frame. Only the wgpu renderer supports changing the sample count.
+    ///
+    fn multisampling(&self) -> BindRef<u32> {
+        BindRef::from(bind(4))
+    }
+
+    ///
+    /// The maximum number of frames per second that the window will present (0, the default, for no limit)
+    ///
+    /// When a frame is ready before the limit allows, the window waits before presenting it, which also holds back the next
+    /// `NewFrame` event so animations driven by that event slow down to match. With `PresentMode::Vsync` the display rate is
+    /// already a limit, so this only has an effect when it's lower than the refresh rate. With `PresentMode::LowLatency` or
+    /// `PresentMode::Immediate` frames are otherwise presented as fast as they can be drawn, so this can be used to stop
+    /// animations from using more CPU and GPU time than they need.
+    ///
+    fn max_frame_rate(&self) -> BindRef<u32> {
+        BindRef::from(bind(0))
+    }
 }
 
 ///
@@ -66,7 +97,10 @@ pub struct WindowProperties {
     pub size:               BindRef<(u64, u64)>,
     pub fullscreen:         BindRef<bool>,
     pub has_decorations:    BindRef<bool>,
-    pub mouse_pointer:      BindRef<MousePointer>
+    pub mouse_pointer:      BindRef<MousePointer>,
+    pub present_mode:       BindRef<PresentMode>,
+    pub multisampling:      BindRef<u32>,
+    pub max_frame_rate:     BindRef<u32>,
 }
 
 impl WindowProperties {
@@ -79,7 +113,10 @@ impl WindowProperties {
             size:               properties.size(),
             fullscreen:         properties.fullscreen(),
             has_decorations:    properties.has_decorations(),
-            mouse_pointer:      properties.mouse_pointer()
+            mouse_pointer:      properties.mouse_pointer(),
+            present_mode:       properties.present_mode(),
+            multisampling:      properties.multisampling(),
+            max_frame_rate:     properties.max_frame_rate(),
         }
     }
 }
@@ -90,4 +127,7 @@ impl FloWindowProperties for WindowProperties {
     fn fullscreen(&self) -> BindRef<bool>               { self.fullscreen.clone() }
     fn has_decorations(&self) -> BindRef<bool>          { self.has_decorations.clone() }
     fn mouse_pointer(&self) -> BindRef<MousePointer>    { self.mouse_pointer.clone() }
+    fn present_mode(&self) -> BindRef<PresentMode>      { self.present_mode.clone() }
+    fn multisampling(&self) -> BindRef<u32>             { self.multisampling.clone() }
+    fn max_frame_rate(&self) -> BindRef<u32>            { self.max_frame_rate.clone() }
 }
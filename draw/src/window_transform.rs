@@ -0,0 +1,118 @@
+use crate::events::*;
+
+use flo_canvas::*;
+
+use futures::prelude::*;
+
+use std::sync::*;
+
+///
+/// Tracks the transform between window coordinates and canvas coordinates for a window
+///
+/// This is updated whenever a `DrawEvent::CanvasTransform` event passes through the event stream returned by
+/// `track_window_transform()`, so it stays in step with changes to `CanvasHeight`, `CenterRegion` and the
+/// size of the window. The scale factor of the window (the ratio of physical pixels to logical pixels) is tracked
+/// from the `DrawEvent::Scale` events in the same way.
+///
+#[derive(Clone)]
+pub struct WindowTransform {
+    /// The most recent transform from window coordinates to canvas coordinates
+    transform: Arc<Mutex<Transform2D>>,
+
+    /// The most recent scale factor reported by the window
+    scale_factor: Arc<Mutex<f64>>,
+}
+
+impl WindowTransform {
+    ///
+    /// Returns the current transform from window coordinates to canvas coordinates
+    ///
+    pub fn transform(&self) -> Transform2D {
+        *self.transform.lock().unwrap()
+    }
+
+    ///
+    /// Returns the current scale factor of the window (eg, 2.0 for a window on a 'retina' display)
+    ///
+    pub fn scale_factor(&self) -> f64 {
+        *self.scale_factor.lock().unwrap()
+    }
+
+    ///
+    /// Converts a position in window coordinates (as found in `PointerState::location_in_window`) to canvas coordinates
+    ///
+    pub fn window_to_canvas(&self, x: f32, y: f32) -> (f32, f32) {
+        self.transform().transform_point(x, y)
+    }
+}
+
+///
+/// Tracks the canvas transform reported by the events from a window
+///
+/// The returned stream passes on the events unchanged, and the `WindowTransform` can be used to convert window
+/// coordinates to canvas coordinates at any time. Until the window reports its first transform, window and
+/// canvas coordinates are treated as being the same.
+///
+pub fn track_window_transform<TEventStream>(events: TEventStream) -> (WindowTransform, impl Stream<Item=DrawEvent>)
+where
+    TEventStream: Stream<Item=DrawEvent>,
+{
+    let window_transform    = WindowTransform { transform: Arc::new(Mutex::new(Transform2D::identity())), scale_factor: Arc::new(Mutex::new(1.0)) };
+    let transform           = Arc::clone(&window_transform.transform);
+    let scale_factor        = Arc::clone(&window_transform.scale_factor);
+
+    let events              = events.map(move |event| {
+        match &event {
+            DrawEvent::CanvasTransform(new_transform)   => { *transform.lock().unwrap() = *new_transform; }
+            DrawEvent::Scale(new_scale)                 => { *scale_factor.lock().unwrap() = *new_scale; }
+            _                                           => { }
+        }
+
+        event
+    });
+
+    (window_transform, events)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::stream;
+    use futures::executor;
+
+    #[test]
+    fn window_and_canvas_coordinates_are_the_same_before_a_transform_is_reported() {
+        let (window_transform, _events) = track_window_transform(stream::empty::<DrawEvent>());
+
+        assert!(window_transform.window_to_canvas(10.0, 20.0) == (10.0, 20.0));
+        assert!(window_transform.scale_factor() == 1.0);
+    }
+
+    #[test]
+    fn converts_using_the_reported_transform() {
+        let transform                   = Transform2D::translate(-100.0, 50.0) * Transform2D::scale(2.0, -2.0);
+        let (window_transform, events)  = track_window_transform(stream::iter(vec![DrawEvent::CanvasTransform(transform), DrawEvent::Scale(2.0)]));
+
+        // Events are passed through unchanged
+        let events = executor::block_on(events.collect::<Vec<_>>());
+        assert!(events.len() == 2);
+
+        let (x, y) = window_transform.window_to_canvas(10.0, 20.0);
+        assert!((x - -80.0).abs() < 0.001, "{:?}", (x, y));
+        assert!((y - 10.0).abs() < 0.001, "{:?}", (x, y));
+        assert!(window_transform.scale_factor() == 2.0);
+    }
+
+    #[test]
+    fn uses_the_most_recent_transform() {
+        let (window_transform, events)  = track_window_transform(stream::iter(vec![
+            DrawEvent::CanvasTransform(Transform2D::scale(2.0, 2.0)),
+            DrawEvent::CanvasTransform(Transform2D::translate(5.0, 7.0)),
+        ]));
+        executor::block_on(events.collect::<Vec<_>>());
+
+        let (x, y) = window_transform.window_to_canvas(1.0, 2.0);
+        assert!((x - 6.0).abs() < 0.001 && (y - 9.0).abs() < 0.001, "{:?}", (x, y));
+    }
+}
@@ -26,5 +26,8 @@ typedef enum FragmentInputIndex {
     FragmentIndexClipMaskTexture    = 2,
 
     /// The alpha value to use for the fragment
-    FragmentAlpha                   = 3
+    FragmentAlpha                   = 3,
+
+    /// The sampler to use when reading from the texture
+    FragmentIndexSampler            = 4
 } FragmentInputIndex;
@@ -0,0 +1,84 @@
+// Number of pixels processed by each workgroup (must match BLUR_COMPUTE_WORKGROUP_SIZE in blur_compute.rs)
+const WORKGROUP_SIZE: u32 = 128u;
+
+// Largest radius supported by the shader (must match BLUR_COMPUTE_MAX_RADIUS in blur_compute.rs)
+const MAX_RADIUS: u32 = 256u;
+
+@group(0)
+@binding(0)
+var input_texture: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var output_texture: texture_storage_2d<STORAGE_FORMAT, write>;
+
+@group(0)
+@binding(2)
+var<storage, read> weights: array<f32>;
+
+// The pixels read by the workgroup, including the kernel window either side of the pixels being generated
+var<workgroup> window: array<vec4<f32>, 640>;
+
+// Loads the window for a row or column into workgroup memory
+// 'start' is the position of the first pixel in the window and 'step' is the direction to read in
+fn load_window(local_idx: u32, start: vec2<i32>, step: vec2<i32>, radius: u32) {
+    let size        = vec2<i32>(textureDimensions(input_texture));
+    let window_len  = WORKGROUP_SIZE + radius * 2u;
+
+    // The number of iterations is the same for every invocation, so the barrier that follows is in uniform control flow
+    let iterations  = (window_len + WORKGROUP_SIZE - 1u) / WORKGROUP_SIZE;
+
+    for (var iteration = 0u; iteration < iterations; iteration++) {
+        let idx = local_idx + iteration * WORKGROUP_SIZE;
+
+        if (idx < window_len) {
+            // Pixels outside the texture are clamped to the edge (matching the sampler used by the fragment shader)
+            let pos = clamp(start + step * i32(idx), vec2<i32>(0, 0), size - vec2<i32>(1, 1));
+            window[idx] = textureLoad(input_texture, pos, 0);
+        }
+    }
+}
+
+// Applies the kernel to the window around a pixel
+fn blur_pixel(local_idx: u32, radius: u32) -> vec4<f32> {
+    let center  = local_idx + radius;
+    var color   = window[center] * weights[0];
+
+    for (var idx = 1u; idx <= radius; idx++) {
+        color = color + (window[center - idx] + window[center + idx]) * weights[idx];
+    }
+
+    return color;
+}
+
+@compute
+@workgroup_size(128, 1, 1)
+fn blur_compute_horiz(@builtin(workgroup_id) group: vec3<u32>, @builtin(local_invocation_id) local: vec3<u32>) {
+    let radius  = min(arrayLength(&weights) - 1u, MAX_RADIUS);
+    let row     = i32(group.y);
+    let first_x = i32(group.x * WORKGROUP_SIZE);
+
+    load_window(local.x, vec2<i32>(first_x - i32(radius), row), vec2<i32>(1, 0), radius);
+    workgroupBarrier();
+
+    let x = first_x + i32(local.x);
+    if (x < i32(textureDimensions(input_texture).x)) {
+        textureStore(output_texture, vec2<i32>(x, row), blur_pixel(local.x, radius));
+    }
+}
+
+@compute
+@workgroup_size(128, 1, 1)
+fn blur_compute_vert(@builtin(workgroup_id) group: vec3<u32>, @builtin(local_invocation_id) local: vec3<u32>) {
+    let radius  = min(arrayLength(&weights) - 1u, MAX_RADIUS);
+    let column  = i32(group.y);
+    let first_y = i32(group.x * WORKGROUP_SIZE);
+
+    load_window(local.x, vec2<i32>(column, first_y - i32(radius)), vec2<i32>(0, 1), radius);
+    workgroupBarrier();
+
+    let y = first_y + i32(local.x);
+    if (y < i32(textureDimensions(input_texture).y)) {
+        textureStore(output_texture, vec2<i32>(column, y), blur_pixel(local.x, radius));
+    }
+}
@@ -7,13 +7,14 @@ fn clip(color: vec4<f32>, position: vec4<f32>) -> vec4<f32> {
     let clip_y              = position[1];
 
     let clip_pos            = vec2<i32>(i32(clip_x), i32(clip_y));
+    let num_samples         = i32(textureNumSamples(clip_texture));
     var clip_alpha          = f32(0.0);
 
-    for (var sample_num: i32 = 0; sample_num < 4; sample_num++) {
+    for (var sample_num: i32 = 0; sample_num < num_samples; sample_num++) {
         clip_alpha += textureLoad(clip_texture, clip_pos, sample_num)[0];
     }
 
-    clip_alpha              *= 0.25;
+    clip_alpha              /= f32(num_samples);
 
     let clip_color          = vec4<f32>(
         color[0] * clip_alpha,
@@ -25,9 +25,8 @@ vertex RasterizerData texture_vertex(
 fragment float4 texture_fragment(
       RasterizerData              in [[stage_in]],
       constant float              *texture_alpha [[ buffer(FragmentAlpha) ]],
-      metal::texture2d<half>      texture [[ texture(FragmentIndexTexture) ]]) {
-    constexpr metal::sampler texture_sampler (metal::mag_filter::linear, metal::min_filter::linear);
-
+      metal::texture2d<half>      texture [[ texture(FragmentIndexTexture) ]],
+      metal::sampler              texture_sampler [[ sampler(FragmentIndexSampler) ]]) {
     const half4 color_sample  = texture.sample(texture_sampler, in.v_TexCoord);
 
     float4 color              = float4(color_sample);
@@ -60,9 +59,9 @@ fragment float4 texture_clip_mask_multisample_fragment(
       RasterizerData              in [[stage_in]],
       constant float              *texture_alpha [[ buffer(FragmentAlpha) ]],
       metal::texture2d<half>      texture [[ texture(FragmentIndexTexture) ]],
-      metal::texture2d_ms<half>   clip_mask_texture [[ texture(FragmentIndexClipMaskTexture) ]]) {
+      metal::texture2d_ms<half>   clip_mask_texture [[ texture(FragmentIndexClipMaskTexture) ]],
+      metal::sampler              texture_sampler [[ sampler(FragmentIndexSampler) ]]) {
     // Color from the texture
-    constexpr metal::sampler texture_sampler (metal::mag_filter::linear, metal::min_filter::linear);
     const half4 color_sample    = texture.sample(texture_sampler, in.v_TexCoord);
 
     // Apply the clip mask
@@ -74,9 +73,8 @@ fragment float4 texture_clip_mask_multisample_fragment(
 fragment float4 texture_fragment_invert_color_alpha(
       RasterizerData              in [[stage_in]],
       constant float              *texture_alpha [[ buffer(FragmentAlpha) ]],
-      metal::texture2d<half>      texture [[ texture(FragmentIndexTexture) ]]) {
-    constexpr metal::sampler texture_sampler (metal::mag_filter::linear, metal::min_filter::linear);
-
+      metal::texture2d<half>      texture [[ texture(FragmentIndexTexture) ]],
+      metal::sampler              texture_sampler [[ sampler(FragmentIndexSampler) ]]) {
     const half4 color_sample  = texture.sample(texture_sampler, in.v_TexCoord);
 
     float4 color              = float4(color_sample);
@@ -109,9 +107,9 @@ fragment float4 texture_clip_mask_multisample_fragment_invert_color_alpha(
       RasterizerData              in [[stage_in]],
       constant float              *texture_alpha [[ buffer(FragmentAlpha) ]],
       metal::texture2d<half>      texture [[ texture(FragmentIndexTexture) ]],
-      metal::texture2d_ms<half>   clip_mask_texture [[ texture(FragmentIndexClipMaskTexture) ]]) {
+      metal::texture2d_ms<half>   clip_mask_texture [[ texture(FragmentIndexClipMaskTexture) ]],
+      metal::sampler              texture_sampler [[ sampler(FragmentIndexSampler) ]]) {
     // Color from the texture
-    constexpr metal::sampler texture_sampler (metal::mag_filter::linear, metal::min_filter::linear);
     const half4 color_sample    = texture.sample(texture_sampler, in.v_TexCoord);
 
     // Apply the clip mask
@@ -4,8 +4,10 @@ mod render_action_type;
 mod render_target_type;
 mod color;
 mod blend_mode;
+mod stencil_mode;
 mod shader_type;
 mod texture_filter;
+mod texture_wrap_mode;
 
 pub use self::identities::*;
 pub use self::render_action::*;
@@ -13,5 +15,7 @@ pub use self::render_action_type::*;
 pub use self::render_target_type::*;
 pub use self::color::*;
 pub use self::blend_mode::*;
+pub use self::stencil_mode::*;
 pub use self::shader_type::*;
 pub use self::texture_filter::*;
+pub use self::texture_wrap_mode::*;
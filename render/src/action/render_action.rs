@@ -1,6 +1,7 @@
 use super::color::*;
 use super::identities::*;
 use super::blend_mode::*;
+use super::stencil_mode::*;
 use super::shader_type::*;
 use super::texture_filter::*;
 use super::render_target_type::*;
@@ -77,11 +78,28 @@ pub enum RenderAction {
     ///
     FreeIndexBuffer(IndexBufferId),
 
+    ///
+    /// Releases any memory that the renderer is keeping so it can re-use it for future vertex and index buffers
+    ///
+    /// This is sent when a lot of buffers have been freed at once (for example, when the canvas is cleared), to avoid holding on
+    /// to memory that might not be needed again.
+    ///
+    TrimBufferPools,
+
     ///
     /// Sets the blend mode for future drawing operations (SourceOver is the default)
     ///
     BlendMode(BlendMode),
 
+    ///
+    /// Sets how future drawing operations use the stencil buffer (`Disabled` is the default)
+    ///
+    /// A render target is given a stencil buffer the first time a mode other than `Disabled` is set while it's selected, so
+    /// render targets that never use it don't pay for it. The stencil buffer is reset to 0 by `Clear`. The main frame buffer
+    /// has no stencil buffer, so this has no effect while it's selected.
+    ///
+    StencilMode(StencilMode),
+
     ///
     /// Creates a new render target of the specified size, as the specified texture
     ///
@@ -112,11 +130,29 @@ pub enum RenderAction {
     ///
     DrawFrameBuffer(RenderTargetId, FrameBufferRegion, Alpha),
 
+    ///
+    /// Restricts future drawing to the specified region of the render target (in the same coordinates as `DrawFrameBuffer`)
+    ///
+    /// The scissor region applies to whichever render target is selected until it's replaced or removed with `ClearScissor`.
+    /// `Clear` and `FilterTexture` are not affected by the scissor region.
+    ///
+    SetScissor(FrameBufferRegion),
+
+    ///
+    /// Removes the scissor region so that drawing can affect the whole render target again
+    ///
+    ClearScissor,
+
     ///
     /// Creates an 8-bit BGRA 2D texture of the specified size
     ///
     CreateTextureBgra(TextureId, Size2D),           // TODO: I think everything except WGPU seems to wind up working as an RGBA texture (actually, this is determined by WriteTextureData: textures are BGRA but are read as RGBA)
 
+    ///
+    /// Creates an 8-bit BGRA 2D texture of the specified size, whose pixels will be written with their color values already multiplied by their alpha value
+    ///
+    CreateTextureBgraPremultiplied(TextureId, Size2D),
+
     ///
     /// Creates an 8-bit monochrome 2D texture of the specified size
     ///
@@ -221,6 +257,23 @@ impl FrameBufferRegion {
     #[inline] pub fn max_y(&self) -> f32 {
         Self::clip(self.1.1)
     }
+
+    ///
+    /// Returns the pixels covered by this region in a render target of the specified size, as `(x, y, width, height)`
+    ///
+    /// The y coordinate is measured upwards from the bottom of the render target. Partially covered pixels are included
+    /// in the result.
+    ///
+    pub fn to_pixels(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let to_pixel    = |pos: f32, size: u32| (pos + 1.0) / 2.0 * (size as f32);
+
+        let min_x       = (to_pixel(self.min_x(), width).floor() as u32).min(width);
+        let min_y       = (to_pixel(self.min_y(), height).floor() as u32).min(height);
+        let max_x       = (to_pixel(self.max_x(), width).ceil() as u32).min(width).max(min_x);
+        let max_y       = (to_pixel(self.max_y(), height).ceil() as u32).min(height).max(min_y);
+
+        (min_x, min_y, max_x - min_x, max_y - min_y)
+    }
 }
 
 impl RenderAction {
@@ -236,14 +289,19 @@ impl RenderAction {
             CreateIndexBuffer(buffer_id, indexes)                           => format!("CreateIndexBuffer({:?}, [{} indexes])", buffer_id, indexes.len()),
             FreeVertexBuffer(buffer_id)                                     => format!("FreeVertexBuffer({:?})", buffer_id),
             FreeIndexBuffer(buffer_id)                                      => format!("FreeIndexBuffer({:?})", buffer_id),
+            TrimBufferPools                                                 => format!("TrimBufferPools"),
             BlendMode(blend_mode)                                           => format!("BlendMode({:?})", blend_mode),
+            StencilMode(stencil_mode)                                       => format!("StencilMode({:?})", stencil_mode),
             CreateRenderTarget(render_id, texture_id, size, target_type)    => format!("CreateRenderTarget({:?}, {:?}, {:?}, {:?})", render_id, texture_id, size, target_type),
             FreeRenderTarget(render_id)                                     => format!("FreeRenderTarget({:?})", render_id),
             SelectRenderTarget(render_id)                                   => format!("SelectRenderTarget({:?})", render_id),
             RenderToFrameBuffer                                             => format!("RenderToFrameBuffer"),
             ShowFrameBuffer                                                 => format!("ShowFrameBuffer"),
             DrawFrameBuffer(render_id, region, alpha)                       => format!("DrawFrameBuffer({:?}, {:?}, {:?})", render_id, region, alpha),
+            SetScissor(region)                                              => format!("SetScissor({:?})", region),
+            ClearScissor                                                    => format!("ClearScissor"),
             CreateTextureBgra(texture_id, size)                             => format!("CreateTextureBgra({:?}, {:?})", texture_id, size),
+            CreateTextureBgraPremultiplied(texture_id, size)                => format!("CreateTextureBgraPremultiplied({:?}, {:?})", texture_id, size),
             CreateTextureMono(texture_id, size)                             => format!("CreateTextureMono({:?}, {:?})", texture_id, size),
             Create1DTextureBgra(texture_id, w)                              => format!("Create1DTextureBgra({:?}, {:?})", texture_id, w),
             Create1DTextureMono(texture_id, w)                              => format!("Create1DTextureMono({:?}, {:?})", texture_id, w),
@@ -260,3 +318,29 @@ impl RenderAction {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn whole_region_to_pixels() {
+        assert!(FrameBufferRegion::default().to_pixels(100, 50) == (0, 0, 100, 50));
+    }
+
+    #[test]
+    fn partial_region_to_pixels() {
+        assert!(FrameBufferRegion((-0.5, 0.0), (0.5, 1.0)).to_pixels(100, 50) == (25, 25, 50, 25));
+    }
+
+    #[test]
+    fn partial_pixels_are_included() {
+        assert!(FrameBufferRegion((-0.51, -0.01), (0.51, 0.01)).to_pixels(100, 100) == (24, 49, 52, 2));
+    }
+
+    #[test]
+    fn region_outside_target_is_empty() {
+        let (_, _, width, height) = FrameBufferRegion((2.0, 2.0), (3.0, 3.0)).to_pixels(100, 100);
+        assert!(width == 0 && height == 0);
+    }
+}
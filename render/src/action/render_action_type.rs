@@ -13,14 +13,19 @@ pub enum RenderActionType {
     CreateIndexBuffer,
     FreeVertexBuffer,
     FreeIndexBuffer,
+    TrimBufferPools,
     BlendMode,
+    StencilMode,
     CreateRenderTarget,
     FreeRenderTarget,
     SelectRenderTarget,
     RenderToFrameBuffer,
     ShowFrameBuffer,
     DrawFrameBuffer,
+    SetScissor,
+    ClearScissor,
     CreateTextureBgra,
+    CreateTextureBgraPremultiplied,
     CreateTextureMono,
     Create1DTextureBgra,
     Create1DTextureMono,
@@ -49,14 +54,19 @@ impl From<&RenderAction> for RenderActionType {
             RenderAction::CreateIndexBuffer(_, _)           => RenderActionType::CreateIndexBuffer,
             RenderAction::FreeVertexBuffer(_)               => RenderActionType::FreeVertexBuffer,
             RenderAction::FreeIndexBuffer(_)                => RenderActionType::FreeIndexBuffer,
+            RenderAction::TrimBufferPools                   => RenderActionType::TrimBufferPools,
             RenderAction::BlendMode(_)                      => RenderActionType::BlendMode,
+            RenderAction::StencilMode(_)                    => RenderActionType::StencilMode,
             RenderAction::CreateRenderTarget(_, _, _, _)    => RenderActionType::CreateRenderTarget,
             RenderAction::FreeRenderTarget(_)               => RenderActionType::FreeRenderTarget,
             RenderAction::SelectRenderTarget(_)             => RenderActionType::SelectRenderTarget,
             RenderAction::RenderToFrameBuffer               => RenderActionType::RenderToFrameBuffer,
             RenderAction::ShowFrameBuffer                   => RenderActionType::ShowFrameBuffer,
             RenderAction::DrawFrameBuffer(_, _, _)          => RenderActionType::DrawFrameBuffer,
+            RenderAction::SetScissor(_)                     => RenderActionType::SetScissor,
+            RenderAction::ClearScissor                      => RenderActionType::ClearScissor,
             RenderAction::CreateTextureBgra(_, _)           => RenderActionType::CreateTextureBgra,
+            RenderAction::CreateTextureBgraPremultiplied(_, _)  => RenderActionType::CreateTextureBgraPremultiplied,
             RenderAction::CreateTextureMono(_, _)           => RenderActionType::CreateTextureMono,
             RenderAction::Create1DTextureBgra(_, _)         => RenderActionType::Create1DTextureBgra,
             RenderAction::Create1DTextureMono(_, _)         => RenderActionType::Create1DTextureMono,
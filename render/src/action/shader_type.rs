@@ -1,4 +1,5 @@
 use super::identities::*;
+use super::texture_wrap_mode::*;
 
 use crate::buffer::*;
 
@@ -15,7 +16,7 @@ pub enum ShaderType {
     DashedLine { dash_texture: TextureId, clip_texture: Option<TextureId> },
 
     /// Colour derived from a texture with a transform mapping from canvas coordinates to texture coordinates
-    Texture { texture: TextureId, texture_transform: Matrix, repeat: bool, alpha: f32, clip_texture: Option<TextureId> },
+    Texture { texture: TextureId, texture_transform: Matrix, wrap_mode: TextureWrapMode, alpha: f32, clip_texture: Option<TextureId> },
 
     /// Colour derived from a 1D texture using a transform mapping (used for rendering linear gradients)
     LinearGradient { texture: TextureId, texture_transform: Matrix, repeat: bool, alpha: f32, clip_texture: Option<TextureId> }
@@ -31,7 +32,7 @@ impl ShaderType {
         match self {
             Simple { clip_texture: _ }                                                      => Simple           { clip_texture: new_clip_mask_texture },
             DashedLine { dash_texture, clip_texture: _ }                                    => DashedLine       { dash_texture: dash_texture, clip_texture: new_clip_mask_texture },
-            Texture { texture, texture_transform, repeat, alpha, clip_texture: _ }          => Texture          { texture: texture, texture_transform: texture_transform, repeat, alpha, clip_texture: new_clip_mask_texture },
+            Texture { texture, texture_transform, wrap_mode, alpha, clip_texture: _ }       => Texture          { texture: texture, texture_transform: texture_transform, wrap_mode, alpha, clip_texture: new_clip_mask_texture },
             LinearGradient { texture, texture_transform, repeat, alpha, clip_texture: _ }   => LinearGradient   { texture: texture, texture_transform: texture_transform, repeat, alpha, clip_texture: new_clip_mask_texture }
         }
     }
@@ -0,0 +1,27 @@
+///
+/// How drawing operations use the stencil buffer of the current render target
+///
+/// Clip regions can be intersected by drawing each region with `Increment`, then drawing with `Equal(n)` where `n` is the
+/// number of regions: only the pixels covered by every region are drawn. Drawing a region again with `Decrement` removes
+/// it from the stack.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StencilMode {
+    /// The stencil buffer is not used (this is the default)
+    Disabled,
+
+    /// Adds one to the stencil value of every pixel that's drawn, without changing its colour
+    Increment,
+
+    /// Subtracts one from the stencil value of every pixel that's drawn, without changing its colour
+    Decrement,
+
+    /// Only draws the pixels whose stencil value is equal to the specified value
+    Equal(u8),
+}
+
+impl Default for StencilMode {
+    fn default() -> StencilMode {
+        StencilMode::Disabled
+    }
+}
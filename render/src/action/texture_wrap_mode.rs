@@ -0,0 +1,14 @@
+///
+/// How a texture is addressed when sampling outside of the range 0..1
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextureWrapMode {
+    /// Texture coordinates are clamped to the edge of the texture
+    Clamp,
+
+    /// The texture repeats
+    Repeat,
+
+    /// The texture repeats, with every other repetition mirrored
+    MirrorRepeat
+}
@@ -45,6 +45,12 @@ pub struct GlRenderer {
     /// The matrix that's currently in use
     transform_matrix: Option<[gl::types::GLfloat; 16]>,
 
+    /// The region of the render target that rendering is restricted to
+    scissor: Option<FrameBufferRegion>,
+
+    /// How drawing operations use the stencil buffer of the active render target
+    stencil_mode: StencilMode,
+
     /// The 'main' render target that represents the output for this renderer
     default_render_target: Option<RenderTarget>,
 
@@ -75,6 +81,8 @@ impl GlRenderer {
             blend_mode:                     BlendMode::SourceOver,
             source_is_premultiplied:        false,
             transform_matrix:               None,
+            scissor:                        None,
+            stencil_mode:                   StencilMode::Disabled,
             render_targets:                 vec![],
             shader_programs:                shader_programs,
 
@@ -118,6 +126,9 @@ impl GlRenderer {
         // If the renderer was previously left in a state that had an active render target, select that now
         if let Some(render_target) = self.active_render_target {
             self.select_render_target(render_target);
+        } else {
+            self.update_scissor();
+            self.update_stencil();
         }
 
         for action in actions {
@@ -135,22 +146,27 @@ impl GlRenderer {
                 CreateIndexBuffer(id, indices)                                                  => { self.create_index_buffer(id, indices); }
                 FreeVertexBuffer(id)                                                            => { self.free_vertex_buffer(id); }
                 FreeIndexBuffer(id)                                                             => { self.free_index_buffer(id); }
+                TrimBufferPools                                                                 => { /* Buffers are not pooled by this renderer */ }
                 BlendMode(blend_mode)                                                           => { self.blend_mode(blend_mode, self.source_is_premultiplied); }
+                StencilMode(stencil_mode)                                                       => { self.set_stencil_mode(stencil_mode); }
                 CreateRenderTarget(render_id, texture_id, Size2D(width, height), render_type)   => { self.create_render_target(render_id, texture_id, width, height, render_type); }
                 FreeRenderTarget(render_id)                                                     => { self.free_render_target(render_id); }
                 SelectRenderTarget(render_id)                                                   => { self.select_render_target(render_id); }
                 RenderToFrameBuffer                                                             => { self.select_main_frame_buffer(); }
                 DrawFrameBuffer(render_id, region, Alpha(alpha))                                => { self.draw_frame_buffer(render_id, region, alpha); }
+                SetScissor(region)                                                              => { self.set_scissor(Some(region)); }
+                ClearScissor                                                                    => { self.set_scissor(None); }
                 ShowFrameBuffer                                                                 => { /* This doesn't double-buffer so nothing to do */ }
-                CreateTextureBgra(texture_id, Size2D(width, height))                            => { self.create_bgra_texture(texture_id, width, height); }
+                CreateTextureBgra(texture_id, Size2D(width, height))                            => { self.create_bgra_texture(texture_id, width, height, false); }
+                CreateTextureBgraPremultiplied(texture_id, Size2D(width, height))               => { self.create_bgra_texture(texture_id, width, height, true); }
                 CreateTextureMono(texture_id, Size2D(width, height))                            => { self.create_mono_texture(texture_id, width, height); }
                 Create1DTextureBgra(texture_id, Size1D(width))                                  => { self.create_1d_bgra_texture(texture_id, width); }
                 Create1DTextureMono(texture_id, Size1D(width))                                  => { self.create_1d_mono_texture(texture_id, width); }
                 WriteTextureData(texture_id, Position2D(x1, y1), Position2D(x2, y2), data)      => { self.write_texture_data_2d(texture_id, (x1, y1), (x2, y2), &*data); }
                 WriteTexture1D(texture_id, Position1D(x1), Position1D(x2), data)                => { self.write_texture_data_1d(texture_id, x1, x2, &*data); }
                 CreateMipMaps(texture_id)                                                       => { self.create_mipmaps(texture_id); }
-                CopyTexture(source, target)                                                     => { self.copy_texture(source, target); }
-                FilterTexture(texture, filter)                                                  => { self.filter_texture(texture, filter); }
+                CopyTexture(source, target)                                                     => { self.suspend_scissor(); self.suspend_stencil(); self.copy_texture(source, target); self.update_scissor(); self.update_stencil(); }
+                FilterTexture(texture, filter)                                                  => { self.suspend_scissor(); self.suspend_stencil(); self.filter_texture(texture, filter); self.update_scissor(); self.update_stencil(); }
                 FreeTexture(texture_id)                                                         => { self.free_texture(texture_id); }
                 Clear(color)                                                                    => { self.clear(color); }
                 UseShader(shader_type)                                                          => { self.use_shader(shader_type); }
@@ -164,13 +180,13 @@ impl GlRenderer {
             self.profiler.finish_action(action_type);
         }
 
-        // Reset options
-        self.disable_options();
-
         // Always leave on the main frame buffer after rendering 
         // This is so that a future `prepare_to_render_to_active_framebuffer` doesn't pick up the frame buffer we might have set here
         self.select_main_frame_buffer();
 
+        // Reset options
+        self.disable_options();
+
         panic_on_gl_error("Render tidy up");
 
         #[cfg(feature="profile")]
@@ -202,6 +218,132 @@ impl GlRenderer {
     fn disable_options(&self) {
         unsafe {
             gl::Disable(gl::BLEND);
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::Disable(gl::STENCIL_TEST);
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+        }
+    }
+
+    ///
+    /// Returns the size of the render target that is currently selected
+    ///
+    fn active_render_target_size(&self) -> Option<(u16, u16)> {
+        match self.active_render_target {
+            Some(RenderTargetId(render_id)) => self.render_targets.get(render_id).and_then(|target| target.as_ref()).map(|target| target.get_size()),
+            None                            => self.default_render_target.as_ref().map(|target| target.get_size()),
+        }
+    }
+
+    ///
+    /// Sets the scissor region for future rendering operations
+    ///
+    fn set_scissor(&mut self, region: Option<FrameBufferRegion>) {
+        self.scissor = region;
+        self.update_scissor();
+    }
+
+    ///
+    /// Updates the GL scissor state to match the scissor region for the active render target
+    ///
+    fn update_scissor(&self) {
+        unsafe {
+            match (self.scissor, self.active_render_target_size()) {
+                (Some(region), Some((width, height))) => {
+                    let (x, y, width, height) = region.to_pixels(width as _, height as _);
+
+                    gl::Enable(gl::SCISSOR_TEST);
+                    gl::Scissor(x as _, y as _, width as _, height as _);
+                }
+
+                _ => {
+                    gl::Disable(gl::SCISSOR_TEST);
+                }
+            }
+        }
+    }
+
+    ///
+    /// Turns off the scissor test for an operation that should affect the whole of a texture (`update_scissor()` will turn it on again)
+    ///
+    fn suspend_scissor(&self) {
+        unsafe {
+            gl::Disable(gl::SCISSOR_TEST);
+        }
+    }
+
+    ///
+    /// Returns true if the render target that is currently selected has a stencil buffer
+    ///
+    fn active_render_target_has_stencil(&self) -> bool {
+        match self.active_render_target {
+            Some(RenderTargetId(render_id)) => self.render_targets.get(render_id).and_then(|target| target.as_ref()).map(|target| target.has_stencil_buffer()).unwrap_or(false),
+            None                            => false,
+        }
+    }
+
+    ///
+    /// Sets how future drawing operations use the stencil buffer
+    ///
+    fn set_stencil_mode(&mut self, stencil_mode: StencilMode) {
+        self.stencil_mode = stencil_mode;
+
+        // Render targets are given a stencil buffer the first time one is needed
+        if stencil_mode != StencilMode::Disabled {
+            if let Some(RenderTargetId(render_id)) = self.active_render_target {
+                if let Some(Some(render_target)) = self.render_targets.get_mut(render_id) {
+                    render_target.create_stencil_buffer();
+                }
+            }
+        }
+
+        self.update_stencil();
+    }
+
+    ///
+    /// Updates the GL stencil state to match the stencil mode for the active render target
+    ///
+    fn update_stencil(&self) {
+        // The stencil mode has no effect on render targets without a stencil buffer
+        let stencil_mode = if self.active_render_target_has_stencil() { self.stencil_mode } else { StencilMode::Disabled };
+
+        unsafe {
+            match stencil_mode {
+                StencilMode::Disabled => {
+                    gl::Disable(gl::STENCIL_TEST);
+                    gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+                }
+
+                StencilMode::Increment => {
+                    gl::Enable(gl::STENCIL_TEST);
+                    gl::StencilFunc(gl::ALWAYS, 0, 0xff);
+                    gl::StencilOp(gl::KEEP, gl::KEEP, gl::INCR);
+                    gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+                }
+
+                StencilMode::Decrement => {
+                    gl::Enable(gl::STENCIL_TEST);
+                    gl::StencilFunc(gl::ALWAYS, 0, 0xff);
+                    gl::StencilOp(gl::KEEP, gl::KEEP, gl::DECR);
+                    gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+                }
+
+                StencilMode::Equal(value) => {
+                    gl::Enable(gl::STENCIL_TEST);
+                    gl::StencilFunc(gl::EQUAL, value as _, 0xff);
+                    gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+                    gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+                }
+            }
+        }
+    }
+
+    ///
+    /// Turns off the stencil test for an operation that should affect the whole of a texture (`update_stencil()` will turn it on again)
+    ///
+    fn suspend_stencil(&self) {
+        unsafe {
+            gl::Disable(gl::STENCIL_TEST);
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
         }
     }
 
@@ -215,9 +357,17 @@ impl GlRenderer {
         let a = (a as f32)/255.0;
 
         unsafe { 
-            // Clear the buffer
+            // Clear the buffer (clearing is not affected by the scissor region)
+            gl::Disable(gl::SCISSOR_TEST);
             gl::ClearBufferfv(gl::COLOR, 0, &[r, g, b, a][0]); 
+
+            // The stencil buffer is reset along with the colour
+            if self.active_render_target_has_stencil() {
+                gl::ClearBufferiv(gl::STENCIL, 0, &0);
+            }
         }
+
+        self.update_scissor();
     }
 
     ///
@@ -376,9 +526,9 @@ impl GlRenderer {
     }
 
     ///
-    /// Creates a new BGRA texture
+    /// Creates a new BGRA texture (`premultiplied` is true if the pixels written to it will have premultiplied alpha)
     ///
-    fn create_bgra_texture(&mut self, TextureId(texture_id): TextureId, width: usize, height: usize) {
+    fn create_bgra_texture(&mut self, TextureId(texture_id): TextureId, width: usize, height: usize, premultiplied: bool) {
         // Extend the textures array as needed
         if texture_id >= self.textures.len() {
             self.textures.extend((self.textures.len()..(texture_id+1))
@@ -392,6 +542,7 @@ impl GlRenderer {
         // Create a new texture
         let mut new_texture = Texture::new();
         new_texture.create_empty(width as u16, height as u16);
+        new_texture.premultiplied = premultiplied;
 
         // Store the texture
         self.textures[texture_id] = Some(new_texture);
@@ -823,6 +974,9 @@ impl GlRenderer {
                 gl::BindFramebuffer(gl::FRAMEBUFFER, **render_target);
                 gl::Viewport(0, 0, width as gl::types::GLsizei, height as gl::types::GLsizei);
             }
+
+            self.update_scissor();
+            self.update_stencil();
         }
     }
 
@@ -840,6 +994,9 @@ impl GlRenderer {
                 gl::Viewport(0, 0, width as gl::types::GLsizei, height as gl::types::GLsizei);
             }
         }
+
+        self.update_scissor();
+        self.update_stencil();
     }
 
     ///
@@ -1038,7 +1195,7 @@ impl GlRenderer {
                 panic_on_gl_error("Set dash shader");
             }
 
-            Texture { texture, texture_transform, repeat, alpha, clip_texture } => {
+            Texture { texture, texture_transform, wrap_mode, alpha, clip_texture } => {
                 let textures            = &self.textures;
                 let alpha_blend_step    = self.alpha_blend_step_for_texture(&texture);
                 let TextureId(texture)  = texture;
@@ -1061,13 +1218,14 @@ impl GlRenderer {
                         gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as _);
                         gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
 
-                        if repeat {
-                            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as _);
-                            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as _);
-                        } else {
-                            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
-                            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
-                        }
+                        let wrap = match wrap_mode {
+                            TextureWrapMode::Repeat         => gl::REPEAT,
+                            TextureWrapMode::MirrorRepeat   => gl::MIRRORED_REPEAT,
+                            TextureWrapMode::Clamp          => gl::CLAMP_TO_EDGE,
+                        };
+
+                        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap as _);
+                        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap as _);
 
                         // Set in the program uniform
                         program.uniform_location(ShaderUniform::Texture, "t_Texture")
@@ -1231,6 +1389,13 @@ impl GlRenderer {
         }
     }
 
+    ///
+    /// Retrieves a texture created by this renderer
+    ///
+    pub (crate) fn texture(&self, TextureId(texture_id): TextureId) -> Option<&Texture> {
+        self.textures.get(texture_id)?.as_ref()
+    }
+
     ///
     /// Flushes all changes to the device
     ///
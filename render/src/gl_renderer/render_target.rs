@@ -6,6 +6,17 @@ use gl;
 
 use std::ops::{Deref};
 
+///
+/// The storage used for the stencil buffer of a render target
+///
+enum StencilBuffer {
+    /// A render buffer, used alongside render buffers and textures that aren't multisampled
+    RenderBuffer(gl::types::GLuint),
+
+    /// A multisampled depth/stencil texture, used alongside multisampled textures so the number of samples always matches
+    Texture(Texture),
+}
+
 ///
 /// An OpenGL render target
 ///
@@ -16,11 +27,14 @@ pub struct RenderTarget {
     /// The render buffer for this render target
     render_buffer: Option<gl::types::GLuint>,
 
+    /// The stencil buffer for this render target (created the first time it's needed)
+    stencil_buffer: Option<StencilBuffer>,
+
     /// The texture attached to the framebuffer (if we're tracking it)
     texture: Option<Texture>,
 
     /// The target type of this render surface
-    _render_type: RenderTargetType,
+    render_type: RenderTargetType,
 
     /// Set to true if this should drop its frame buffer when done
     drop_frame_buffer: bool,
@@ -134,8 +148,9 @@ impl RenderTarget {
                 frame_buffer:       frame_buffer,
                 texture:            texture,
                 render_buffer:      render_buffer,
+                stencil_buffer:     None,
                 size:               (width, height),
-                _render_type:       render_type,
+                render_type:        render_type,
                 drop_frame_buffer:  true
             }
         }
@@ -187,8 +202,9 @@ impl RenderTarget {
                 frame_buffer:       frame_buffer,
                 texture:            Some(texture),
                 render_buffer:      render_buffer,
+                stencil_buffer:     None,
                 size:               (width as _, height as _),
-                _render_type:       render_type,
+                render_type:        render_type,
                 drop_frame_buffer:  true
             })
         }
@@ -208,8 +224,9 @@ impl RenderTarget {
             frame_buffer:       current_frame_buffer as u32,
             texture:            None,
             render_buffer:      None,
+            stencil_buffer:     None,
             drop_frame_buffer:  false,
-            _render_type:       RenderTargetType::Standard,
+            render_type:        RenderTargetType::Standard,
             size:               (width, height)
         }
     }
@@ -227,6 +244,98 @@ impl RenderTarget {
     pub fn get_size(&self) -> (u16, u16) {
         self.size
     }
+
+    ///
+    /// True if this render target has a stencil buffer
+    ///
+    pub fn has_stencil_buffer(&self) -> bool {
+        self.stencil_buffer.is_some()
+    }
+
+    ///
+    /// Attaches a stencil buffer to this render target, if it doesn't already have one
+    ///
+    /// Render targets that are references to an existing framebuffer can't be given a stencil buffer. The stencil buffer is
+    /// removed again if the driver can't use it with the framebuffer, so `has_stencil_buffer()` will return false afterwards.
+    ///
+    pub fn create_stencil_buffer(&mut self) {
+        if self.stencil_buffer.is_some() || !self.drop_frame_buffer {
+            return;
+        }
+
+        unsafe {
+            let (width, height) = self.size;
+
+            // Find the currently bound frame buffer and render buffer (so we can rebind them)
+            let mut old_frame_buffer = 0;
+            let mut old_renderbuffer = 0;
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut old_frame_buffer);
+            gl::GetIntegerv(gl::RENDERBUFFER_BINDING, &mut old_renderbuffer);
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.frame_buffer);
+
+            // The stencil buffer needs the same number of samples as the colour buffer
+            let multisampled_texture = self.texture.as_ref().filter(|texture| texture.texture_target == gl::TEXTURE_2D_MULTISAMPLE);
+
+            let stencil_buffer = if let Some(multisampled_texture) = multisampled_texture {
+                // Multisampled textures get a multisampled stencil texture with the same number of samples
+                let num_samples         = multisampled_texture.num_samples();
+                let mut max_samples     = 0;
+                gl::GetIntegerv(gl::MAX_DEPTH_TEXTURE_SAMPLES, &mut max_samples);
+
+                if num_samples > max_samples as usize {
+                    // The driver can't create a stencil texture to match the colour texture
+                    None
+                } else {
+                    let mut stencil_texture = Texture::new();
+                    stencil_texture.create_stencil_multisampled(width, height, num_samples);
+
+                    gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::TEXTURE_2D_MULTISAMPLE, *stencil_texture, 0);
+
+                    Some(StencilBuffer::Texture(stencil_texture))
+                }
+            } else {
+                // Everything else uses a render buffer
+                let mut stencil_buffer = 0;
+                gl::GenRenderbuffers(1, &mut stencil_buffer);
+                gl::BindRenderbuffer(gl::RENDERBUFFER, stencil_buffer);
+
+                if self.render_type == RenderTargetType::Multisampled {
+                    gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, 4, gl::STENCIL_INDEX8, width as gl::types::GLsizei, height as gl::types::GLsizei);
+                } else {
+                    gl::RenderbufferStorage(gl::RENDERBUFFER, gl::STENCIL_INDEX8, width as gl::types::GLsizei, height as gl::types::GLsizei);
+                }
+
+                gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::STENCIL_ATTACHMENT, gl::RENDERBUFFER, stencil_buffer);
+
+                Some(StencilBuffer::RenderBuffer(stencil_buffer))
+            };
+            panic_on_gl_error("Create stencil buffer");
+
+            // Only keep the stencil buffer if the framebuffer is still usable with it attached
+            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) == gl::FRAMEBUFFER_COMPLETE {
+                self.stencil_buffer = stencil_buffer;
+            } else {
+                match stencil_buffer {
+                    Some(StencilBuffer::Texture(_stencil_texture)) => {
+                        gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::TEXTURE_2D_MULTISAMPLE, 0, 0);
+                    }
+
+                    Some(StencilBuffer::RenderBuffer(stencil_buffer)) => {
+                        gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::STENCIL_ATTACHMENT, gl::RENDERBUFFER, 0);
+                        gl::DeleteRenderbuffers(1, &stencil_buffer);
+                    }
+
+                    None => { }
+                }
+
+                panic_on_gl_error("Remove unusable stencil buffer");
+            }
+
+            gl::BindRenderbuffer(gl::RENDERBUFFER, old_renderbuffer as u32);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, old_frame_buffer as _);
+        }
+    }
 }
 
 impl Drop for RenderTarget {
@@ -239,6 +348,11 @@ impl Drop for RenderTarget {
             if let Some(render_buffer) = self.render_buffer {
                 gl::DeleteRenderbuffers(1, &render_buffer);
             }
+
+            // Stencil textures are freed when they're dropped
+            if let Some(StencilBuffer::RenderBuffer(stencil_buffer)) = &self.stencil_buffer {
+                gl::DeleteRenderbuffers(1, stencil_buffer);
+            }
         }
     }
 }
@@ -10,6 +10,7 @@ use crate::buffer::*;
 use gl;
 
 use std::ptr;
+use std::ffi::{c_void};
 use std::rc::*;
 use std::ops::{Deref};
 
@@ -118,10 +119,10 @@ impl Texture {
 
             self.num_samples    = samples as _;
 
-            // Set up a MSAA texture
+            // Set up a MSAA texture (with fixed sample locations, so it can share a framebuffer with a stencil buffer)
             gl::BindTexture(gl::TEXTURE_2D_MULTISAMPLE, texture_id);
 
-            gl::TexImage2DMultisample(gl::TEXTURE_2D_MULTISAMPLE, samples, gl::RGBA, width as _, height as _, gl::FALSE);
+            gl::TexImage2DMultisample(gl::TEXTURE_2D_MULTISAMPLE, samples, gl::RGBA, width as _, height as _, gl::TRUE);
 
             panic_on_gl_error("Create multisampled texture");
         }
@@ -167,15 +168,38 @@ impl Texture {
 
             self.num_samples    = samples as _;
 
-            // Set up a MSAA texture
+            // Set up a MSAA texture (with fixed sample locations, so it can share a framebuffer with a stencil buffer)
             gl::BindTexture(gl::TEXTURE_2D_MULTISAMPLE, texture_id);
 
-            gl::TexImage2DMultisample(gl::TEXTURE_2D_MULTISAMPLE, samples, gl::RED, width as _, height as _, gl::FALSE);
+            gl::TexImage2DMultisample(gl::TEXTURE_2D_MULTISAMPLE, samples, gl::RED, width as _, height as _, gl::TRUE);
 
             panic_on_gl_error("Create monochrome multisampled texture");
         }
     }
 
+    ///
+    /// Creates an empty MSAA depth/stencil texture, for use as the stencil buffer of a render target with a multisampled texture
+    ///
+    /// The number of samples should be the same as the `num_samples()` of the texture the stencil buffer is for (it's not clamped here,
+    /// as a framebuffer can only be complete if every attachment has the same number of samples)
+    ///
+    pub fn create_stencil_multisampled(&mut self, width: u16, height: u16, samples: usize) {
+        unsafe {
+            let texture_id      = self.texture.texture_id;
+            self.texture_target = gl::TEXTURE_2D_MULTISAMPLE;
+            self.texture_format = gl::DEPTH_STENCIL;
+            self.width          = width as _;
+            self.height         = height as _;
+            self.num_samples    = samples;
+
+            gl::BindTexture(gl::TEXTURE_2D_MULTISAMPLE, texture_id);
+
+            gl::TexImage2DMultisample(gl::TEXTURE_2D_MULTISAMPLE, samples as _, gl::DEPTH24_STENCIL8, width as _, height as _, gl::TRUE);
+
+            panic_on_gl_error("Create multisampled stencil texture");
+        }
+    }
+
     ///
     /// Associates an empty image with this texture
     ///
@@ -389,7 +413,7 @@ impl Texture {
                     // Set up a MSAA texture
                     gl::BindTexture(gl::TEXTURE_2D_MULTISAMPLE, texture_id);
 
-                    gl::TexImage2DMultisample(gl::TEXTURE_2D_MULTISAMPLE, samples, gl::RGBA, width as _, height as _, gl::FALSE);
+                    gl::TexImage2DMultisample(gl::TEXTURE_2D_MULTISAMPLE, samples, gl::RGBA, width as _, height as _, gl::TRUE);
                     panic_on_gl_error("Create multisampled copy target");
                 }
 
@@ -433,6 +457,13 @@ impl Texture {
         }
     }
 
+    ///
+    /// The number of samples per pixel for a multisampled texture
+    ///
+    pub fn num_samples(&self) -> usize {
+        self.num_samples
+    }
+
     ///
     /// True if this is a monochrome texture
     ///
@@ -440,6 +471,36 @@ impl Texture {
         self.texture_format == gl::RED
     }
 
+    ///
+    /// True if the colours in this texture have premultiplied alpha
+    ///
+    pub fn is_premultiplied(&self) -> bool {
+        self.premultiplied
+    }
+
+    ///
+    /// Reads back the pixels of a 2D texture as RGBA bytes, starting with the first row stored in the texture
+    ///
+    /// The colours are returned as they're stored, so they have premultiplied alpha if the texture does. Returns None if this
+    /// isn't a 2D texture (multisampled textures can't be read directly).
+    ///
+    pub fn read_pixels(&self) -> Option<Vec<u8>> {
+        if self.texture_target != gl::TEXTURE_2D {
+            return None;
+        }
+
+        let mut pixels = vec![0; (self.width as usize) * (self.height as usize) * 4];
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, **self);
+            gl::GetTexImage(gl::TEXTURE_2D, 0, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut c_void);
+
+            panic_on_gl_error("Read texture");
+        }
+
+        Some(pixels)
+    }
+
     ///
     /// Performs a filter operation, creating a new texture (typically used to replace this one)
     ///
@@ -13,7 +13,7 @@ pub use self::buffer::*;
 pub use self::offscreen::*;
 #[cfg(feature="gl")] pub use self::gl_renderer::GlRenderer;
 #[cfg(feature="osx-metal")] pub use self::metal_renderer::MetalRenderer;
-#[cfg(feature="render-wgpu")] pub use self::wgpu_renderer::WgpuRenderer;
+#[cfg(feature="render-wgpu")] pub use self::wgpu_renderer::{WgpuRenderer, PipelineConfiguration, BufferPoolStats, AdapterOptions, DeviceLimits, request_adapter_with_fallback, request_adapter_with_options, default_device_limits, no_device_error, check_surface_supported};
 
 #[cfg(feature="render-wgpu")]
 pub use wgpu;
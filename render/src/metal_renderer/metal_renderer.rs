@@ -13,7 +13,7 @@ use metal;
 
 use std::sync::*;
 use std::ops::{Range};
-use std::collections::{HashMap};
+use std::collections::{HashMap, HashSet};
 
 ///
 /// Renderer that can write to a surface using Apple's Metal API
@@ -43,8 +43,17 @@ pub struct MetalRenderer {
     /// The tetures for this renderer
     textures: Vec<Option<metal::Texture>>,
 
+    /// The textures whose pixels have premultiplied alpha
+    premultiplied_textures: HashSet<usize>,
+
     /// The cache of render pipeline states used by this renderer
-    pipeline_states: HashMap<PipelineConfiguration, metal::RenderPipelineState>
+    pipeline_states: HashMap<PipelineConfiguration, metal::RenderPipelineState>,
+
+    /// The sampler states used for each of the texture wrap modes
+    sampler_states: HashMap<TextureWrapMode, metal::SamplerState>,
+
+    /// The depth/stencil states used for each of the stencil modes
+    depth_stencil_states: HashMap<StencilMode, metal::DepthStencilState>
 }
 
 ///
@@ -57,6 +66,15 @@ struct RenderState<'a> {
     /// The current target render buffer
     target_texture: metal::Texture,
 
+    /// The render target that is selected, or None if rendering to the main texture
+    render_target: Option<usize>,
+
+    /// The stencil texture attached to the current render target
+    target_stencil: Option<metal::Texture>,
+
+    /// How drawing operations use the stencil buffer of the current render target
+    stencil_mode: StencilMode,
+
     /// The texture that is being used for a fill operation
     fill_texture: Option<metal::Texture>,
 
@@ -72,6 +90,12 @@ struct RenderState<'a> {
     /// The alpha value to apply to the texture
     texture_alpha: Option<f64>,
 
+    /// How the fill texture is addressed outside of its bounds
+    wrap_mode: TextureWrapMode,
+
+    /// The region that drawing is restricted to, or None if drawing can affect the whole render target
+    scissor: Option<FrameBufferRegion>,
+
     /// The active pipeline configuration
     pipeline_config: PipelineConfiguration,
 
@@ -95,15 +119,18 @@ impl MetalRenderer {
         let shader_library  = device.new_library_with_data(include_bytes![concat!(env!("OUT_DIR"), "/flo.metallib")]).unwrap();
 
         MetalRenderer {
-            device:             device,
-            flip_y:             false,
-            command_queue:      command_queue,
-            vertex_buffers:     vec![],
-            index_buffers:      vec![],
-            render_targets:     vec![],
-            textures:           vec![],
-            shader_library:     shader_library,
-            pipeline_states:    HashMap::new()
+            device:                 device,
+            flip_y:                 false,
+            command_queue:          command_queue,
+            vertex_buffers:         vec![],
+            index_buffers:          vec![],
+            render_targets:         vec![],
+            textures:               vec![],
+            premultiplied_textures: HashSet::new(),
+            shader_library:         shader_library,
+            pipeline_states:        HashMap::new(),
+            sampler_states:         HashMap::new(),
+            depth_stencil_states:   HashMap::new()
         }
     }
 
@@ -116,15 +143,18 @@ impl MetalRenderer {
         let shader_library  = device.new_library_with_data(include_bytes![concat!(env!("OUT_DIR"), "/flo.metallib")]).unwrap();
 
         MetalRenderer {
-            device:             device,
-            flip_y:             flip_y,
-            command_queue:      command_queue,
-            vertex_buffers:     vec![],
-            index_buffers:      vec![],
-            render_targets:     vec![],
-            textures:           vec![],
-            shader_library:     shader_library,
-            pipeline_states:    HashMap::new()
+            device:                 device,
+            flip_y:                 flip_y,
+            command_queue:          command_queue,
+            vertex_buffers:         vec![],
+            index_buffers:          vec![],
+            render_targets:         vec![],
+            textures:               vec![],
+            premultiplied_textures: HashSet::new(),
+            shader_library:         shader_library,
+            pipeline_states:        HashMap::new(),
+            sampler_states:         HashMap::new(),
+            depth_stencil_states:   HashMap::new()
         }
     }
 
@@ -148,10 +178,82 @@ impl MetalRenderer {
         }
     }
 
+    ///
+    /// Returns the sampler state used to read textures with the specified wrap mode
+    ///
+    fn get_sampler_state(&mut self, wrap_mode: TextureWrapMode) -> metal::SamplerState {
+        let device = &self.device;
+
+        self.sampler_states.entry(wrap_mode)
+            .or_insert_with(|| {
+                let address_mode = match wrap_mode {
+                    TextureWrapMode::Clamp          => metal::MTLSamplerAddressMode::ClampToEdge,
+                    TextureWrapMode::Repeat         => metal::MTLSamplerAddressMode::Repeat,
+                    TextureWrapMode::MirrorRepeat   => metal::MTLSamplerAddressMode::MirrorRepeat
+                };
+
+                let descriptor = metal::SamplerDescriptor::new();
+                descriptor.set_min_filter(metal::MTLSamplerMinMagFilter::Linear);
+                descriptor.set_mag_filter(metal::MTLSamplerMinMagFilter::Linear);
+                descriptor.set_address_mode_s(address_mode);
+                descriptor.set_address_mode_t(address_mode);
+
+                device.new_sampler(&descriptor)
+            })
+            .clone()
+    }
+
+    ///
+    /// Returns the depth/stencil state to use for the specified stencil mode
+    ///
+    fn get_depth_stencil_state(&mut self, stencil_mode: StencilMode) -> metal::DepthStencilState {
+        let device = &self.device;
+
+        self.depth_stencil_states.entry(stencil_mode)
+            .or_insert_with(|| {
+                let descriptor = metal::DepthStencilDescriptor::new();
+
+                let operations = match stencil_mode {
+                    StencilMode::Disabled   => None,
+                    StencilMode::Increment  => Some((metal::MTLCompareFunction::Always, metal::MTLStencilOperation::IncrementClamp)),
+                    StencilMode::Decrement  => Some((metal::MTLCompareFunction::Always, metal::MTLStencilOperation::DecrementClamp)),
+                    StencilMode::Equal(_)   => Some((metal::MTLCompareFunction::Equal, metal::MTLStencilOperation::Keep))
+                };
+
+                if let Some((compare_function, pass_operation)) = operations {
+                    let stencil_descriptor = metal::StencilDescriptor::new();
+                    stencil_descriptor.set_stencil_compare_function(compare_function);
+                    stencil_descriptor.set_stencil_failure_operation(metal::MTLStencilOperation::Keep);
+                    stencil_descriptor.set_depth_failure_operation(metal::MTLStencilOperation::Keep);
+                    stencil_descriptor.set_depth_stencil_pass_operation(pass_operation);
+                    stencil_descriptor.set_read_mask(0xff);
+                    stencil_descriptor.set_write_mask(0xff);
+
+                    descriptor.set_front_face_stencil(Some(&stencil_descriptor));
+                    descriptor.set_back_face_stencil(Some(&stencil_descriptor));
+                }
+
+                device.new_depth_stencil_state(&descriptor)
+            })
+            .clone()
+    }
+
+    ///
+    /// Attaches a stencil texture to a render pass
+    ///
+    fn attach_stencil(render_descriptor: &metal::RenderPassDescriptorRef, stencil: &metal::Texture, load_action: metal::MTLLoadAction) {
+        let stencil_attachment  = render_descriptor.stencil_attachment().unwrap();
+
+        stencil_attachment.set_texture(Some(stencil));
+        stencil_attachment.set_clear_stencil(0);
+        stencil_attachment.set_load_action(load_action);
+        stencil_attachment.set_store_action(metal::MTLStoreAction::Store);
+    }
+
     ///
     /// Creates a command encoder for rendering to the specified texture
     ///
-    fn get_command_encoder<'a>(&mut self, command_buffer: &'a metal::CommandBufferRef, render_target: &metal::Texture) -> &'a metal::RenderCommandEncoderRef {
+    fn get_command_encoder<'a>(&mut self, command_buffer: &'a metal::CommandBufferRef, render_target: &metal::Texture, stencil: Option<&metal::Texture>) -> &'a metal::RenderCommandEncoderRef {
         let render_descriptor   = metal::RenderPassDescriptor::new();
         let color_attachment    = render_descriptor.color_attachments().object_at(0).unwrap();
 
@@ -159,6 +261,10 @@ impl MetalRenderer {
         color_attachment.set_load_action(metal::MTLLoadAction::Load);
         color_attachment.set_store_action(metal::MTLStoreAction::Store);
 
+        if let Some(stencil) = stencil {
+            Self::attach_stencil(render_descriptor, stencil, metal::MTLLoadAction::Load);
+        }
+
         command_buffer.new_render_command_encoder(&render_descriptor)
     }
 
@@ -172,7 +278,7 @@ impl MetalRenderer {
     ///
     /// Creates a command encoder for rendering to the specified texture, after clearing it
     ///
-    fn get_command_encoder_with_clear<'a>(&mut self, command_buffer: &'a metal::CommandBufferRef, render_target: &metal::Texture, clear_color: Rgba8) -> &'a metal::RenderCommandEncoderRef {
+    fn get_command_encoder_with_clear<'a>(&mut self, command_buffer: &'a metal::CommandBufferRef, render_target: &metal::Texture, stencil: Option<&metal::Texture>, clear_color: Rgba8) -> &'a metal::RenderCommandEncoderRef {
         let render_descriptor   = metal::RenderPassDescriptor::new();
         let color_attachment    = render_descriptor.color_attachments().object_at(0).unwrap();
         let Rgba8([r, g, b, a]) = clear_color;
@@ -183,6 +289,11 @@ impl MetalRenderer {
         color_attachment.set_load_action(metal::MTLLoadAction::Clear);
         color_attachment.set_store_action(metal::MTLStoreAction::Store);
 
+        // The stencil buffer is reset along with the colour
+        if let Some(stencil) = stencil {
+            Self::attach_stencil(render_descriptor, stencil, metal::MTLLoadAction::Clear);
+        }
+
         command_buffer.new_render_command_encoder(&render_descriptor)
     }
 
@@ -198,6 +309,9 @@ impl MetalRenderer {
         state.command_encoder.set_fragment_texture(FragmentInputIndex_FragmentIndexClipMaskTexture as u64, state.clip_texture.as_ref().map::<&metal::TextureRef, _>(|t| t));
         state.command_encoder.set_fragment_texture(FragmentInputIndex_FragmentIndexTexture as u64, state.fill_texture.as_ref().map::<&metal::TextureRef, _>(|t| t));
 
+        let sampler_state = self.get_sampler_state(state.wrap_mode);
+        state.command_encoder.set_fragment_sampler_state(FragmentInputIndex_FragmentIndexSampler as u64, Some(&sampler_state));
+
         if let Some(texture_matrix) = &state.texture_transform {
             state.command_encoder.set_vertex_buffer(VertexInputIndex_VertexTextureMatrix as u64, Some(texture_matrix), 0);
         }
@@ -207,6 +321,10 @@ impl MetalRenderer {
             let alpha = alpha.to_ne_bytes();
             state.command_encoder.set_fragment_bytes(FragmentInputIndex_FragmentAlpha as u64, 4, alpha.as_ptr() as _);
         }
+
+        // New command encoders don't keep the scissor rect or the stencil state from the previous one
+        self.update_scissor(state);
+        self.update_stencil(state);
     }
 
     ///
@@ -220,16 +338,21 @@ impl MetalRenderer {
         let pipeline_config     = PipelineConfiguration::for_texture(target_texture);
         let pipeline_state      = self.get_pipeline_state(&pipeline_config);
         let command_buffer      = command_queue.new_command_buffer();
-        let command_encoder     = self.get_command_encoder_with_clear(command_buffer, target_texture, Rgba8([0, 0, 0, 0]));
+        let command_encoder     = self.get_command_encoder_with_clear(command_buffer, target_texture, None, Rgba8([0, 0, 0, 0]));
 
         let mut render_state    = RenderState {
             main_texture:           target_texture.clone(),
             target_texture:         target_texture.clone(),
+            render_target:          None,
+            target_stencil:         None,
+            stencil_mode:           StencilMode::Disabled,
             fill_texture:           None,
             clip_texture:           None,
             matrix:                 matrix,
             texture_transform:      None,
             texture_alpha:          None,
+            wrap_mode:              TextureWrapMode::Clamp,
+            scissor:                None,
             pipeline_config:        pipeline_config,
             pipeline_state:         pipeline_state,
             command_buffer:         command_buffer,
@@ -248,14 +371,19 @@ impl MetalRenderer {
                 CreateIndexBuffer(id, indices)                                                  => { self.create_index_buffer(id, indices); }
                 FreeVertexBuffer(id)                                                            => { self.free_vertex_buffer(id); }
                 FreeIndexBuffer(id)                                                             => { self.free_index_buffer(id); }
+                TrimBufferPools                                                                 => { /* Buffers are not pooled by this renderer */ }
                 BlendMode(blend_mode)                                                           => { self.blend_mode(blend_mode, &mut render_state); }
+                StencilMode(stencil_mode)                                                       => { self.set_stencil_mode(stencil_mode, &mut render_state); }
                 CreateRenderTarget(render_id, texture_id, Size2D(width, height), render_type)   => { self.create_render_target(render_id, texture_id, width, height, render_type); }
                 FreeRenderTarget(render_id)                                                     => { self.free_render_target(render_id); }
                 SelectRenderTarget(render_id)                                                   => { self.select_render_target(render_id, &mut render_state); }
                 RenderToFrameBuffer                                                             => { self.select_main_frame_buffer(&mut render_state); }
                 DrawFrameBuffer(render_id, region, Alpha(alpha))                                => { self.draw_frame_buffer(render_id, region, alpha, &mut render_state); }
+                SetScissor(region)                                                              => { self.set_scissor(Some(region), &mut render_state); }
+                ClearScissor                                                                    => { self.set_scissor(None, &mut render_state); }
                 ShowFrameBuffer                                                                 => { /* This doesn't double-buffer so nothing to do */ }
-                CreateTextureBgra(texture_id, Size2D(width, height))                            => { self.create_bgra_texture(texture_id, width, height); }
+                CreateTextureBgra(texture_id, Size2D(width, height))                            => { self.create_bgra_texture(texture_id, width, height, false); }
+                CreateTextureBgraPremultiplied(texture_id, Size2D(width, height))               => { self.create_bgra_texture(texture_id, width, height, true); }
                 CreateTextureMono(texture_id, Size2D(width, height))                            => { self.create_mono_texture(texture_id, width, height); }
                 Create1DTextureBgra(texture_id, Size1D(width))                                  => { self.create_bgra_1d_texture(texture_id, width); }
                 Create1DTextureMono(texture_id, Size1D(width))                                  => { self.create_mono_1d_texture(texture_id, width); }
@@ -370,6 +498,113 @@ impl MetalRenderer {
         state.command_encoder.set_render_pipeline_state(&state.pipeline_state);
     }
 
+    ///
+    /// Restricts drawing to a region of the current render target (or removes the restriction if the region is None)
+    ///
+    fn set_scissor(&mut self, region: Option<FrameBufferRegion>, state: &mut RenderState) {
+        state.scissor = region;
+        self.update_scissor(state);
+    }
+
+    ///
+    /// Sets the scissor rect of the command encoder to match the scissor region for the current render target
+    ///
+    fn update_scissor(&self, state: &RenderState) {
+        let target_width            = state.target_texture.width() as u32;
+        let target_height           = state.target_texture.height() as u32;
+
+        let (x, y, width, height)   = match state.scissor {
+            Some(region) => {
+                let (x, y, width, height) = region.to_pixels(target_width, target_height);
+
+                if self.flip_y {
+                    // The rendering is upside-down, so the region is already in texture coordinates
+                    (x, y, width, height)
+                } else {
+                    // Texture coordinates have the origin at the top-left
+                    (x, target_height - y - height, width, height)
+                }
+            }
+
+            None => (0, 0, target_width, target_height)
+        };
+
+        state.command_encoder.set_scissor_rect(metal::MTLScissorRect { x: x as _, y: y as _, width: width as _, height: height as _ });
+    }
+
+    ///
+    /// Returns the stencil mode that applies to the current render target (render targets without a stencil buffer ignore the stencil mode)
+    ///
+    fn active_stencil_mode(state: &RenderState) -> StencilMode {
+        if state.target_stencil.is_some() { state.stencil_mode } else { StencilMode::Disabled }
+    }
+
+    ///
+    /// Updates the pipeline configuration so it matches the stencil attachment and stencil mode of the current render target
+    ///
+    fn update_stencil_config(state: &mut RenderState) {
+        state.pipeline_config.has_stencil   = state.target_stencil.is_some();
+        state.pipeline_config.write_color   = match Self::active_stencil_mode(state) {
+            StencilMode::Increment | StencilMode::Decrement => false,
+            StencilMode::Disabled | StencilMode::Equal(_)   => true
+        };
+    }
+
+    ///
+    /// Sets how future drawing operations use the stencil buffer
+    ///
+    fn set_stencil_mode(&mut self, stencil_mode: StencilMode, state: &mut RenderState) {
+        state.stencil_mode = stencil_mode;
+
+        // Render targets are given a stencil buffer the first time one is needed
+        if stencil_mode != StencilMode::Disabled {
+            if let Some(render_id) = state.render_target {
+                let device = &self.device;
+
+                if let Some(Some(render_target)) = self.render_targets.get_mut(render_id) {
+                    if render_target.create_stencil_buffer(device) {
+                        state.target_stencil = render_target.stencil_texture().cloned();
+
+                        // Start a new render pass with the stencil attached, clearing it to 0 as its initial contents are undefined
+                        let render_descriptor   = metal::RenderPassDescriptor::new();
+                        let color_attachment    = render_descriptor.color_attachments().object_at(0).unwrap();
+
+                        color_attachment.set_texture(Some(&state.target_texture));
+                        color_attachment.set_load_action(metal::MTLLoadAction::Load);
+                        color_attachment.set_store_action(metal::MTLStoreAction::Store);
+
+                        if let Some(stencil) = &state.target_stencil {
+                            Self::attach_stencil(render_descriptor, stencil, metal::MTLLoadAction::Clear);
+                        }
+
+                        state.command_encoder.end_encoding();
+                        state.command_encoder = state.command_buffer.new_render_command_encoder(&render_descriptor);
+                    }
+                }
+            }
+        }
+
+        // The colour write mask and the stencil format are part of the pipeline state
+        Self::update_stencil_config(state);
+        state.pipeline_state = self.get_pipeline_state(&state.pipeline_config);
+
+        self.setup_command_encoder(state);
+    }
+
+    ///
+    /// Sets the depth/stencil state of the command encoder to match the stencil mode for the current render target
+    ///
+    fn update_stencil(&mut self, state: &RenderState) {
+        let stencil_mode        = Self::active_stencil_mode(state);
+        let depth_stencil_state = self.get_depth_stencil_state(stencil_mode);
+
+        state.command_encoder.set_depth_stencil_state(&depth_stencil_state);
+
+        if let StencilMode::Equal(value) = stencil_mode {
+            state.command_encoder.set_stencil_reference_value(value as u32);
+        }
+    }
+
     ///
     /// Creates a render target and its backing texture
     ///
@@ -390,6 +625,7 @@ impl MetalRenderer {
         // Free any existing texture or render target
         self.render_targets[render_id]  = None;
         self.textures[texture_id]       = None;
+        self.premultiplied_textures.remove(&texture_id);
 
         // Create the render target
         let new_render_target = RenderTarget::new(&self.device, width, height, render_target_type);
@@ -415,12 +651,15 @@ impl MetalRenderer {
 
         // Set the state to point at the new texture
         state.target_texture    = render_target.render_texture().clone();
+        state.target_stencil    = render_target.stencil_texture().cloned();
+        state.render_target     = Some(render_id);
 
         // Create a command encoder that will use this texture
         state.command_encoder.end_encoding();
-        state.command_encoder   = self.get_command_encoder(state.command_buffer, &state.target_texture);
+        state.command_encoder   = self.get_command_encoder(state.command_buffer, &state.target_texture, state.target_stencil.as_ref());
 
         state.pipeline_config.update_for_texture(&state.target_texture);
+        Self::update_stencil_config(state);
         state.pipeline_state    = self.get_pipeline_state(&state.pipeline_config);
         state.command_encoder.set_render_pipeline_state(&state.pipeline_state);
 
@@ -431,14 +670,17 @@ impl MetalRenderer {
     /// Sets the main frame buffer to be the current render target
     ///
     fn select_main_frame_buffer(&mut self, state: &mut RenderState) {
-        // Reset the state to point at the main texture
+        // Reset the state to point at the main texture (which has no stencil buffer)
         state.target_texture    = state.main_texture.clone();
+        state.target_stencil    = None;
+        state.render_target     = None;
 
         // Create a command encoder that will use this texture
         state.command_encoder.end_encoding();
-        state.command_encoder   = self.get_command_encoder(state.command_buffer, &state.target_texture);
+        state.command_encoder   = self.get_command_encoder(state.command_buffer, &state.target_texture, None);
 
         state.pipeline_config.update_for_texture(&state.target_texture);
+        Self::update_stencil_config(state);
         state.pipeline_state    = self.get_pipeline_state(&state.pipeline_config);
         state.command_encoder.set_render_pipeline_state(&state.pipeline_state);
 
@@ -465,6 +707,8 @@ impl MetalRenderer {
             config.blend_mode               = BlendMode::SourceOver;
             config.source_is_premultiplied  = true;
             config.fragment_shader          = if source_buffer.is_multisampled() { String::from("texture_multisample_fragment") } else { String::from("texture_fragment") };
+            config.has_stencil              = state.pipeline_config.has_stencil;
+            config.write_color              = state.pipeline_config.write_color;
 
             // Convert to a pipeline state
             let pipeline_state              = self.get_pipeline_state(&config);
@@ -512,6 +756,9 @@ impl MetalRenderer {
             state.command_encoder.set_vertex_buffer(VertexInputIndex_VertexInputIndexVertices as u64, Some(&triangle_strip), 0);
             state.command_encoder.set_fragment_texture(FragmentInputIndex_FragmentIndexTexture as u64, Some(&source_texture));
 
+            let sampler_state               = self.get_sampler_state(TextureWrapMode::Clamp);
+            state.command_encoder.set_fragment_sampler_state(FragmentInputIndex_FragmentIndexSampler as u64, Some(&sampler_state));
+
             let alpha = alpha as f32;
             let alpha = alpha.to_ne_bytes();
             state.command_encoder.set_fragment_bytes(FragmentInputIndex_FragmentAlpha as u64, 4, alpha.as_ptr() as _);
@@ -530,18 +777,28 @@ impl MetalRenderer {
     ///
     /// Stores a texture with the specified texture ID
     ///
+    ///
+    /// Retrieves a texture created by this renderer, along with whether or not it contains premultiplied alpha
+    ///
+    pub (crate) fn texture(&self, TextureId(texture_id): TextureId) -> Option<(metal::Texture, bool)> {
+        let texture = self.textures.get(texture_id)?.as_ref()?;
+
+        Some((texture.clone(), self.premultiplied_textures.contains(&texture_id)))
+    }
+
     #[inline] fn store_texture(&mut self, texture_id: usize, texture: metal::Texture) {
         while self.textures.len() <= texture_id {
             self.textures.push(None);
         }
 
         self.textures[texture_id] = Some(texture);
+        self.premultiplied_textures.remove(&texture_id);
     }
 
     ///
-    /// Creates a BGRA formatted 2D texture
+    /// Creates a BGRA formatted 2D texture (`premultiplied` is true if the pixels written to it will have premultiplied alpha)
     ///
-    fn create_bgra_texture(&mut self, TextureId(texture_id): TextureId, width: usize, height: usize) {
+    fn create_bgra_texture(&mut self, TextureId(texture_id): TextureId, width: usize, height: usize, premultiplied: bool) {
         // Create the texture descriptor
         let texture_descriptor  = metal::TextureDescriptor::new();
 
@@ -557,6 +814,10 @@ impl MetalRenderer {
 
         // Store in the textures
         self.store_texture(texture_id, texture);
+
+        if premultiplied {
+            self.premultiplied_textures.insert(texture_id);
+        }
     }
 
     ///
@@ -704,7 +965,7 @@ impl MetalRenderer {
         blit_encoder.end_encoding();
 
         // Generate a new command encoder
-        state.command_encoder = self.get_command_encoder(state.command_buffer, &state.target_texture);
+        state.command_encoder = self.get_command_encoder(state.command_buffer, &state.target_texture, state.target_stencil.as_ref());
         self.setup_command_encoder(state);
     }
 
@@ -754,11 +1015,15 @@ impl MetalRenderer {
         blit_encoder.end_encoding();
 
         // Generate a new command encoder
-        state.command_encoder = self.get_command_encoder(state.command_buffer, &state.target_texture);
+        state.command_encoder = self.get_command_encoder(state.command_buffer, &state.target_texture, state.target_stencil.as_ref());
         self.setup_command_encoder(state);
 
         // Store the target texture
         self.store_texture(tgt_texture_id, tgt_texture);
+
+        if self.premultiplied_textures.contains(&src_texture_id) {
+            self.premultiplied_textures.insert(tgt_texture_id);
+        }
     }
 
     ///
@@ -775,6 +1040,8 @@ impl MetalRenderer {
         if texture_id < self.textures.len() {
             self.textures[texture_id] = None;
         }
+
+        self.premultiplied_textures.remove(&texture_id);
     }
 
     ///
@@ -783,7 +1050,7 @@ impl MetalRenderer {
     fn clear(&mut self, color: Rgba8, state: &mut RenderState) {
         // Metal forces clears to be done at the start of a new render pass
         state.command_encoder.end_encoding();
-        state.command_encoder = self.get_command_encoder_with_clear(state.command_buffer, &state.target_texture, color);
+        state.command_encoder = self.get_command_encoder_with_clear(state.command_buffer, &state.target_texture, state.target_stencil.as_ref(), color);
 
         self.setup_command_encoder(state);
     }
@@ -793,10 +1060,12 @@ impl MetalRenderer {
     ///
     fn use_shader(&mut self, shader_type: ShaderType, state: &mut RenderState) {
         // Reset the current shader state
-        state.pipeline_config.vertex_shader = String::from("simple_vertex");
-        state.fill_texture                  = None;
-        state.clip_texture                  = None;
-        state.texture_transform             = None;
+        state.pipeline_config.vertex_shader             = String::from("simple_vertex");
+        state.pipeline_config.source_is_premultiplied   = false;
+        state.fill_texture                              = None;
+        state.clip_texture                              = None;
+        state.texture_transform                         = None;
+        state.wrap_mode                                 = TextureWrapMode::Clamp;
 
         // Update the state according to the shader type
         match shader_type {
@@ -814,23 +1083,27 @@ impl MetalRenderer {
                 state.clip_texture                      = self.textures[clip_texture].clone();
             }
 
-            ShaderType::Texture { texture: TextureId(fill_texture), texture_transform, repeat, alpha, clip_texture: None } => { 
+            ShaderType::Texture { texture: TextureId(fill_texture), texture_transform, wrap_mode, alpha, clip_texture: None } => { 
                 state.pipeline_config.vertex_shader     = String::from("texture_vertex");
                 state.pipeline_config.fragment_shader   = String::from("texture_fragment");
                 state.texture_transform                 = Some(MatrixBuffer::from_matrix(&self.device, texture_transform));
                 state.texture_alpha                     = Some(alpha as _);
+                state.wrap_mode                         = wrap_mode;
 
                 state.fill_texture                      = self.textures[fill_texture].clone();
+                state.pipeline_config.source_is_premultiplied = self.premultiplied_textures.contains(&fill_texture);
             }
 
-            ShaderType::Texture { texture: TextureId(fill_texture), texture_transform, repeat, alpha, clip_texture: Some(TextureId(clip_texture)) } => { 
+            ShaderType::Texture { texture: TextureId(fill_texture), texture_transform, wrap_mode, alpha, clip_texture: Some(TextureId(clip_texture)) } => { 
                 state.pipeline_config.vertex_shader     = String::from("texture_vertex");
                 state.pipeline_config.fragment_shader   = String::from("texture_clip_mask_multisample_fragment");
                 state.texture_transform                 = Some(MatrixBuffer::from_matrix(&self.device, texture_transform));
                 state.texture_alpha                     = Some(alpha as _);
+                state.wrap_mode                         = wrap_mode;
 
                 state.fill_texture                      = self.textures[fill_texture].clone();
                 state.clip_texture                      = self.textures[clip_texture].clone();
+                state.pipeline_config.source_is_premultiplied = self.premultiplied_textures.contains(&fill_texture);
             }
 
             ShaderType::LinearGradient { texture: TextureId(gradient_texture), texture_transform, repeat, alpha, clip_texture: None } => { 
@@ -35,7 +35,17 @@ pub struct PipelineConfiguration {
     ///
     /// The name of the fragment shader to use
     ///
-    pub fragment_shader: String
+    pub fragment_shader: String,
+
+    ///
+    /// True if the render target has a stencil attachment
+    ///
+    pub has_stencil: bool,
+
+    ///
+    /// False if the fragment shader should only update the stencil buffer and leave the colour unchanged
+    ///
+    pub write_color: bool
 }
 
 impl Default for PipelineConfiguration {
@@ -46,7 +56,9 @@ impl Default for PipelineConfiguration {
             blend_mode:                 BlendMode::SourceOver,
             source_is_premultiplied:    false,
             vertex_shader:              String::from("simple_vertex"),
-            fragment_shader:            String::from("simple_fragment")
+            fragment_shader:            String::from("simple_fragment"),
+            has_stencil:                false,
+            write_color:                true
         }
     }
 }
@@ -136,6 +148,15 @@ impl PipelineConfiguration {
         descriptor.color_attachments().object_at(0).unwrap().set_source_alpha_blend_factor(src_alpha);
         descriptor.color_attachments().object_at(0).unwrap().set_destination_alpha_blend_factor(dst_alpha);
 
+        if !self.write_color {
+            descriptor.color_attachments().object_at(0).unwrap().set_write_mask(metal::MTLColorWriteMask::empty());
+        }
+
+        // The stencil format has to match the render pass the pipeline is used with
+        if self.has_stencil {
+            descriptor.set_stencil_attachment_pixel_format(metal::MTLPixelFormat::Stencil8);
+        }
+
         // Create the state
         device.new_render_pipeline_state(&descriptor).unwrap()
     }
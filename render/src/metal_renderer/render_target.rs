@@ -10,6 +10,7 @@ pub enum RenderTarget {
     /// Simple texture
     Texture {
         texture:    metal::Texture,
+        stencil:    Option<metal::Texture>,
         width:      usize,
         height:     usize
     },
@@ -18,6 +19,7 @@ pub enum RenderTarget {
     Multisampled {
         samples:    metal::Texture,
         resolved:   Option<metal::Texture>,
+        stencil:    Option<metal::Texture>,
         width:      usize,
         height:     usize
     }
@@ -72,6 +74,7 @@ impl RenderTarget {
                 // Just create a normal texture
                 RenderTarget::Texture { 
                     texture:    render_texture,
+                    stencil:    None,
                     width:      width,
                     height:     height
                 }
@@ -81,6 +84,7 @@ impl RenderTarget {
                 RenderTarget::Multisampled {
                     samples:    render_texture,
                     resolved:   None,
+                    stencil:    None,
                     width:      width,
                     height:     height
                 }
@@ -90,6 +94,7 @@ impl RenderTarget {
                 RenderTarget::Multisampled {
                     samples:    render_texture,
                     resolved:   None,
+                    stencil:    None,
                     width:      width,
                     height:     height
                 }
@@ -102,8 +107,8 @@ impl RenderTarget {
     ///
     pub fn size(&self) -> (usize, usize) {
         match self {
-            RenderTarget::Texture { texture: _, width, height }                     => (*width, *height),
-            RenderTarget::Multisampled { samples: _, resolved: _, width, height }   => (*width, *height)
+            RenderTarget::Texture { texture: _, stencil: _, width, height }                     => (*width, *height),
+            RenderTarget::Multisampled { samples: _, resolved: _, stencil: _, width, height }   => (*width, *height)
         }
     }
 
@@ -112,8 +117,8 @@ impl RenderTarget {
     ///
     pub fn render_texture(&self) -> &metal::Texture {
         match self {
-            RenderTarget::Texture { texture, width: _, height: _ }                      => texture,
-            RenderTarget::Multisampled { samples, resolved: _, width: _, height: _ }    => samples
+            RenderTarget::Texture { texture, stencil: _, width: _, height: _ }                      => texture,
+            RenderTarget::Multisampled { samples, resolved: _, stencil: _, width: _, height: _ }    => samples
         }
     }
 
@@ -122,8 +127,48 @@ impl RenderTarget {
     ///
     pub fn is_multisampled(&self) -> bool {
         match self {
-            RenderTarget::Texture { texture: _, width: _, height: _ }                   => false,
-            RenderTarget::Multisampled { samples: _, width: _, height: _, resolved: _ } => true
+            RenderTarget::Texture { texture: _, stencil: _, width: _, height: _ }                   => false,
+            RenderTarget::Multisampled { samples: _, width: _, height: _, stencil: _, resolved: _ } => true
         }
     }
+
+    ///
+    /// Returns the stencil texture for this render target, if it has one
+    ///
+    pub fn stencil_texture(&self) -> Option<&metal::Texture> {
+        match self {
+            RenderTarget::Texture { texture: _, stencil, width: _, height: _ }                      => stencil.as_ref(),
+            RenderTarget::Multisampled { samples: _, resolved: _, stencil, width: _, height: _ }    => stencil.as_ref()
+        }
+    }
+
+    ///
+    /// Creates a stencil texture for this render target if it doesn't already have one, returning true if a new texture was created
+    ///
+    /// The contents of a new stencil texture are undefined, so it should be cleared when it's first attached to a render pass.
+    ///
+    pub fn create_stencil_buffer(&mut self, device: &metal::Device) -> bool {
+        if self.stencil_texture().is_some() { return false; }
+
+        // The stencil texture needs to match the size and sample count of the render texture
+        let render_texture      = self.render_texture();
+        let texture_descriptor  = metal::TextureDescriptor::new();
+
+        texture_descriptor.set_texture_type(render_texture.texture_type());
+        texture_descriptor.set_width(render_texture.width());
+        texture_descriptor.set_height(render_texture.height());
+        texture_descriptor.set_sample_count(render_texture.sample_count());
+        texture_descriptor.set_pixel_format(metal::MTLPixelFormat::Stencil8);
+        texture_descriptor.set_usage(metal::MTLTextureUsage::RenderTarget);
+        texture_descriptor.set_storage_mode(metal::MTLStorageMode::Private);
+
+        let new_stencil         = device.new_texture(&texture_descriptor);
+
+        match self {
+            RenderTarget::Texture { texture: _, stencil, width: _, height: _ }                      => { *stencil = Some(new_stencil); }
+            RenderTarget::Multisampled { samples: _, resolved: _, stencil, width: _, height: _ }    => { *stencil = Some(new_stencil); }
+        }
+
+        true
+    }
 }
@@ -0,0 +1,82 @@
+use super::offscreen_trait::*;
+
+use crate::action::*;
+use crate::buffer::*;
+
+use std::sync::*;
+
+///
+/// Draws an opaque red square in the top-left quadrant and a 50% transparent blue square in the bottom-right quadrant of a
+/// 100x100 render target, and checks that `realize()` returns it as top-down RGBA pixels with straight alpha
+///
+pub (crate) fn check_realize_layout<Context: OffscreenRenderContext>(context: &mut Context) {
+    use self::RenderAction::*;
+
+    let quad            = |min_x: f32, min_y: f32, color: [u8; 4]| vec![
+        Vertex2D { pos: [min_x, min_y],         tex_coord: [0.0, 0.0], color: color },
+        Vertex2D { pos: [min_x+1.0, min_y],     tex_coord: [0.0, 0.0], color: color },
+        Vertex2D { pos: [min_x+1.0, min_y+1.0], tex_coord: [0.0, 0.0], color: color },
+
+        Vertex2D { pos: [min_x, min_y],         tex_coord: [0.0, 0.0], color: color },
+        Vertex2D { pos: [min_x, min_y+1.0],     tex_coord: [0.0, 0.0], color: color },
+        Vertex2D { pos: [min_x+1.0, min_y+1.0], tex_coord: [0.0, 0.0], color: color },
+    ];
+
+    let mut renderer    = context.create_render_target(100, 100);
+    renderer.render(vec![
+        Clear(Rgba8([0, 0, 0, 0])),
+        UseShader(ShaderType::Simple { clip_texture: None }),
+        BlendMode(crate::action::BlendMode::SourceOver),
+        CreateVertex2DBuffer(VertexBufferId(0), quad(-1.0, 0.0, [255, 0, 0, 255])),
+        CreateVertex2DBuffer(VertexBufferId(1), quad(0.0, -1.0, [0, 0, 255, 128])),
+        DrawTriangles(VertexBufferId(0), 0..6),
+        DrawTriangles(VertexBufferId(1), 0..6),
+    ]);
+
+    let image           = renderer.realize();
+
+    // Every backend should produce the same tightly packed layout, starting with the top-left pixel
+    assert!(image.len() == 100*100*4, "{}", image.len());
+
+    for y in 0..100 {
+        for x in 0..100 {
+            let pos         = (x + y*100) * 4;
+            let pixel       = [image[pos], image[pos+1], image[pos+2], image[pos+3]];
+
+            let expected    = if x < 50 && y < 50 {
+                [255, 0, 0, 255]
+            } else if x >= 50 && y >= 50 {
+                [0, 0, 255, 128]
+            } else {
+                [0, 0, 0, 0]
+            };
+
+            let matches     = pixel.iter().zip(expected.iter()).all(|(actual, expected)| (*actual as i32 - *expected as i32).abs() <= 1);
+            assert!(matches, "{} {} {:?} {:?}", x, y, pixel, expected);
+        }
+    }
+}
+
+///
+/// Writes a premultiplied texture with different pixels in each corner, and checks that `read_texture()` returns them in
+/// the order they were written, with straight alpha
+///
+pub (crate) fn check_texture_readback<Context: OffscreenRenderContext>(context: &mut Context) {
+    use self::RenderAction::*;
+
+    let mut renderer    = context.create_render_target(4, 4);
+    renderer.render(vec![
+        CreateTextureBgraPremultiplied(TextureId(0), Size2D(2, 2)),
+        WriteTextureData(TextureId(0), Position2D(0, 0), Position2D(2, 2), Arc::new(vec![
+            255, 0, 0, 255,     0, 0, 128, 128,
+            0, 0, 0, 0,         0, 255, 0, 255,
+        ])),
+    ]);
+
+    let texture         = renderer.read_texture(TextureId(0));
+
+    assert!(texture == Some(vec![
+        255, 0, 0, 255,     0, 0, 255, 128,
+        0, 0, 0, 0,         0, 255, 0, 255,
+    ]), "{:?}", texture);
+}
@@ -1,3 +1,6 @@
+use std::fmt;
+use std::error::Error;
+
 #[allow(dead_code)]
 
 ///
@@ -8,6 +11,12 @@ pub enum RenderInitError {
     /// The required rendering API is not available
     ApiNotAvailable,
 
+    /// No graphics adapter matched the requested options (the string describes the options that were requested)
+    NoAdapter(String),
+
+    /// An adapter was found but a device could not be created on it (the string describes the adapter and the error)
+    NoDevice(String),
+
     /// Indicates that the graphics device could not be opened
     CannotOpenGraphicsDevice,
 
@@ -33,5 +42,40 @@ pub enum RenderInitError {
     CouldNotCreateSurface,
 
     /// Could not set the active context
-    ContextDidNotStart
+    ContextDidNotStart,
+
+    /// The surface does not support any texture format that the renderer can use (the string describes the adapter)
+    UnsupportedFormat(String),
+
+    /// A call to the graphics driver failed (the name of the call and the error code that the driver reported)
+    DriverError(String, u32),
+
+    /// The window was closed before its renderer could be created
+    WindowClosed,
 }
+
+impl fmt::Display for RenderInitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::RenderInitError::*;
+
+        match self {
+            ApiNotAvailable                 => write!(f, "the required rendering API is not available"),
+            NoAdapter(description)          => write!(f, "{}", description),
+            NoDevice(description)           => write!(f, "{}", description),
+            CannotOpenGraphicsDevice        => write!(f, "the graphics device could not be opened"),
+            CannotCreateGraphicsDevice      => write!(f, "the graphics device could not be attached to"),
+            CannotStartGraphicsDriver       => write!(f, "the graphics driver failed to initialise"),
+            DisplayNotAvailable             => write!(f, "the graphics display is not available"),
+            MissingRequiredExtension        => write!(f, "a required graphics extension is missing"),
+            CouldNotConfigureDisplay        => write!(f, "unable to configure the display"),
+            CouldNotCreateContext           => write!(f, "the rendering context could not be created"),
+            CouldNotCreateSurface           => write!(f, "the render surface could not be created"),
+            ContextDidNotStart              => write!(f, "the rendering context could not be made active"),
+            UnsupportedFormat(description)  => write!(f, "{}", description),
+            DriverError(call, code)         => write!(f, "{} failed with error {:#x}", call, code),
+            WindowClosed                    => write!(f, "the window was closed before its renderer could be created"),
+        }
+    }
+}
+
+impl Error for RenderInitError { }
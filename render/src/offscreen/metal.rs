@@ -1,11 +1,13 @@
 use super::error::*;
 use super::offscreen_trait::*;
+use super::readback::*;
 
 use crate::action::*;
 use crate::metal_renderer::*;
 
 use metal;
 
+use std::ptr;
 use std::ffi::{c_void};
 
 ///
@@ -91,9 +93,18 @@ impl OffscreenRenderTarget for MetalOffscreenRenderTarget {
     }
 
     ///
-    /// Consumes this render target and returns the realized pixels as a byte array
+    /// Changes the size of this render target
     ///
-    fn realize(self) -> Vec<u8> {
+    fn resize(&mut self, width: usize, height: usize) {
+        self.render_target  = RenderTarget::new(&self.device, width, height, RenderTargetType::StandardForReading);
+        self.width          = width;
+        self.height         = height;
+    }
+
+    ///
+    /// Returns the pixels that have been rendered so far as a byte array
+    ///
+    fn read_pixels(&mut self) -> Vec<u8> {
         let mut result  = vec![0; self.width * self.height * 4];
 
         let texture     = self.render_target.render_texture();
@@ -103,6 +114,64 @@ impl OffscreenRenderTarget for MetalOffscreenRenderTarget {
         };
         texture.get_bytes(result.as_mut_ptr() as *mut c_void, (self.width*4) as u64, region, 0);
 
+        // The renderer flips the y axis to match OpenGL, so the texture starts with the bottom row
+        flip_rows(&mut result, self.width);
+        premultiplied_to_straight_alpha(&mut result);
+
         result
     }
+
+    ///
+    /// Reads back the pixels of a texture created by the actions that have been rendered to this target
+    ///
+    fn read_texture(&mut self, texture_id: TextureId) -> Option<Vec<u8>> {
+        let (texture, is_premultiplied) = self.renderer.texture(texture_id)?;
+
+        if texture.pixel_format() != metal::MTLPixelFormat::BGRA8Unorm || texture.sample_count() != 1 {
+            return None;
+        }
+
+        // Textures can be in private storage, so they're copied to a buffer that can be read from the CPU
+        let width           = texture.width();
+        let height          = texture.height();
+        let bytes_per_row   = width * 4;
+        let buffer          = self.device.new_buffer(bytes_per_row * height, metal::MTLResourceOptions::StorageModeShared);
+
+        let command_queue   = self.device.new_command_queue();
+        let command_buffer  = command_queue.new_command_buffer();
+        let blit_encoder    = command_buffer.new_blit_command_encoder();
+        blit_encoder.copy_from_texture_to_buffer(&texture, 0, 0, metal::MTLOrigin { x: 0, y: 0, z: 0 }, metal::MTLSize { width, height, depth: 1 },
+            &buffer, 0, bytes_per_row, bytes_per_row * height, metal::MTLBlitOption::empty());
+        blit_encoder.end_encoding();
+
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+
+        // The bytes are in the same order they were written to the texture
+        let mut result = vec![0; (bytes_per_row * height) as usize];
+        unsafe { ptr::copy_nonoverlapping(buffer.contents() as *const u8, result.as_mut_ptr(), result.len()); }
+
+        if is_premultiplied {
+            premultiplied_to_straight_alpha(&mut result);
+        }
+
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::offscreen::*;
+    use crate::offscreen::conformance::*;
+
+    #[test]
+    fn metal_readback_matches_shared_layout() {
+        let mut context = match metal_initialize_offscreen_rendering() {
+            Ok(context) => context,
+            Err(_)      => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        check_realize_layout(&mut context);
+        check_texture_readback(&mut context);
+    }
 }
@@ -1,5 +1,6 @@
 mod error;
 mod offscreen_trait;
+mod readback;
 
 #[cfg(feature="opengl")]                                                    mod opengl;
 #[cfg(all(feature="opengl", target_os = "windows"))]                        mod opengl_wgl_init;
@@ -10,6 +11,7 @@ mod offscreen_trait;
 
 pub use self::error::*;
 pub use self::offscreen_trait::*;
+pub use self::readback::premultiplied_to_straight_alpha;
 
 #[cfg(all(feature="opengl", target_os = "windows"))]                        pub use self::opengl_wgl_init::*;
 #[cfg(all(feature="opengl", target_os = "linux"))]                          pub use self::opengl_egl_init::*;
@@ -17,4 +19,5 @@ pub use self::offscreen_trait::*;
 #[cfg(feature="osx-metal")]                                                 pub use self::metal::*;
 #[cfg(feature="render-wgpu")]                                               pub use self::wgpu_offscreen::*;
 
+#[cfg(all(test, any(feature="opengl", feature="osx-metal", feature="render-wgpu")))] mod conformance;
 #[cfg(test)] mod test;
@@ -10,9 +10,37 @@ pub trait OffscreenRenderTarget {
     fn render<ActionIter: IntoIterator<Item=RenderAction>>(&mut self, actions: ActionIter);
 
     ///
-    /// Consumes this render target and returns the realized pixels as a byte array
+    /// Changes the size of this render target
     ///
-    fn realize(self) -> Vec<u8>;
+    /// Only the texture that is rendered to is replaced: the renderer keeps its resources (textures, buffers, render targets
+    /// and pipelines), so rendering can continue straight away. The contents of the render target are lost, so the next
+    /// frame should start with a `Clear`.
+    ///
+    fn resize(&mut self, width: usize, height: usize);
+
+    ///
+    /// Returns the pixels that have been rendered so far as a byte array, leaving the render target available for more rendering
+    ///
+    /// Every backend uses the same layout: the pixels are tightly packed RGBA bytes with straight (not premultiplied) alpha,
+    /// starting with the top-left pixel. The top row is the one at y = 1.0 in the render target's coordinates, so rendering
+    /// that appears the right way up in a window also appears the right way up here.
+    ///
+    fn read_pixels(&mut self) -> Vec<u8>;
+
+    ///
+    /// Reads back the pixels of a texture created by the actions that have been rendered to this target
+    ///
+    /// The pixels are copied directly from the texture as tightly packed RGBA bytes with straight alpha, in the same order that
+    /// `WriteTextureData` writes them. Returns None if the texture doesn't exist or isn't a single-sampled RGBA texture.
+    ///
+    fn read_texture(&mut self, texture_id: TextureId) -> Option<Vec<u8>>;
+
+    ///
+    /// Consumes this render target and returns the realized pixels as a byte array (in the same format as `read_pixels()`)
+    ///
+    fn realize(mut self) -> Vec<u8> where Self: Sized {
+        self.read_pixels()
+    }
 }
 
 ///
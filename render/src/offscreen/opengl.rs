@@ -1,4 +1,5 @@
 use super::offscreen_trait::*;
+use super::readback::*;
 
 use crate::action::*;
 use crate::gl_renderer::*;
@@ -73,9 +74,21 @@ impl OffscreenRenderTarget for OpenGlOffscreenRenderer {
     }
 
     ///
-    /// Consumes this render target and returns the realized pixels as a byte array
+    /// Changes the size of this render target
     ///
-    fn realize(self) -> Vec<u8> {
+    fn resize(&mut self, width: usize, height: usize) {
+        // Replacing the render target frees the old frame buffer and texture
+        self.main_render_target = RenderTarget::new(width as u16, height as u16, RenderTargetType::Standard);
+        self.width              = width;
+        self.height             = height;
+
+        unsafe { panic_on_gl_error("Resizing offscreen buffer"); }
+    }
+
+    ///
+    /// Returns the pixels that have been rendered so far as a byte array
+    ///
+    fn read_pixels(&mut self) -> Vec<u8> {
         // Allocate space for the image
         let size_bytes  = self.width * self.height * 4;
         let mut pixels  = vec![0; size_bytes];
@@ -89,6 +102,46 @@ impl OffscreenRenderTarget for OpenGlOffscreenRenderer {
             panic_on_gl_error("Read offscreen texture");
         }
 
+        // OpenGL textures start with the bottom row
+        flip_rows(&mut pixels, self.width);
+        premultiplied_to_straight_alpha(&mut pixels);
+
         pixels
     }
+
+    ///
+    /// Reads back the pixels of a texture created by the actions that have been rendered to this target
+    ///
+    fn read_texture(&mut self, texture_id: TextureId) -> Option<Vec<u8>> {
+        let texture = self.renderer.texture(texture_id)?;
+        if texture.is_mono() {
+            return None;
+        }
+
+        // Unlike the render target, the rows are already in the order they were written to the texture
+        let mut pixels = texture.read_pixels()?;
+
+        if texture.is_premultiplied() {
+            premultiplied_to_straight_alpha(&mut pixels);
+        }
+
+        Some(pixels)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::offscreen::*;
+    use crate::offscreen::conformance::*;
+
+    #[test]
+    fn opengl_readback_matches_shared_layout() {
+        let mut context = match opengl_initialize_offscreen_rendering() {
+            Ok(context) => context,
+            Err(_)      => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        check_realize_layout(&mut context);
+        check_texture_readback(&mut context);
+    }
 }
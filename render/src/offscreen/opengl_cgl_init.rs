@@ -96,7 +96,7 @@ pub fn opengl_initialize_offscreen_rendering() -> Result<impl OffscreenRenderCon
 
         // Check for errors
         let error = gl::GetError();
-        if error != gl::NO_ERROR { println!("gl::GetError {:x}", error); Err(RenderInitError::ContextDidNotStart)? }
+        if error != gl::NO_ERROR { Err(RenderInitError::DriverError("glGetError".to_string(), error))? }
         assert!(error == gl::NO_ERROR);
 
         // Result is a CGL offscreen context
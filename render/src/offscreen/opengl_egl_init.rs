@@ -50,12 +50,12 @@ pub fn opengl_initialize_offscreen_rendering() -> Result<impl OffscreenRenderCon
 
         let egl_display = ffi::eglGetPlatformDisplay(egl::EGL_PLATFORM_GBM_MESA, gbm as *mut c_void, ptr::null());
         let egl_display = if egl_display.is_null() { None } else { Some(egl_display) };
-        let egl_display = if let Some(egl_display) = egl_display { egl_display } else { println!("eglGetPlatformDisplay {:x}", egl::get_error()); Err(RenderInitError::DisplayNotAvailable)? };
+        let egl_display = if let Some(egl_display) = egl_display { egl_display } else { Err(RenderInitError::DriverError("eglGetPlatformDisplay".to_string(), egl::get_error() as u32))? };
 
         let mut major = 0;
         let mut minor = 0;
         let init_result = egl::initialize(egl_display as *mut c_void, &mut major, &mut minor);
-        if !init_result { println!("egl::initialize {:x}", egl::get_error()); Err(RenderInitError::CannotStartGraphicsDriver)? }
+        if !init_result { Err(RenderInitError::DriverError("eglInitialize".to_string(), egl::get_error() as u32))? }
 
         // Check for the create context and surfaceless extensions
         let extensions = egl::query_string(egl_display, egl::EGL_EXTENSIONS);
@@ -75,7 +75,7 @@ pub fn opengl_initialize_offscreen_rendering() -> Result<impl OffscreenRenderCon
                 egl::EGL_RENDERABLE_TYPE,   egl::EGL_OPENGL_BIT, 
                 egl::EGL_NONE
             ], 1);
-        let config = if let Some(config) = config { config } else { println!("egl::choose_config {:x}", egl::get_error()); Err(RenderInitError::CouldNotConfigureDisplay)? };
+        let config = if let Some(config) = config { config } else { Err(RenderInitError::DriverError("eglChooseConfig".to_string(), egl::get_error() as u32))? };
 
         // Create the context
         let context = egl::create_context(egl_display, config, egl::EGL_NO_CONTEXT, &[
@@ -83,17 +83,17 @@ pub fn opengl_initialize_offscreen_rendering() -> Result<impl OffscreenRenderCon
                 egl::EGL_CONTEXT_MINOR_VERSION, 3, 
                 egl::EGL_NONE
             ]);
-        let context = if let Some(context) = context { context } else { println!("egl::create_context {:x}", egl::get_error()); Err(RenderInitError::CouldNotCreateContext)? };
+        let context = if let Some(context) = context { context } else { Err(RenderInitError::DriverError("eglCreateContext".to_string(), egl::get_error() as u32))? };
 
         // End with this set as the current context
         let activated_context = egl::make_current(egl_display, egl::EGL_NO_SURFACE, egl::EGL_NO_SURFACE, context);
 
-        if !activated_context { println!("egl::make_current {:x}", egl::get_error()); Err(RenderInitError::ContextDidNotStart)? }
+        if !activated_context { Err(RenderInitError::DriverError("eglMakeCurrent".to_string(), egl::get_error() as u32))? }
 
         // Set up the GL funcitons and check for errors
         gl::load_with(|s| egl::get_proc_address(s) as *const c_void);
         let error = gl::GetError();
-        if error != gl::NO_ERROR { println!("gl::GetError {:x}", error); Err(RenderInitError::ContextDidNotStart)? }
+        if error != gl::NO_ERROR { Err(RenderInitError::DriverError("glGetError".to_string(), error))? }
         assert!(error == gl::NO_ERROR);
 
         Ok(EglOffscreenRenderContext {
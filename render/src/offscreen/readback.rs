@@ -0,0 +1,49 @@
+///
+/// Reverses the order of the rows in an RGBA image (for APIs that read back the bottom row first)
+///
+#[cfg(any(feature="opengl", feature="osx-metal"))]
+pub (crate) fn flip_rows(pixels: &mut [u8], width: usize) {
+    let row_len     = width * 4;
+    let num_rows    = if row_len == 0 { 0 } else { pixels.len() / row_len };
+
+    for row in 0..(num_rows/2) {
+        let (top, bottom) = pixels.split_at_mut((num_rows - 1 - row) * row_len);
+        top[(row * row_len)..((row + 1) * row_len)].swap_with_slice(&mut bottom[0..row_len]);
+    }
+}
+
+///
+/// Converts RGBA pixels read back from a frame buffer, where the colors are multiplied by the alpha value, to straight alpha
+///
+/// The renderers blend in the same color space as the pixels are supplied in, so this is the reverse of what happens when a
+/// color is drawn. `realize()` and `read_texture()` already return straight alpha, so this is only needed for pixels that
+/// were read back some other way.
+///
+pub fn premultiplied_to_straight_alpha(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+
+        if alpha == 0 {
+            // Color is lost entirely for transparent pixels
+            pixel[0..3].copy_from_slice(&[0, 0, 0]);
+        } else if alpha < 255 {
+            for component in pixel[0..3].iter_mut() {
+                *component = ((*component as u32 * 255 + alpha/2) / alpha).min(255) as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn premultiplied_pixels_are_converted_to_straight_alpha() {
+        let mut pixels = vec![255, 128, 0, 255,  64, 32, 0, 128,  10, 20, 30, 0,  200, 10, 10, 100];
+        premultiplied_to_straight_alpha(&mut pixels);
+
+        // Opaque pixels are unchanged, transparent ones lose their color, and components are clamped to 255
+        assert!(pixels == vec![255, 128, 0, 255,  128, 64, 0, 128,  0, 0, 0, 0,  255, 26, 26, 100], "{:?}", pixels);
+    }
+}
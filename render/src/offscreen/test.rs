@@ -1,8 +1,9 @@
-#[cfg(all(test, any(feature = "opengl", feature = "osx-metal")))]
+#[cfg(all(test, any(feature = "opengl", feature = "osx-metal", feature = "render-wgpu")))]
 mod test {
     use crate::action::*;
     use crate::buffer::*;
     use crate::offscreen::*;
+    use crate::offscreen::conformance::*;
 
     #[test]
     fn clear_offscreen() {
@@ -11,6 +12,8 @@ mod test {
         let mut context     = match context {
             Ok(context)     => context,
             Err(RenderInitError::CannotCreateGraphicsDevice)    => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoDevice(_))                   => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoAdapter(_))                  => { println!("Test not run: graphics device unavailable"); return; }
             Err(other)      => { panic!("Unexpected error: {:?}", other); }
         };
 
@@ -56,6 +59,8 @@ mod test {
         let mut context     = match context {
             Ok(context)     => context,
             Err(RenderInitError::CannotCreateGraphicsDevice)    => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoDevice(_))                   => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoAdapter(_))                  => { println!("Test not run: graphics device unavailable"); return; }
             Err(other)      => { panic!("Unexpected error: {:?}", other); }
         };
 
@@ -101,6 +106,8 @@ mod test {
         let mut context     = match context {
             Ok(context)     => context,
             Err(RenderInitError::CannotCreateGraphicsDevice)    => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoDevice(_))                   => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoAdapter(_))                  => { println!("Test not run: graphics device unavailable"); return; }
             Err(other)      => { panic!("Unexpected error: {:?}", other); }
         };
 
@@ -124,18 +131,19 @@ mod test {
 
         assert!(image.len() == 100*100*4);
 
-        // First pixel should be black
-        assert!(image[0] == 0);
-        assert!(image[1] == 0);
-        assert!(image[2] == 0);
-        assert!(image[3] == 255);
+        // First pixel of the bottom row should be black
+        let bottom_left = 99*100*4;
+        assert!(image[bottom_left+0] == 0);
+        assert!(image[bottom_left+1] == 0);
+        assert!(image[bottom_left+2] == 0);
+        assert!(image[bottom_left+3] == 255);
 
         for y in 0..100 {
             for x in 0..100 {
                 let pos         = (x + y*100) * 4;
                 let pixel       = (image[pos], image[pos+1], image[pos+2], image[pos+3]);
 
-                let expected    = if x >= y {
+                let expected    = if x + y >= 99 {
                     (0, 0, 0, 255)
                 } else {
                     (128, 128, 128, 255)
@@ -157,6 +165,8 @@ mod test {
         let mut context     = match context {
             Ok(context)     => context,
             Err(RenderInitError::CannotCreateGraphicsDevice)    => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoDevice(_))                   => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoAdapter(_))                  => { println!("Test not run: graphics device unavailable"); return; }
             Err(other)      => { panic!("Unexpected error: {:?}", other); }
         };
 
@@ -181,18 +191,19 @@ mod test {
 
         assert!(image.len() == 100*100*4);
 
-        // First pixel should be black
-        assert!(image[0] == 0);
-        assert!(image[1] == 0);
-        assert!(image[2] == 0);
-        assert!(image[3] == 255);
+        // First pixel of the bottom row should be black
+        let bottom_left = 99*100*4;
+        assert!(image[bottom_left+0] == 0);
+        assert!(image[bottom_left+1] == 0);
+        assert!(image[bottom_left+2] == 0);
+        assert!(image[bottom_left+3] == 255);
 
         for y in 0..100 {
             for x in 0..100 {
                 let pos         = (x + y*100) * 4;
                 let pixel       = (image[pos], image[pos+1], image[pos+2], image[pos+3]);
 
-                let expected    = if x >= y {
+                let expected    = if x + y >= 99 {
                     (0, 0, 0, 255)
                 } else {
                     (128, 128, 128, 255)
@@ -214,6 +225,8 @@ mod test {
         let mut context     = match context {
             Ok(context)     => context,
             Err(RenderInitError::CannotCreateGraphicsDevice)    => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoDevice(_))                   => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoAdapter(_))                  => { println!("Test not run: graphics device unavailable"); return; }
             Err(other)      => { panic!("Unexpected error: {:?}", other); }
         };
 
@@ -237,19 +250,20 @@ mod test {
 
         assert!(image.len() == 100*100*4);
 
-        println!("({:x}, {:x}, {:x}, {:x})", image[0], image[1], image[2], image[3]);
+        println!("({:x}, {:x}, {:x}, {:x})", image[99*100*4], image[99*100*4+1], image[99*100*4+2], image[99*100*4+3]);
 
-        assert!(image[0] == 1);
-        assert!(image[1] == 2);
-        assert!(image[2] == 3);
-        assert!(image[3] == 255);
+        let bottom_left = 99*100*4;
+        assert!(image[bottom_left+0] == 1);
+        assert!(image[bottom_left+1] == 2);
+        assert!(image[bottom_left+2] == 3);
+        assert!(image[bottom_left+3] == 255);
 
         for y in 0..100 {
             for x in 0..100 {
                 let pos         = (x + y*100) * 4;
                 let pixel       = (image[pos], image[pos+1], image[pos+2], image[pos+3]);
 
-                let expected    = if x >= y {
+                let expected    = if x + y >= 99 {
                     (1, 2, 3, 255)
                 } else {
                     (128, 129, 130, 255)
@@ -263,4 +277,34 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn realize_is_top_down_with_straight_alpha() {
+        // Initialise offscreen rendering
+        let context         = initialize_offscreen_rendering();
+        let mut context     = match context {
+            Ok(context)     => context,
+            Err(RenderInitError::CannotCreateGraphicsDevice)    => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoDevice(_))                   => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoAdapter(_))                  => { println!("Test not run: graphics device unavailable"); return; }
+            Err(other)      => { panic!("Unexpected error: {:?}", other); }
+        };
+
+        check_realize_layout(&mut context);
+    }
+
+    #[test]
+    fn read_texture_is_in_write_order_with_straight_alpha() {
+        // Initialise offscreen rendering
+        let context         = initialize_offscreen_rendering();
+        let mut context     = match context {
+            Ok(context)     => context,
+            Err(RenderInitError::CannotCreateGraphicsDevice)    => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoDevice(_))                   => { println!("Test not run: graphics device unavailable"); return; }
+            Err(RenderInitError::NoAdapter(_))                  => { println!("Test not run: graphics device unavailable"); return; }
+            Err(other)      => { panic!("Unexpected error: {:?}", other); }
+        };
+
+        check_texture_readback(&mut context);
+    }
 }
@@ -1,4 +1,5 @@
 use super::error::*;
+use super::readback::*;
 use super::offscreen_trait::*;
 
 use crate::action::*;
@@ -18,9 +19,10 @@ static WGPU_BACKGROUND: Lazy<Desync<()>> = Lazy::new(|| Desync::new(()));
 /// A WGPU offscreen render context
 ///
 struct WgpuOffscreenRenderContext {
-    device:     Arc<wgpu::Device>,
-    adapter:    Arc<wgpu::Adapter>,
-    queue:      Arc<wgpu::Queue>,
+    device:             Arc<wgpu::Device>,
+    adapter:            Arc<wgpu::Adapter>,
+    queue:              Arc<wgpu::Queue>,
+    multisample_count:  Option<u32>,
 }
 
 struct WgpuOffscreenRenderTarget {
@@ -40,26 +42,62 @@ struct WgpuOffscreenRenderTarget {
 /// This version is the Metal version for Mac OS X
 ///
 pub async fn wgpu_initialize_offscreen_rendering() -> Result<impl OffscreenRenderContext, RenderInitError> {
+    create_wgpu_offscreen_context(&AdapterOptions::default()).await
+}
+
+///
+/// Performs on-startup initialisation steps for offscreen rendering using the WGPU implementation, choosing the adapter
+/// according to a set of options
+///
+/// This can be used to request a high-performance or a low-power GPU, a particular backend or to disallow the fallback
+/// adapter. If no adapter matches the options, this returns `RenderInitError::NoAdapter`.
+///
+pub async fn wgpu_initialize_offscreen_rendering_with_options(options: AdapterOptions) -> Result<impl OffscreenRenderContext, RenderInitError> {
+    create_wgpu_offscreen_context(&options).await
+}
+
+///
+/// Performs on-startup initialisation steps for offscreen rendering using the WGPU implementation, using a particular number of
+/// samples per pixel to antialias the rendering
+///
+/// See `WgpuRenderer::set_multisample_count()` for details of how the sample count is chosen and how much memory it uses.
+///
+pub async fn wgpu_initialize_offscreen_rendering_with_multisample_count(sample_count: u32) -> Result<impl OffscreenRenderContext, RenderInitError> {
+    let mut context = create_wgpu_offscreen_context(&AdapterOptions::default()).await?;
+    context.multisample_count = Some(sample_count);
+
+    Ok(context)
+}
+
+///
+/// Creates the device and queue used for WGPU offscreen rendering
+///
+async fn create_wgpu_offscreen_context(options: &AdapterOptions) -> Result<WgpuOffscreenRenderContext, RenderInitError> {
     // Create a new WGPU instance and adapter
-    let instance    = wgpu::Instance::new(wgpu::InstanceDescriptor { backends: wgpu::Backends::all(), dx12_shader_compiler: wgpu::Dx12Compiler::default(), ..Default::default() });
-    let adapter     = instance.request_adapter(&wgpu::RequestAdapterOptions {
-        power_preference:       wgpu::PowerPreference::default(),
-        force_fallback_adapter: false,
-        compatible_surface:     None,
-    }).await.unwrap();
+    let backends    = options.backends_or(wgpu::Backends::all());
+    let instance    = wgpu::Instance::new(wgpu::InstanceDescriptor { backends: backends, dx12_shader_compiler: wgpu::Dx12Compiler::default(), ..Default::default() });
+    let adapter     = request_adapter_with_options(&instance, None, options).await?;
+
+    // Request the limits needed for compute shaders if the adapter supports them (filters can use compute shaders when they're available)
+    let limits = options.device_limits(&adapter, default_device_limits(&adapter));
+
+    // Sample counts other than 4 need the adapter-specific texture format features
+    let features = adapter.features() & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
 
     // Fetch the device and the queue
     let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor {
             label:      None,
-            features:   wgpu::Features::empty(),
-            limits:     wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits())
-        }, None).await.unwrap();
+            features:   features,
+            limits:     limits,
+        }, None).await
+        .map_err(|err| no_device_error(&adapter, err))?;
 
     // Result is a WGPU offscreen render context
     Ok(WgpuOffscreenRenderContext {
-        device:     Arc::new(device),
-        adapter:    Arc::new(adapter),
-        queue:      Arc::new(queue),
+        device:             Arc::new(device),
+        adapter:            Arc::new(adapter),
+        queue:              Arc::new(queue),
+        multisample_count:  None,
     })
 }
 
@@ -73,7 +111,25 @@ pub async fn wgpu_initialize_offscreen_rendering() -> Result<impl OffscreenRende
 ///
 #[cfg(not(any(feature="opengl", feature="osx-metal")))]
 pub fn initialize_offscreen_rendering() -> Result<impl OffscreenRenderContext, RenderInitError> {
-    WGPU_BACKGROUND.future_desync(|_| async { wgpu_initialize_offscreen_rendering().await }.boxed()).sync().unwrap()
+    // The initialisation is only cancelled if it panics on the background thread
+    WGPU_BACKGROUND.future_desync(|_| async { wgpu_initialize_offscreen_rendering().await }.boxed()).sync()
+        .unwrap_or_else(|_| Err(RenderInitError::CannotStartGraphicsDriver))
+}
+
+///
+/// Creates the texture that an offscreen render target draws on
+///
+fn create_target_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label:              Some("WgpuOffscreenRenderTarget"),
+        size:               wgpu::Extent3d { width: width, height: height, depth_or_array_layers: 1 },
+        mip_level_count:    1,
+        sample_count:       1,
+        dimension:          wgpu::TextureDimension::D2,
+        format:             wgpu::TextureFormat::Rgba8Unorm,
+        usage:              wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats:       &[wgpu::TextureFormat::Rgba8Unorm],
+    })
 }
 
 impl OffscreenRenderContext for WgpuOffscreenRenderContext {
@@ -84,21 +140,14 @@ impl OffscreenRenderContext for WgpuOffscreenRenderContext {
     ///
     fn create_render_target(&mut self, width: usize, height: usize) -> Self::RenderTarget {
         // Create a texture to render on
-        let target_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-            label:              Some("WgpuOffscreenRenderTarget"),
-            size:               wgpu::Extent3d { width: width as _, height: height as _, depth_or_array_layers: 1 },
-            mip_level_count:    1,
-            sample_count:       1,
-            dimension:          wgpu::TextureDimension::D2,
-            format:             wgpu::TextureFormat::Rgba8Unorm,
-            usage:              wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::RENDER_ATTACHMENT,
-            view_formats:       &[wgpu::TextureFormat::Rgba8Unorm],
-        });
-
-        let target_texture = Arc::new(target_texture);
+        let target_texture = Arc::new(create_target_texture(&self.device, width as _, height as _));
 
         // Create a renderer that will write to this texture
-        let renderer = WgpuRenderer::from_texture(Arc::clone(&self.device), Arc::clone(&self.queue), Arc::clone(&target_texture), Arc::clone(&self.adapter), wgpu::TextureFormat::Rgba8Unorm, (width as _, height as _));
+        let mut renderer = WgpuRenderer::from_texture(Arc::clone(&self.device), Arc::clone(&self.queue), Arc::clone(&target_texture), Arc::clone(&self.adapter), wgpu::TextureFormat::Rgba8Unorm, (width as _, height as _));
+
+        if let Some(sample_count) = self.multisample_count {
+            renderer.set_multisample_count(sample_count);
+        }
 
         // Build the render target
         WgpuOffscreenRenderTarget {
@@ -121,22 +170,79 @@ impl OffscreenRenderTarget for WgpuOffscreenRenderTarget {
     }
 
     ///
-    /// Consumes this render target and returns the realized pixels as a byte array
+    /// Changes the size of this render target
     ///
-    fn realize(self) -> Vec<u8> {
+    fn resize(&mut self, width: usize, height: usize) {
+        let target_texture = Arc::new(create_target_texture(&self.device, width as _, height as _));
+
+        self.renderer.set_target_texture(Arc::clone(&target_texture), (width as _, height as _));
+        self.texture    = target_texture;
+        self.size       = (width as _, height as _);
+    }
+
+    ///
+    /// Returns the pixels that have been rendered so far as a byte array
+    ///
+    fn read_pixels(&mut self) -> Vec<u8> {
+        let mut result = self.copy_texture_to_vec(&self.texture, self.size.0, self.size.1);
+        premultiplied_to_straight_alpha(&mut result);
+
+        result
+    }
+
+    ///
+    /// Reads back the pixels of a texture created by the actions that have been rendered to this target
+    ///
+    fn read_texture(&mut self, texture_id: TextureId) -> Option<Vec<u8>> {
+        let (texture, is_premultiplied) = self.renderer.texture(texture_id)?;
+
+        // Multisampled textures can't be copied to a buffer, and the other formats aren't colour textures
+        let is_bgra = match texture.format() {
+            wgpu::TextureFormat::Rgba8Unorm => false,
+            wgpu::TextureFormat::Bgra8Unorm => true,
+            _                               => { return None; }
+        };
+
+        if texture.sample_count() != 1 {
+            return None;
+        }
+
+        let size        = texture.size();
+        let mut result  = self.copy_texture_to_vec(&texture, size.width, size.height);
+
+        // Render targets use the BGRA format
+        if is_bgra {
+            for pixel in result.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        if is_premultiplied {
+            premultiplied_to_straight_alpha(&mut result);
+        }
+
+        Some(result)
+    }
+}
+
+impl WgpuOffscreenRenderTarget {
+    ///
+    /// Copies the contents of an RGBA texture into a byte array, starting with the first row in the texture
+    ///
+    fn copy_texture_to_vec(&self, texture: &wgpu::Texture, width: u32, height: u32) -> Vec<u8> {
         // Create a buffer to store the result
-        let bytes_per_row   = (((self.size.0 * 4 - 1) / 256) + 1) * 256;
+        let bytes_per_row   = (((width * 4 - 1) / 256) + 1) * 256;
         let buffer          = self.device.create_buffer(&wgpu::BufferDescriptor {
-            label:              Some("WgpuOffscreenRenderTarget::realize"),
-            size:               (bytes_per_row as u64) * (self.size.1 as u64),
+            label:              Some("WgpuOffscreenRenderTarget::copy_texture_to_vec"),
+            size:               (bytes_per_row as u64) * (height as u64),
             usage:              wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
             mapped_at_creation: false,
         });
 
         // Copy the texture to the buffer
-        let mut encoder     = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("WgpuOffscreenRenderTarget::realize") });
+        let mut encoder     = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("WgpuOffscreenRenderTarget::copy_texture_to_vec") });
         let buffer_copy     = wgpu::ImageCopyBuffer { buffer: &buffer, layout: wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(bytes_per_row), rows_per_image: None } };
-        encoder.copy_texture_to_buffer(self.texture.as_image_copy(), buffer_copy, wgpu::Extent3d { width: self.size.0, height: self.size.1, depth_or_array_layers: 1 });
+        encoder.copy_texture_to_buffer(texture.as_image_copy(), buffer_copy, wgpu::Extent3d { width: width, height: height, depth_or_array_layers: 1 });
         self.queue.submit(Some(encoder.finish()));
 
         // Take the whole buffer as a slice
@@ -155,16 +261,16 @@ impl OffscreenRenderTarget for WgpuOffscreenRenderTarget {
         }
 
         // Prepare to write the buffer
-        let mut result      = vec![0; (self.size.0 * self.size.1 * 4) as usize];
+        let mut result      = vec![0; (width * height * 4) as usize];
 
         // Poll for the result
         let mapped_buffer   = buffer_slice.get_mapped_range();
 
-        // Copy to a Vec<u8>
-        let row_len = (self.size.0 * 4) as usize;
-        for row in 0..self.size.1 {
+        // Copy to a Vec<u8>, keeping the rows in the order they're stored in the texture (for the render target, that's starting with the top row)
+        let row_len = (width * 4) as usize;
+        for row in 0..height {
             let buffer_row_start    = (row * bytes_per_row) as usize;
-            let row_start           = ((self.size.1 - 1 - row) * self.size.0 * 4) as usize;
+            let row_start           = (row * width * 4) as usize;
 
             result[row_start..(row_start+row_len)].copy_from_slice(&mapped_buffer[buffer_row_start..(buffer_row_start+row_len)]);
         }
@@ -172,3 +278,663 @@ impl OffscreenRenderTarget for WgpuOffscreenRenderTarget {
         result
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::buffer::*;
+    use crate::offscreen::conformance::*;
+
+    ///
+    /// Renders a texture with a large gaussian blur applied to it
+    ///
+    fn render_blurred_texture(context: &mut WgpuOffscreenRenderContext, use_compute: bool) -> Vec<u8> {
+        use self::RenderAction::*;
+
+        // Texture with some sharp edges in it
+        let texture_data = (0..64).flat_map(|y| (0..64).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                if (x/16 + y/16) % 2 == 0 {
+                    vec![255, (x*4) as u8, (y*4) as u8, 255]
+                } else {
+                    vec![0, 0, 0, 0]
+                }
+            })
+            .collect::<Vec<u8>>();
+
+        // Render the blurred texture over the whole target
+        let white               = [255, 255, 255, 255];
+        let mut render_target   = context.create_render_target(64, 64);
+        render_target.renderer.set_compute_blur(use_compute);
+
+        render_target.render(vec![
+            CreateTextureBgra(TextureId(0), Size2D(64, 64)),
+            WriteTextureData(TextureId(0), Position2D(0, 0), Position2D(64, 64), Arc::new(texture_data)),
+            FilterTexture(TextureId(0), vec![TextureFilter::GaussianBlurHorizontal(10.0, 1.0, 80), TextureFilter::GaussianBlurVertical(10.0, 1.0, 80)]),
+
+            Clear(Rgba8([0, 0, 0, 255])),
+            UseShader(ShaderType::Texture { 
+                texture:            TextureId(0), 
+                texture_transform:  Matrix([[0.5, 0.0, 0.0, 0.5], [0.0, 0.5, 0.0, 0.5], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]]), 
+                wrap_mode:          TextureWrapMode::Clamp, 
+                alpha:              1.0, 
+                clip_texture:       None,
+            }),
+            CreateVertex2DBuffer(VertexBufferId(0), vec![
+                Vertex2D { pos: [-1.0, -1.0],   tex_coord: [0.0, 0.0], color: white },
+                Vertex2D { pos: [1.0, -1.0],    tex_coord: [0.0, 0.0], color: white },
+                Vertex2D { pos: [1.0, 1.0],     tex_coord: [0.0, 0.0], color: white },
+
+                Vertex2D { pos: [-1.0, -1.0],   tex_coord: [0.0, 0.0], color: white },
+                Vertex2D { pos: [-1.0, 1.0],    tex_coord: [0.0, 0.0], color: white },
+                Vertex2D { pos: [1.0, 1.0],     tex_coord: [0.0, 0.0], color: white },
+            ]),
+            DrawTriangles(VertexBufferId(0), 0..6),
+        ]);
+
+        render_target.realize()
+    }
+
+    #[test]
+    fn compute_blur_matches_fragment_blur() {
+        let mut context = match WGPU_BACKGROUND.future_desync(|_| async { create_wgpu_offscreen_context(&AdapterOptions::default()).await }.boxed()).sync() {
+            Ok(Ok(context)) => context,
+            _               => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        // The comparison can only be made if the device supports compute shaders
+        let compute_target = context.create_render_target(1, 1);
+        if !compute_target.renderer.compute_blur_enabled() {
+            println!("Test not run: compute shaders are not supported");
+            return;
+        }
+
+        let fragment_image  = render_blurred_texture(&mut context, false);
+        let compute_image   = render_blurred_texture(&mut context, true);
+
+        assert!(fragment_image.len() == compute_image.len());
+
+        // Results might not be exactly the same due to rounding errors
+        let max_difference = fragment_image.iter().zip(compute_image.iter())
+            .map(|(fragment, compute)| (*fragment as i32 - *compute as i32).abs())
+            .max()
+            .unwrap_or(0);
+
+        assert!(max_difference <= 3, "Compute blur differs from fragment blur by {}", max_difference);
+    }
+
+    #[test]
+    fn mid_grey_fill_is_not_gamma_corrected() {
+        use self::RenderAction::*;
+
+        let mut context = match WGPU_BACKGROUND.future_desync(|_| async { create_wgpu_offscreen_context(&AdapterOptions::default()).await }.boxed()).sync() {
+            Ok(Ok(context)) => context,
+            _               => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        // Fill an offscreen render target with 50% grey and then draw it to the frame buffer
+        let grey                = [128, 128, 128, 255];
+        let mut render_target   = context.create_render_target(16, 16);
+        render_target.render(vec![
+            CreateRenderTarget(RenderTargetId(0), TextureId(0), Size2D(16, 16), RenderTargetType::Standard),
+            SelectRenderTarget(RenderTargetId(0)),
+            Clear(Rgba8([0, 0, 0, 255])),
+            UseShader(ShaderType::Simple { clip_texture: None }),
+            BlendMode(crate::action::BlendMode::SourceOver),
+            CreateVertex2DBuffer(VertexBufferId(0), vec![
+                Vertex2D { pos: [-1.0, -1.0],   tex_coord: [0.0, 0.0], color: grey },
+                Vertex2D { pos: [1.0, -1.0],    tex_coord: [0.0, 0.0], color: grey },
+                Vertex2D { pos: [1.0, 1.0],     tex_coord: [0.0, 0.0], color: grey },
+
+                Vertex2D { pos: [-1.0, -1.0],   tex_coord: [0.0, 0.0], color: grey },
+                Vertex2D { pos: [-1.0, 1.0],    tex_coord: [0.0, 0.0], color: grey },
+                Vertex2D { pos: [1.0, 1.0],     tex_coord: [0.0, 0.0], color: grey },
+            ]),
+            DrawTriangles(VertexBufferId(0), 0..6),
+
+            RenderToFrameBuffer,
+            Clear(Rgba8([0, 0, 0, 255])),
+            DrawFrameBuffer(RenderTargetId(0), FrameBufferRegion::default(), Alpha(1.0)),
+        ]);
+
+        // Colours are sRGB values that pass through the pipeline unchanged, so the result should be the same grey
+        let image = render_target.realize();
+
+        for pixel in image.chunks_exact(4) {
+            assert!(pixel[0..3].iter().all(|component| (*component as i32 - 128).abs() <= 1), "{:?}", pixel);
+            assert!(pixel[3] == 255);
+        }
+    }
+
+    ///
+    /// Draws texture 1 (set up by `create_texture`) over the whole of a white 16x16 render target and returns the result
+    ///
+    fn draw_texture_over_white(create_texture: Vec<RenderAction>, texture_transform: Matrix) -> Option<Vec<u8>> {
+        draw_wrapped_texture_over_white(create_texture, texture_transform, TextureWrapMode::Clamp)
+    }
+
+    ///
+    /// Draws texture 1 (set up by `create_texture`) over the whole of a white 16x16 render target using the specified wrap mode and returns the result
+    ///
+    fn draw_wrapped_texture_over_white(create_texture: Vec<RenderAction>, texture_transform: Matrix, wrap_mode: TextureWrapMode) -> Option<Vec<u8>> {
+        use self::RenderAction::*;
+
+        let mut context = match WGPU_BACKGROUND.future_desync(|_| async { create_wgpu_offscreen_context(&AdapterOptions::default()).await }.boxed()).sync() {
+            Ok(Ok(context)) => context,
+            _               => { return None; }
+        };
+
+        let white               = [255, 255, 255, 255];
+        let mut render_target   = context.create_render_target(16, 16);
+        render_target.render(create_texture);
+        render_target.render(vec![
+            CreateRenderTarget(RenderTargetId(0), TextureId(0), Size2D(16, 16), RenderTargetType::Standard),
+            SelectRenderTarget(RenderTargetId(0)),
+            Clear(Rgba8([255, 255, 255, 255])),
+            UseShader(ShaderType::Texture { texture: TextureId(1), texture_transform, wrap_mode, alpha: 1.0, clip_texture: None }),
+            BlendMode(crate::action::BlendMode::SourceOver),
+            CreateVertex2DBuffer(VertexBufferId(0), vec![
+                Vertex2D { pos: [-1.0, -1.0],   tex_coord: [0.0, 0.0], color: white },
+                Vertex2D { pos: [1.0, -1.0],    tex_coord: [0.0, 0.0], color: white },
+                Vertex2D { pos: [1.0, 1.0],     tex_coord: [0.0, 0.0], color: white },
+
+                Vertex2D { pos: [-1.0, -1.0],   tex_coord: [0.0, 0.0], color: white },
+                Vertex2D { pos: [-1.0, 1.0],    tex_coord: [0.0, 0.0], color: white },
+                Vertex2D { pos: [1.0, 1.0],     tex_coord: [0.0, 0.0], color: white },
+            ]),
+            DrawTriangles(VertexBufferId(0), 0..6),
+
+            RenderToFrameBuffer,
+            Clear(Rgba8([0, 0, 0, 255])),
+            DrawFrameBuffer(RenderTargetId(0), FrameBufferRegion::default(), Alpha(1.0)),
+        ]);
+
+        Some(render_target.realize())
+    }
+
+    #[test]
+    fn straight_alpha_texture_composites_over_white() {
+        use self::RenderAction::*;
+
+        // A 1x1 texture containing a single 50% alpha red pixel, with straight alpha
+        let image = draw_texture_over_white(vec![
+            CreateTextureBgra(TextureId(1), Size2D(1, 1)),
+            WriteTextureData(TextureId(1), Position2D(0, 0), Position2D(1, 1), Arc::new(vec![255, 0, 0, 128])),
+        ], Matrix::identity());
+        let image = if let Some(image) = image { image } else { println!("Test not run: graphics device unavailable"); return; };
+
+        // 50% red over white is (255, 127.5, 127.5): the texture color must be multiplied by its alpha exactly once
+        for pixel in image.chunks_exact(4) {
+            assert!(pixel[0] >= 254, "{:?}", pixel);
+            assert!((pixel[1] as i32 - 127).abs() <= 2, "{:?}", pixel);
+            assert!((pixel[2] as i32 - 127).abs() <= 2, "{:?}", pixel);
+            assert!(pixel[3] == 255);
+        }
+    }
+
+    #[test]
+    fn premultiplied_texture_composites_over_white() {
+        use self::RenderAction::*;
+
+        // The same 50% alpha red pixel, with the color already multiplied by the alpha
+        let image = draw_texture_over_white(vec![
+            CreateTextureBgraPremultiplied(TextureId(1), Size2D(1, 1)),
+            WriteTextureData(TextureId(1), Position2D(0, 0), Position2D(1, 1), Arc::new(vec![128, 0, 0, 128])),
+        ], Matrix::identity());
+        let image = if let Some(image) = image { image } else { println!("Test not run: graphics device unavailable"); return; };
+
+        // The texture color must not be multiplied by its alpha a second time
+        for pixel in image.chunks_exact(4) {
+            assert!(pixel[0] >= 254, "{:?}", pixel);
+            assert!((pixel[1] as i32 - 127).abs() <= 2, "{:?}", pixel);
+            assert!((pixel[2] as i32 - 127).abs() <= 2, "{:?}", pixel);
+            assert!(pixel[3] == 255);
+        }
+    }
+
+    #[test]
+    fn premultiplied_texture_edge_has_no_dark_fringe() {
+        use self::RenderAction::*;
+
+        // Opaque red next to a transparent pixel, stretched across the render target so that the edge between them is filtered
+        let stretch = Matrix([
+            [0.5, 0.0, 0.0, 0.5],
+            [0.0, 0.5, 0.0, 0.5],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let image = draw_texture_over_white(vec![
+            CreateTextureBgraPremultiplied(TextureId(1), Size2D(2, 1)),
+            WriteTextureData(TextureId(1), Position2D(0, 0), Position2D(2, 1), Arc::new(vec![255, 0, 0, 255, 0, 0, 0, 0])),
+        ], stretch);
+        let image = if let Some(image) = image { image } else { println!("Test not run: graphics device unavailable"); return; };
+
+        // Every pixel is a mix of red and white, so the red channel stays at full intensity (a dark fringe would reduce it)
+        for pixel in image.chunks_exact(4) {
+            assert!(pixel[0] >= 254, "{:?}", pixel);
+            assert!((pixel[1] as i32 - pixel[2] as i32).abs() <= 1, "{:?}", pixel);
+        }
+
+        // Both ends of the gradient are drawn
+        assert!(image.chunks_exact(4).any(|pixel| pixel[1] < 8));
+        assert!(image.chunks_exact(4).any(|pixel| pixel[1] > 248));
+    }
+
+    ///
+    /// Draws a red and blue 2x1 texture repeated twice across a 16x16 render target, returning the middle row of the result
+    ///
+    fn draw_texture_twice_across(wrap_mode: TextureWrapMode) -> Option<Vec<[u8; 4]>> {
+        use self::RenderAction::*;
+
+        // The texture coordinates run from 0 to 2 across the render target
+        let twice_across = Matrix([
+            [1.0, 0.0, 0.0, 1.0],
+            [0.0, 0.5, 0.0, 0.5],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let image = draw_wrapped_texture_over_white(vec![
+            CreateTextureBgra(TextureId(1), Size2D(2, 1)),
+            WriteTextureData(TextureId(1), Position2D(0, 0), Position2D(2, 1), Arc::new(vec![255, 0, 0, 255, 0, 0, 255, 255])),
+        ], twice_across, wrap_mode)?;
+
+        Some(image[8*16*4..9*16*4].chunks_exact(4).map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]]).collect())
+    }
+
+    #[test]
+    fn repeated_texture_fill_tiles_the_texture() {
+        let row = draw_texture_twice_across(TextureWrapMode::Repeat);
+        let row = if let Some(row) = row { row } else { println!("Test not run: graphics device unavailable"); return; };
+
+        // Red, then blue, then red and blue again
+        for red_pixel in [1, 2, 9, 10] {
+            assert!(row[red_pixel][0] > 200 && row[red_pixel][2] < 60, "{}: {:?}", red_pixel, row);
+        }
+        for blue_pixel in [5, 6, 13, 14] {
+            assert!(row[blue_pixel][2] > 200 && row[blue_pixel][0] < 60, "{}: {:?}", blue_pixel, row);
+        }
+    }
+
+    #[test]
+    fn mirrored_texture_fill_reflects_the_texture() {
+        let row = draw_texture_twice_across(TextureWrapMode::MirrorRepeat);
+        let row = if let Some(row) = row { row } else { println!("Test not run: graphics device unavailable"); return; };
+
+        // Red and blue, then reflected as blue and red
+        for red_pixel in [1, 2, 13, 14] {
+            assert!(row[red_pixel][0] > 200 && row[red_pixel][2] < 60, "{}: {:?}", red_pixel, row);
+        }
+        for blue_pixel in [5, 6, 9, 10] {
+            assert!(row[blue_pixel][2] > 200 && row[blue_pixel][0] < 60, "{}: {:?}", blue_pixel, row);
+        }
+    }
+
+    #[test]
+    fn clamped_texture_fill_extends_the_edge() {
+        let row = draw_texture_twice_across(TextureWrapMode::Clamp);
+        let row = if let Some(row) = row { row } else { println!("Test not run: graphics device unavailable"); return; };
+
+        // Red and blue, then the blue edge extends to the end of the row
+        for red_pixel in [1, 2] {
+            assert!(row[red_pixel][0] > 200 && row[red_pixel][2] < 60, "{}: {:?}", red_pixel, row);
+        }
+        for blue_pixel in [5, 6, 9, 10, 13, 14, 15] {
+            assert!(row[blue_pixel][2] > 200 && row[blue_pixel][0] < 60, "{}: {:?}", blue_pixel, row);
+        }
+    }
+
+    #[test]
+    fn resized_render_target_keeps_resources() {
+        use self::RenderAction::*;
+
+        let mut context = match WGPU_BACKGROUND.future_desync(|_| async { create_wgpu_offscreen_context(&AdapterOptions::default()).await }.boxed()).sync() {
+            Ok(Ok(context)) => context,
+            _               => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        // Upload a texture and a vertex buffer covering the whole render target before resizing it
+        let white               = [255, 255, 255, 255];
+        let mut render_target   = context.create_render_target(16, 16);
+        render_target.render(vec![
+            CreateTextureBgra(TextureId(1), Size2D(1, 1)),
+            WriteTextureData(TextureId(1), Position2D(0, 0), Position2D(1, 1), Arc::new(vec![0, 0, 255, 255])),
+            CreateVertex2DBuffer(VertexBufferId(0), vec![
+                Vertex2D { pos: [-1.0, -1.0],   tex_coord: [0.0, 0.0], color: white },
+                Vertex2D { pos: [1.0, -1.0],    tex_coord: [0.0, 0.0], color: white },
+                Vertex2D { pos: [1.0, 1.0],     tex_coord: [0.0, 0.0], color: white },
+
+                Vertex2D { pos: [-1.0, -1.0],   tex_coord: [0.0, 0.0], color: white },
+                Vertex2D { pos: [-1.0, 1.0],    tex_coord: [0.0, 0.0], color: white },
+                Vertex2D { pos: [1.0, 1.0],     tex_coord: [0.0, 0.0], color: white },
+            ]),
+        ]);
+
+        // Shrink and grow the render target a few times
+        for (width, height) in [(8, 8), (64, 32), (4, 4), (32, 16)] {
+            render_target.resize(width, height);
+        }
+
+        // Draw using the resources that were sent before the resize
+        render_target.render(vec![
+            RenderToFrameBuffer,
+            Clear(Rgba8([0, 0, 0, 255])),
+            UseShader(ShaderType::Texture { texture: TextureId(1), texture_transform: Matrix::identity(), wrap_mode: TextureWrapMode::Clamp, alpha: 1.0, clip_texture: None }),
+            BlendMode(crate::action::BlendMode::SourceOver),
+            DrawTriangles(VertexBufferId(0), 0..6),
+        ]);
+
+        // The result should be the new size, and filled with the texture color
+        let image = render_target.realize();
+        assert!(image.len() == 32 * 16 * 4);
+
+        for pixel in image.chunks_exact(4) {
+            assert!(pixel[0] == 0 && pixel[1] == 0 && pixel[2] >= 254 && pixel[3] == 255, "{:?}", pixel);
+        }
+    }
+
+    #[test]
+    fn multisample_count_falls_back_to_supported_count() {
+        let mut context = match WGPU_BACKGROUND.future_desync(|_| async { create_wgpu_offscreen_context(&AdapterOptions::default()).await }.boxed()).sync() {
+            Ok(Ok(context)) => context,
+            _               => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        let mut render_target = context.create_render_target(1, 1);
+
+        // Every device supports 4 samples, and unsupported counts are rounded to the nearest supported count
+        assert!(render_target.renderer.multisample_count() == 4);
+        assert!(render_target.renderer.set_multisample_count(4) == 4);
+        assert!([2, 4].contains(&render_target.renderer.set_multisample_count(3)));
+        assert!([2, 4, 8, 16].contains(&render_target.renderer.set_multisample_count(100)));
+        assert!([2, 4, 8, 16].contains(&render_target.renderer.set_multisample_count(0)));
+    }
+
+    #[test]
+    fn prewarmed_pipelines_are_compiled() {
+        let mut context = match WGPU_BACKGROUND.future_desync(|_| async { create_wgpu_offscreen_context(&AdapterOptions::default()).await }.boxed()).sync() {
+            Ok(Ok(context)) => context,
+            _               => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        let mut render_target   = context.create_render_target(1, 1);
+        let config              = PipelineConfiguration::simple(wgpu::TextureFormat::Rgba8Unorm, BlendMode::AllChannelAlphaSourceOver, false, Some(4));
+
+        assert!(PipelineConfiguration::common_configurations(wgpu::TextureFormat::Rgba8Unorm, Some(4)).contains(&config));
+        assert!(!render_target.renderer.compiled_pipelines().contains(&config));
+
+        // Pipelines are compiled in the background, so wait for the configuration to show up
+        render_target.renderer.prewarm_pipelines(vec![config.clone()]);
+
+        let mut compiled = false;
+        for _ in 0..500 {
+            if render_target.renderer.compiled_pipelines().contains(&config) {
+                compiled = true;
+                break;
+            }
+
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        assert!(compiled);
+    }
+
+    #[test]
+    fn overlapping_stencil_clips_intersect() {
+        use self::RenderAction::*;
+
+        let mut context = match WGPU_BACKGROUND.future_desync(|_| async { create_wgpu_offscreen_context(&AdapterOptions::default()).await }.boxed()).sync() {
+            Ok(Ok(context)) => context,
+            _               => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        // A rectangle covering the whole height of the render target between two x coordinates
+        let white   = [255, 255, 255, 255];
+        let rect    = |min_x: f32, max_x: f32| vec![
+            Vertex2D { pos: [min_x, -1.0],  tex_coord: [0.0, 0.0], color: white },
+            Vertex2D { pos: [max_x, -1.0],  tex_coord: [0.0, 0.0], color: white },
+            Vertex2D { pos: [max_x, 1.0],   tex_coord: [0.0, 0.0], color: white },
+
+            Vertex2D { pos: [min_x, -1.0],  tex_coord: [0.0, 0.0], color: white },
+            Vertex2D { pos: [min_x, 1.0],   tex_coord: [0.0, 0.0], color: white },
+            Vertex2D { pos: [max_x, 1.0],   tex_coord: [0.0, 0.0], color: white },
+        ];
+
+        // Two overlapping clip regions are added to the stencil buffer, then the whole render target is filled where both of them overlap
+        let mut render_target = context.create_render_target(64, 64);
+        render_target.render(vec![
+            CreateRenderTarget(RenderTargetId(0), TextureId(0), Size2D(64, 64), RenderTargetType::Standard),
+            SelectRenderTarget(RenderTargetId(0)),
+            Clear(Rgba8([0, 0, 0, 255])),
+            UseShader(ShaderType::Simple { clip_texture: None }),
+            BlendMode(crate::action::BlendMode::SourceOver),
+
+            CreateVertex2DBuffer(VertexBufferId(0), rect(-1.0, 0.5)),
+            CreateVertex2DBuffer(VertexBufferId(1), rect(-0.5, 1.0)),
+            CreateVertex2DBuffer(VertexBufferId(2), rect(-1.0, 1.0)),
+
+            StencilMode(crate::action::StencilMode::Increment),
+            DrawTriangles(VertexBufferId(0), 0..6),
+            DrawTriangles(VertexBufferId(1), 0..6),
+            StencilMode(crate::action::StencilMode::Equal(2)),
+            DrawTriangles(VertexBufferId(2), 0..6),
+            StencilMode(crate::action::StencilMode::Disabled),
+
+            RenderToFrameBuffer,
+            Clear(Rgba8([0, 0, 0, 255])),
+            DrawFrameBuffer(RenderTargetId(0), FrameBufferRegion::default(), Alpha(1.0)),
+        ]);
+
+        let image = render_target.realize();
+        let pixel = |x: usize, y: usize| image[(y*64 + x)*4];
+
+        // Only the middle of the render target is inside both clip regions
+        assert!(pixel(8, 32) == 0, "Left edge is {}", pixel(8, 32));
+        assert!(pixel(32, 32) == 255, "Middle is {}", pixel(32, 32));
+        assert!(pixel(56, 32) == 0, "Right edge is {}", pixel(56, 32));
+    }
+
+    #[test]
+    fn many_transforms_per_frame_reuse_matrix_buffers() {
+        use self::RenderAction::*;
+
+        let mut context = match WGPU_BACKGROUND.future_desync(|_| async { create_wgpu_offscreen_context(&AdapterOptions::default()).await }.boxed()).sync() {
+            Ok(Ok(context)) => context,
+            _               => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        // A small square around the origin, which is moved around the left half of the render target by changing the transform
+        let white   = [255, 255, 255, 255];
+        let square  = vec![
+            Vertex2D { pos: [-0.05, -0.05], tex_coord: [0.0, 0.0], color: white },
+            Vertex2D { pos: [0.05, -0.05],  tex_coord: [0.0, 0.0], color: white },
+            Vertex2D { pos: [0.05, 0.05],   tex_coord: [0.0, 0.0], color: white },
+
+            Vertex2D { pos: [-0.05, -0.05], tex_coord: [0.0, 0.0], color: white },
+            Vertex2D { pos: [-0.05, 0.05],  tex_coord: [0.0, 0.0], color: white },
+            Vertex2D { pos: [0.05, 0.05],   tex_coord: [0.0, 0.0], color: white },
+        ];
+
+        let mut render_target = context.create_render_target(64, 64);
+        render_target.render(vec![CreateVertex2DBuffer(VertexBufferId(0), square)]);
+
+        // Render several frames, each with 1000 transform and draw pairs
+        let mut buffer_counts   = vec![];
+        let start_time          = std::time::Instant::now();
+
+        for _frame in 0..5 {
+            let mut actions = vec![
+                Clear(Rgba8([0, 0, 0, 255])),
+                UseShader(ShaderType::Simple { clip_texture: None }),
+                BlendMode(crate::action::BlendMode::SourceOver),
+            ];
+
+            for idx in 0..1000 {
+                let x = -0.9 + 0.8 * ((idx % 100) as f32) / 99.0;
+
+                actions.push(SetTransform(Matrix([[1.0, 0.0, 0.0, x], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]])));
+                actions.push(DrawTriangles(VertexBufferId(0), 0..6));
+            }
+
+            render_target.render(actions);
+            buffer_counts.push(render_target.renderer.matrix_buffer_count());
+        }
+
+        println!("5 frames of 1000 transforms rendered in {:?}", start_time.elapsed());
+
+        // The matrix buffers allocated for the first frame are re-used for the later frames
+        assert!(buffer_counts.iter().all(|count| *count == buffer_counts[0]), "Matrix buffer counts: {:?}", buffer_counts);
+        assert!(buffer_counts[0] <= 2, "Matrix buffer counts: {:?}", buffer_counts);
+
+        // Each square is drawn with its own transform, covering the left half of the target but not the right half
+        let image = render_target.realize();
+        let pixel = |x: usize, y: usize| image[(y*64 + x)*4];
+
+        assert!(pixel(4, 32) == 255, "Left edge is {}", pixel(4, 32));
+        assert!(pixel(16, 32) == 255, "Left middle is {}", pixel(16, 32));
+        assert!(pixel(28, 32) == 255, "Centre is {}", pixel(28, 32));
+        assert!(pixel(44, 32) == 0, "Right middle is {}", pixel(44, 32));
+        assert!(pixel(16, 8) == 0, "Top is {}", pixel(16, 8));
+    }
+
+    #[test]
+    fn default_limits_allow_compute_shaders_when_supported() {
+        let context = match WGPU_BACKGROUND.future_desync(|_| async { create_wgpu_offscreen_context(&AdapterOptions::default()).await }.boxed()).sync() {
+            Ok(Ok(context)) => context,
+            _               => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        let limits = default_device_limits(&context.adapter);
+
+        if context.adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS) {
+            assert!(limits.max_compute_invocations_per_workgroup > 0);
+            assert!(context.device.limits().max_compute_invocations_per_workgroup >= limits.max_compute_invocations_per_workgroup);
+        } else {
+            assert!(limits.max_compute_invocations_per_workgroup == 0);
+        }
+    }
+
+    #[test]
+    fn adapter_native_limits_allow_large_textures() {
+        use self::RenderAction::*;
+
+        let options     = AdapterOptions { limits: DeviceLimits::AdapterNative, ..AdapterOptions::default() };
+        let mut context = match WGPU_BACKGROUND.future_desync(move |_| async move { create_wgpu_offscreen_context(&options).await }.boxed()).sync() {
+            Ok(Ok(context)) => context,
+            _               => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        // The device should get the adapter's limits rather than the WebGL2 limits
+        let webgl2_max_size = wgpu::Limits::downlevel_webgl2_defaults().max_texture_dimension_2d;
+        let adapter_limits  = context.adapter.limits();
+        assert!(context.device.limits().max_texture_dimension_2d == adapter_limits.max_texture_dimension_2d);
+
+        if adapter_limits.max_texture_dimension_2d <= webgl2_max_size {
+            println!("Test not run: adapter does not support textures larger than the WebGL2 limit");
+            return;
+        }
+
+        // Render to a target that's wider than WebGL2 allows
+        let width               = webgl2_max_size as usize + 64;
+        let mut render_target   = context.create_render_target(width, 4);
+        render_target.render(vec![Clear(Rgba8([255, 0, 0, 255]))]);
+
+        let pixels = render_target.realize();
+        assert!(pixels.len() == width * 4 * 4);
+        assert!(pixels[(width-1)*4..width*4] == [255, 0, 0, 255]);
+    }
+
+    ///
+    /// Renders some actions to a 4x4 render target and reads back a texture, returning None if there's no graphics device
+    ///
+    fn read_texture_after(actions: Vec<RenderAction>, texture_id: TextureId) -> Option<Option<Vec<u8>>> {
+        let mut context = match WGPU_BACKGROUND.future_desync(|_| async { create_wgpu_offscreen_context(&AdapterOptions::default()).await }.boxed()).sync() {
+            Ok(Ok(context)) => context,
+            _               => { return None; }
+        };
+
+        let mut render_target = context.create_render_target(4, 4);
+        render_target.render(actions);
+
+        Some(render_target.read_texture(texture_id))
+    }
+
+    #[test]
+    fn straight_alpha_texture_reads_back_exactly() {
+        use self::RenderAction::*;
+
+        // 3x2 texture, so the rows need to be unpacked from the padded buffer
+        let pixels = vec![
+            255, 0, 0, 255,     0, 255, 0, 128,     0, 0, 255, 1,
+            10, 20, 30, 40,     200, 100, 50, 0,    1, 2, 3, 255,
+        ];
+
+        let texture = match read_texture_after(vec![
+            CreateTextureBgra(TextureId(1), Size2D(3, 2)),
+            WriteTextureData(TextureId(1), Position2D(0, 0), Position2D(3, 2), Arc::new(pixels.clone())),
+        ], TextureId(1)) {
+            Some(texture)   => texture,
+            None            => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        assert!(texture == Some(pixels), "{:?}", texture);
+    }
+
+    #[test]
+    fn premultiplied_texture_reads_back_as_straight_alpha() {
+        use self::RenderAction::*;
+
+        let texture = match read_texture_after(vec![
+            CreateTextureBgraPremultiplied(TextureId(1), Size2D(2, 2)),
+            WriteTextureData(TextureId(1), Position2D(0, 0), Position2D(2, 2), Arc::new(vec![
+                255, 0, 0, 255,     64, 32, 0, 128,
+                0, 0, 0, 0,         10, 20, 30, 40,
+            ])),
+        ], TextureId(1)) {
+            Some(texture)   => texture,
+            None            => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        assert!(texture == Some(vec![
+            255, 0, 0, 255,     128, 64, 0, 128,
+            0, 0, 0, 0,         64, 128, 191, 40,
+        ]), "{:?}", texture);
+    }
+
+    #[test]
+    fn render_target_texture_reads_back() {
+        use self::RenderAction::*;
+
+        let texture = match read_texture_after(vec![
+            CreateRenderTarget(RenderTargetId(0), TextureId(2), Size2D(2, 2), RenderTargetType::Standard),
+            SelectRenderTarget(RenderTargetId(0)),
+            Clear(Rgba8([255, 128, 0, 255])),
+            RenderToFrameBuffer,
+        ], TextureId(2)) {
+            Some(texture)   => texture,
+            None            => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        assert!(texture == Some([255, 128, 0, 255].repeat(4)), "{:?}", texture);
+    }
+
+    #[test]
+    fn missing_texture_reads_back_as_none() {
+        let texture = match read_texture_after(vec![], TextureId(3)) {
+            Some(texture)   => texture,
+            None            => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        assert!(texture.is_none());
+    }
+
+    #[test]
+    fn wgpu_readback_matches_shared_layout() {
+        let mut context = match WGPU_BACKGROUND.future_desync(|_| async { create_wgpu_offscreen_context(&AdapterOptions::default()).await }.boxed()).sync() {
+            Ok(Ok(context)) => context,
+            _               => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        check_realize_layout(&mut context);
+        check_texture_readback(&mut context);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::offscreen::*;
+
+use wgpu;
+
+use std::env;
+
+///
+/// The environment variable that forces wgpu's fallback (software) adapter to be used instead of a hardware adapter
+///
+const FORCE_FALLBACK_ADAPTER: &str = "FLO_FORCE_FALLBACK_ADAPTER";
+
+///
+/// True if the environment requests that the fallback adapter is always used
+///
+fn fallback_adapter_forced() -> bool {
+    match env::var(FORCE_FALLBACK_ADAPTER) {
+        Ok(value)   => !value.is_empty() && value != "0",
+        Err(_)      => false,
+    }
+}
+
+///
+/// The limits to request when creating a device
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceLimits {
+    /// The limits the renderer normally uses: see `default_device_limits()`
+    Default,
+
+    /// The limits that are guaranteed to work with WebGL2 (including its maximum texture size of 2048 pixels)
+    WebGl2Compatible,
+
+    /// The limits supported by the adapter, which allows the largest textures and buffers the hardware can support
+    AdapterNative,
+}
+
+///
+/// Options that control which adapter (GPU) is used for rendering
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdapterOptions {
+    /// Whether to prefer a high-performance or a low-power GPU when there's more than one available
+    pub power_preference: wgpu::PowerPreference,
+
+    /// The backends (Vulkan, DX12, Metal, GL, etc) to choose from, or None to use the default backends (which can be set with the `WGPU_BACKEND` environment variable)
+    pub backends: Option<wgpu::Backends>,
+
+    /// True if the fallback (software) adapter can be used when no hardware adapter is available
+    pub allow_fallback_adapter: bool,
+
+    /// The limits to request when creating the device
+    pub limits: DeviceLimits,
+}
+
+impl Default for AdapterOptions {
+    fn default() -> AdapterOptions {
+        AdapterOptions {
+            power_preference:       wgpu::PowerPreference::default(),
+            backends:               None,
+            allow_fallback_adapter: true,
+            limits:                 DeviceLimits::Default,
+        }
+    }
+}
+
+impl AdapterOptions {
+    ///
+    /// Returns the backends to create the wgpu instance with, using the `WGPU_BACKEND` environment variable or the specified
+    /// default backends if none were set in the options
+    ///
+    pub fn backends_or(&self, default_backends: wgpu::Backends) -> wgpu::Backends {
+        self.backends
+            .or_else(|| wgpu::util::backend_bits_from_env())
+            .unwrap_or(default_backends)
+    }
+
+    ///
+    /// Returns the limits to request when creating a device on an adapter, given the limits that would be used for `DeviceLimits::Default`
+    ///
+    pub fn device_limits(&self, adapter: &wgpu::Adapter, default_limits: wgpu::Limits) -> wgpu::Limits {
+        match self.limits {
+            DeviceLimits::Default           => default_limits,
+            DeviceLimits::WebGl2Compatible  => wgpu::Limits::downlevel_webgl2_defaults(),
+            DeviceLimits::AdapterNative     => adapter.limits(),
+        }
+    }
+}
+
+///
+/// Returns the limits that the renderer requests by default for a device created on an adapter
+///
+/// These are the downlevel limits raised to the adapter's maximum texture sizes. Adapters that support compute shaders get the limits
+/// that allow the filters to use them, and other adapters get the WebGL2 limits.
+///
+pub fn default_device_limits(adapter: &wgpu::Adapter) -> wgpu::Limits {
+    if adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS) {
+        wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits())
+    } else {
+        wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits())
+    }
+}
+
+///
+/// Creates the error returned when a device can't be created on an adapter
+///
+pub fn no_device_error(adapter: &wgpu::Adapter, error: wgpu::RequestDeviceError) -> RenderInitError {
+    let info = adapter.get_info();
+
+    RenderInitError::NoDevice(format!("could not create a device on '{}' ({:?}, {:?}, driver '{} {}'): {}",
+        info.name, info.device_type, info.backend, info.driver, info.driver_info, error))
+}
+
+///
+/// Checks that an adapter can render to a surface, returning `RenderInitError::UnsupportedFormat` if the surface doesn't offer
+/// any texture formats for it
+///
+pub fn check_surface_supported(surface: &wgpu::Surface, adapter: &wgpu::Adapter) -> Result<(), RenderInitError> {
+    if !surface.get_capabilities(adapter).formats.is_empty() {
+        Ok(())
+    } else {
+        let info = adapter.get_info();
+
+        Err(RenderInitError::UnsupportedFormat(format!("the surface has no texture formats that '{}' ({:?}, {:?}) can render to",
+            info.name, info.device_type, info.backend)))
+    }
+}
+
+///
+/// Requests an adapter from a wgpu instance, using the fallback adapter (a software implementation such as WARP or llvmpipe,
+/// where the platform provides one) if no hardware adapter is available
+///
+/// Setting the `FLO_FORCE_FALLBACK_ADAPTER` environment variable to `1` skips the hardware adapter, which is useful for tracking
+/// down rendering differences between devices. Returns None if neither kind of adapter is available.
+///
+pub async fn request_adapter_with_fallback(instance: &wgpu::Instance, compatible_surface: Option<&wgpu::Surface>) -> Option<wgpu::Adapter> {
+    request_adapter_with_options(instance, compatible_surface, &AdapterOptions::default()).await.ok()
+}
+
+///
+/// Requests an adapter from a wgpu instance that matches a set of options
+///
+/// The backends in the options are not used here: they need to be passed to the instance when it's created (see
+/// `AdapterOptions::backends_or()`). The `FLO_FORCE_FALLBACK_ADAPTER` environment variable is respected if the fallback
+/// adapter is allowed. Returns `RenderInitError::NoAdapter` with a description of the options if no adapter matches.
+///
+pub async fn request_adapter_with_options(instance: &wgpu::Instance, compatible_surface: Option<&wgpu::Surface>, options: &AdapterOptions) -> Result<wgpu::Adapter, RenderInitError> {
+    // Try for a hardware adapter first unless the fallback adapter is being forced
+    if !(options.allow_fallback_adapter && fallback_adapter_forced()) {
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference:       options.power_preference,
+            force_fallback_adapter: false,
+            compatible_surface:     compatible_surface,
+        }).await;
+
+        if let Some(adapter) = adapter {
+            return Ok(adapter);
+        }
+    }
+
+    // Use the fallback adapter if there's no hardware adapter
+    if options.allow_fallback_adapter {
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference:       options.power_preference,
+            force_fallback_adapter: true,
+            compatible_surface:     compatible_surface,
+        }).await;
+
+        if let Some(adapter) = adapter {
+            return Ok(adapter);
+        }
+    }
+
+    Err(RenderInitError::NoAdapter(format!("no {} adapter is available (power preference {:?}, backends {:?}{})",
+        if options.allow_fallback_adapter { "hardware or fallback" } else { "hardware" },
+        options.power_preference,
+        options.backends_or(wgpu::Backends::all()),
+        if compatible_surface.is_some() { ", compatible with the window surface" } else { "" })))
+}
@@ -0,0 +1,230 @@
+use super::texture::*;
+use super::wgpu_shader::*;
+use super::to_buffer::*;
+
+use wgpu;
+
+use std::borrow::{Cow};
+use std::sync::*;
+
+///
+/// The number of pixels generated by each workgroup in the compute blur shader
+///
+const BLUR_COMPUTE_WORKGROUP_SIZE: u32 = 128;
+
+///
+/// The largest kernel radius supported by the compute blur shader (larger kernels use the fragment shader)
+///
+const BLUR_COMPUTE_MAX_RADIUS: usize = 256;
+
+///
+/// The amount of workgroup memory used by the compute blur shader
+///
+const BLUR_COMPUTE_WORKGROUP_STORAGE: u32 = (BLUR_COMPUTE_WORKGROUP_SIZE + (BLUR_COMPUTE_MAX_RADIUS as u32) * 2) * 16;
+
+///
+/// Compute pipelines that perform a separable gaussian blur on a texture of a particular format
+///
+pub (crate) struct BlurComputePipeline {
+    /// The layout of the bind group for the blur shader
+    layout: wgpu::BindGroupLayout,
+
+    /// The pipeline that blurs along the x axis
+    horizontal: wgpu::ComputePipeline,
+
+    /// The pipeline that blurs along the y axis
+    vertical: wgpu::ComputePipeline,
+}
+
+impl BlurComputePipeline {
+    ///
+    /// True if the device is able to run the compute blur shader
+    ///
+    pub fn device_supports_compute_blur(adapter: &wgpu::Adapter, device: &wgpu::Device) -> bool {
+        let limits = device.limits();
+
+        adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+            && limits.max_compute_workgroup_size_x >= BLUR_COMPUTE_WORKGROUP_SIZE
+            && limits.max_compute_invocations_per_workgroup >= BLUR_COMPUTE_WORKGROUP_SIZE
+            && limits.max_compute_workgroup_storage_size >= BLUR_COMPUTE_WORKGROUP_STORAGE
+            && limits.max_storage_textures_per_shader_stage >= 1
+            && limits.max_storage_buffers_per_shader_stage >= 1
+    }
+
+    ///
+    /// True if a kernel with the specified number of weights can be processed by the compute shader
+    ///
+    #[inline]
+    pub fn supports_kernel(weights: &[f32]) -> bool {
+        !weights.is_empty() && weights.len() <= BLUR_COMPUTE_MAX_RADIUS + 1
+    }
+
+    ///
+    /// Returns the name of a texture format as it appears in a WGSL storage texture declaration, if it's one that the compute shader can write to
+    ///
+    fn storage_format_name(format: wgpu::TextureFormat) -> Option<&'static str> {
+        match format {
+            wgpu::TextureFormat::Rgba8Unorm     => Some("rgba8unorm"),
+            wgpu::TextureFormat::Rgba16Float    => Some("rgba16float"),
+            wgpu::TextureFormat::Rgba32Float    => Some("rgba32float"),
+            _                                   => None,
+        }
+    }
+
+    ///
+    /// Creates the compute blur pipelines for a texture format, or returns None if the format can't be used as a storage texture on this adapter
+    ///
+    pub fn new(adapter: &wgpu::Adapter, device: &wgpu::Device, format: wgpu::TextureFormat) -> Option<BlurComputePipeline> {
+        // The format has to be one that the adapter can use as a storage texture
+        let format_name = Self::storage_format_name(format)?;
+        if !adapter.get_texture_format_features(format).allowed_usages.contains(wgpu::TextureUsages::STORAGE_BINDING) {
+            return None;
+        }
+
+        // Load the shader (the storage texture format is part of the shader source)
+        let source          = include_str!("../../shaders/filters/blur_compute.wgsl").replace("STORAGE_FORMAT", format_name);
+        let shader_module   = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label:  Some("BlurComputePipeline"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+
+        // Input texture, output texture, weights
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label:      Some("BlurComputePipeline"),
+            entries:    &[
+                wgpu::BindGroupLayoutEntry {
+                    binding:    0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty:         wgpu::BindingType::Texture {
+                        sample_type:    wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled:   false,
+                    },
+                    count:      None,
+                },
+
+                wgpu::BindGroupLayoutEntry {
+                    binding:    1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty:         wgpu::BindingType::StorageTexture {
+                        access:         wgpu::StorageTextureAccess::WriteOnly,
+                        format:         format,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count:      None,
+                },
+
+                wgpu::BindGroupLayoutEntry {
+                    binding:    2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty:         wgpu::BindingType::Buffer {
+                        ty:                 wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size:   None,
+                    },
+                    count:      None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label:                  Some("BlurComputePipeline"),
+            bind_group_layouts:     &[&layout],
+            push_constant_ranges:   &[],
+        });
+
+        // Create the pipelines for the two directions
+        let horizontal = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label:          Some("BlurComputePipeline::horizontal"),
+            layout:         Some(&pipeline_layout),
+            module:         &shader_module,
+            entry_point:    "blur_compute_horiz",
+        });
+
+        let vertical = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label:          Some("BlurComputePipeline::vertical"),
+            layout:         Some(&pipeline_layout),
+            module:         &shader_module,
+            entry_point:    "blur_compute_vert",
+        });
+
+        Some(BlurComputePipeline { layout, horizontal, vertical })
+    }
+}
+
+///
+/// Blurs a texture in one direction using the compute shader
+///
+/// `weights` are the (unnormalised) weights for the center pixel followed by the pixels on either side of it, as generated by
+/// `TextureFilter::weights_for_gaussian_blur()`.
+///
+pub (crate) fn blur_compute(device: &wgpu::Device, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, blur_pipeline: &BlurComputePipeline, direction: BlurDirection, source_texture: &WgpuTexture, weights: Vec<f32>) -> WgpuTexture {
+    let weights_len = weights.len().min(BLUR_COMPUTE_MAX_RADIUS + 1);
+
+    // Store the weights in a storage buffer
+    let weights_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label:              Some("blur_compute"),
+        size:               (weights_len * 4) as u64,
+        usage:              wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    queue.write_buffer(&weights_buffer, 0, weights[0..weights_len].to_vec().to_u8_slice());
+
+    // Create a target texture that the shader can write to
+    let mut target_descriptor   = source_texture.descriptor.clone();
+    target_descriptor.usage     |= wgpu::TextureUsages::STORAGE_BINDING;
+    let target_texture          = device.create_texture(&target_descriptor);
+
+    // Bind the resources
+    let single_mip_level    = wgpu::TextureViewDescriptor { mip_level_count: Some(1), ..Default::default() };
+    let source_view         = source_texture.texture.create_view(&single_mip_level);
+    let target_view         = target_texture.create_view(&single_mip_level);
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label:      Some("blur_compute"),
+        layout:     &blur_pipeline.layout,
+        entries:    &[
+            wgpu::BindGroupEntry {
+                binding:    0,
+                resource:   wgpu::BindingResource::TextureView(&source_view),
+            },
+
+            wgpu::BindGroupEntry {
+                binding:    1,
+                resource:   wgpu::BindingResource::TextureView(&target_view),
+            },
+
+            wgpu::BindGroupEntry {
+                binding:    2,
+                resource:   weights_buffer.as_entire_binding(),
+            },
+        ]
+    });
+
+    // Each workgroup processes a span of pixels in a single row or column
+    let width           = target_descriptor.size.width;
+    let height          = target_descriptor.size.height;
+    let (pipeline, workgroups) = match direction {
+        BlurDirection::Horizontal   => (&blur_pipeline.horizontal, ((width + BLUR_COMPUTE_WORKGROUP_SIZE - 1) / BLUR_COMPUTE_WORKGROUP_SIZE, height)),
+        BlurDirection::Vertical     => (&blur_pipeline.vertical, ((height + BLUR_COMPUTE_WORKGROUP_SIZE - 1) / BLUR_COMPUTE_WORKGROUP_SIZE, width)),
+    };
+
+    // Run the compute pass
+    {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label:              Some("blur_compute"),
+            timestamp_writes:   None,
+        });
+
+        compute_pass.set_pipeline(pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        compute_pass.dispatch_workgroups(workgroups.0, workgroups.1, 1);
+    }
+
+    // Result is the new texture
+    WgpuTexture {
+        descriptor:         target_descriptor,
+        texture:            Arc::new(target_texture),
+        is_premultiplied:   source_texture.is_premultiplied,
+    }
+}
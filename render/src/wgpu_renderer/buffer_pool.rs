@@ -0,0 +1,293 @@
+use wgpu;
+
+use std::collections::{HashMap};
+use std::sync::*;
+
+///
+/// Diagnostic information about how a renderer is re-using its vertex and index buffers
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct BufferPoolStats {
+    /// The number of buffer uploads that re-used a pooled buffer instead of allocating a new one
+    pub allocations_saved: usize,
+
+    /// The number of bytes of buffer memory that are currently waiting in the pool to be re-used
+    pub bytes_pooled: usize,
+}
+
+///
+/// Pool of buffers used to store vertex or index data
+///
+/// Buffers are allocated in power-of-two size classes, so a buffer that's freed can be re-used for any later upload of a similar size.
+/// As `queue.write_buffer()` only takes effect at the start of the next submission, buffers that are released while generating a frame
+/// can't be re-used until `frame_finished()` has been called.
+///
+pub (crate) struct BufferPool {
+    /// The usage for the buffers in this pool (COPY_DST is added so that the buffers can be written to)
+    usage: wgpu::BufferUsages,
+
+    /// The buffers that are available for re-use, indexed by size class
+    free: HashMap<u64, Vec<wgpu::Buffer>>,
+
+    /// The buffers that have been released during the current frame
+    released: Vec<Arc<wgpu::Buffer>>,
+
+    /// Set to true if the buffers released during the current frame should be destroyed instead of being added to the pool
+    trim_released: bool,
+
+    /// The total size of the buffers in the free list
+    pooled_bytes: u64,
+
+    /// The maximum number of bytes to keep in the free list (buffers beyond this size are destroyed instead of being pooled)
+    max_pooled_bytes: u64,
+
+    /// The number of times a buffer has been re-used instead of being allocated
+    allocations_saved: usize,
+}
+
+///
+/// The smallest buffer that will be allocated by a buffer pool
+///
+const MIN_BUFFER_SIZE: u64 = 256;
+
+///
+/// The maximum number of bytes of freed vertex or index buffers that the renderer keeps for re-use
+///
+pub (crate) const MAX_POOLED_BUFFER_BYTES: u64 = 32 * 1024 * 1024;
+
+impl BufferPool {
+    ///
+    /// Creates a new buffer pool that stores buffers with the specified usage
+    ///
+    pub fn new(usage: wgpu::BufferUsages, max_pooled_bytes: u64) -> BufferPool {
+        BufferPool {
+            usage:              usage | wgpu::BufferUsages::COPY_DST,
+            free:               HashMap::new(),
+            released:           vec![],
+            trim_released:      false,
+            pooled_bytes:       0,
+            max_pooled_bytes:   max_pooled_bytes,
+            allocations_saved:  0,
+        }
+    }
+
+    ///
+    /// Returns the size of the buffer that will be used to store a particular number of bytes
+    ///
+    #[inline]
+    fn size_class(len: u64) -> u64 {
+        len.max(MIN_BUFFER_SIZE).next_power_of_two()
+    }
+
+    ///
+    /// Retrieves a buffer from the pool (or allocates a new one) and writes the specified data to it
+    ///
+    pub fn create_buffer(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, contents: &[u8]) -> Arc<wgpu::Buffer> {
+        let size_class = Self::size_class(contents.len() as u64);
+
+        // Re-use a buffer from the pool if possible
+        let buffer = if let Some(buffer) = self.free.get_mut(&size_class).and_then(|buffers| buffers.pop()) {
+            self.pooled_bytes       -= size_class;
+            self.allocations_saved  += 1;
+
+            buffer
+        } else {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label:              Some("BufferPool::create_buffer"),
+                size:               size_class,
+                usage:              self.usage,
+                mapped_at_creation: false,
+            })
+        };
+
+        // Write the contents (writes need to be a multiple of COPY_BUFFER_ALIGNMENT bytes)
+        if !contents.is_empty() {
+            let alignment = wgpu::COPY_BUFFER_ALIGNMENT as usize;
+
+            if contents.len() % alignment == 0 {
+                queue.write_buffer(&buffer, 0, contents);
+            } else {
+                let mut padded = contents.to_vec();
+                padded.resize(((contents.len() + alignment - 1) / alignment) * alignment, 0);
+
+                queue.write_buffer(&buffer, 0, &padded);
+            }
+        }
+
+        Arc::new(buffer)
+    }
+
+    ///
+    /// Returns a buffer to the pool once the current frame has been submitted
+    ///
+    pub fn release_buffer(&mut self, buffer: Arc<wgpu::Buffer>) {
+        self.released.push(buffer);
+    }
+
+    ///
+    /// Makes the buffers released during the current frame available for re-use (called once the frame has been submitted to the queue)
+    ///
+    pub fn frame_finished(&mut self) {
+        let released            = self.released.drain(..).collect::<Vec<_>>();
+        let trim_released       = self.trim_released;
+        self.trim_released      = false;
+
+        for buffer in released {
+            match Arc::try_unwrap(buffer) {
+                Ok(buffer) => {
+                    let size_class = buffer.size();
+
+                    if !trim_released && self.pooled_bytes + size_class <= self.max_pooled_bytes {
+                        // Keep the buffer for the next upload of this size
+                        self.pooled_bytes += size_class;
+                        self.free.entry(size_class).or_insert_with(|| vec![]).push(buffer);
+                    } else {
+                        // Pool is full (or is being trimmed): free the buffer
+                        buffer.destroy();
+                    }
+                }
+
+                Err(buffer) => {
+                    // Still in use somewhere: try again after the next frame
+                    self.released.push(buffer);
+                }
+            }
+        }
+    }
+
+    ///
+    /// Frees all of the buffers that are waiting in the pool
+    ///
+    /// Buffers that have been released during the current frame are freed when the frame is finished instead of being added to the pool.
+    ///
+    pub fn trim(&mut self) {
+        for buffer in self.free.drain().flat_map(|(_, buffers)| buffers) {
+            buffer.destroy();
+        }
+
+        self.pooled_bytes   = 0;
+        self.trim_released  = true;
+    }
+
+    ///
+    /// Retrieves the diagnostic information for this pool
+    ///
+    pub fn stats(&self) -> BufferPoolStats {
+        BufferPoolStats {
+            allocations_saved:  self.allocations_saved,
+            bytes_pooled:       self.pooled_bytes as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::executor;
+
+    ///
+    /// Creates a device to allocate buffers on, or returns None if there's no graphics device available
+    ///
+    fn test_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+        executor::block_on(async {
+            let instance    = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+            let adapter     = instance.request_adapter(&wgpu::RequestAdapterOptions::default()).await?;
+
+            adapter.request_device(&wgpu::DeviceDescriptor { label: None, features: wgpu::Features::empty(), limits: wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()) }, None).await.ok()
+        })
+    }
+
+    #[test]
+    fn size_classes_are_powers_of_two() {
+        assert!(BufferPool::size_class(0) == 256);
+        assert!(BufferPool::size_class(1) == 256);
+        assert!(BufferPool::size_class(256) == 256);
+        assert!(BufferPool::size_class(257) == 512);
+        assert!(BufferPool::size_class(1000) == 1024);
+        assert!(BufferPool::size_class(1024*1024 + 1) == 2*1024*1024);
+    }
+
+    #[test]
+    fn released_buffer_is_reused_for_same_size_class() {
+        let (device, queue) = match test_device() {
+            Some(device)    => device,
+            None            => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        let mut pool = BufferPool::new(wgpu::BufferUsages::VERTEX, MAX_POOLED_BUFFER_BYTES);
+
+        let buffer = pool.create_buffer(&device, &queue, &[0u8; 300]);
+        assert!(buffer.size() == 512);
+
+        // Buffers can't be re-used until the frame they were released in has finished
+        pool.release_buffer(buffer);
+        assert!(pool.stats().bytes_pooled == 0);
+
+        pool.frame_finished();
+        assert!(pool.stats().bytes_pooled == 512);
+
+        // A buffer from a different size class is allocated
+        let other_buffer = pool.create_buffer(&device, &queue, &[0u8; 100]);
+        assert!(other_buffer.size() == 256);
+        assert!(pool.stats().allocations_saved == 0);
+
+        // A buffer in the same size class re-uses the pooled buffer
+        let reused_buffer = pool.create_buffer(&device, &queue, &[0u8; 400]);
+        assert!(reused_buffer.size() == 512);
+        assert!(pool.stats().allocations_saved == 1);
+        assert!(pool.stats().bytes_pooled == 0);
+    }
+
+    #[test]
+    fn pooled_bytes_are_capped_at_32mb() {
+        let (device, queue) = match test_device() {
+            Some(device)    => device,
+            None            => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        let mut pool    = BufferPool::new(wgpu::BufferUsages::VERTEX, MAX_POOLED_BUFFER_BYTES);
+        let size        = 16 * 1024 * 1024;
+        let contents    = vec![0u8; size];
+
+        // Release three 16MB buffers: only two of them fit in the pool
+        let buffers = (0..3).map(|_| pool.create_buffer(&device, &queue, &contents)).collect::<Vec<_>>();
+        for buffer in buffers {
+            pool.release_buffer(buffer);
+        }
+        pool.frame_finished();
+
+        assert!(MAX_POOLED_BUFFER_BYTES == 32 * 1024 * 1024);
+        assert!(pool.stats().bytes_pooled == 32 * 1024 * 1024, "{:?}", pool.stats());
+    }
+
+    #[test]
+    fn trim_frees_pooled_and_released_buffers() {
+        let (device, queue) = match test_device() {
+            Some(device)    => device,
+            None            => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        let mut pool = BufferPool::new(wgpu::BufferUsages::VERTEX, MAX_POOLED_BUFFER_BYTES);
+
+        let pooled_buffer = pool.create_buffer(&device, &queue, &[0u8; 300]);
+        pool.release_buffer(pooled_buffer);
+        pool.frame_finished();
+        assert!(pool.stats().bytes_pooled == 512);
+
+        // Trimming discards the pooled buffer and the buffers released during the same frame
+        let released_buffer = pool.create_buffer(&device, &queue, &[0u8; 100]);
+        pool.release_buffer(released_buffer);
+        pool.trim();
+        pool.frame_finished();
+
+        assert!(pool.stats().bytes_pooled == 0);
+
+        // Later frames use the pool again
+        let later_buffer = pool.create_buffer(&device, &queue, &[0u8; 100]);
+        pool.release_buffer(later_buffer);
+        pool.frame_finished();
+
+        assert!(pool.stats().bytes_pooled == 256);
+    }
+}
@@ -0,0 +1,95 @@
+use wgpu;
+use once_cell::sync::Lazy;
+
+use std::error::Error;
+use std::sync::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+///
+/// The devices that have an error handler watching for them to be lost, along with the flag that is set when that happens
+///
+static DEVICE_LOST_FLAGS: Lazy<Mutex<Vec<(Weak<wgpu::Device>, Arc<AtomicBool>)>>> = Lazy::new(|| Mutex::new(vec![]));
+
+///
+/// Returns a flag that is set when a device reports that it has been lost
+///
+/// The flag is shared by all of the renderers that use the same device. This installs an uncaptured error handler on the device
+/// the first time it's called: errors other than device loss are treated as fatal, the same as the default wgpu handler.
+///
+pub (crate) fn device_lost_flag(device: &Arc<wgpu::Device>) -> Arc<AtomicBool> {
+    let mut device_flags = DEVICE_LOST_FLAGS.lock().unwrap();
+
+    // Forget about any devices that have been released
+    device_flags.retain(|(device, _)| device.strong_count() > 0);
+
+    // Re-use the existing flag if the device is already being watched
+    if let Some((_, flag)) = device_flags.iter().find(|(watched_device, _)| watched_device.upgrade().map(|watched_device| Arc::ptr_eq(&watched_device, device)).unwrap_or(false)) {
+        return Arc::clone(flag);
+    }
+
+    // Set the flag from the uncaptured error handler
+    let flag            = Arc::new(AtomicBool::new(false));
+    let handler_flag    = Arc::clone(&flag);
+
+    device.on_uncaptured_error(Box::new(move |err| {
+        if is_device_lost_error(&err) {
+            handler_flag.store(true, Ordering::Release);
+        } else {
+            panic!("wgpu error: {}\n", err);
+        }
+    }));
+
+    device_flags.push((Arc::downgrade(device), Arc::clone(&flag)));
+
+    flag
+}
+
+///
+/// True if a wgpu error was caused by the device being lost
+///
+fn is_device_lost_error(err: &wgpu::Error) -> bool {
+    // wgpu reports device loss as a 'DeviceError::Lost' somewhere in the error's source chain
+    let mut source: Option<&(dyn Error + 'static)> = Some(err);
+
+    while let Some(err) = source {
+        if err.to_string().contains("device is lost") {
+            return true;
+        }
+
+        source = err.source();
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fmt;
+
+    #[derive(Debug)]
+    struct TestError(&'static str);
+
+    impl fmt::Display for TestError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl Error for TestError { }
+
+    #[test]
+    fn lost_device_is_detected_in_error_source() {
+        let err = wgpu::Error::Validation { source: Box::new(TestError("Parent device is lost")), description: "Validation Error".to_string() };
+
+        assert!(is_device_lost_error(&err));
+    }
+
+    #[test]
+    fn other_validation_errors_are_not_device_loss() {
+        let err = wgpu::Error::Validation { source: Box::new(TestError("Buffer is invalid")), description: "Validation Error".to_string() };
+
+        assert!(!is_device_lost_error(&err));
+    }
+}
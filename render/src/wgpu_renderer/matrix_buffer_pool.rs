@@ -0,0 +1,141 @@
+use super::pipeline::*;
+
+use wgpu;
+
+use std::mem;
+use std::slice;
+use std::sync::*;
+use std::ffi::{c_void};
+
+///
+/// A uniform buffer that stores the transformation matrices for a render pass
+///
+/// Matrices are stored at `aligned_size` intervals, and are selected using a dynamic offset when the bind group is set.
+///
+pub (crate) struct MatrixBuffer {
+    /// The buffer containing the matrices
+    buffer: wgpu::Buffer,
+
+    /// The number of matrices that can be stored in the buffer
+    capacity: usize,
+
+    /// The bind group that binds this buffer as the matrix uniform
+    pub (crate) bind_group: wgpu::BindGroup,
+}
+
+///
+/// Pool of matrix buffers, which are re-used between frames instead of being re-allocated every time the matrices are written
+///
+/// Buffers are written using `queue.write_buffer()`, which takes effect at the start of the next submission, so a buffer can't be
+/// written to twice while generating the same frame. Buffers are taken from the 'free' list when they're needed for a render pass,
+/// and returned to it by `frame_finished()` once the commands for the frame have been submitted.
+///
+pub (crate) struct MatrixBufferPool {
+    /// The buffers that are available for use in the next render pass
+    free: Vec<MatrixBuffer>,
+
+    /// The buffers that have been used by the current frame
+    in_use: Vec<Arc<MatrixBuffer>>,
+}
+
+///
+/// The size of a single transformation matrix
+///
+const MATRIX_SIZE: usize = mem::size_of::<[[f32; 4]; 4]>();
+
+///
+/// The smallest number of matrices to allocate in a buffer
+///
+const MIN_CAPACITY: usize = 16;
+
+impl MatrixBufferPool {
+    ///
+    /// Creates an empty pool of matrix buffers
+    ///
+    pub fn new() -> MatrixBufferPool {
+        MatrixBufferPool {
+            free:   vec![],
+            in_use: vec![],
+        }
+    }
+
+    ///
+    /// Returns the spacing between matrices in a matrix buffer (matrices have to be aligned according to the device's min_uniform_buffer_offset_alignment)
+    ///
+    pub fn aligned_matrix_size(device: &wgpu::Device) -> usize {
+        let alignment       = device.limits().min_uniform_buffer_offset_alignment as usize;
+        let mut group_size  = 0;
+
+        while group_size < MATRIX_SIZE {
+            group_size += alignment;
+        }
+
+        group_size
+    }
+
+    ///
+    /// Writes a set of matrices to a buffer from the pool, returning the buffer that contains them
+    ///
+    pub fn write_matrices(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, pipeline: &Pipeline, matrices: &[[[f32; 4]; 4]]) -> Arc<MatrixBuffer> {
+        let group_size = Self::aligned_matrix_size(device);
+
+        // Convert the matrix list to a u8 pointer
+        let matrices_void   = matrices.as_ptr() as *const c_void;
+        let matrices_len    = MATRIX_SIZE * matrices.len();
+        let matrices_u8     = unsafe { slice::from_raw_parts(matrices_void as *const u8, matrices_len) };
+
+        // Copy the matrices aligned at group_size bytes
+        let mut aligned_matrices = vec![0; group_size * matrices.len()];
+        for matrix_num in 0..matrices.len() {
+            let original_offset = MATRIX_SIZE * matrix_num;
+            let new_offset      = group_size * matrix_num;
+
+            aligned_matrices[new_offset..(new_offset + MATRIX_SIZE)].copy_from_slice(&matrices_u8[original_offset..(original_offset + MATRIX_SIZE)]);
+        }
+
+        // Find a free buffer that's large enough, or allocate a new one
+        let buffer = if let Some(idx) = self.free.iter().position(|buffer| buffer.capacity >= matrices.len()) {
+            self.free.swap_remove(idx)
+        } else {
+            let capacity    = matrices.len().max(MIN_CAPACITY).next_power_of_two();
+            let buffer      = device.create_buffer(&wgpu::BufferDescriptor {
+                label:              Some("MatrixBufferPool::write_matrices"),
+                size:               (capacity * group_size) as u64,
+                usage:              wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group  = pipeline.bind_matrix_buffer(device, &buffer, 0);
+
+            MatrixBuffer { buffer, capacity, bind_group }
+        };
+
+        // Write the matrices into the buffer
+        queue.write_buffer(&buffer.buffer, 0, &aligned_matrices);
+
+        // Buffer stays in use until the frame is finished
+        let buffer = Arc::new(buffer);
+        self.in_use.push(Arc::clone(&buffer));
+
+        buffer
+    }
+
+    ///
+    /// The number of buffers that have been allocated by this pool
+    ///
+    #[cfg(test)]
+    pub fn buffer_count(&self) -> usize {
+        self.free.len() + self.in_use.len()
+    }
+
+    ///
+    /// Marks the buffers used by the current frame as available again (called once the frame has been submitted to the queue)
+    ///
+    pub fn frame_finished(&mut self) {
+        for buffer in self.in_use.drain(..) {
+            // Buffers still referenced elsewhere are just released (they'll be freed by wgpu when they're no longer needed)
+            if let Ok(buffer) = Arc::try_unwrap(buffer) {
+                self.free.push(buffer);
+            }
+        }
+    }
+}
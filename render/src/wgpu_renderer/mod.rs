@@ -1,4 +1,5 @@
 mod texture;
+mod adapter;
 mod pipeline;
 mod samplers;
 mod to_buffer;
@@ -8,8 +9,12 @@ mod render_target;
 mod wgpu_renderer;
 mod renderer_state;
 mod texture_settings;
+mod buffer_pool;
+mod blur_compute;
+mod matrix_buffer_pool;
 mod render_pass_resources;
 mod pipeline_configuration;
+mod device_lost;
 
 mod blur_filter;
 mod mask_filter;
@@ -18,3 +23,6 @@ mod alpha_blend_filter;
 mod displacement_map_filter;
 
 pub use self::wgpu_renderer::*;
+pub use self::adapter::{AdapterOptions, DeviceLimits, request_adapter_with_fallback, request_adapter_with_options, default_device_limits, no_device_error, check_surface_supported};
+pub use self::buffer_pool::{BufferPoolStats};
+pub use self::pipeline_configuration::{PipelineConfiguration};
@@ -12,8 +12,11 @@ use std::mem;
 ///
 /// Description of a WGPU pipeline configuration (used to create the configuration and as a hash key)
 ///
-#[derive(Clone, PartialEq, Eq, Hash)]
-pub (crate) struct PipelineConfiguration {
+/// The `common_configurations()` function can be used to generate the configurations used most often when rendering a canvas,
+/// which can be passed to `WgpuRenderer::prewarm_pipelines()` to avoid compiling them when they're first used.
+///
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct PipelineConfiguration {
     /// Format of the texture that this will render against
     pub (crate) texture_format:             wgpu::TextureFormat,
 
@@ -31,6 +34,9 @@ pub (crate) struct PipelineConfiguration {
 
     /// The number of samples the target texture uses (or None for no multisampling)
     pub (crate) multisampling_count:        Option<u32>,
+
+    /// How the stencil buffer is used (or None if the target has no stencil buffer)
+    pub (crate) stencil_mode:               Option<StencilMode>,
 }
 
 impl Default for PipelineConfiguration {
@@ -41,7 +47,8 @@ impl Default for PipelineConfiguration {
             blending_mode:              Some(BlendMode::SourceOver),
             source_is_premultiplied:    false,
             flip_vertical:              false,
-            multisampling_count:        None
+            multisampling_count:        None,
+            stencil_mode:               None,
         }
     }
 }
@@ -101,13 +108,99 @@ impl PipelineConfiguration {
     ///
     /// Creates a pipeline configuration targeting the specified texture
     ///
-    pub fn for_texture(texture: &WgpuTexture) -> PipelineConfiguration {
+    pub (crate) fn for_texture(texture: &WgpuTexture) -> PipelineConfiguration {
         let mut config          = Self::default();
         config.texture_format   = texture.descriptor.format;
 
         config
     }
 
+    ///
+    /// Returns the pipeline configurations most commonly used when rendering to a texture with the specified format and number of samples
+    ///
+    /// These are the flat colour and texture shaders (with and without premultiplied alpha), with and without a clipping mask, for each of the
+    /// standard blend modes.
+    ///
+    pub fn common_configurations(texture_format: wgpu::TextureFormat, multisampling_count: Option<u32>) -> Vec<PipelineConfiguration> {
+        use self::BlendMode::*;
+
+        let blend_modes = [SourceOver, DestinationOver, SourceIn, DestinationIn, SourceOut, DestinationOut, SourceATop, DestinationATop, Multiply, Screen, AllChannelAlphaSourceOver, AllChannelAlphaDestinationOver];
+        let mut configs = vec![];
+
+        for blend_mode in blend_modes {
+            for clipping in [false, true] {
+                configs.push(Self::simple(texture_format, blend_mode, clipping, multisampling_count));
+                configs.push(Self::texture(texture_format, blend_mode, clipping, false, multisampling_count));
+                configs.push(Self::texture(texture_format, blend_mode, clipping, true, multisampling_count));
+            }
+        }
+
+        configs
+    }
+
+    ///
+    /// Creates the configuration used for rendering flat colours with the specified blend mode
+    ///
+    /// `clipping` should be true for the variant of the shader that renders through a clipping mask
+    ///
+    pub fn simple(texture_format: wgpu::TextureFormat, blend_mode: BlendMode, clipping: bool, multisampling_count: Option<u32>) -> PipelineConfiguration {
+        let variant         = if clipping { StandardShaderVariant::ClippingMask } else { StandardShaderVariant::NoClipping };
+        let post_processing = ColorPostProcessingStep::for_blend_mode(blend_mode);
+
+        PipelineConfiguration {
+            texture_format,
+            shader_module:              WgpuShader::Simple(variant, post_processing),
+            blending_mode:              Some(blend_mode),
+            source_is_premultiplied:    false,
+            flip_vertical:              false,
+            multisampling_count,
+            stencil_mode:               None,
+        }
+    }
+
+    ///
+    /// Creates the configuration used for rendering a (non-multisampled) texture with the specified blend mode
+    ///
+    /// `source_is_premultiplied` should be true for textures that contain premultiplied alpha (such as the ones produced by rendering
+    /// to a texture)
+    ///
+    pub fn texture(texture_format: wgpu::TextureFormat, blend_mode: BlendMode, clipping: bool, source_is_premultiplied: bool, multisampling_count: Option<u32>) -> PipelineConfiguration {
+        let variant         = if clipping { StandardShaderVariant::ClippingMask } else { StandardShaderVariant::NoClipping };
+        let post_processing = ColorPostProcessingStep::for_blend_mode(blend_mode);
+        let alpha_blend     = if source_is_premultiplied { AlphaBlendStep::Premultiply } else { AlphaBlendStep::NoPremultiply };
+
+        PipelineConfiguration {
+            texture_format,
+            shader_module:              WgpuShader::Texture(variant, InputTextureType::Sampler, TexturePosition::InputPosition, alpha_blend, post_processing),
+            blending_mode:              Some(blend_mode),
+            source_is_premultiplied,
+            flip_vertical:              false,
+            multisampling_count,
+            stencil_mode:               None,
+        }
+    }
+
+    ///
+    /// The format of the texture that this configuration renders to
+    ///
+    pub fn texture_format(&self) -> wgpu::TextureFormat {
+        self.texture_format
+    }
+
+    ///
+    /// The blend mode used by this configuration (or None if blending is disabled)
+    ///
+    pub fn blend_mode(&self) -> Option<BlendMode> {
+        self.blending_mode
+    }
+
+    ///
+    /// The number of samples in the target texture (or None if it isn't multisampled)
+    ///
+    pub fn multisampling_count(&self) -> Option<u32> {
+        self.multisampling_count
+    }
+
     ///
     /// Retrieves the configured blend state for this pipeline
     ///
@@ -180,15 +273,56 @@ impl PipelineConfiguration {
     pub fn color_targets(&self) -> Vec<Option<wgpu::ColorTargetState>> {
         let blend_state = self.blend_state();
 
+        // Only the stencil buffer is updated when incrementing or decrementing the stencil values
+        let write_mask  = match self.stencil_mode {
+            Some(StencilMode::Increment)    |
+            Some(StencilMode::Decrement)    => wgpu::ColorWrites::empty(),
+            _                               => wgpu::ColorWrites::ALL,
+        };
+
         vec![
             Some(wgpu::ColorTargetState {
                 format:     self.texture_format,
                 blend:      blend_state,
-                write_mask: wgpu::ColorWrites::ALL, 
+                write_mask: write_mask, 
             })
         ]
     }
 
+    ///
+    /// Returns the depth/stencil state for this pipeline configuration (None if the target has no stencil buffer)
+    ///
+    pub fn depth_stencil_state(&self) -> Option<wgpu::DepthStencilState> {
+        let stencil_mode = self.stencil_mode?;
+
+        let (compare, pass_op) = match stencil_mode {
+            StencilMode::Disabled   => (wgpu::CompareFunction::Always, wgpu::StencilOperation::Keep),
+            StencilMode::Increment  => (wgpu::CompareFunction::Always, wgpu::StencilOperation::IncrementClamp),
+            StencilMode::Decrement  => (wgpu::CompareFunction::Always, wgpu::StencilOperation::DecrementClamp),
+            StencilMode::Equal(_)   => (wgpu::CompareFunction::Equal, wgpu::StencilOperation::Keep),
+        };
+
+        let face_state = wgpu::StencilFaceState {
+            compare:        compare,
+            fail_op:        wgpu::StencilOperation::Keep,
+            depth_fail_op:  wgpu::StencilOperation::Keep,
+            pass_op:        pass_op,
+        };
+
+        Some(wgpu::DepthStencilState {
+            format:                 wgpu::TextureFormat::Stencil8,
+            depth_write_enabled:    false,
+            depth_compare:          wgpu::CompareFunction::Always,
+            stencil:                wgpu::StencilState {
+                front:      face_state,
+                back:       face_state,
+                read_mask:  0xff,
+                write_mask: 0xff,
+            },
+            bias:                   wgpu::DepthBiasState::default(),
+        })
+    }
+
     ///
     /// Returns the vertex buffer layout we'll use for this pipeline configuration
     ///
@@ -269,7 +403,7 @@ impl PipelineConfiguration {
                 count:              None,
                 ty:                 wgpu::BindingType::Buffer {
                     ty:                 wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
+                    has_dynamic_offset: true,
                     min_binding_size:   wgpu::BufferSize::new(64),
                 }
             },
@@ -776,7 +910,7 @@ impl PipelineConfiguration {
             vertex:         self.vertex_state(shader_cache),
             fragment:       self.fragment_state(shader_cache, temp_storage),
             primitive:      wgpu::PrimitiveState::default(),
-            depth_stencil:  None,
+            depth_stencil:  self.depth_stencil_state(),
             multisample:    multisampling,
             multiview:      None,
         }
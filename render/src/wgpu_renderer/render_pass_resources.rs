@@ -1,4 +1,5 @@
 use super::pipeline::*;
+use super::matrix_buffer_pool::*;
 use super::texture_settings::*;
 
 use wgpu;
@@ -27,6 +28,9 @@ pub struct RenderPassResources {
     /// The texture view that this render pass will write to
     pub (crate) target_view: Option<Arc<wgpu::TextureView>>,
 
+    /// The stencil buffer for the render target (None if the render target has no stencil buffer)
+    pub (crate) stencil_view: Option<Arc<wgpu::TextureView>>,
+
     /// The render pipelines that this render pass will write to
     pub (crate) pipelines: Vec<Arc<wgpu::RenderPipeline>>,
 
@@ -46,11 +50,8 @@ pub struct RenderPassResources {
     /// The matrices that will be loaded into the matrix buffer for this render pass
     pub (crate) matrices: Vec<[[f32; 4]; 4]>,
 
-    /// Once the render pass is running, the buffer containing the matrices that were previously in 'matrices'
-    pub (crate) matrix_buffer: Option<wgpu::Buffer>,
-
-    /// Once the render pass is running, the bind groups for each of the matrices in the matrix buffer (corresponding to the original index in the matrices Vec)
-    pub (crate) matrix_bind_groups: Vec<wgpu::BindGroup>,
+    /// Once the render pass is running, the buffer containing the matrices that were previously in 'matrices' (each matrix is selected using a dynamic offset)
+    pub (crate) matrix_buffer: Option<Arc<MatrixBuffer>>,
 
     /// The texture settings that will be loaded into the texture settings buffer for this render pass
     pub (crate) texture_settings: Vec<(Arc<Pipeline>, TextureSettings, Option<Arc<wgpu::Texture>>, Option<Arc<wgpu::Sampler>>)>,
@@ -67,6 +68,7 @@ impl Default for RenderPassResources {
         RenderPassResources {
             target_texture:                 None,
             target_view:                    None,
+            stencil_view:                   None,
             pipelines:                      vec![],
             buffers:                        vec![],
             bind_groups:                    vec![],
@@ -75,7 +77,6 @@ impl Default for RenderPassResources {
             texture_settings:               vec![],
             clear:                          None,
             matrix_buffer:                  None,
-            matrix_bind_groups:             vec![],
             texture_settings_buffer:        None,
             texture_settings_bind_groups:   vec![],
         }
@@ -108,50 +109,41 @@ impl RenderPassResources {
     }
 
     ///
-    /// Loads the matrices in this render pass into the matrix_buffer object
+    /// Generates the stencil attachment for the render pass, if the render target has a stencil buffer
     ///
-    pub (crate) fn fill_matrix_buffer(&mut self, device: &wgpu::Device, pipeline: &Pipeline) {
-        // Take the matrices in preparation to load them into the buffer
-        let matrices    = mem::take(&mut self.matrices);
-        let matrix_size = mem::size_of::<[[f32; 4]; 4]>();
-
-        // Convert the matrix list to a u8 pointer
-        let matrices_void     = matrices.as_ptr() as *const c_void;
-        let matrices_len      = matrix_size * matrices.len();
-        let matrices_u8       = unsafe { slice::from_raw_parts(matrices_void as *const u8, matrices_len) };
-
-        // Need to make another buffer from this where everything is aligned according to the min_uniform_buffer_offset_alignment
-        let limits              = device.limits();
-        let mut group_offset    = 0;
+    /// The stencil buffer is cleared to 0 whenever the colour attachment is cleared
+    ///
+    #[inline]
+    pub fn depth_stencil_attachment(&self) -> Option<wgpu::RenderPassDepthStencilAttachment> {
+        let load_op = if self.clear.is_some() {
+            wgpu::LoadOp::Clear(0)
+        } else {
+            wgpu::LoadOp::Load
+        };
 
-        while group_offset < mem::size_of::<[[f32; 4]; 4]>() {
-            group_offset += limits.min_uniform_buffer_offset_alignment as usize;
-        }
+        self.stencil_view.as_ref().map(|stencil_view| {
+            wgpu::RenderPassDepthStencilAttachment {
+                view:           &**stencil_view,
+                depth_ops:      None,
+                stencil_ops:    Some(wgpu::Operations { load: load_op, store: wgpu::StoreOp::Store }),
+            }
+        })
+    }
 
-        // Copy the matrices aligned at group_offset bytes
-        let mut aligned_matrices = vec![0; group_offset * matrices.len()];
-        for matrix_num in 0..matrices.len() {
-            let original_offset = matrix_size * matrix_num;
-            let new_offset      = group_offset * matrix_num;
+    ///
+    /// Loads the matrices in this render pass into a matrix buffer from the pool
+    ///
+    pub (crate) fn fill_matrix_buffer(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, pipeline: &Pipeline, pool: &mut MatrixBufferPool) {
+        // Take the matrices in preparation to load them into the buffer
+        let matrices = mem::take(&mut self.matrices);
 
-            aligned_matrices[new_offset..(new_offset + matrix_size)].copy_from_slice(&matrices_u8[original_offset..(original_offset + matrix_size)]);
+        // Nothing to do if no matrices were set during this render pass
+        if matrices.is_empty() {
+            return;
         }
 
-        // Load into a buffer
-        let matrix_buffer   = device.create_buffer_init(&util::BufferInitDescriptor {
-            label:      Some("fill_matrix_buffer"),
-            contents:   &aligned_matrices,
-            usage:      wgpu::BufferUsages::UNIFORM,
-        });
-
-        // Create bind groups for each of the matrices in the buffer
-        let bind_groups = (0..matrices.len()).into_iter()
-            .map(|offset| pipeline.bind_matrix_buffer(device, &matrix_buffer, offset * group_offset))
-            .collect();
-
-        // Store the matrix buffer for use during the render pass
-        self.matrix_buffer      = Some(matrix_buffer);
-        self.matrix_bind_groups = bind_groups;
+        // Write to a buffer from the pool, and store it for use during the render pass
+        self.matrix_buffer = Some(pool.write_matrices(device, queue, pipeline, &matrices));
     }
 
     ///
@@ -12,6 +12,7 @@ pub enum RenderTarget {
     Texture {
         texture:            Arc<wgpu::Texture>,
         texture_descriptor: wgpu::TextureDescriptor<'static>,
+        stencil:            Option<Arc<wgpu::Texture>>,
         width:              u32,
         height:             u32,
     },
@@ -21,6 +22,7 @@ pub enum RenderTarget {
         texture:            Arc<wgpu::Texture>,
         texture_descriptor: wgpu::TextureDescriptor<'static>,
         resolved:           Option<Arc<wgpu::Texture>>,
+        stencil:            Option<Arc<wgpu::Texture>>,
         width:              u32,
         height:             u32,
     },
@@ -30,7 +32,9 @@ impl RenderTarget {
     ///
     /// Creates a new render target
     ///
-    pub fn new(device: &wgpu::Device, width: u32, height: u32, render_target_type: RenderTargetType) -> RenderTarget {
+    /// The sample count is used for the multisampled render target types, and must be a value supported by the device
+    ///
+    pub fn new(device: &wgpu::Device, width: u32, height: u32, render_target_type: RenderTargetType, sample_count: u32) -> RenderTarget {
         // Set up the texture descriptor (basic width and height and standard format)
         let mut descriptor = wgpu::TextureDescriptor {
             label:  Some("render_target"),
@@ -53,9 +57,9 @@ impl RenderTarget {
             Standard                        => { }
             StandardForReading              => { descriptor.view_formats = &[wgpu::TextureFormat::Bgra8Unorm] }
             Multisampled                    |
-            MultisampledTexture             => { descriptor.sample_count = 4; }
+            MultisampledTexture             => { descriptor.sample_count = sample_count; }
             Monochrome                      => { descriptor.format = wgpu::TextureFormat::R8Unorm; },
-            MonochromeMultisampledTexture   => { descriptor.format = wgpu::TextureFormat::R8Unorm; descriptor.sample_count = 4; }
+            MonochromeMultisampledTexture   => { descriptor.format = wgpu::TextureFormat::R8Unorm; descriptor.sample_count = sample_count; }
         }
 
         // Create the texture for this render target
@@ -69,6 +73,7 @@ impl RenderTarget {
                 RenderTarget::Texture {
                     texture:            Arc::new(texture),
                     texture_descriptor: descriptor, 
+                    stencil:            None,
                     width:              width,
                     height:             height,
                 }
@@ -81,6 +86,7 @@ impl RenderTarget {
                     texture:        Arc::new(texture),
                     texture_descriptor: descriptor,
                     resolved:       None,
+                    stencil:        None,
                     width:          width,
                     height:         height,
                 }
@@ -98,6 +104,45 @@ impl RenderTarget {
         }
     }
 
+    ///
+    /// Retrieves the stencil texture for this render target, if it has one
+    ///
+    pub fn stencil_texture(&self) -> Option<Arc<wgpu::Texture>> {
+        match self {
+            RenderTarget::Texture { stencil, .. }       => stencil.clone(),
+            RenderTarget::Multisampled { stencil, .. }  => stencil.clone(),
+        }
+    }
+
+    ///
+    /// Creates a stencil texture for this render target if it doesn't already have one, and returns it
+    ///
+    pub fn create_stencil_texture(&mut self, device: &wgpu::Device) -> Arc<wgpu::Texture> {
+        let (width, height)     = self.size();
+        let sample_count        = self.sample_count().unwrap_or(1);
+
+        let stencil = match self {
+            RenderTarget::Texture { stencil, .. }       => stencil,
+            RenderTarget::Multisampled { stencil, .. }  => stencil,
+        };
+
+        // The stencil texture must have the same size and number of samples as the render target
+        let stencil = stencil.get_or_insert_with(|| {
+            Arc::new(device.create_texture(&wgpu::TextureDescriptor {
+                label:              Some("render_target_stencil"),
+                size:               wgpu::Extent3d { width: width, height: height, depth_or_array_layers: 1 },
+                mip_level_count:    1,
+                sample_count:       sample_count,
+                dimension:          wgpu::TextureDimension::D2,
+                format:             wgpu::TextureFormat::Stencil8,
+                usage:              wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats:       &[],
+            }))
+        });
+
+        Arc::clone(stencil)
+    }
+
     ///
     /// Retrieves the size of this render target
     ///
@@ -133,8 +178,8 @@ impl RenderTarget {
     ///
     pub fn sample_count(&self) -> Option<u32> {
         match self {
-            RenderTarget::Texture { .. }        => None,
-            RenderTarget::Multisampled { .. }   => Some(4),
+            RenderTarget::Texture { .. }                            => None,
+            RenderTarget::Multisampled { texture_descriptor, .. }   => Some(texture_descriptor.sample_count),
         }
     }
 }
\ No newline at end of file
@@ -1,7 +1,9 @@
 use super::pipeline::*;
 use super::texture_settings::*;
+use super::matrix_buffer_pool::*;
 use super::render_pass_resources::*;
 use super::pipeline_configuration::*;
+use crate::action::*;
 use crate::buffer::*;
 
 use wgpu;
@@ -66,17 +68,36 @@ pub (crate) struct RendererState {
 
     /// The texture to present to the surface once the rendering is done
     pub present:                        Option<wgpu::SurfaceTexture>,
+
+    /// The buffers used to store the transformation matrices for each render pass
+    pub matrix_buffers:                 MatrixBufferPool,
+
+    /// The spacing between matrices in a matrix buffer
+    matrix_alignment:                   usize,
+
+    /// The region of the render target that drawing is restricted to (None to draw to the whole render target)
+    pub scissor:                        Option<FrameBufferRegion>,
+
+    /// The scissor region that was set when the pending render pass started
+    pass_scissor:                       Option<FrameBufferRegion>,
+
+    /// The value that stencil values are compared against
+    stencil_reference:                  u32,
+
+    /// The stencil reference value that was set when the pending render pass started
+    pass_stencil_reference:             u32,
 }
 
 impl RendererState {
     ///
     /// Creates a default render state
     ///
-    pub fn new(command_queue: Arc<wgpu::Queue>, device: Arc<wgpu::Device>) -> RendererState {
+    pub fn new(command_queue: Arc<wgpu::Queue>, device: Arc<wgpu::Device>, matrix_buffers: MatrixBufferPool) -> RendererState {
         // TODO: we can avoid re-creating some of these structures every frame: eg, the binding groups in particular
 
         // Create all the state structures
         let encoder             = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("RendererState::new") });
+        let matrix_alignment    = MatrixBufferPool::aligned_matrix_size(&*device);
 
         RendererState {
             device:                             device,
@@ -98,6 +119,12 @@ impl RendererState {
             clip_texture:                       None,
             sampler:                            None,
             present:                            None,
+            matrix_buffers:                     matrix_buffers,
+            matrix_alignment:                   matrix_alignment,
+            scissor:                            None,
+            pass_scissor:                       None,
+            stencil_reference:                  0,
+            pass_stencil_reference:             0,
         }
     }
 
@@ -115,7 +142,7 @@ impl RendererState {
     pub fn bind_current_matrix(&mut self) {
         if let Some(pipeline) = &self.pipeline {
             // Add the matrix to the buffer
-            let matrix_buffer_index     = self.render_pass_resources.matrices.len();
+            let matrix_offset           = (self.render_pass_resources.matrices.len() * self.matrix_alignment) as u32;
             let matrix_group            = pipeline.matrix_group_index();
 
             let mut active_matrix       = self.active_matrix.0;
@@ -131,7 +158,7 @@ impl RendererState {
 
             // Bind the matrix as the next step in the pending render pass
             self.render_pass.push(Box::new(move |resources, render_pass| {
-                render_pass.set_bind_group(matrix_group, &resources.matrix_bind_groups[matrix_buffer_index], &[]);
+                render_pass.set_bind_group(matrix_group, &resources.matrix_buffer.as_ref().unwrap().bind_group, &[matrix_offset]);
             }));
         }
     }
@@ -183,6 +210,58 @@ impl RendererState {
         }
     }
 
+    ///
+    /// Returns the scissor rectangle for a region of the current render target, as `(x, y, width, height)` in framebuffer coordinates
+    ///
+    fn scissor_rect(&self, region: &FrameBufferRegion) -> (u32, u32, u32, u32) {
+        let (target_width, target_height)   = self.target_size;
+        let (x, y, width, height)           = region.to_pixels(target_width, target_height);
+
+        if self.pipeline_configuration.flip_vertical {
+            // Render targets are rendered upside-down
+            (x, y, width, height)
+        } else {
+            // Framebuffer coordinates have the origin at the top-left
+            (x, target_height - y - height, width, height)
+        }
+    }
+
+    ///
+    /// Restricts drawing to a region of the current render target (or removes the restriction if the region is None)
+    ///
+    pub fn set_scissor(&mut self, region: Option<FrameBufferRegion>) {
+        self.scissor = region;
+
+        if self.render_pass.is_empty() {
+            // The scissor rect will be set when the next render pass starts
+            self.pass_scissor = region;
+        } else {
+            // Change the scissor rect as the next step in the pending render pass
+            let (x, y, width, height) = region.map(|region| self.scissor_rect(&region)).unwrap_or((0, 0, self.target_size.0, self.target_size.1));
+
+            self.render_pass.push(Box::new(move |_resources, render_pass| {
+                render_pass.set_scissor_rect(x, y, width, height);
+            }));
+        }
+    }
+
+    ///
+    /// Sets the value that the stencil buffer is compared against
+    ///
+    pub fn set_stencil_reference(&mut self, reference: u32) {
+        self.stencil_reference = reference;
+
+        if self.render_pass.is_empty() {
+            // The reference value will be set when the next render pass starts
+            self.pass_stencil_reference = reference;
+        } else {
+            // Change the reference value as the next step in the pending render pass
+            self.render_pass.push(Box::new(move |_resources, render_pass| {
+                render_pass.set_stencil_reference(reference);
+            }));
+        }
+    }
+
     ///
     /// Runs the pending render pass
     ///
@@ -191,8 +270,16 @@ impl RendererState {
         let render_actions  = mem::take(&mut self.render_pass);
         let mut resources   = mem::take(&mut self.render_pass_resources);
 
+        // The next render pass starts with the current scissor region
+        let pass_scissor    = mem::replace(&mut self.pass_scissor, self.scissor);
+        let pass_scissor    = pass_scissor.map(|region| self.scissor_rect(&region));
+
+        // ... and the current stencil reference value
+        let pass_stencil    = mem::replace(&mut self.pass_stencil_reference, self.stencil_reference);
+
         // Keep the current texture view for the next render pass
         self.render_pass_resources.target_view  = resources.target_view.clone();
+        self.render_pass_resources.stencil_view = resources.stencil_view.clone();
 
         // This resets the active pipeline configuration
         self.active_pipeline_configuration      = None;
@@ -206,17 +293,26 @@ impl RendererState {
         // Start a new render pass using the current encoder
         if resources.target_view.is_some() {
             // Create any buffers required
-            resources.fill_matrix_buffer(&*self.device, self.pipeline.as_ref().unwrap());
+            resources.fill_matrix_buffer(&*self.device, &*self.queue, self.pipeline.as_ref().unwrap(), &mut self.matrix_buffers);
             resources.fill_texture_settings_buffer(&*self.device);
 
             // Start the render pass
             let mut render_pass = self.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label:                      Some("run_render_pass"),
-                depth_stencil_attachment:   None,
+                depth_stencil_attachment:   resources.depth_stencil_attachment(),
                 color_attachments:          &resources.color_attachments(),
                 ..Default::default()
             });
 
+            // Restrict the render pass to the scissor region, if there is one
+            if let Some((x, y, width, height)) = pass_scissor {
+                render_pass.set_scissor_rect(x, y, width, height);
+            }
+
+            if resources.stencil_view.is_some() {
+                render_pass.set_stencil_reference(pass_stencil);
+            }
+
             // Run all of the actions
             for action in render_actions.into_iter() {
                 (action)(&resources, &mut render_pass);
@@ -12,6 +12,9 @@ pub (crate) struct Samplers {
     /// Sampler that doesn't repeat
     non_repeating_sampler: Arc<wgpu::Sampler>,
 
+    /// Sampler that repeats, mirroring every other repetition
+    mirror_repeating_sampler: Arc<wgpu::Sampler>,
+
     /// The sampler used for rendering gradients
     gradient_sampler: Arc<wgpu::Sampler>,
 
@@ -54,6 +57,21 @@ impl Samplers {
             border_color:       None,
         });
 
+        let mirror_repeating_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("mirror_repeating_sampler"),
+            address_mode_u:     wgpu::AddressMode::MirrorRepeat,
+            address_mode_v:     wgpu::AddressMode::MirrorRepeat,
+            address_mode_w:     wgpu::AddressMode::MirrorRepeat,
+            mag_filter:         wgpu::FilterMode::Linear,
+            min_filter:         wgpu::FilterMode::Linear,
+            mipmap_filter:      wgpu::FilterMode::Linear,
+            lod_min_clamp:      0.0,
+            lod_max_clamp:      8.0,
+            compare:            None,
+            anisotropy_clamp:   1,
+            border_color:       None,
+        });
+
         let gradient_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("gradient_sampler"),
             address_mode_u:     wgpu::AddressMode::MirrorRepeat,
@@ -87,6 +105,7 @@ impl Samplers {
         Samplers {
             default_sampler:                Arc::new(default_sampler),
             non_repeating_sampler:          Arc::new(non_repeating_sampler),
+            mirror_repeating_sampler:       Arc::new(mirror_repeating_sampler),
             gradient_sampler:               Arc::new(gradient_sampler),
             non_repeating_gradient_sampler: Arc::new(non_repeating_gradient_sampler),
         }
@@ -100,6 +119,10 @@ impl Samplers {
         Arc::clone(&self.non_repeating_sampler)
     } 
 
+    #[inline] pub fn mirror_repeating_sampler(&self) -> Arc<wgpu::Sampler> {
+        Arc::clone(&self.mirror_repeating_sampler)
+    } 
+
     #[inline] pub fn gradient_sampler(&self) -> Arc<wgpu::Sampler> {
         Arc::clone(&self.gradient_sampler)
     } 
@@ -63,6 +63,28 @@ impl ToU8Slice for Vec<f32> {
     }
 }
 
+impl ToU8Slice for Vec<Vertex2D> {
+    #[inline]
+    fn to_u8_slice(&self) -> &[u8] {
+        let contents_void   = self.as_ptr() as *const c_void;
+        let contents_len    = self.len() * mem::size_of::<Vertex2D>();
+        let contents_u8     = unsafe { slice::from_raw_parts(contents_void as *const u8, contents_len) };
+
+        contents_u8
+    }
+}
+
+impl ToU8Slice for Vec<u16> {
+    #[inline]
+    fn to_u8_slice(&self) -> &[u8] {
+        let contents_void   = self.as_ptr() as *const u16;
+        let contents_len    = self.len() * mem::size_of::<u16>();
+        let contents_u8     = unsafe { slice::from_raw_parts(contents_void as *const u8, contents_len) };
+
+        contents_u8
+    }
+}
+
 impl ToWgpuBuffer for Vec<f32> {
     #[inline]
     fn to_buffer(&self, device: &wgpu::Device, usage: wgpu::BufferUsages) -> wgpu::Buffer {
@@ -6,10 +6,14 @@ use super::wgpu_shader::*;
 use super::shader_cache::*;
 use super::render_target::*;
 use super::renderer_state::*;
+use super::buffer_pool::*;
+use super::matrix_buffer_pool::*;
 use super::texture_settings::*;
 use super::pipeline_configuration::*;
+use super::device_lost::*;
 
 use super::blur_filter::*;
+use super::blur_compute::*;
 use super::mask_filter::*;
 use super::reduce_filter::*;
 use super::alpha_blend_filter::*;
@@ -29,8 +33,10 @@ use wgpu::util::DeviceExt;
 
 use std::mem;
 use std::slice;
+use std::thread;
 use std::ops::Range;
 use std::sync::*;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::collections::HashMap;
 use std::ffi::c_void;
 
@@ -44,6 +50,11 @@ use wgpu_profiler::{GpuProfiler, GpuProfilerSettings};
 #[cfg(feature="wgpu-profiler")]
 use std::path::Path;
 
+///
+/// The number of samples per pixel used for multisampled render targets unless another count is requested (all devices support this count)
+///
+const DEFAULT_MULTISAMPLE_COUNT: u32 = 4;
+
 ///
 /// Renderer that uses the `wgpu` abstract library as a render target
 ///
@@ -75,12 +86,33 @@ pub struct WgpuRenderer {
     /// The height of the target surface
     height: u32,
 
+    /// The present mode to request when configuring the target surface
+    present_mode: wgpu::PresentMode,
+
+    /// The number of samples per pixel to use for multisampled render targets
+    multisample_count: u32,
+
+    /// Set to true if the target surface should be reconfigured before the next frame even if its size is unchanged
+    reconfigure_surface: bool,
+
+    /// Set to true when the device reports that it has been lost (the renderer will need to be recreated with a new device)
+    device_lost: Arc<AtomicBool>,
+
+    /// Texture that frames are rendered to when no surface texture could be acquired (so the actions for a skipped frame can still be processed)
+    scratch_target: Option<(wgpu::Texture, Arc<wgpu::TextureView>)>,
+
     /// The vertex buffers for this renderer
     vertex_buffers: Vec<Option<Arc<wgpu::Buffer>>>,
 
     /// The index buffers for this renderer
     index_buffers: Vec<Option<Arc<wgpu::Buffer>>>,
 
+    /// Buffers that have been freed and can be re-used for new vertex data
+    vertex_buffer_pool: BufferPool,
+
+    /// Buffers that have been freed and can be re-used for new index data
+    index_buffer_pool: BufferPool,
+
     /// The textures for this renderer
     textures: Vec<Option<WgpuTexture>>,
 
@@ -90,6 +122,12 @@ pub struct WgpuRenderer {
     /// The cache of render pipeline states used by this renderer
     pipeline_states: HashMap<PipelineConfiguration, Arc<Pipeline>>,
 
+    /// Sends pipelines that have been compiled in the background by `prewarm_pipelines()`
+    prewarmed_pipelines_sender: mpsc::Sender<(PipelineConfiguration, Arc<Pipeline>)>,
+
+    /// Receives pipelines that have been compiled in the background by `prewarm_pipelines()`
+    prewarmed_pipelines: mpsc::Receiver<(PipelineConfiguration, Arc<Pipeline>)>,
+
     /// The cache of shader modules that have been loaded for this render session
     shader_cache: ShaderCache<WgpuShader>,
 
@@ -102,9 +140,24 @@ pub struct WgpuRenderer {
     /// The currently active blend mode
     active_blend_mode: Option<BlendMode>,
 
+    /// The currently active scissor region
+    active_scissor: Option<FrameBufferRegion>,
+
+    /// How the stencil buffer is currently being used
+    active_stencil_mode: StencilMode,
+
     /// The texture samplers used by this renderer
     samplers: Samplers,
 
+    /// The uniform buffers used to store transformation matrices (re-used between frames)
+    matrix_buffers: Option<MatrixBufferPool>,
+
+    /// True if large gaussian blurs should be performed using a compute shader (false if the device doesn't support compute shaders)
+    compute_blur_enabled: bool,
+
+    /// The compute blur pipelines for each texture format (None if the format can't be used with the compute shader)
+    blur_compute_pipelines: HashMap<wgpu::TextureFormat, Option<Arc<BlurComputePipeline>>>,
+
     /// Profiler is used to display a breakdown of the time spent during a render pass
     #[cfg(feature="profile")]
     profiler: Rc<RefCell<RenderProfiler<RenderActionType>>>,
@@ -122,6 +175,9 @@ impl WgpuRenderer {
         #[cfg(feature="wgpu-profiler")]
         let wgpu_profiler = GpuProfiler::new(GpuProfilerSettings { max_num_pending_frames: 4, ..Default::default()}).expect("Failed to create WGPU profiler");
 
+        let (prewarmed_pipelines_sender, prewarmed_pipelines) = mpsc::channel();
+        let compute_blur_enabled                                = BlurComputePipeline::device_supports_compute_blur(&target_adapter, &device);
+
         WgpuRenderer {
             adapter:                target_adapter,
             device:                 device.clone(),
@@ -132,16 +188,30 @@ impl WgpuRenderer {
             target_texture:         None,
             vertex_buffers:         vec![],
             index_buffers:          vec![],
+            vertex_buffer_pool:     BufferPool::new(wgpu::BufferUsages::VERTEX, MAX_POOLED_BUFFER_BYTES),
+            index_buffer_pool:      BufferPool::new(wgpu::BufferUsages::INDEX, MAX_POOLED_BUFFER_BYTES),
             textures:               vec![],
             render_targets:         vec![],
             pipeline_states:        HashMap::new(),
+            prewarmed_pipelines_sender,
+            prewarmed_pipelines,
             shader_cache:           ShaderCache::empty(device.clone()),
             width:                  0,
             height:                 0,
+            present_mode:           wgpu::PresentMode::AutoVsync,
+            multisample_count:      DEFAULT_MULTISAMPLE_COUNT,
+            reconfigure_surface:    false,
+            device_lost:            device_lost_flag(&device),
+            scratch_target:         None,
             active_render_target:   None,
             active_shader:          Some(ShaderType::Simple { clip_texture: None }),
             active_blend_mode:      Some(BlendMode::SourceOver),
+            active_scissor:         None,
+            active_stencil_mode:    StencilMode::Disabled,
             samplers:               Samplers::new(&*device),
+            matrix_buffers:         Some(MatrixBufferPool::new()),
+            compute_blur_enabled,
+            blur_compute_pipelines: HashMap::new(),
 
             #[cfg(feature="profile")]
             profiler:               Rc::new(RefCell::new(RenderProfiler::new())),
@@ -157,6 +227,9 @@ impl WgpuRenderer {
         #[cfg(feature="wgpu-profiler")]
         let wgpu_profiler = GpuProfiler::new(GpuProfilerSettings { max_num_pending_frames: 4, ..Default::default()}).expect("Failed to create WGPU profiler");
 
+        let (prewarmed_pipelines_sender, prewarmed_pipelines) = mpsc::channel();
+        let compute_blur_enabled                                = BlurComputePipeline::device_supports_compute_blur(&target_adapter, &device);
+
         WgpuRenderer {
             adapter:                target_adapter,
             device:                 device.clone(),
@@ -167,16 +240,30 @@ impl WgpuRenderer {
             target_texture:         Some(target_texture),
             vertex_buffers:         vec![],
             index_buffers:          vec![],
+            vertex_buffer_pool:     BufferPool::new(wgpu::BufferUsages::VERTEX, MAX_POOLED_BUFFER_BYTES),
+            index_buffer_pool:      BufferPool::new(wgpu::BufferUsages::INDEX, MAX_POOLED_BUFFER_BYTES),
             textures:               vec![],
             render_targets:         vec![],
             pipeline_states:        HashMap::new(),
+            prewarmed_pipelines_sender,
+            prewarmed_pipelines,
             shader_cache:           ShaderCache::empty(device.clone()),
             width:                  texture_size.0,
             height:                 texture_size.1,
+            present_mode:           wgpu::PresentMode::AutoVsync,
+            multisample_count:      DEFAULT_MULTISAMPLE_COUNT,
+            reconfigure_surface:    false,
+            device_lost:            device_lost_flag(&device),
+            scratch_target:         None,
             active_render_target:   None,
             active_shader:          Some(ShaderType::Simple { clip_texture: None }),
             active_blend_mode:      Some(BlendMode::SourceOver),
+            active_scissor:         None,
+            active_stencil_mode:    StencilMode::Disabled,
             samplers:               Samplers::new(&*device),
+            matrix_buffers:         Some(MatrixBufferPool::new()),
+            compute_blur_enabled,
+            blur_compute_pipelines: HashMap::new(),
 
             #[cfg(feature="profile")]
             profiler:               Rc::new(RefCell::new(RenderProfiler::new())),
@@ -186,12 +273,210 @@ impl WgpuRenderer {
         }
     }
 
+    ///
+    /// Returns diagnostic information about how many vertex and index buffer allocations have been avoided by re-using freed buffers
+    ///
+    pub fn buffer_pool_stats(&self) -> BufferPoolStats {
+        let vertex_stats    = self.vertex_buffer_pool.stats();
+        let index_stats     = self.index_buffer_pool.stats();
+
+        BufferPoolStats {
+            allocations_saved:  vertex_stats.allocations_saved + index_stats.allocations_saved,
+            bytes_pooled:       vertex_stats.bytes_pooled + index_stats.bytes_pooled,
+        }
+    }
+
+    ///
+    /// Releases the memory used by any vertex or index buffers that are waiting to be re-used
+    ///
+    pub fn trim_buffer_pools(&mut self) {
+        self.vertex_buffer_pool.trim();
+        self.index_buffer_pool.trim();
+    }
+
+    ///
+    /// Sets whether or not large gaussian blurs should be performed using a compute shader
+    ///
+    /// The compute shader is used by default if the device supports it and the fragment shader is used otherwise. Compute blurs
+    /// can't be enabled on a device that doesn't support compute shaders, so this is mainly useful for forcing the fragment shader
+    /// to be used instead.
+    ///
+    pub fn set_compute_blur(&mut self, enabled: bool) {
+        self.compute_blur_enabled = enabled && BlurComputePipeline::device_supports_compute_blur(&self.adapter, &self.device);
+    }
+
+    ///
+    /// True if large gaussian blurs will be performed using a compute shader
+    ///
+    pub fn compute_blur_enabled(&self) -> bool {
+        self.compute_blur_enabled
+    }
+
+    ///
+    /// Retrieves a texture created by this renderer, along with whether or not it contains premultiplied alpha
+    ///
+    pub (crate) fn texture(&self, TextureId(texture_id): TextureId) -> Option<(Arc<wgpu::Texture>, bool)> {
+        let texture = self.textures.get(texture_id)?.as_ref()?;
+
+        Some((Arc::clone(&texture.texture), texture.is_premultiplied))
+    }
+
+    ///
+    /// Replaces the texture that a renderer created by `from_texture()` draws on
+    ///
+    /// The new texture must have the same format as the original one. Everything else that has been sent to the renderer is kept,
+    /// so this can be used to change the size of the rendering without having to set it up again.
+    ///
+    pub fn set_target_texture(&mut self, target_texture: Arc<wgpu::Texture>, texture_size: (u32, u32)) {
+        self.target_texture = Some(target_texture);
+        self.width          = texture_size.0;
+        self.height         = texture_size.1;
+    }
+
+    ///
+    /// Sets the present mode to use for the target surface
+    ///
+    /// The surface is reconfigured at the next call to `prepare_to_render()`. If the surface doesn't support the requested mode,
+    /// it will fall back to `AutoVsync`.
+    ///
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
+        if present_mode != self.present_mode {
+            self.present_mode           = present_mode;
+            self.reconfigure_surface    = self.target_surface.is_some();
+        }
+    }
+
+    ///
+    /// Sets the number of samples per pixel to use for multisampled render targets, returning the count that will actually be used
+    ///
+    /// Multisampled render targets are used to antialias the edges of shapes: the canvas renderer draws into them before copying the
+    /// result to the window. Higher counts produce smoother edges, but a multisampled render target uses as much memory as that many
+    /// single-sampled ones: a 1920x1080 target takes around 8MB per sample, so 33MB with the default of 4 samples and 66MB with 8.
+    ///
+    /// Counts of 2, 4, 8 and 16 are possible. If the device doesn't support the requested count, the nearest one that it does support
+    /// is used instead (every device supports 4). The new count applies to render targets created after this call.
+    ///
+    pub fn set_multisample_count(&mut self, sample_count: u32) -> u32 {
+        self.multisample_count = self.nearest_supported_multisample_count(sample_count);
+        self.multisample_count
+    }
+
+    ///
+    /// The number of samples per pixel that will be used for new multisampled render targets
+    ///
+    pub fn multisample_count(&self) -> u32 {
+        self.multisample_count
+    }
+
+    ///
+    /// Finds the supported sample count that's nearest to a requested sample count
+    ///
+    fn nearest_supported_multisample_count(&self, sample_count: u32) -> u32 {
+        // Counts other than 4 are only available if the device was created with the adapter-specific format features
+        let adapter_specific    = self.device.features().contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+        let is_supported        = |count: u32| {
+            if count == DEFAULT_MULTISAMPLE_COUNT {
+                true
+            } else if adapter_specific {
+                // Both the color and the clip mask render targets need to support the sample count
+                [wgpu::TextureFormat::Bgra8Unorm, wgpu::TextureFormat::R8Unorm].iter()
+                    .all(|format| self.adapter.get_texture_format_features(*format).flags.sample_count_supported(count))
+            } else {
+                false
+            }
+        };
+
+        // Pick the supported count closest to the requested count (preferring the higher count when two are equally close)
+        let sample_count = sample_count.max(1) as i64;
+
+        [16, 8, 4, 2].iter()
+            .copied()
+            .filter(|count| is_supported(*count))
+            .min_by_key(|count| (*count as i64 - sample_count).abs())
+            .unwrap_or(DEFAULT_MULTISAMPLE_COUNT)
+    }
+
+    ///
+    /// Compiles a set of render pipelines in the background, so that there's no delay when they're first used
+    ///
+    /// `PipelineConfiguration::common_configurations()` will generate a list of the pipelines that are usually needed, or `compiled_pipelines()`
+    /// can be used to find the pipelines that were used by a previous rendering session.
+    ///
+    pub fn prewarm_pipelines(&mut self, configurations: Vec<PipelineConfiguration>) {
+        let device  = Arc::clone(&self.device);
+        let sender  = self.prewarmed_pipelines_sender.clone();
+
+        thread::spawn(move || {
+            let mut shader_cache = ShaderCache::empty(Arc::clone(&device));
+
+            for config in configurations {
+                let pipeline = Pipeline::from_configuration(&config, &device, &mut shader_cache);
+
+                if sender.send((config, Arc::new(pipeline))).is_err() {
+                    // Renderer has been dropped
+                    break;
+                }
+            }
+        });
+    }
+
+    ///
+    /// Compiles the pipelines commonly used when rendering a canvas in the background
+    ///
+    /// This covers the pipelines used for multisampled render targets, as well as the main frame buffer if `prepare_to_render()` has been
+    /// called to set its format.
+    ///
+    pub fn prewarm_common_pipelines(&mut self) {
+        let mut configurations = PipelineConfiguration::common_configurations(wgpu::TextureFormat::Bgra8Unorm, Some(self.multisample_count));
+
+        if let Some(target_format) = self.target_format {
+            configurations.extend(PipelineConfiguration::common_configurations(target_format, None));
+        }
+
+        self.prewarm_pipelines(configurations);
+    }
+
+    ///
+    /// Returns the configurations of the pipelines that have been compiled by this renderer so far
+    ///
+    pub fn compiled_pipelines(&mut self) -> Vec<PipelineConfiguration> {
+        self.receive_prewarmed_pipelines();
+        self.pipeline_states.keys().cloned().collect()
+    }
+
+    ///
+    /// Returns the number of matrix uniform buffers that have been allocated for re-use between frames
+    ///
+    #[cfg(test)]
+    pub (crate) fn matrix_buffer_count(&self) -> usize {
+        self.matrix_buffers.as_ref().map(|matrix_buffers| matrix_buffers.buffer_count()).unwrap_or(0)
+    }
+
+    ///
+    /// Adds any pipelines that have finished compiling in the background to the cache
+    ///
+    fn receive_prewarmed_pipelines(&mut self) {
+        while let Ok((config, pipeline)) = self.prewarmed_pipelines.try_recv() {
+            self.pipeline_states.entry(config).or_insert(pipeline);
+        }
+    }
+
+    ///
+    /// Returns true if the device used by this renderer has been lost
+    ///
+    /// Once the device has been lost, rendering will have no effect: the renderer will need to be recreated with a new device
+    /// and all of the resources (buffers, textures and render targets) will need to be sent again.
+    ///
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Acquire)
+    }
+
     ///
     /// Sets up the surface to render at a new size
     ///
     pub fn prepare_to_render(&mut self, width: u32, height: u32) {
         // Leave the settings as-is if the width and height are the same
-        if width == self.width && height == self.height && self.target_format.is_some() {
+        if width == self.width && height == self.height && self.target_format.is_some() && !self.reconfigure_surface {
             return;
         }
 
@@ -199,26 +484,35 @@ impl WgpuRenderer {
         self.target_surface_texture = None;
 
         if let Some(target_surface) = &self.target_surface {
-            // Fetch the format
-            let possible_formats    = target_surface.get_capabilities(&*self.adapter).formats;
+            // Fetch the format (colours are sRGB values that are blended without conversion, so a non-sRGB format is used to avoid converting them a second time)
+            let capabilities        = target_surface.get_capabilities(&*self.adapter);
+            let possible_formats    = &capabilities.formats;
             let actual_format       = possible_formats.iter().filter(|format| !format.is_srgb()).next().copied();
             let actual_format       = actual_format.unwrap_or(possible_formats[0]);
 
+            // Use the requested present mode if the surface supports it, or fall back to vsync if it doesn't
+            let present_mode        = match self.present_mode {
+                wgpu::PresentMode::AutoVsync    |
+                wgpu::PresentMode::AutoNoVsync  => self.present_mode,
+                present_mode                    => if capabilities.present_modes.contains(&present_mode) { present_mode } else { wgpu::PresentMode::AutoVsync },
+            };
+
             let surface_config      = wgpu::SurfaceConfiguration {
                 usage:          wgpu::TextureUsages::RENDER_ATTACHMENT,
                 format:         actual_format,
                 width:          width,
                 height:         height,
-                present_mode:   wgpu::PresentMode::AutoVsync,
+                present_mode:   present_mode,
                 alpha_mode:     wgpu::CompositeAlphaMode::Auto,
                 view_formats:   vec![actual_format]
             };
 
             target_surface.configure(&*self.device, &surface_config);
 
-            self.width          = width;
-            self.height         = height;
-            self.target_format  = Some(actual_format);
+            self.width                  = width;
+            self.height                 = height;
+            self.target_format          = Some(actual_format);
+            self.reconfigure_surface    = false;
         }
     }
 
@@ -233,7 +527,8 @@ impl WgpuRenderer {
         self.profiler.borrow_mut().start_frame();
 
         // Create the render state
-        let mut render_state    = RendererState::new(Arc::clone(&self.queue), Arc::clone(&self.device));
+        let matrix_buffers      = self.matrix_buffers.take().unwrap_or_else(|| MatrixBufferPool::new());
+        let mut render_state    = RendererState::new(Arc::clone(&self.queue), Arc::clone(&self.device), matrix_buffers);
 
         // If wgpu-profiler is enabled, then start a new scope
         #[cfg(feature="wgpu-profiler")] self.wgpu_profiler.begin_scope("render_to_surface", &mut render_state.encoder, &*self.device);
@@ -252,6 +547,10 @@ impl WgpuRenderer {
         if let Some(shader) = self.active_shader.take() {
             self.use_shader(shader, &mut render_state);
         }
+        if let Some(scissor) = self.active_scissor {
+            render_state.set_scissor(Some(scissor));
+        }
+        self.stencil_mode(self.active_stencil_mode, &mut render_state);
 
         // Evaluate the actions
         for action in actions {
@@ -269,14 +568,19 @@ impl WgpuRenderer {
                 CreateIndexBuffer(id, indices)                                                  => { self.create_index_buffer(id, indices); }
                 FreeVertexBuffer(id)                                                            => { self.free_vertex_buffer(id); }
                 FreeIndexBuffer(id)                                                             => { self.free_index_buffer(id); }
+                TrimBufferPools                                                                 => { self.trim_buffer_pools(); }
                 BlendMode(blend_mode)                                                           => { self.blend_mode(blend_mode, &mut render_state); }
+                StencilMode(stencil_mode)                                                       => { self.stencil_mode(stencil_mode, &mut render_state); }
                 CreateRenderTarget(render_id, texture_id, Size2D(width, height), render_type)   => { self.create_render_target(render_id, texture_id, width, height, render_type); }
                 FreeRenderTarget(render_id)                                                     => { self.free_render_target(render_id); }
                 SelectRenderTarget(render_id)                                                   => { self.select_render_target(render_id, &mut render_state); }
                 RenderToFrameBuffer                                                             => { self.select_main_frame_buffer(&mut render_state); }
                 DrawFrameBuffer(render_id, region, Alpha(alpha))                                => { self.draw_frame_buffer(render_id, region, alpha, &mut render_state); }
+                SetScissor(region)                                                              => { self.set_scissor(Some(region), &mut render_state); }
+                ClearScissor                                                                    => { self.set_scissor(None, &mut render_state); }
                 ShowFrameBuffer                                                                 => { self.show_frame_buffer(&mut render_state); }
-                CreateTextureBgra(texture_id, Size2D(width, height))                            => { self.create_bgra_texture(texture_id, width, height); }
+                CreateTextureBgra(texture_id, Size2D(width, height))                            => { self.create_bgra_texture(texture_id, width, height, false); }
+                CreateTextureBgraPremultiplied(texture_id, Size2D(width, height))               => { self.create_bgra_texture(texture_id, width, height, true); }
                 CreateTextureMono(texture_id, Size2D(width, height))                            => { self.create_mono_texture(texture_id, width, height); }
                 Create1DTextureBgra(texture_id, Size1D(width))                                  => { self.create_bgra_1d_texture(texture_id, width); }
                 Create1DTextureMono(texture_id, Size1D(width))                                  => { self.create_mono_1d_texture(texture_id, width); }
@@ -310,6 +614,14 @@ impl WgpuRenderer {
 
         #[cfg(feature="profile")] self.profiler.borrow_mut().finish_action(RenderActionType::SubmitQueue);
 
+        // The matrix buffers can be written to again once the frame has been submitted
+        render_state.matrix_buffers.frame_finished();
+        self.matrix_buffers = Some(render_state.matrix_buffers);
+
+        // Any vertex buffers that were freed during this frame can now be re-used
+        self.vertex_buffer_pool.frame_finished();
+        self.index_buffer_pool.frame_finished();
+
         // Display the profiler information
         #[cfg(feature="profile")]
         {
@@ -327,10 +639,28 @@ impl WgpuRenderer {
         render_state.present.take()
     }
 
+    ///
+    /// Returns the compute pipeline to use to blur a texture with the specified kernel, or None if the fragment shader should be used instead
+    ///
+    fn blur_compute_pipeline_for_texture(&mut self, texture: &WgpuTexture, weights: &[f32]) -> Option<Arc<BlurComputePipeline>> {
+        if !self.compute_blur_enabled || texture.descriptor.sample_count != 1 || !BlurComputePipeline::supports_kernel(weights) {
+            return None;
+        }
+
+        let adapter = &self.adapter;
+        let device  = &self.device;
+
+        self.blur_compute_pipelines.entry(texture.descriptor.format)
+            .or_insert_with(|| BlurComputePipeline::new(adapter, device, texture.descriptor.format).map(Arc::new))
+            .clone()
+    }
+
     ///
     /// Loads a pipeline from a configuration object
     ///
     fn pipeline_for_configuration(&mut self, config: PipelineConfiguration) -> Arc<Pipeline> {
+        self.receive_prewarmed_pipelines();
+
         let device          = &self.device;
         let shader_cache    = &mut self.shader_cache;  
         let pipeline_states = &mut self.pipeline_states;
@@ -412,14 +742,11 @@ impl WgpuRenderer {
     /// Loads a buffer of vertex data to the GPU
     ///
     fn create_vertex_buffer_2d(&mut self, VertexBufferId(vertex_id): VertexBufferId, vertices: Vec<Vertex2D>) {
-        // If there's an existing buffer with this index, drop it
-        if let Some(Some(buffer)) = self.vertex_buffers.get(vertex_id) {
-            buffer.destroy();
-            self.vertex_buffers[vertex_id] = None;
-        }
+        // If there's an existing buffer with this index, return it to the pool
+        self.free_vertex_buffer(VertexBufferId(vertex_id));
 
         // Create the buffer
-        let vertex_buffer = vertices.to_buffer(&*self.device, wgpu::BufferUsages::VERTEX);
+        let vertex_buffer = self.vertex_buffer_pool.create_buffer(&*self.device, &*self.queue, vertices.to_u8_slice());
 
         // Store associated with the vertex ID
         if vertex_id >= self.vertex_buffers.len() {
@@ -428,21 +755,18 @@ impl WgpuRenderer {
                 .map(|_| None));
         }
 
-        self.vertex_buffers[vertex_id] = Some(Arc::new(vertex_buffer));
+        self.vertex_buffers[vertex_id] = Some(vertex_buffer);
     }
     
     ///
     /// Loads a buffer of index data to the GPU
     ///
     fn create_index_buffer(&mut self, IndexBufferId(index_id): IndexBufferId, indices: Vec<u16>) {
-        // If there's an existing buffer with this index, drop it
-        if let Some(Some(buffer)) = self.index_buffers.get(index_id) {
-            buffer.destroy();
-            self.index_buffers[index_id] = None;
-        }
+        // If there's an existing buffer with this index, return it to the pool
+        self.free_index_buffer(IndexBufferId(index_id));
 
         // Create the buffer
-        let index_buffer = indices.to_buffer(&*self.device, wgpu::BufferUsages::INDEX);
+        let index_buffer = self.index_buffer_pool.create_buffer(&*self.device, &*self.queue, indices.to_u8_slice());
 
         // Store associated with the index ID
         if index_id >= self.index_buffers.len() {
@@ -451,15 +775,15 @@ impl WgpuRenderer {
                 .map(|_| None));
         }
 
-        self.index_buffers[index_id] = Some(Arc::new(index_buffer));
+        self.index_buffers[index_id] = Some(index_buffer);
     }
     
     ///
     /// Indicates that a vertex buffer is unused
     ///
     fn free_vertex_buffer(&mut self, VertexBufferId(vertex_id): VertexBufferId) {
-        if let Some(buffer) = self.vertex_buffers.get_mut(vertex_id) {
-            *buffer = None;
+        if let Some(Some(buffer)) = self.vertex_buffers.get_mut(vertex_id).map(|buffer| buffer.take()) {
+            self.vertex_buffer_pool.release_buffer(buffer);
         }
     }
     
@@ -467,8 +791,8 @@ impl WgpuRenderer {
     /// Indicates that an index buffer is unused
     ///
     fn free_index_buffer(&mut self, IndexBufferId(index_id): IndexBufferId) {
-        if let Some(buffer) = self.index_buffers.get_mut(index_id) {
-            *buffer = None;
+        if let Some(Some(buffer)) = self.index_buffers.get_mut(index_id).map(|buffer| buffer.take()) {
+            self.index_buffer_pool.release_buffer(buffer);
         }
     }
     
@@ -483,6 +807,38 @@ impl WgpuRenderer {
         self.active_blend_mode = Some(blend_mode);
         self.update_shader(self.active_shader, self.active_blend_mode, state);
     }
+
+    ///
+    /// Sets how the following render instructions use the stencil buffer
+    ///
+    fn stencil_mode(&mut self, stencil_mode: StencilMode, state: &mut RendererState) {
+        self.active_stencil_mode = stencil_mode;
+
+        // Render targets are given a stencil buffer the first time one is needed
+        if stencil_mode != StencilMode::Disabled && state.render_pass_resources.stencil_view.is_none() {
+            if let Some(RenderTargetId(render_id)) = self.active_render_target {
+                if let Some(Some(render_target)) = self.render_targets.get_mut(render_id) {
+                    // The stencil buffer is attached from the next render pass
+                    #[cfg(feature="profile")] self.profiler.borrow_mut().start_action(RenderActionType::RunRenderPass);
+                    state.run_render_pass();
+                    #[cfg(feature="profile")] self.profiler.borrow_mut().finish_action(RenderActionType::RunRenderPass);
+
+                    let stencil_texture                         = render_target.create_stencil_texture(&*self.device);
+                    state.render_pass_resources.stencil_view    = Some(Arc::new(stencil_texture.create_view(&wgpu::TextureViewDescriptor::default())));
+                }
+            }
+        }
+
+        // The stencil mode has no effect on render targets without a stencil buffer
+        if state.render_pass_resources.stencil_view.is_some() {
+            state.pipeline_configuration.stencil_mode   = Some(stencil_mode);
+            state.pipeline_config_changed               = true;
+
+            if let StencilMode::Equal(reference) = stencil_mode {
+                state.set_stencil_reference(reference as u32);
+            }
+        }
+    }
     
     ///
     /// Creates an off-screen render target and its texture
@@ -498,7 +854,7 @@ impl WgpuRenderer {
         }
 
         // Create a new render target
-        let new_render_target = RenderTarget::new(&*self.device, width as _, height as _, render_target_type);
+        let new_render_target = RenderTarget::new(&*self.device, width as _, height as _, render_target_type, self.multisample_count);
 
         // Make space for the render target and the texture
         if render_id >= self.render_targets.len() {
@@ -556,12 +912,16 @@ impl WgpuRenderer {
             let texture_format  = new_render_target.texture_format();
             let samples         = new_render_target.sample_count();
             let texture_view    = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let stencil_view    = new_render_target.stencil_texture().map(|stencil| Arc::new(stencil.create_view(&wgpu::TextureViewDescriptor::default())));
+            let stencil_mode    = stencil_view.as_ref().map(|_| self.active_stencil_mode);
 
             state.target_size                                   = target_size;
             state.render_pass_resources.target_view             = Some(Arc::new(texture_view));
             state.render_pass_resources.target_texture          = Some(texture);
+            state.render_pass_resources.stencil_view            = stencil_view;
             state.pipeline_configuration.texture_format         = texture_format;
             state.pipeline_configuration.multisampling_count    = samples;
+            state.pipeline_configuration.stencil_mode           = stencil_mode;
             state.pipeline_configuration.flip_vertical          = true;
             state.pipeline_config_changed                       = true;
             state.pipeline_bindings_changed                     = true;
@@ -570,17 +930,77 @@ impl WgpuRenderer {
         }
     }
     
+    ///
+    /// Retrieves the next texture to render to from the target surface
+    ///
+    /// Lost or outdated surfaces are reconfigured. This returns None if no texture could be acquired, in which case the frame
+    /// is skipped (device loss is only reported when the device itself signals it, via `is_device_lost()`)
+    ///
+    fn acquire_surface_texture(&mut self) -> Option<wgpu::SurfaceTexture> {
+        let target_surface = Arc::clone(self.target_surface.as_ref()?);
+
+        match target_surface.get_current_texture() {
+            Ok(surface_texture)                     => Some(surface_texture),
+            Err(wgpu::SurfaceError::OutOfMemory)    => None,
+
+            Err(wgpu::SurfaceError::Timeout)        => {
+                // Try again once before skipping the frame
+                target_surface.get_current_texture().ok()
+            }
+
+            Err(wgpu::SurfaceError::Lost)           |
+            Err(wgpu::SurfaceError::Outdated)       => {
+                // Reconfigure the surface and try again, skipping the frame if it's still unavailable
+                let (width, height)         = (self.width, self.height);
+                self.reconfigure_surface    = true;
+                self.prepare_to_render(width, height);
+
+                target_surface.get_current_texture().ok()
+            }
+        }
+    }
+
+    ///
+    /// Returns a view of the texture to use for a frame that can't be rendered to the surface
+    ///
+    /// The texture is kept so it can be re-used for later frames that are skipped, until the size or format of the surface changes
+    ///
+    fn scratch_target_view(&mut self, texture_format: wgpu::TextureFormat) -> Arc<wgpu::TextureView> {
+        let size = wgpu::Extent3d { width: self.width.max(1), height: self.height.max(1), depth_or_array_layers: 1 };
+
+        match &self.scratch_target {
+            Some((texture, view)) if texture.size() == size && texture.format() == texture_format => Arc::clone(view),
+
+            _ => {
+                let scratch_texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                    label:              Some("discarded_frame"),
+                    size:               size,
+                    mip_level_count:    1,
+                    sample_count:       1,
+                    dimension:          wgpu::TextureDimension::D2,
+                    format:             texture_format,
+                    usage:              wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats:       &[],
+                });
+                let scratch_view    = Arc::new(scratch_texture.create_view(&wgpu::TextureViewDescriptor::default()));
+
+                self.scratch_target = Some((scratch_texture, Arc::clone(&scratch_view)));
+
+                scratch_view
+            }
+        }
+    }
+
     ///
     /// Renders to the main frame buffer
     ///
     fn select_main_frame_buffer(&mut self, state: &mut RendererState) {
         self.active_render_target = None;
 
-        if let Some(target_surface) = &self.target_surface {
+        if self.target_surface.is_some() {
             // Ensure that there's a main frame buffer to render to
             if self.target_surface_texture.is_none() {
-                let surface_texture = target_surface.get_current_texture().unwrap();
-                self.target_surface_texture = Some(surface_texture);
+                self.target_surface_texture = self.acquire_surface_texture();
             }
 
             // Finish the current render pass
@@ -588,15 +1008,21 @@ impl WgpuRenderer {
             state.run_render_pass();
             #[cfg(feature="profile")] self.profiler.borrow_mut().finish_action(RenderActionType::RunRenderPass);
 
-            // Switch to the surface texture
-            let surface_texture     = self.target_surface_texture.as_ref().unwrap();
-            let texture_view        = surface_texture.texture.create_view(&wgpu::TextureViewDescriptor::default());
+            // Switch to the surface texture (or a scratch texture if the surface is unavailable, so the remaining actions can still be processed)
+            let texture_view        = if let Some(surface_texture) = &self.target_surface_texture {
+                Arc::new(surface_texture.texture.create_view(&wgpu::TextureViewDescriptor::default()))
+            } else {
+                let texture_format  = self.target_format.expect("prepare_to_render must be called before rendering");
+                self.scratch_target_view(texture_format)
+            };
 
             state.target_size                                   = (self.width, self.height);
-            state.render_pass_resources.target_view             = Some(Arc::new(texture_view));
+            state.render_pass_resources.target_view             = Some(texture_view);
             state.render_pass_resources.target_texture          = None;
+            state.render_pass_resources.stencil_view            = None;
             state.pipeline_configuration.texture_format         = self.target_format.expect("prepare_to_render must be called before rendering");
             state.pipeline_configuration.multisampling_count    = None;
+            state.pipeline_configuration.stencil_mode           = None;
             state.pipeline_configuration.flip_vertical          = false;
             state.pipeline_config_changed                       = true;
             state.pipeline_bindings_changed                     = true;
@@ -612,8 +1038,10 @@ impl WgpuRenderer {
             state.target_size                                   = (self.width, self.height);
             state.render_pass_resources.target_view             = Some(Arc::new(texture_view));
             state.render_pass_resources.target_texture          = None;
+            state.render_pass_resources.stencil_view            = None;
             state.pipeline_configuration.texture_format         = self.target_format.expect("prepare_to_render must be called before rendering");
             state.pipeline_configuration.multisampling_count    = None;
+            state.pipeline_configuration.stencil_mode           = None;
             state.pipeline_configuration.flip_vertical          = false;
             state.pipeline_config_changed                       = true;
             state.pipeline_bindings_changed                     = true;
@@ -775,9 +1203,9 @@ impl WgpuRenderer {
     }
     
     ///
-    /// Creates a 2D texture with the BGRA pixel format
+    /// Creates a 2D texture with the BGRA pixel format (`premultiplied` is true if the pixels written to it will have premultiplied alpha)
     ///
-    fn create_bgra_texture(&mut self, TextureId(texture_id): TextureId, width: usize, height: usize) {
+    fn create_bgra_texture(&mut self, TextureId(texture_id): TextureId, width: usize, height: usize, premultiplied: bool) {
         // Free the old texture if there is one
         if let Some(old_texture) = self.textures.get_mut(texture_id) {
             *old_texture = None;
@@ -804,7 +1232,7 @@ impl WgpuRenderer {
         let new_texture = WgpuTexture {
             descriptor:         descriptor,
             texture:            Arc::new(new_texture),
-            is_premultiplied:   false,
+            is_premultiplied:   premultiplied,
         };
 
         // Store the texture
@@ -1107,23 +1535,34 @@ impl WgpuRenderer {
 
                     TextureFilter::GaussianBlurHorizontal(sigma, step, kernel_size) |
                     TextureFilter::GaussianBlurVertical(sigma, step, kernel_size)   => {
-                        let mut blur_pipeline       = PipelineConfiguration::for_texture(&final_texture);
-                        blur_pipeline.blending_mode = None;
-                        blur_pipeline.shader_module = match filter {
-                            TextureFilter::GaussianBlurVertical(..)     => WgpuShader::Filter(FilterShader::BlurTexture(BlurDirection::Horizontal)),
-                            TextureFilter::GaussianBlurHorizontal(..)   => WgpuShader::Filter(FilterShader::BlurTexture(BlurDirection::Vertical)),
+                        let direction               = match filter {
+                            TextureFilter::GaussianBlurVertical(..)     => BlurDirection::Horizontal,
+                            TextureFilter::GaussianBlurHorizontal(..)   => BlurDirection::Vertical,
 
-                            _ => WgpuShader::Filter(FilterShader::BlurTexture(BlurDirection::Horizontal)),
+                            _ => BlurDirection::Horizontal,
                         };
-                        let blur_pipeline           = self.pipeline_for_configuration(blur_pipeline);
-
                         let weights                 = TextureFilter::weights_for_gaussian_blur(sigma, step, kernel_size);
-                        let (weights, offsets)      = TextureFilter::weights_and_offsets_for_gaussian_blur(weights);
 
-                        let queue   = &state.queue;
-                        let encoder = &mut state.encoder;
+                        if let Some(blur_compute_pipeline) = self.blur_compute_pipeline_for_texture(&final_texture, &weights) {
+                            // Use the compute shader if it's available
+                            let queue   = &state.queue;
+                            let encoder = &mut state.encoder;
+
+                            final_texture = blur_compute(&*self.device, queue, encoder, &*blur_compute_pipeline, direction, &final_texture, weights);
+                        } else {
+                            // Fall back to the fragment shader
+                            let mut blur_pipeline       = PipelineConfiguration::for_texture(&final_texture);
+                            blur_pipeline.blending_mode = None;
+                            blur_pipeline.shader_module = WgpuShader::Filter(FilterShader::BlurTexture(direction));
+                            let blur_pipeline           = self.pipeline_for_configuration(blur_pipeline);
+
+                            let (weights, offsets)      = TextureFilter::weights_and_offsets_for_gaussian_blur(weights);
+
+                            let queue   = &state.queue;
+                            let encoder = &mut state.encoder;
 
-                        final_texture = blur_texture(&*self.device, queue, encoder, &*blur_pipeline, &final_texture, weights, offsets);
+                            final_texture = blur_texture(&*self.device, queue, encoder, &*blur_pipeline, &final_texture, weights, offsets);
+                        }
                     }
                     
                     TextureFilter::Mask(TextureId(mask_texture)) => { 
@@ -1172,6 +1611,14 @@ impl WgpuRenderer {
         }
     }
     
+    ///
+    /// Restricts future rendering to a region of the render target
+    ///
+    fn set_scissor(&mut self, region: Option<FrameBufferRegion>, state: &mut RendererState) {
+        self.active_scissor = region;
+        state.set_scissor(region);
+    }
+
     ///
     /// Clears the current render target to a single colour
     ///
@@ -1223,12 +1670,7 @@ impl WgpuRenderer {
         state.pipeline_configuration.blending_mode = Some(blend_mode);
 
         // The post-processing step depends on the blend mode
-        let post_processing = match blend_mode {
-            BlendMode::Multiply     => ColorPostProcessingStep::InvertColorAlpha,
-            BlendMode::Screen       => ColorPostProcessingStep::MultiplyAlpha,
-
-            _                       => ColorPostProcessingStep::NoPostProcessing
-        };
+        let post_processing = ColorPostProcessingStep::for_blend_mode(blend_mode);
 
         // Set up the pipeline based on the shader type
         match shader_type {
@@ -1253,7 +1695,7 @@ impl WgpuRenderer {
                 // TODO (this shader doesn't work anyway so should probably be deprecated)
             }
 
-            Texture { texture, texture_transform, repeat, alpha, clip_texture } => {
+            Texture { texture, texture_transform, wrap_mode, alpha, clip_texture } => {
                 // Fetch the input texture
                 let TextureId(texture_id)   = texture;
                 let texture                 = if let Some(Some(texture)) = self.textures.get(texture_id) {
@@ -1293,11 +1735,11 @@ impl WgpuRenderer {
                 state.texture_settings  = TextureSettings { transform: texture_transform.0, alpha: alpha as _, ..Default::default() };
                 state.clip_texture      = clip_texture;
                 state.input_texture     = texture.map(|t| Arc::clone(&t.texture));
-                if repeat {
-                    state.sampler       = Some(self.samplers.default_sampler());
-                } else {
-                    state.sampler       = Some(self.samplers.non_repeating_sampler());    
-                }
+                state.sampler           = match wrap_mode {
+                    TextureWrapMode::Repeat         => Some(self.samplers.default_sampler()),
+                    TextureWrapMode::MirrorRepeat   => Some(self.samplers.mirror_repeating_sampler()),
+                    TextureWrapMode::Clamp          => Some(self.samplers.non_repeating_sampler()),
+                };
 
                 if let Some(texture) = &texture {
                     state.pipeline_configuration.shader_module              = WgpuShader::Texture(variant, texture_type, TexturePosition::InputPosition, alpha_blend, post_processing);
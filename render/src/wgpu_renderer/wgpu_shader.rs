@@ -1,5 +1,6 @@
 use super::texture::*;
 use super::shader_cache::*;
+use crate::action::*;
 
 use wgpu;
 
@@ -150,6 +151,18 @@ impl Default for WgpuShader {
 }
 
 impl ColorPostProcessingStep {
+    ///
+    /// Returns the post-processing step that's needed for a particular blend mode
+    ///
+    pub fn for_blend_mode(blend_mode: BlendMode) -> ColorPostProcessingStep {
+        match blend_mode {
+            BlendMode::Multiply     => ColorPostProcessingStep::InvertColorAlpha,
+            BlendMode::Screen       => ColorPostProcessingStep::MultiplyAlpha,
+
+            _                       => ColorPostProcessingStep::NoPostProcessing
+        }
+    }
+
     ///
     /// Retrieves the `color_post_process` function for this post-processing step
     ///
@@ -300,7 +300,6 @@ fn section_gradients() {
 
 fn section_text_rendering() {
     section_badge("draw/guide_images/s_text_rendering.png", Color::Rgba(0.1, 0.5, 1.0, 0.8), |gc| {
-        gc.transform(Transform2D::scale(1.0, -1.0));
         gc.define_font_data(FontId(0), Arc::clone(&LATO));
 
         gc.fill_color(Color::Rgba(0.7, 0.6, 0.2, 1.0));
@@ -313,7 +312,6 @@ fn section_text_rendering() {
 
 fn section_text_layout() {
     section_badge("draw/guide_images/s_text_layout.png", Color::Rgba(0.1, 0.5, 1.0, 0.8), |gc| {
-        gc.transform(Transform2D::scale(1.0, -1.0));
         gc.define_font_data(FontId(0), Arc::clone(&LATO));
 
         let metrics             = LATO.font_metrics(50.0).unwrap();
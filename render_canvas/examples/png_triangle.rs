@@ -22,7 +22,6 @@ pub fn main() {
         let mut drawing     = vec![];
         drawing.clear_canvas(Color::Rgba(0.0, 0.0, 0.0, 0.0));
         drawing.canvas_height(1000.0);
-        drawing.transform(Transform2D::scale(1.0, -1.0));
         drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
         
         drawing.new_path();
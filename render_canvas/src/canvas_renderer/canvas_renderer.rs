@@ -4,6 +4,8 @@ use crate::renderer_worker::*;
 use crate::renderer_stream::*;
 use crate::resource_ids::*;
 use crate::layer_handle::*;
+use crate::layer_bounds::*;
+use crate::resource_events::*;
 
 use super::tessellate_build_path::*;
 
@@ -17,7 +19,7 @@ use ::desync::*;
 use futures::prelude::*;
 use num_cpus;
 
-use std::collections::{HashMap};
+use std::collections::{HashMap, HashSet, BTreeMap};
 use std::ops::{Range};
 use std::sync::*;
 
@@ -52,6 +54,9 @@ pub struct CanvasRenderer {
     /// The inverse of the viewport transformation
     inverse_viewport_transform: canvas::Transform2D,
 
+    /// An extra transformation applied to the canvas when rendering (used to zoom or pan a view without changing the canvas)
+    view_transform: canvas::Transform2D,
+
     /// The currently active transformation
     pub (super) active_transform: canvas::Transform2D,
 
@@ -71,7 +76,16 @@ pub struct CanvasRenderer {
     viewport_origin: (f32, f32),
 
     /// The width and size of the viewport we're rendering to
-    pub (super) viewport_size: (f32, f32)
+    pub (super) viewport_size: (f32, f32),
+
+    /// The region of the viewport that the next frame should redraw (in viewport coordinates), or None to redraw the whole frame
+    damage_region: Option<LayerBounds>,
+
+    /// Textures that could not be loaded since the last call to `take_texture_errors()`, along with a description of the problem
+    pub (super) texture_errors: Vec<(canvas::TextureId, String)>,
+
+    /// The publisher for resource events, and the resources it's tracking (None if nothing has subscribed to the resource events)
+    resource_events: Option<(ExpiringPublisher<RenderResourceEvent>, Arc<Mutex<RenderResourceTracker>>)>,
 }
 
 impl CanvasRenderer {
@@ -79,11 +93,21 @@ impl CanvasRenderer {
     /// Creates a new canvas renderer
     ///
     pub fn new() -> CanvasRenderer {
+        Self::with_worker_count(0)
+    }
+
+    ///
+    /// Creates a new canvas renderer that uses the specified number of background workers for tessellation
+    ///
+    /// A worker count of 0 means 'use one worker per CPU' (with a minimum of 2), which is what `new()` does. Specifying a
+    /// lower number is useful when many renderers are running at once, as each renderer has its own set of workers.
+    ///
+    pub fn with_worker_count(worker_count: usize) -> CanvasRenderer {
         // Create the shared core
         let core = RenderCore {
             frame_starts:               0,
             setup_actions:              vec![],
-            layers:                     vec![],
+            layers:                     BTreeMap::new(),
             free_layers:                vec![],
             layer_definitions:          vec![],
             background_color:           render::Rgba8([0, 0, 0, 0]),
@@ -97,12 +121,17 @@ impl CanvasRenderer {
             canvas_textures:            HashMap::new(),
             canvas_gradients:           HashMap::new(),
             texture_alpha:              HashMap::new(),
+            texture_wrap_mode:          HashMap::new(),
+            textures_without_mipmaps:   HashSet::new(),
+            premultiplied_textures:     HashSet::new(),
+            stencil_clipping:           false,
             unused_vertex_buffer:       0,
             free_vertex_buffers:        vec![],
             unused_texture_id:          16,
             free_textures:              vec![],
             unused_render_target_id:    16,
             free_render_targets:        vec![],
+            sprite_render_depth:        0,
         };
         let core = Arc::new(Desync::new(core));
 
@@ -110,12 +139,12 @@ impl CanvasRenderer {
         let initial_layer = Self::create_default_layer();
         let initial_layer = core.sync(move |core| {
             let layer0 = core.allocate_layer_handle(initial_layer);
-            core.layers.push(layer0);
+            core.layers.insert(canvas::LayerId(0), layer0);
             layer0
         });
 
-        // Create one worker per cpu
-        let num_workers = num_cpus::get().max(2);
+        // Create the workers (one worker per cpu by default)
+        let num_workers = if worker_count == 0 { num_cpus::get().max(2) } else { worker_count };
         let mut workers = Vec::with_capacity(num_workers);
 
         for _ in 0..num_workers {
@@ -132,6 +161,7 @@ impl CanvasRenderer {
             current_sprite:             None,
             viewport_transform:         canvas::Transform2D::identity(),
             inverse_viewport_transform: canvas::Transform2D::identity(),
+            view_transform:             canvas::Transform2D::identity(),
             active_transform:           canvas::Transform2D::identity(),
             transform_stack:            vec![],
             namespace_stack:            vec![],
@@ -140,6 +170,9 @@ impl CanvasRenderer {
             window_scale:               1.0,
             viewport_origin:            (0.0, 0.0),
             viewport_size:              (1.0, 1.0),
+            damage_region:              None,
+            texture_errors:             vec![],
+            resource_events:            None,
         }
     }
 
@@ -212,6 +245,51 @@ impl CanvasRenderer {
         (x_range, y_range)
     }
 
+    ///
+    /// Restricts the next frame generated by `draw()` to a region of the viewport
+    ///
+    /// The region is in viewport pixels, with (0, 0) at the bottom-left corner of the viewport. Only the part of the frame
+    /// buffer inside this region is updated and anything outside is left as it was, so this is only useful when the frame buffer
+    /// still contains the previous frame. Entities that lie entirely outside of the region are not drawn at all. This setting
+    /// only applies to the next frame: pass `None` (or don't call this at all) to redraw the whole viewport.
+    ///
+    pub fn set_damage_region(&mut self, region: Option<(Range<f32>, Range<f32>)>) {
+        let viewport_size   = render::Size2D(self.viewport_size.0 as usize, self.viewport_size.1 as usize);
+
+        self.damage_region  = region.map(|(x, y)| {
+            LayerBounds { min_x: x.start, min_y: y.start, max_x: x.end, max_y: y.end }.to_viewport_coordinates(&viewport_size)
+        });
+    }
+
+    ///
+    /// Sets an extra transformation to apply to the canvas when it's rendered
+    ///
+    /// This can be used to zoom or pan the rendered canvas without changing its contents. The transformation is applied
+    /// after the canvas transform, in normalized coordinates (where the visible part of the window runs from -1 to 1 vertically
+    /// and 0,0 is the center of the window).
+    ///
+    pub fn set_view_transform(&mut self, view_transform: canvas::Transform2D) {
+        self.view_transform = view_transform;
+    }
+
+    ///
+    /// Sets whether or not clip paths are intersected using a stencil buffer
+    ///
+    /// When a layer has been clipped more than once, it's normally drawn wherever any of the clip paths cover. With stencil
+    /// clipping turned on, it's only drawn where all of the clip paths overlap. This is off by default: the stencil buffer is only
+    /// allocated when a frame with overlapping clip paths is rendered with it turned on.
+    ///
+    pub fn set_stencil_clipping(&mut self, enabled: bool) {
+        self.core.sync(|core| core.stencil_clipping = enabled);
+    }
+
+    ///
+    /// Retrieves the transformation set by `set_view_transform()`
+    ///
+    pub fn get_view_transform(&self) -> canvas::Transform2D {
+        self.view_transform
+    }
+
     ///
     /// Retrieves the active transform for the canvas (which is fully up to date after rendering)
     ///
@@ -223,7 +301,7 @@ impl CanvasRenderer {
     /// Retrieves a transformation that maps a point from canvas coordinates to viewport coordinates
     ///
     pub fn get_viewport_transform(&self) -> canvas::Transform2D {
-        let to_normalized_coordinates   = self.get_active_transform();
+        let to_normalized_coordinates   = self.view_transform * self.get_active_transform();
         let scale_x                     = self.window_size.0/2.0;
         let scale_y                     = self.window_size.1/2.0;
 
@@ -233,11 +311,107 @@ impl CanvasRenderer {
             * to_normalized_coordinates 
     }
 
+    ///
+    /// Retrieves the size in pixels of a texture in the current namespace
+    ///
+    /// Dynamic textures are sized according to the viewport, so their size is only known once a frame using them has been rendered
+    ///
+    pub fn get_texture_size(&self, texture_id: canvas::TextureId) -> Option<(usize, usize)> {
+        let namespace_id = self.current_namespace;
+
+        self.core.sync(|core| {
+            let render_texture: render::TextureId   = core.canvas_textures.get(&(namespace_id, texture_id))?.into();
+            let render::Size2D(width, height)       = *core.texture_size.get(&render_texture)?;
+
+            Some((width, height))
+        })
+    }
+
+    ///
+    /// Retrieves the render texture used for a texture in the current namespace
+    ///
+    pub (crate) fn get_render_texture(&self, texture_id: canvas::TextureId) -> Option<render::TextureId> {
+        let namespace_id = self.current_namespace;
+
+        self.core.sync(|core| core.canvas_textures.get(&(namespace_id, texture_id)).map(|render_texture| render_texture.into()))
+    }
+
+    ///
+    /// Retrieves the bounds of what has been drawn into a sprite in the specified namespace, in the sprite's own coordinates
+    ///
+    /// The result is `((min_x, min_y), (max_x, max_y))`, and includes any sprites drawn into the sprite. It reflects all of the
+    /// drawing that has been processed so far, and is `None` if the sprite is not defined or is empty. Clipping paths are not
+    /// counted as drawing, and don't reduce the bounds either.
+    ///
+    pub fn get_sprite_bounds(&self, namespace_id: canvas::NamespaceId, sprite_id: canvas::SpriteId) -> Option<((f64, f64), (f64, f64))> {
+        let namespace_id = namespace_id.local_id();
+
+        self.core.sync(|core| {
+            let sprite_layer_handle = *core.sprites.get(&(namespace_id, sprite_id))?;
+            let bounds              = core.layer_content_bounds(sprite_layer_handle, 0);
+
+            if bounds.is_undefined() {
+                None
+            } else {
+                Some(((bounds.min_x as f64, bounds.min_y as f64), (bounds.max_x as f64, bounds.max_y as f64)))
+            }
+        })
+    }
+
+    ///
+    /// Retrieves the bounds of what has been drawn on a layer, in canvas coordinates
+    ///
+    /// The result is `((min_x, min_y), (max_x, max_y))`, and includes any sprites drawn on the layer. The drawing is mapped back to
+    /// canvas coordinates using the transform that's currently set on the canvas. This is `None` if the layer doesn't exist or is
+    /// empty, or if the current transform can't be inverted.
+    ///
+    pub fn get_layer_bounds(&self, layer_id: canvas::LayerId) -> Option<((f64, f64), (f64, f64))> {
+        let to_canvas_coordinates = self.active_transform.invert()?;
+
+        self.core.sync(|core| {
+            let layer_handle    = *core.layers.get(&layer_id)?;
+            let bounds          = core.layer_content_bounds(layer_handle, 0);
+
+            if bounds.is_undefined() {
+                None
+            } else {
+                let bounds = bounds.transform(&to_canvas_coordinates);
+                Some(((bounds.min_x as f64, bounds.min_y as f64), (bounds.max_x as f64, bounds.max_y as f64)))
+            }
+        })
+    }
+
+    ///
+    /// Returns the textures that have failed to load since this was last called, along with a description of why
+    ///
+    /// Textures set from encoded images that can't be decoded are left empty rather than stopping the rendering, so this
+    /// is the way to find out what went wrong.
+    ///
+    pub fn take_texture_errors(&mut self) -> Vec<(canvas::TextureId, String)> {
+        std::mem::take(&mut self.texture_errors)
+    }
+
+    ///
+    /// Returns the render actions that free all of the buffers, textures and render targets this renderer has created
+    ///
+    /// The renderer should be discarded after this is called: this is for when it's being replaced by a new renderer that
+    /// will send the drawing again, so the resources it was using don't stay allocated on the render target.
+    ///
+    pub fn free_resources(&mut self) -> Vec<render::RenderAction> {
+        let mut render_actions = self.core.sync(|core| core.free_all_resources());
+
+        if let Some(background_vertex_buffer) = self.background_vertex_buffer.take() {
+            render_actions.push(render::RenderAction::FreeVertexBuffer(background_vertex_buffer));
+        }
+
+        render_actions
+    }
+
     ///
     /// Retrieves a transformation that maps a point from canvas coordinates to window coordinates
     ///
     pub fn get_window_transform(&self) -> canvas::Transform2D {
-        let to_normalized_coordinates   = self.get_active_transform();
+        let to_normalized_coordinates   = self.view_transform * self.get_active_transform();
         let scale_x                     = self.window_size.0/2.0;
         let scale_y                     = self.window_size.1/2.0;
 
@@ -263,7 +437,7 @@ impl CanvasRenderer {
                 if core.layers.len() == 0 {
                     let layer0          = Self::create_default_layer();
                     let layer0          = core.allocate_layer_handle(layer0);
-                    core.layers         = vec![layer0];
+                    core.layers.insert(canvas::LayerId(0), layer0);
                     self.current_layer  = layer0;
                     self.current_sprite = None;
                 }
@@ -397,14 +571,33 @@ impl CanvasRenderer {
             .map(|_| ())
     }
 
+    ///
+    /// Returns a stream of events describing the textures and vertex buffers that this renderer creates and releases
+    ///
+    /// The events are generated as the render actions are read from the streams returned by `draw()`. Resources created
+    /// before the first subscription are not tracked, so the events for releasing them will have a size of 0. Drawing never
+    /// waits for the subscribers: one that falls too far behind will miss the oldest events.
+    ///
+    /// Nothing is tracked until this is called, and tracking stops again once all of the subscribers have been dropped.
+    ///
+    pub fn subscribe_resource_events(&mut self) -> impl Send+Stream<Item=RenderResourceEvent> {
+        let (publisher, _tracker) = self.resource_events.get_or_insert_with(|| (ExpiringPublisher::new(1000), Arc::new(Mutex::new(RenderResourceTracker::default()))));
+
+        publisher.subscribe()
+    }
+
     ///
     /// Returns a stream of render actions after applying a set of canvas drawing operations to this renderer
     ///
+    /// The renderer keeps the vertex buffers it has uploaded for each layer and sprite between calls. Only the drawing that's
+    /// new or has changed is tessellated and uploaded: anything else is drawn from the buffers that were created for it earlier.
+    ///
     pub fn draw<'a, DrawIter: 'a+Send+Iterator<Item=canvas::Draw>>(&'a mut self, drawing: DrawIter) -> impl 'a+Send+Stream<Item=render::RenderAction> {
         // Set up the initial set of rendering actions
-        let viewport_transform  = self.viewport_transform;
+        let viewport_transform  = self.viewport_transform * self.view_transform;
         let viewport_size       = render::Size2D(self.viewport_size.0 as usize, self.viewport_size.1 as usize);
-        let viewport_matrix     = transform_to_matrix(&self.viewport_transform);
+        let viewport_matrix     = transform_to_matrix(&viewport_transform);
+        let damage_region       = self.damage_region.take();
         let mut initialise      = vec![
             render::RenderAction::SelectRenderTarget(MAIN_RENDER_TARGET),
             render::RenderAction::BlendMode(render::BlendMode::SourceOver),
@@ -423,7 +616,7 @@ impl CanvasRenderer {
             RenderTargetType::MonochromeMultisampledTexture));
 
         // When finished, render the MSAA buffer to the main framebuffer
-        let finalize            = vec![
+        let mut finalize        = vec![
             render::RenderAction::RenderToFrameBuffer,
             render::RenderAction::BlendMode(render::BlendMode::SourceOver),
             render::RenderAction::SetTransform(render::Matrix::identity()),
@@ -437,6 +630,11 @@ impl CanvasRenderer {
             render::RenderAction::FreeTexture(CLIP_RENDER_TEXTURE),
         ];
 
+        // The scissor region is left set for the whole frame, so clear it before the frame is shown
+        if damage_region.is_some() {
+            finalize.insert(4, render::RenderAction::ClearScissor);
+        }
+
         // The render stream needs a vertex buffer to render the background to, so make sure that's allocated
         let background_vertex_buffer = match self.background_vertex_buffer {
             Some(buffer_id) => buffer_id,
@@ -449,12 +647,42 @@ impl CanvasRenderer {
             }
         };
 
+        // Stop tracking resources once there's nothing left subscribed to the events
+        if let Some((publisher, _tracker)) = &self.resource_events {
+            if publisher.count_subscribers() == 0 {
+                self.resource_events = None;
+            }
+        }
+
+        // Republish the resource events if anything has subscribed to them
+        let resource_events     = self.resource_events.as_ref()
+            .map(|(publisher, tracker)| (publisher.republish(), Arc::clone(tracker)));
+
         // Start processing the drawing instructions
         let core                = Arc::clone(&self.core);
         let processing          = self.process_drawing(drawing);
 
         // Return a stream of results from processing the drawing
-        RenderStream::new(core, processing, viewport_transform, viewport_size, background_vertex_buffer, initialise, finalize)
+        let render_stream = RenderStream::new(core, processing, viewport_transform, viewport_size, damage_region, background_vertex_buffer, initialise, finalize);
+
+        match resource_events {
+            None                        => render_stream.left_stream(),
+            Some((publisher, tracker))  => {
+                // Generate the resource events as each action is read from the stream
+                let instrumented_stream = futures::stream::unfold((Box::pin(render_stream), publisher, tracker), |(mut render_stream, mut publisher, tracker)| async move {
+                    let action = render_stream.next().await?;
+                    let events = tracker.lock().unwrap().events_for_action(&action);
+
+                    for event in events {
+                        publisher.publish(event).await;
+                    }
+
+                    Some((action, (render_stream, publisher, tracker)))
+                });
+
+                Box::pin(instrumented_stream).right_stream()
+            }
+        }
     }
 }
 
@@ -632,6 +860,31 @@ mod test {
         });
     }
 
+    #[test]
+    pub fn window_transform_with_view_transform() {
+        let mut renderer = CanvasRenderer::new();
+
+        executor::block_on(async move {
+            // Set the canvas height, and zoom in by a factor of 2
+            renderer.set_viewport(0.0..2048.0, 0.0..1536.0, 2048.0, 1536.0, 1.0);
+            renderer.set_view_transform(Transform2D::scale(2.0, 2.0));
+            renderer.draw(vec![Draw::ClearCanvas(Color::Rgba(0.0, 0.0, 0.0, 0.0)), Draw::CanvasHeight(1000.0)].into_iter()).collect::<Vec<_>>().await;
+
+            // Fetch the window transform
+            let window_transform = renderer.get_window_transform();
+
+            // The point 0, 250 should be at the top-middle of the window due to the zoom
+            let (x, y) = window_transform.transform_point(0.0, 250.0);
+            assert!((x-(1024.0)).abs() < 0.01);
+            assert!((y-(1536.0)).abs() < 0.01);
+
+            // The center of the canvas is unchanged
+            let (x, y) = window_transform.transform_point(0.0, 0.0);
+            assert!((x-(1024.0)).abs() < 0.01);
+            assert!((y-(768.0)).abs() < 0.01);
+        });
+    }
+
     #[test]
     pub fn viewport_transform_for_full_viewport_window() {
         let mut renderer = CanvasRenderer::new();
@@ -707,4 +960,86 @@ mod test {
             assert!((y-(0.0)).abs() < 0.01);
         });
     }
+
+    #[test]
+    pub fn with_worker_count_creates_requested_workers() {
+        assert!(CanvasRenderer::with_worker_count(1).workers.len() == 1);
+        assert!(CanvasRenderer::with_worker_count(3).workers.len() == 3);
+    }
+
+    #[test]
+    pub fn zero_workers_means_one_per_cpu() {
+        assert!(CanvasRenderer::with_worker_count(0).workers.len() == num_cpus::get().max(2));
+        assert!(CanvasRenderer::new().workers.len() == num_cpus::get().max(2));
+    }
+
+    #[test]
+    pub fn clear_layer_discards_pending_tessellation() {
+        use crate::render_entity::*;
+        use crate::render_entity_details::*;
+        use lyon::tessellation::{VertexBuffers};
+
+        let mut renderer    = CanvasRenderer::with_worker_count(1);
+        let mut path_state  = PathState::default();
+        let layer_id        = renderer.current_layer;
+
+        // Start tessellating an entity on the current layer, in the same way as tes_fill() does
+        let old_entity_id           = renderer.next_entity_id;
+        renderer.next_entity_id     += 1;
+        let old_entity              = renderer.core.sync(|core| {
+            let layer           = core.layer(layer_id);
+            let entity_index    = layer.render_order.len();
+            layer.render_order.push(RenderEntity::Tessellating(old_entity_id));
+
+            LayerEntityRef { layer_id, entity_index, entity_id: old_entity_id }
+        });
+
+        // Clear the layer while the job is pending, and start tessellating a new entity in the same position
+        renderer.tes_clear_layer(&mut path_state);
+
+        let new_entity_id           = renderer.next_entity_id;
+        renderer.next_entity_id     += 1;
+        renderer.core.sync(|core| core.layer(layer_id).render_order.push(RenderEntity::Tessellating(new_entity_id)));
+
+        // The result of the old job arrives after the clear, and should be dropped
+        renderer.core.sync(|core| core.store_job_result(old_entity, RenderEntity::VertexBuffer(VertexBuffers::new(), VertexBufferIntent::Draw), RenderEntityDetails { bounds: LayerBounds::default() }));
+
+        renderer.core.sync(|core| {
+            let layer = core.layer(layer_id);
+
+            assert!(layer.render_order.iter().any(|entity| match entity { RenderEntity::Tessellating(entity_id) => *entity_id == new_entity_id, _ => false }));
+            assert!(!layer.render_order.iter().any(|entity| match entity { RenderEntity::VertexBuffer(_, _) => true, _ => false }));
+            assert!(layer.entity_bounds.is_empty());
+        });
+    }
+
+    #[test]
+    pub fn large_layer_ids_do_not_create_intermediate_layers() {
+        let mut renderer = CanvasRenderer::with_worker_count(1);
+
+        executor::block_on(async {
+            renderer.draw(vec![Draw::Layer(LayerId(1_000_000)), Draw::Layer(LayerId(u64::MAX)), Draw::Layer(LayerId(0))].into_iter()).collect::<Vec<_>>().await;
+        });
+
+        // Only the layers that were selected should exist, in ascending order of ID
+        let layer_ids = renderer.core.sync(|core| core.layers.keys().cloned().collect::<Vec<_>>());
+        assert!(layer_ids == vec![LayerId(0), LayerId(1_000_000), LayerId(u64::MAX)], "{:?}", layer_ids);
+    }
+
+    #[test]
+    pub fn reselecting_a_layer_returns_to_the_same_layer() {
+        let mut renderer = CanvasRenderer::with_worker_count(1);
+
+        executor::block_on(async {
+            renderer.draw(vec![Draw::Layer(LayerId(42))].into_iter()).collect::<Vec<_>>().await;
+        });
+        let first_layer = renderer.current_layer;
+
+        executor::block_on(async {
+            renderer.draw(vec![Draw::Layer(LayerId(7)), Draw::Layer(LayerId(42))].into_iter()).collect::<Vec<_>>().await;
+        });
+
+        assert!(renderer.current_layer == first_layer);
+        assert!(renderer.core.sync(|core| core.layers.get(&LayerId(42)).copied()) == Some(first_layer));
+    }
 }
@@ -6,6 +6,7 @@ use crate::layer_state::*;
 use crate::layer_bounds::*;
 use crate::layer_handle::*;
 use crate::render_entity::*;
+use crate::renderer_core::*;
 use crate::renderer_layer::*;
 use crate::stroke_settings::*;
 
@@ -16,6 +17,7 @@ use lyon::tessellation::{FillRule};
 
 use std::mem;
 use std::sync::*;
+use std::collections::{HashMap};
 
 impl CanvasRenderer {
     ///
@@ -38,6 +40,7 @@ impl CanvasRenderer {
                 restore_point:      None
             },
             bounds:                     LayerBounds::default(),
+            entity_bounds:              HashMap::new(),
             stored_states:              vec![],
             commit_before_rendering:    false,
             commit_after_rendering:     false,
@@ -50,9 +53,7 @@ impl CanvasRenderer {
     /// Clears the canvas entirely
     ///
     pub (super) fn tes_clear_canvas(&mut self, background: canvas::Color, path_state: &mut PathState) {
-        //todo!("Stop any incoming tessellated data for this layer");
-        //todo!("Mark vertex buffers as freed");
-
+        // Any tessellation jobs still in progress for the old layers are discarded by `store_job_result()` when they finish
         *path_state = PathState::default();
         let core    = Arc::clone(&self.core);
 
@@ -65,11 +66,15 @@ impl CanvasRenderer {
                 core.used_textures.get_mut(&render_id).map(|usage_count| *usage_count -= 1);
             }
 
+            core.textures_without_mipmaps.clear();
+            core.texture_wrap_mode.clear();
+            core.premultiplied_textures.clear();
+
             // Release the existing layers
             let old_layers = mem::take(&mut core.layers);
 
-            for layer_id in old_layers {
-                let layer = core.release_layer_handle(layer_id);
+            for (_layer_id, layer_handle) in old_layers {
+                let layer = core.release_layer_handle(layer_handle);
                 core.free_layer_entities(layer);
             }
 
@@ -81,13 +86,16 @@ impl CanvasRenderer {
                 core.free_layer_entities(layer);
             }
 
+            // Most of the buffers will have been freed, so the renderer doesn't need to keep their memory around for re-use
+            core.setup_actions.push(render::RenderAction::TrimBufferPools);
+
             // Set the background colour for when we start rendering
             core.background_color   = Self::render_color(background);
 
             // Create a new default layer
             let layer0 = Self::create_default_layer();
             let layer0 = core.allocate_layer_handle(layer0);
-            core.layers.push(layer0);
+            core.layers.insert(canvas::LayerId(0), layer0);
 
             self.current_layer      = layer0;
             self.current_sprite     = None;
@@ -97,24 +105,31 @@ impl CanvasRenderer {
         self.active_transform   = canvas::Transform2D::identity();
     }
 
+    ///
+    /// Returns the handle of the layer with the specified ID, creating an empty layer if it doesn't exist yet
+    ///
+    fn layer_with_id(core: &mut RenderCore, layer_id: canvas::LayerId) -> LayerHandle {
+        if let Some(layer_handle) = core.layers.get(&layer_id) {
+            *layer_handle
+        } else {
+            let new_layer = Self::create_default_layer();
+            let new_layer = core.allocate_layer_handle(new_layer);
+            core.layers.insert(layer_id, new_layer);
+
+            new_layer
+        }
+    }
+
     ///
     /// Selects a particular layer for drawing
     /// Layer 0 is selected initially. Layers are drawn in order starting from 0.
-    /// Layer IDs don't have to be sequential.
+    /// Layer IDs don't have to be sequential, and only the layers that are used are created.
     ///
-    pub (super) fn tes_layer(&mut self, canvas::LayerId(layer_id): canvas::LayerId) {
-        let layer_id    = layer_id as usize;
+    pub (super) fn tes_layer(&mut self, layer_id: canvas::LayerId) {
         let core        = Arc::clone(&self.core);
 
-        // Generate layers 
         core.sync(|core| {
-            while core.layers.len() <= layer_id  {
-                let new_layer = Self::create_default_layer();
-                let new_layer = core.allocate_layer_handle(new_layer);
-                core.layers.push(new_layer);
-            }
-
-            self.current_layer  = core.layers[layer_id];
+            self.current_layer  = Self::layer_with_id(core, layer_id);
             self.current_sprite = None;
         });
     }
@@ -122,13 +137,10 @@ impl CanvasRenderer {
     ///
     /// Sets how a particular layer is blended with the underlying layer
     ///
-    pub (super) fn tes_layer_blend(&mut self, canvas::LayerId(layer_id): canvas::LayerId, blend_mode: canvas::BlendMode) {
+    pub (super) fn tes_layer_blend(&mut self, layer_id: canvas::LayerId, blend_mode: canvas::BlendMode) {
         self.core.sync(move |core| {
-            let layer_id = layer_id as usize;
-
-            if layer_id < core.layers.len() {
+            if let Some(layer_handle) = core.layers.get(&layer_id).copied() {
                 // Fetch the layer
-                let layer_handle    = core.layers[layer_id];
                 let layer           = core.layer(layer_handle);
 
                 // Update the blend mode and set the layer's 'commit' mode
@@ -145,13 +157,10 @@ impl CanvasRenderer {
     ///
     /// Sets the alpha blend mode for a particular layer
     ///
-    pub (super) fn tes_layer_alpha(&mut self, canvas::LayerId(layer_id): canvas::LayerId, layer_alpha: f32) {
+    pub (super) fn tes_layer_alpha(&mut self, layer_id: canvas::LayerId, layer_alpha: f32) {
         self.core.sync(move |core| {
-            let layer_id = layer_id as usize;
-
-            if layer_id < core.layers.len() {
+            if let Some(layer_handle) = core.layers.get(&layer_id).copied() {
                 // Fetch the layer
-                let layer_handle    = core.layers[layer_id];
                 let layer           = core.layer(layer_handle);
 
                 let layer_alpha     = f32::max(0.0, f32::min(1.0, layer_alpha));
@@ -205,11 +214,11 @@ impl CanvasRenderer {
         *path_state = PathState::default();
 
         self.core.sync(|core| {
-            let handles = core.layers.clone();
+            let handles = core.layers.values().cloned().collect::<Vec<_>>();
 
             for handle in handles.into_iter() {
                 // Sprite layers are left alone
-                if core.layer(self.current_layer).state.is_sprite {
+                if core.layer(handle).state.is_sprite {
                     continue;
                 }
 
@@ -228,20 +237,12 @@ impl CanvasRenderer {
     ///
     /// Swaps two layers (changing their render order)
     ///
-    pub (super) fn tes_swap_layers(&mut self, canvas::LayerId(layer1): canvas::LayerId, canvas::LayerId(layer2): canvas::LayerId) {
+    pub (super) fn tes_swap_layers(&mut self, layer1: canvas::LayerId, layer2: canvas::LayerId) {
         if layer1 != layer2 {
             self.core.sync(move |core| {
                 // Create layers if they don't already exist so we can swap with arbitrary layers
-                let max_layer_id = u64::max(layer1, layer2) as usize;
-                while core.layers.len() <= max_layer_id  {
-                    let new_layer = Self::create_default_layer();
-                    let new_layer = core.allocate_layer_handle(new_layer);
-                    core.layers.push(new_layer);
-                }
-
-                // Swap the two layers in the core
-                let LayerHandle(handle1) = core.layers[layer1 as usize];
-                let LayerHandle(handle2) = core.layers[layer2 as usize];
+                let LayerHandle(handle1) = Self::layer_with_id(core, layer1);
+                let LayerHandle(handle2) = Self::layer_with_id(core, layer2);
 
                 if handle1 != handle2 {
                     core.layer_definitions.swap(handle1 as usize, handle2 as usize);
@@ -4,11 +4,11 @@ use flo_canvas as canvas;
 
 impl CanvasRenderer {
     ///
-    /// Clears the currently selected sprite
+    /// Sets the namespace used to identify sprites, textures and gradients in the following drawing instructions
     ///
     #[inline]
     pub (super) fn tes_namespace(&mut self, namespace: canvas::NamespaceId) {
-        // The current namespace is used to identify different groupds of resources
+        // The current namespace is used to identify different groups of resources
         self.current_namespace = namespace.local_id();
     }
 }
@@ -50,13 +50,13 @@ impl CanvasRenderer {
                             layer.render_order.push(RenderEntity::SetFlatColor);
                         }
 
-                        FillState::Texture(render_texture, _canvas_texture, matrix, repeat, alpha) => {
+                        FillState::Texture(render_texture, _canvas_texture, matrix, wrap_mode, alpha) => {
                             // Increase the usage count for this texture
                             core.used_textures.get_mut(&render_texture)
                                 .map(|usage_count| *usage_count += 1);
 
                             // Add to the layer
-                            core.layer(layer_id).render_order.push(RenderEntity::SetFillTexture(render_texture, matrix, repeat, alpha));
+                            core.layer(layer_id).render_order.push(RenderEntity::SetFillTexture(render_texture, matrix, wrap_mode, alpha));
                         }
 
                         FillState::LinearGradient(gradient_texture, _canvas_texture, matrix, repeat, alpha) => {
@@ -108,9 +108,10 @@ impl CanvasRenderer {
             if let Some(render_texture) = render_texture {
                 // Choose this texture
                 let alpha               = core.texture_alpha.get(&(namespace_id, texture_id)).cloned().unwrap_or(1.0);
+                let wrap_mode           = core.texture_wrap_mode.get(&(namespace_id, texture_id)).cloned().unwrap_or(render::TextureWrapMode::Repeat);
                 let layer               = core.layer(self.current_layer);
 
-                layer.state.fill_color  = FillState::texture_fill(render_texture, texture_id, x1, y1, x2, y2, alpha)
+                layer.state.fill_color  = FillState::texture_fill(render_texture, texture_id, x1, y1, x2, y2, wrap_mode, alpha)
             }
         });
     }
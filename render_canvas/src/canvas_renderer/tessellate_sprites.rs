@@ -140,10 +140,16 @@ impl CanvasRenderer {
         self.tes_clear_layer(path_state);
 
         self.core.sync(|core| {
-            // Remove the definition from the existing sprite
+            // Remove the definition from the existing sprite (if the sprite doesn't exist, the current sprite is just left cleared)
             if let Some(sprite_layer_handle) = core.sprites.remove(&(namespace_id, move_from_sprite_id)) {
-                // Set the current sprite to use the layer we just removed
-                core.sprites.insert((namespace_id, current_sprite_id), sprite_layer_handle);
+                // Set the current sprite to use the layer we just removed, releasing the layer it was using before
+                if let Some(old_layer_handle) = core.sprites.insert((namespace_id, current_sprite_id), sprite_layer_handle) {
+                    let old_layer = core.release_layer_handle(old_layer_handle);
+                    core.free_layer_entities(old_layer);
+                }
+
+                // Further drawing adds to the moved sprite
+                self.current_layer = sprite_layer_handle;
             }
         })
     }
@@ -31,6 +31,7 @@ impl CanvasRenderer {
                 // Remove entries from the layer until we reach the restore point
                 while layer.render_order.len() > restore_point {
                     let removed_entity = layer.render_order.pop();
+                    layer.entity_bounds.remove(&layer.render_order.len());
                     removed_entity.map(|removed| core.free_entity(removed));
 
                     // Reborrow the layer after removal
@@ -57,7 +58,7 @@ impl CanvasRenderer {
         self.namespace_stack.push(self.current_namespace);
 
         self.core.sync(|core| {
-            let all_layers = core.layers.iter().cloned()
+            let all_layers = core.layers.values().cloned()
                 .chain(core.sprites.iter().map(|(_, layer_id)| *layer_id))
                 .collect::<Vec<_>>();
 
@@ -79,7 +80,7 @@ impl CanvasRenderer {
         self.core.sync(|core| {
             core.layer(self.current_layer).update_transform(&self.active_transform);
 
-            let all_layers = core.layers.iter().cloned()
+            let all_layers = core.layers.values().cloned()
                 .chain(core.sprites.iter().map(|(_, layer_id)| *layer_id))
                 .collect::<Vec<_>>();
 
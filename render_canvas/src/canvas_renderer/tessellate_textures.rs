@@ -9,6 +9,29 @@ use flo_render as render;
 
 use std::sync::*;
 
+///
+/// Converts RGBA pixels with straight alpha to premultiplied alpha
+///
+/// The renderers blend in the same color space as the pixels are supplied in, so the colors are multiplied in that space too.
+/// Storing the textures premultiplied means that filtering and mipmapping never mix in the color of fully transparent pixels,
+/// which would otherwise show up as dark fringes around transparent edges.
+///
+fn straight_to_premultiplied_alpha(pixels: &[u8]) -> Vec<u8> {
+    let mut premultiplied = pixels.to_vec();
+
+    for pixel in premultiplied.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+
+        if alpha < 255 {
+            for component in pixel[0..3].iter_mut() {
+                *component = ((*component as u32 * alpha + 127) / 255) as u8;
+            }
+        }
+    }
+
+    premultiplied
+}
+
 impl CanvasRenderer {
     ///
     /// Dispatches a texture operation
@@ -16,15 +39,18 @@ impl CanvasRenderer {
     #[inline]
     pub (super) fn tes_texture(&mut self, namespace_id: usize, texture_id: canvas::TextureId, op: canvas::TextureOp) {
         use canvas::TextureOp::*;
-        use canvas::{TextureSize, TextureFormat};
+        use canvas::{TextureSize};
 
         match op {
-            Create(TextureSize(w, h), TextureFormat::Rgba)              => self.tes_texture_create_rgba(namespace_id, texture_id, w, h),
+            Create(TextureSize(w, h), format)                           => self.tes_texture_create_rgba(namespace_id, texture_id, w, h, format),
             Free                                                        => self.tes_texture_free(namespace_id, texture_id),
             SetBytes(position, size, bytes)                             => self.tes_texture_set_bytes(namespace_id, texture_id, position, size, bytes),
+            SetFromEncodedImage(bytes)                                  => self.tes_texture_set_from_encoded_image(namespace_id, texture_id, bytes),
             SetFromSprite(sprite_id, bounds)                            => self.tes_texture_set_from_sprite(namespace_id, texture_id, sprite_id, bounds),
             CreateDynamicSprite(sprite_id, sprite_bounds, canvas_size)  => self.tes_texture_create_dynamic_sprite(namespace_id, texture_id, sprite_id, sprite_bounds, canvas_size),
             FillTransparency(alpha)                                     => self.tes_texture_fill_transparency(namespace_id, texture_id, alpha),
+            SetWrapMode(wrap_mode)                                      => self.tes_texture_set_wrap_mode(namespace_id, texture_id, wrap_mode),
+            SetMipMaps(enabled)                                         => self.tes_texture_set_mipmaps(namespace_id, texture_id, enabled),
             Copy(target_texture_id)                                     => self.tes_texture_copy(namespace_id, texture_id, namespace_id, target_texture_id),
             Filter(filter)                                              => self.tes_texture_filter(namespace_id, texture_id, filter),
         }
@@ -33,8 +59,14 @@ impl CanvasRenderer {
     ///
     /// Creates or replaces a texture
     ///
-    fn tes_texture_create_rgba(&mut self, namespace_id: usize, texture_id: canvas::TextureId, width: u32, height: u32) {
+    fn tes_texture_create_rgba(&mut self, namespace_id: usize, texture_id: canvas::TextureId, width: u32, height: u32, format: canvas::TextureFormat) {
         self.core.sync(|core| {
+            // Remember if the bytes for this texture are already premultiplied (textures are always stored with premultiplied alpha)
+            match format {
+                canvas::TextureFormat::Rgba                 => { core.premultiplied_textures.remove(&(namespace_id, texture_id)); }
+                canvas::TextureFormat::RgbaPremultiplied    => { core.premultiplied_textures.insert((namespace_id, texture_id)); }
+            }
+
             // If the texture ID was previously in use, reduce the usage count
             let render_texture = if let Some(old_render_texture) = core.canvas_textures.get(&(namespace_id, texture_id)) {
                 let old_render_texture  = old_render_texture.into();
@@ -66,7 +98,7 @@ impl CanvasRenderer {
 
             // Create the texture in the texture request section
             use canvas::{TextureSize, TextureFormat};
-            core.layer_textures.push((render_texture, TextureRenderRequest::CreateBlankTexture(render_texture, TextureSize(width, height), TextureFormat::Rgba)));
+            core.layer_textures.push((render_texture, TextureRenderRequest::CreateBlankTexture(render_texture, TextureSize(width, height), TextureFormat::RgbaPremultiplied)));
         });
     }
 
@@ -84,6 +116,9 @@ impl CanvasRenderer {
 
             // Unmap the texture
             core.canvas_textures.remove(&(namespace_id, texture_id));
+            core.textures_without_mipmaps.remove(&(namespace_id, texture_id));
+            core.texture_wrap_mode.remove(&(namespace_id, texture_id));
+            core.premultiplied_textures.remove(&(namespace_id, texture_id));
         });
     }
 
@@ -92,6 +127,13 @@ impl CanvasRenderer {
     ///
     fn tes_texture_set_bytes(&mut self, namespace_id: usize, texture_id: canvas::TextureId, canvas::TexturePosition(x, y): canvas::TexturePosition, canvas::TextureSize(width, height): canvas::TextureSize, bytes: Arc<Vec<u8>>) {
         self.core.sync(|core| {
+            // Textures are stored with premultiplied alpha, so straight alpha pixels are converted as they're written
+            let bytes = if core.premultiplied_textures.contains(&(namespace_id, texture_id)) {
+                bytes
+            } else {
+                Arc::new(straight_to_premultiplied_alpha(&bytes))
+            };
+
             // Create a canvas renderer job that will write these bytes to the texture
             if let Some(render_texture) = core.canvas_textures.get(&(namespace_id, texture_id)) {
                 let mut render_texture = *render_texture;
@@ -133,6 +175,31 @@ impl CanvasRenderer {
         });
     }
 
+    ///
+    /// Decodes an image file and replaces a texture with its contents
+    ///
+    /// If the image can't be decoded, the texture is freed and the error is recorded for `take_texture_errors()`
+    ///
+    fn tes_texture_set_from_encoded_image(&mut self, namespace_id: usize, texture_id: canvas::TextureId, bytes: Arc<Vec<u8>>) {
+        #[cfg(feature = "image-formats")]
+        let decoded = canvas::decode_image_rgba(&bytes).map_err(|err| err.to_string());
+
+        #[cfg(not(feature = "image-formats"))]
+        let decoded = { let _ = bytes; Err::<(u32, u32, Vec<u8>), _>("decoding images requires the image-formats feature".to_string()) };
+
+        match decoded {
+            Ok((width, height, pixels)) => {
+                self.tes_texture_create_rgba(namespace_id, texture_id, width, height, canvas::TextureFormat::Rgba);
+                self.tes_texture_set_bytes(namespace_id, texture_id, canvas::TexturePosition(0, 0), canvas::TextureSize(width, height), Arc::new(pixels));
+            }
+
+            Err(err) => {
+                self.tes_texture_free(namespace_id, texture_id);
+                self.texture_errors.push((texture_id, err));
+            }
+        }
+    }
+
     ///
     /// Render a texture from a sprite
     ///
@@ -244,6 +311,48 @@ impl CanvasRenderer {
         });
     }
 
+    ///
+    /// Sets how a texture is addressed when it's used as a fill
+    ///
+    fn tes_texture_set_wrap_mode(&mut self, namespace_id: usize, texture_id: canvas::TextureId, wrap_mode: canvas::TextureWrapMode) {
+        let wrap_mode = match wrap_mode {
+            canvas::TextureWrapMode::Clamp          => render::TextureWrapMode::Clamp,
+            canvas::TextureWrapMode::Repeat         => render::TextureWrapMode::Repeat,
+            canvas::TextureWrapMode::MirrorRepeat   => render::TextureWrapMode::MirrorRepeat,
+        };
+
+        self.core.sync(|core| {
+            core.texture_wrap_mode.insert((namespace_id, texture_id), wrap_mode);
+            let layer                   = core.layer(self.current_layer);
+
+            if layer.state.fill_color.texture_id() == Some(texture_id) {
+                layer.state.fill_color  = layer.state.fill_color.with_texture_wrap_mode(wrap_mode);
+            }
+        });
+    }
+
+    ///
+    /// Sets whether or not mipmaps are generated for a texture when it finishes loading
+    ///
+    fn tes_texture_set_mipmaps(&mut self, namespace_id: usize, texture_id: canvas::TextureId, enabled: bool) {
+        self.core.sync(|core| {
+            if enabled {
+                core.textures_without_mipmaps.remove(&(namespace_id, texture_id));
+
+                // A texture that was loaded without mipmaps can be finished again, provided nothing else is using it
+                if let Some(RenderTexture::Ready(render_texture_id)) = core.canvas_textures.get(&(namespace_id, texture_id)) {
+                    let render_texture_id = *render_texture_id;
+
+                    if core.used_textures.get(&render_texture_id) == Some(&1) {
+                        core.canvas_textures.insert((namespace_id, texture_id), RenderTexture::Loading(render_texture_id));
+                    }
+                }
+            } else {
+                core.textures_without_mipmaps.insert((namespace_id, texture_id));
+            }
+        });
+    }
+
     ///
     /// Generates a copy from one texture to another
     ///
@@ -267,6 +376,13 @@ impl CanvasRenderer {
             core.used_textures.insert(target_render_texture, 1);
             core.texture_size.insert(target_render_texture, source_texture_size);
 
+            // The target has the same format as the source
+            if core.premultiplied_textures.contains(&(source_namespace_id, source_texture_id)) {
+                core.premultiplied_textures.insert((target_namespace_id, target_texture_id));
+            } else {
+                core.premultiplied_textures.remove(&(target_namespace_id, target_texture_id));
+            }
+
             // Increase the usage count of the source texture (it's decreased again once the copy completes)
             if let Some(source_usage_count) = core.used_textures.get_mut(&source_render_texture.into()) {
                 *source_usage_count += 1;
@@ -18,7 +18,8 @@ impl CanvasRenderer {
         // Window height is set at 2.0 by the viewport transform
         let window_height       = 2.0;
 
-        // Work out the scale to use for this widget
+        // Work out the scale to use for this widget (heights that can't produce a usable transform are ignored)
+        if !height.is_finite() { return; }
         let height              = f32::max(1.0, height);
         let scale               = window_height / height;
         let scale               = canvas::Transform2D::scale(scale, scale);
@@ -36,9 +37,9 @@ impl CanvasRenderer {
         let center_x                = 0.0;
         let center_y                = 0.0;
 
-        // Find the current center point
+        // Find the current center point (there's no center if the transform has collapsed to a point)
         let current_transform       = self.active_transform.clone();
-        let inverse_transform       = current_transform.invert().unwrap();
+        let inverse_transform       = if let Some(inverse_transform) = current_transform.invert() { inverse_transform } else { return; };
 
         let (center_x, center_y)    = inverse_transform.transform_point(center_x, center_y);
 
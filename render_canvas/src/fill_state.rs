@@ -21,7 +21,7 @@ pub enum FillState {
     ///
     /// Fill with a particular texture
     ///
-    Texture(render::TextureId, canvas::TextureId, render::Matrix, bool, f32),
+    Texture(render::TextureId, canvas::TextureId, render::Matrix, render::TextureWrapMode, f32),
 
     ///
     /// Fill with a particular gradient
@@ -45,7 +45,7 @@ impl FillState {
     ///
     /// Creates a texture fill 
     ///
-    pub fn texture_fill(render_texture: render::TextureId, canvas_texture: canvas::TextureId, x1: f32, y1: f32, x2: f32, y2: f32, alpha: f32) -> FillState {
+    pub fn texture_fill(render_texture: render::TextureId, canvas_texture: canvas::TextureId, x1: f32, y1: f32, x2: f32, y2: f32, wrap_mode: render::TextureWrapMode, alpha: f32) -> FillState {
         // Avoid division by zero
         let x2 = if x2 == x1 { x1 + 0.0000001 } else { x2 };
         let y2 = if y2 == y1 { y1 + 0.0000001 } else { y2 };
@@ -67,7 +67,7 @@ impl FillState {
         ]);
 
         // Create the fill-state for this matrix
-        FillState::Texture(render_texture, canvas_texture, matrix, true, alpha)
+        FillState::Texture(render_texture, canvas_texture, matrix, wrap_mode, alpha)
     }
 
     ///
@@ -131,6 +131,18 @@ impl FillState {
         }
     }
 
+    ///
+    /// Updates the fill state with a new texture wrap mode
+    ///
+    pub fn with_texture_wrap_mode(&self, new_wrap_mode: render::TextureWrapMode) -> Self {
+        match self {
+            FillState::None                                                         => self.clone(),
+            FillState::Color(_)                                                     => self.clone(),
+            FillState::Texture(render_texture, canvas_texture, matrix, _, alpha)    => FillState::Texture(*render_texture, *canvas_texture, *matrix, new_wrap_mode, *alpha),
+            FillState::LinearGradient(_, _, _, _, _)                                => self.clone()
+        }
+    }
+
     ///
     /// Updates the fill state with a transformed matrix
     ///
@@ -18,9 +18,11 @@ mod renderer_stream;
 mod offscreen;
 mod matrix;
 mod dynamic_texture_state;
+mod resource_events;
 
 pub use self::canvas_renderer::*;
 pub use self::offscreen::*;
+pub use self::resource_events::*;
 
 pub use flo_render::*;
 pub use flo_canvas as canvas;
@@ -5,6 +5,8 @@ use flo_render::*;
 
 use futures::prelude::*;
 
+use std::iter;
+
 ///
 /// Renders a canvas in an offscreen context, returning the resulting bitmap
 ///
@@ -40,4 +42,120 @@ where
         // Result is the realized rendering
         render_target.realize()
     }
-}
\ No newline at end of file
+}
+
+///
+/// The position and size of a tile generated by `render_canvas_offscreen_tiles()`, in pixels from the top-left corner of the whole image
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TileCoords {
+    pub x:      usize,
+    pub y:      usize,
+    pub width:  usize,
+    pub height: usize,
+}
+
+///
+/// Renders a canvas in an offscreen context as a set of tiles, so that images too large to be rendered in one go can be generated
+///
+/// Only one tile-sized render target exists at a time: each tile is passed to the callback as it's finished, as bytes in the same
+/// format as `render_canvas_offscreen()` returns. Tiles are generated from left to right and top to bottom, and the tiles along the
+/// right and bottom edges are smaller if the image size is not a multiple of the tile size. Every tile renders a viewport onto the
+/// same window-sized transform, so the tiles join up without gaps or overlaps.
+///
+/// The drawing is rendered again for each tile, so this is slower than `render_canvas_offscreen()` when the whole image will fit
+/// in memory.
+///
+pub fn render_canvas_offscreen_tiles<'a, RenderContext, TileFn>(context: &'a mut RenderContext, width: usize, height: usize, tile_width: usize, tile_height: usize, scale: f32, drawing: &'a [Draw], for_each_tile: TileFn) -> impl 'a+Future<Output=()>
+where
+    RenderContext:  'a+OffscreenRenderContext,
+    TileFn:         'a+FnMut(TileCoords, Vec<u8>),
+{
+    async move {
+        let mut for_each_tile   = for_each_tile;
+        let tile_width          = tile_width.max(1);
+        let tile_height         = tile_height.max(1);
+
+        // The same render target is used for every tile (it's resized for the smaller tiles along the edges)
+        let mut tile_target     = None;
+        let mut target_size     = (0, 0);
+
+        for tile_y in (0..height).step_by(tile_height) {
+            for tile_x in (0..width).step_by(tile_width) {
+                let tile = TileCoords {
+                    x:      tile_x,
+                    y:      tile_y,
+                    width:  tile_width.min(width - tile_x),
+                    height: tile_height.min(height - tile_y),
+                };
+
+                // The viewport is measured from the bottom of the window
+                let min_x       = tile.x as f32;
+                let max_x       = (tile.x + tile.width) as f32;
+                let min_y       = (height - tile.y - tile.height) as f32;
+                let max_y       = (height - tile.y) as f32;
+
+                // Fetch the render target, sized for this tile
+                let render_target = tile_target.get_or_insert_with(|| {
+                    target_size = (tile.width, tile.height);
+                    context.create_render_target(tile.width, tile.height)
+                });
+
+                if target_size != (tile.width, tile.height) {
+                    target_size = (tile.width, tile.height);
+                    render_target.resize(tile.width, tile.height);
+                }
+
+                // Render the drawing into the render target, after clearing away the previous tile
+                let mut renderer = CanvasRenderer::new();
+                renderer.set_viewport(min_x..max_x, min_y..max_y, width as f32, height as f32, scale);
+
+                let rendering = renderer.draw(drawing.iter().cloned()).collect::<Vec<_>>().await;
+                render_target.render(vec![RenderAction::RenderToFrameBuffer, RenderAction::Clear(Rgba8([0, 0, 0, 0]))]);
+                render_target.render(rendering);
+
+                for_each_tile(tile, render_target.read_pixels());
+            }
+        }
+    }
+}
+
+///
+/// Renders a drawing in an offscreen context and reads back the pixels of one of the textures it defines
+///
+/// The result is the width and height of the texture, and its pixels as straight-alpha RGBA bytes in the same order that
+/// `TextureOp::SetBytes` uses. The pixels are copied from the texture after all of the other texture operations in the drawing
+/// have been applied, so copies and filters are included. Dynamic textures are rendered at the size they would have in a viewport
+/// of the specified width and height. The texture is looked up in the default namespace, and None is returned if the drawing
+/// doesn't define it.
+///
+/// Textures are stored with premultiplied alpha, so the colour of pixels that are nearly transparent is only approximately
+/// the colour that was set (and fully transparent pixels are always returned as transparent black).
+///
+pub fn render_texture_offscreen<'a, RenderContext>(context: &'a mut RenderContext, width: usize, height: usize, scale: f32, drawing: &'a [Draw], texture_id: flo_canvas::TextureId) -> impl 'a+Future<Output=Option<(usize, usize, Vec<u8>)>>
+where
+    RenderContext: 'a+OffscreenRenderContext,
+{
+    async move {
+        // Render the drawing so the texture is created and updated in the render target (the drawing can leave any namespace selected)
+        let drawing             = drawing.iter().cloned().chain(iter::once(Draw::Namespace(NamespaceId::default())));
+        let mut render_target   = context.create_render_target(width.max(1), height.max(1));
+        let mut renderer        = CanvasRenderer::new();
+        renderer.set_viewport(0.0..(width as f32), 0.0..(height as f32), width as f32, height as f32, scale);
+
+        let rendering = renderer.draw(drawing).collect::<Vec<_>>().await;
+        render_target.render(rendering);
+
+        // Dynamic textures are only sized once they've been rendered
+        let (texture_width, texture_height) = renderer.get_texture_size(texture_id)?;
+        if texture_width == 0 || texture_height == 0 {
+            return Some((texture_width, texture_height, vec![]));
+        }
+
+        // Copy the pixels directly from the texture
+        let render_texture  = renderer.get_render_texture(texture_id)?;
+        let pixels          = render_target.read_texture(render_texture)?;
+
+        Some((texture_width, texture_height, pixels))
+    }
+}
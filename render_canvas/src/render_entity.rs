@@ -51,7 +51,7 @@ pub enum RenderEntity {
     SetDashPattern(Vec<f32>),
 
     /// Sets the fill texture to use for the following rendering
-    SetFillTexture(render::TextureId, render::Matrix, bool, f32),
+    SetFillTexture(render::TextureId, render::Matrix, render::TextureWrapMode, f32),
 
     /// Sets the gradient texture to use for the following rendering
     SetFillGradient(render::TextureId, render::Matrix, bool, f32),
@@ -60,5 +60,8 @@ pub enum RenderEntity {
     EnableClipping(render::VertexBufferId, render::IndexBufferId, usize),
 
     /// Stop clipping
-    DisableClipping
+    DisableClipping,
+
+    /// Vertex buffer that has been merged into the buffer of an earlier entity in the same layer (so is drawn along with that entity)
+    Batched,
 }
@@ -15,11 +15,21 @@ use super::texture_render_request::*;
 use flo_canvas as canvas;
 use flo_render as render;
 
-use lyon::tessellation::{FillRule};
+use lyon::tessellation::{FillRule, VertexBuffers};
 
 use std::mem;
 use std::sync::*;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, BTreeMap};
+
+///
+/// The maximum number of vertices that can be merged into a single vertex buffer (limited by the size of the index type)
+///
+const MAX_BATCH_VERTICES: usize = u16::MAX as usize;
+
+///
+/// The maximum depth that sprites can be nested when rendering (deeper sprites are not drawn, which stops sprites that draw themselves from recursing forever)
+///
+pub (crate) const MAX_SPRITE_DEPTH: usize = 64;
 
 ///
 /// Parts of the renderer that are shared with the workers
@@ -31,8 +41,8 @@ pub struct RenderCore {
     /// One-time setup actions that are waiting to be rendered
     pub setup_actions: Vec<render::RenderAction>,
 
-    /// The definition for the layers
-    pub layers: Vec<LayerHandle>,
+    /// The layers that make up the canvas, in the order they are rendered (ascending layer ID)
+    pub layers: BTreeMap<canvas::LayerId, LayerHandle>,
 
     /// The background colour to clear to when rendering the canvas
     pub background_color: render::Rgba8,
@@ -67,6 +77,18 @@ pub struct RenderCore {
     /// The alpha value to use for each texture, next time it's used
     pub texture_alpha: HashMap<(usize, canvas::TextureId), f32>,
 
+    /// The wrap mode to use for each texture, next time it's used as a fill (textures repeat if they have no entry here)
+    pub texture_wrap_mode: HashMap<(usize, canvas::TextureId), render::TextureWrapMode>,
+
+    /// Textures that should not have mipmaps generated for them when they're finished loading
+    pub textures_without_mipmaps: HashSet<(usize, canvas::TextureId)>,
+
+    /// Textures that were created with the premultiplied alpha format (bytes written to the other textures are converted to premultiplied alpha)
+    pub premultiplied_textures: HashSet<(usize, canvas::TextureId)>,
+
+    /// True if a layer with more than one clip path should be clipped to the area where the paths intersect (using the stencil buffer)
+    pub stencil_clipping: bool,
+
     /// The actual layer definitions
     pub layer_definitions: Vec<Layer>,
 
@@ -90,6 +112,9 @@ pub struct RenderCore {
 
     /// Render targets that were previously used by are now free
     pub free_render_targets: Vec<render::RenderTargetId>,
+
+    /// The number of sprites that are currently being rendered inside each other
+    pub sprite_render_depth: usize,
 }
 
 impl RenderCore {
@@ -118,6 +143,7 @@ impl RenderCore {
             SetDashPattern(_)                       => { }
             RenderSprite(_, _, _)                   => { }
             DisableClipping                         => { }
+            Batched                                 => { }
 
             SetFillTexture(texture_id, _, _, _)     => { 
                 self.used_textures.get_mut(&texture_id)
@@ -144,6 +170,12 @@ impl RenderCore {
                 if index_id != vertex_id {
                     self.free_vertex_buffers.push(index_id);
                 }
+
+                // Tell the renderer that the buffers are free before the next frame (so it can re-use or release their memory)
+                self.setup_actions.extend(vec![
+                    render::RenderAction::FreeVertexBuffer(render::VertexBufferId(vertex_id)),
+                    render::RenderAction::FreeIndexBuffer(render::IndexBufferId(index_id)),
+                ]);
             }
         }
     }
@@ -170,7 +202,7 @@ impl RenderCore {
             .collect::<HashSet<_>>();
 
         // Remove any texture that's selected as the fill state from the unused list (these still count as 'used')
-        for layer_handle in self.layers.iter() {
+        for layer_handle in self.layers.values() {
             let state = &self.layer_readonly(*layer_handle).state;
             match &state.fill_color {
                 FillState::Texture(texture_id, _, _, _, _)          => { unused_textures.remove(texture_id); }
@@ -212,9 +244,45 @@ impl RenderCore {
         render_actions
     }
 
+    ///
+    /// Frees every GPU resource that this core is using, returning the render actions that release them
+    ///
+    /// This is for when the renderer is about to be discarded: the core is left with no layer entities or textures, so it
+    /// should not be used for rendering afterwards.
+    ///
+    pub fn free_all_resources(&mut self) -> Vec<render::RenderAction> {
+        // The vertex buffers are freed by removing all of the layer entities (this adds to the setup actions)
+        let layers = mem::take(&mut self.layer_definitions);
+        for layer in layers.into_iter() {
+            self.free_layer_entities(layer);
+        }
+
+        let mut render_actions = mem::take(&mut self.setup_actions);
+
+        // Every texture that has been assigned to a canvas texture, a gradient or a layer render request
+        let mut textures = self.used_textures.keys().copied().collect::<HashSet<_>>();
+        textures.extend(self.canvas_textures.drain().map(|(_, render_texture)| render_texture.into()));
+        textures.extend(self.canvas_gradients.drain().filter_map(|(_, gradient)| match gradient {
+            RenderGradient::Ready(texture_id, _)    => Some(texture_id),
+            RenderGradient::Defined(_)              => None,
+        }));
+        textures.extend(self.layer_textures.drain(..).map(|(texture_id, _)| texture_id));
+
+        // Render targets need to be freed before the textures they render to
+        render_actions.extend(self.render_target_for_texture.drain().map(|(_, render_target_id)| render::RenderAction::FreeRenderTarget(render_target_id)));
+        render_actions.extend(textures.into_iter().map(render::RenderAction::FreeTexture));
+
+        self.used_textures.clear();
+
+        render_actions
+    }
+
     ///
     /// Stores the result of a worker job in this core item
     ///
+    /// Entity IDs are never re-used, so if the layer has been cleared (or the canvas has been cleared) since the job was
+    /// started, the result will not match the `Tessellating` entity at its position and is discarded.
+    ///
     pub fn store_job_result(&mut self, entity_ref: LayerEntityRef, render_entity: RenderEntity, details: RenderEntityDetails) {
         let LayerHandle(layer_idx)  = entity_ref.layer_id;
         let layer_idx               = layer_idx as usize;
@@ -239,6 +307,7 @@ impl RenderCore {
                 return;
             }
         } else {
+            self.free_entity(render_entity);
             return;
         }
 
@@ -246,6 +315,7 @@ impl RenderCore {
         let layer = &mut self.layer_definitions[layer_idx];
 
         layer.render_order[entity_ref.entity_index] = render_entity;
+        layer.entity_bounds.insert(entity_ref.entity_index, details.bounds);
         layer.bounds.add_entity_with_details(details);
     }
 
@@ -363,6 +433,77 @@ impl RenderCore {
         }
     }
 
+    ///
+    /// Merges runs of vertex buffers that are next to each other in a layer so that they can be drawn with a single draw call
+    ///
+    /// Entities that are adjacent in the render order are always rendered with the same state, so merging them won't change the
+    /// result. The merged buffer replaces the first entity in each run, and the remaining entities are replaced with `Batched`.
+    /// Runs are never merged across the layer's restore point, so `Restore` still removes whole entities.
+    ///
+    fn batch_vertex_buffers(&mut self, layer_handle: LayerHandle) {
+        let layer           = self.layer(layer_handle);
+        let restore_point   = layer.state.restore_point;
+        let render_order    = &mut layer.render_order;
+        let entity_bounds   = &mut layer.entity_bounds;
+
+        let mut render_idx  = 0;
+        while render_idx < render_order.len() {
+            // Find the run of vertex buffers that starts at this index
+            let mut run_end         = render_idx;
+            let mut num_vertices    = 0;
+            let mut num_indices     = 0;
+
+            while run_end < render_order.len() {
+                if run_end > render_idx && Some(run_end) == restore_point {
+                    break;
+                }
+
+                match &render_order[run_end] {
+                    RenderEntity::VertexBuffer(buffers, VertexBufferIntent::Draw) if num_vertices + buffers.vertices.len() <= MAX_BATCH_VERTICES => {
+                        num_vertices    += buffers.vertices.len();
+                        num_indices     += buffers.indices.len();
+                        run_end         += 1;
+                    }
+
+                    _ => { break; }
+                }
+            }
+
+            if run_end - render_idx > 1 {
+                // Combine the buffers in this run into a single buffer
+                let mut batch = VertexBuffers::with_capacity(num_vertices, num_indices);
+
+                for entity_idx in render_idx..run_end {
+                    if let RenderEntity::VertexBuffer(buffers, _) = mem::replace(&mut render_order[entity_idx], RenderEntity::Batched) {
+                        let index_offset = batch.vertices.len() as u16;
+
+                        batch.vertices.extend(buffers.vertices);
+                        batch.indices.extend(buffers.indices.into_iter().map(|idx| idx + index_offset));
+                    }
+                }
+
+                render_order[render_idx] = RenderEntity::VertexBuffer(batch, VertexBufferIntent::Draw);
+
+                // The merged entity covers the bounds of all the entities in the run (the bounds are left unknown if any entity's bounds are unknown)
+                let batch_bounds = (render_idx..run_end)
+                    .map(|entity_idx| entity_bounds.remove(&entity_idx))
+                    .fold(Some(LayerBounds::default()), |batch_bounds, bounds| {
+                        match (batch_bounds, bounds) {
+                            (Some(mut batch_bounds), Some(bounds))  => { batch_bounds.combine(&bounds); Some(batch_bounds) }
+                            _                                       => None
+                        }
+                    });
+
+                if let Some(batch_bounds) = batch_bounds {
+                    entity_bounds.insert(render_idx, batch_bounds);
+                }
+                render_idx = run_end;
+            } else {
+                render_idx += 1;
+            }
+        }
+    }
+
     ///
     /// Returns the render actions needed to prepare the render buffers for the specified layer (and updates the layer
     /// so that the buffers are not sent again)
@@ -370,6 +511,9 @@ impl RenderCore {
     pub fn send_vertex_buffers(&mut self, layer_handle: LayerHandle) -> Vec<render::RenderAction> {
         use self::RenderEntity::*;
 
+        // Draw calls with the same state are combined before the buffers are sent
+        self.batch_vertex_buffers(layer_handle);
+
         let mut send_vertex_buffers = vec![];
         let mut layer               = self.layer(layer_handle);
         let mut active_transform    = canvas::Transform2D::identity();
@@ -419,6 +563,60 @@ impl RenderCore {
         send_vertex_buffers
     }
 
+    ///
+    /// Calculates the bounds of everything drawn on a layer, including any sprites it draws, in the layer's own coordinates
+    ///
+    /// This is worked out from the entities currently on the layer rather than the `bounds` field, which only includes the
+    /// sprites that were drawn by the last frame that was rendered.
+    ///
+    pub fn layer_content_bounds(&mut self, layer_handle: LayerHandle, depth: usize) -> LayerBounds {
+        use self::RenderEntity::*;
+
+        let mut bounds              = LayerBounds::default();
+        let mut active_transform    = canvas::Transform2D::identity();
+        let mut sprites             = vec![];
+
+        // Clipping paths have bounds but aren't drawn (they're only uploaded as 'EnableClipping' once the layer is rendered)
+        let layer = self.layer(layer_handle);
+        for (entity_index, entity_bounds) in layer.entity_bounds.iter() {
+            match layer.render_order.get(*entity_index) {
+                Some(EnableClipping(_, _, _))                       |
+                Some(VertexBuffer(_, VertexBufferIntent::Clip))     => { }
+                _                                                   => { bounds.combine(entity_bounds); }
+            }
+        }
+
+        for entity in layer.render_order.iter() {
+            match entity {
+                SetTransform(new_transform) => { active_transform = *new_transform; }
+
+                RenderSprite(namespace_id, sprite_id, transform)                    => { sprites.push((*namespace_id, *sprite_id, active_transform * *transform, 0.0)); }
+                RenderSpriteWithFilters(namespace_id, sprite_id, transform, filters) => {
+                    let filter_radius = filters.iter()
+                        .fold(0.0, |radius, filter| f32::max(radius, filter.radius()));
+                    sprites.push((*namespace_id, *sprite_id, active_transform * *transform, filter_radius));
+                }
+
+                _ => { }
+            }
+        }
+
+        // Sprites that draw themselves stop once they're nested too deeply, the same as when they're rendered
+        if depth < MAX_SPRITE_DEPTH {
+            for (namespace_id, sprite_id, transform, filter_radius) in sprites {
+                if let Some(sprite_layer_handle) = self.sprites.get(&(namespace_id, sprite_id)).cloned() {
+                    let sprite_bounds = self.layer_content_bounds(sprite_layer_handle, depth + 1)
+                        .transform(&transform)
+                        .inflate(filter_radius);
+
+                    bounds.combine(&sprite_bounds);
+                }
+            }
+        }
+
+        bounds
+    }
+
     ///
     /// Returns a render texture for a canvas texture
     ///
@@ -430,7 +628,9 @@ impl RenderCore {
                 let render_texture = *render_texture;
 
                 // Finish the texture
-                self.layer_textures.push((render_texture, TextureRenderRequest::CreateMipMaps(render_texture)));
+                if !self.textures_without_mipmaps.contains(&(namespace_id, texture_id)) {
+                    self.layer_textures.push((render_texture, TextureRenderRequest::CreateMipMaps(render_texture)));
+                }
 
                 // Mark as finished
                 if let Some(texture) = self.canvas_textures.get_mut(&(namespace_id, texture_id)) {
@@ -525,6 +725,7 @@ impl RenderCore {
                 restore_point:      None
             },
             bounds:                     LayerBounds::default(),
+            entity_bounds:              HashMap::new(),
             stored_states:              vec![],
             commit_before_rendering:    false,
             commit_after_rendering:     false,
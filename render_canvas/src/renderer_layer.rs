@@ -4,6 +4,8 @@ use super::render_entity::*;
 
 use flo_canvas as canvas;
 
+use std::collections::{HashMap};
+
 ///
 /// Definition of a layer in the canvas
 ///
@@ -14,6 +16,9 @@ pub struct Layer {
     /// The bounds of the entities rendered to this layer
     pub bounds: LayerBounds,
 
+    /// The bounds of the individual entities in this layer, indexed by their position in the render order
+    pub entity_bounds: HashMap<usize, LayerBounds>,
+
     /// The state of this layer
     pub state: LayerState,
 
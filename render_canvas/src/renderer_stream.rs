@@ -43,7 +43,7 @@ enum ShaderModifier {
     DashPattern(Vec<f32>),
 
     /// Shader should use a texture
-    Texture(render::TextureId, render::Matrix, bool, f32),
+    Texture(render::TextureId, render::Matrix, render::TextureWrapMode, f32),
 
     /// Shader should use a gradient
     Gradient(render::TextureId, render::Matrix, bool, f32),
@@ -71,8 +71,8 @@ pub struct RenderStream<'a> {
     /// The current layer ID that we're processing
     layer_id: usize,
 
-    /// The total number of layers in the core
-    layer_count: usize,
+    /// The layers in the core, in the order that they are rendered
+    layer_handles: Vec<LayerHandle>,
 
     /// The render entity within the layer that we're processing
     render_index: usize,
@@ -93,7 +93,10 @@ pub struct RenderStream<'a> {
     viewport_size: render::Size2D,
 
     /// The region of the layer buffer that has been drawn on
-    invalid_bounds: LayerBounds
+    invalid_bounds: LayerBounds,
+
+    /// The region of the viewport that is being redrawn (None if the whole viewport is being redrawn)
+    damage_region: Option<LayerBounds>,
 }
 
 ///
@@ -119,6 +122,9 @@ struct RenderStreamState {
     /// The buffers to use to render the clipping region
     clip_buffers: Option<Vec<(render::VertexBufferId, render::IndexBufferId, usize)>>,
 
+    /// True if the clipping region is where all of the clip buffers overlap rather than where any of them are
+    stencil_clipping: bool,
+
     /// Set to true or false if this layer has left the layer buffer clear (or None if this is unknown)
     is_clear: Option<bool>,
 
@@ -127,6 +133,9 @@ struct RenderStreamState {
 
     /// The size of the viewport
     viewport_size: render::Size2D,
+
+    /// The region of the viewport that is being redrawn (entities outside of this region are not drawn)
+    damage_region: Option<LayerBounds>,
 }
 
 impl<'a> RenderStream<'a> {
@@ -135,7 +144,10 @@ impl<'a> RenderStream<'a> {
     ///
     /// If rendering is suspended at the point that the processing future completes then the initial and final actions will not be taken
     ///
-    pub fn new<ProcessFuture>(core: Arc<Desync<RenderCore>>, processing_future: ProcessFuture, viewport_transform: canvas::Transform2D, viewport_size: render::Size2D, background_vertex_buffer: render::VertexBufferId, initial_actions: Vec<render::RenderAction>, final_actions: Vec<render::RenderAction>) -> RenderStream<'a>
+    /// If a damage region is supplied (in viewport coordinates), the layers are rendered with a scissor region set to that region,
+    /// and any entities that are entirely outside of it are skipped.
+    ///
+    pub fn new<ProcessFuture>(core: Arc<Desync<RenderCore>>, processing_future: ProcessFuture, viewport_transform: canvas::Transform2D, viewport_size: render::Size2D, damage_region: Option<LayerBounds>, background_vertex_buffer: render::VertexBufferId, initial_actions: Vec<render::RenderAction>, final_actions: Vec<render::RenderAction>) -> RenderStream<'a>
     where   ProcessFuture: 'a+Send+Future<Output=()> {
         RenderStream {
             core:                       core,
@@ -149,8 +161,9 @@ impl<'a> RenderStream<'a> {
             viewport_size:              viewport_size,
             layer_buffer_is_clear:      true,
             invalid_bounds:             LayerBounds::default(),
+            damage_region:              damage_region,
             layer_id:                   0,
-            layer_count:                0,
+            layer_handles:              vec![],
             render_index:               0,
         }
     }
@@ -181,12 +194,24 @@ impl RenderStreamState {
             shader_modifier:    None,
             transform:          None,
             clip_buffers:       None,
+            stencil_clipping:   false,
             is_clear:           None,
             viewport_size:      viewport_size,
-            invalid_bounds:     LayerBounds::default()
+            invalid_bounds:     LayerBounds::default(),
+            damage_region:      None,
         }
     }
 
+    ///
+    /// Returns the region that drawing can affect (the damage region if there is one, or the whole viewport otherwise)
+    ///
+    /// Entities whose bounds are outside of this region are skipped when rendering. They stay in their layers, so they are
+    /// drawn again if a later frame moves them back into view.
+    ///
+    fn visible_region(&self) -> LayerBounds {
+        self.damage_region.unwrap_or(LayerBounds { min_x: -1.0, min_y: -1.0, max_x: 1.0, max_y: 1.0 })
+    }
+
     ///
     /// Generates the actions required to set a particular dash pattern
     ///
@@ -244,9 +269,23 @@ impl RenderStreamState {
         // Update the content of the clip mask render target
         if let (Some(clip_buffers), Some(transform)) = (&self.clip_buffers, self.transform) {
             if Some(clip_buffers) != from.clip_buffers.as_ref() && clip_buffers.len() > 0 {
-                let render_clip_buffers = clip_buffers.iter()
+                let mut render_clip_buffers = clip_buffers.iter()
                     .rev()
-                    .map(|(vertices, indices, length)| render::RenderAction::DrawIndexedTriangles(*vertices, *indices, *length));
+                    .map(|(vertices, indices, length)| render::RenderAction::DrawIndexedTriangles(*vertices, *indices, *length))
+                    .collect::<Vec<_>>();
+
+                if self.stencil_clipping && clip_buffers.len() > 1 {
+                    // Count how many clip paths cover each pixel in the stencil buffer, then only draw the last path where all of the others overlap
+                    let num_clip_paths  = clip_buffers.len();
+                    let last_clip_path  = render_clip_buffers.pop().unwrap();
+
+                    render_clip_buffers.insert(0, render::RenderAction::StencilMode(render::StencilMode::Increment));
+                    render_clip_buffers.extend(vec![
+                        render::RenderAction::StencilMode(render::StencilMode::Equal((num_clip_paths-1).min(255) as u8)),
+                        last_clip_path,
+                        render::RenderAction::StencilMode(render::StencilMode::Disabled),
+                    ]);
+                }
 
                 // Set up to render the clip buffers
                 updates.extend(vec![
@@ -294,8 +333,8 @@ impl RenderStreamState {
                 let shader = match modifier {
                     ShaderModifier::Simple                                      => render::ShaderType::Simple { clip_texture: clip },
                     ShaderModifier::DashPattern(_)                              => render::ShaderType::DashedLine { dash_texture: DASH_TEXTURE, clip_texture: clip },
-                    ShaderModifier::Texture(texture_id, matrix, repeat, alpha)  => render::ShaderType::Texture { texture: *texture_id, texture_transform: *matrix, repeat: *repeat, alpha: *alpha, clip_texture: clip },
-                    ShaderModifier::Gradient(texture_id, matrix, repeat, alpha) => render::ShaderType::LinearGradient { texture: *texture_id, texture_transform: *matrix, repeat: *repeat, alpha: *alpha, clip_texture: clip }
+                    ShaderModifier::Texture(texture_id, matrix, wrap_mode, alpha)   => render::ShaderType::Texture { texture: *texture_id, texture_transform: *matrix, wrap_mode: *wrap_mode, alpha: *alpha, clip_texture: clip },
+                    ShaderModifier::Gradient(texture_id, matrix, repeat, alpha)     => render::ShaderType::LinearGradient { texture: *texture_id, texture_transform: *matrix, repeat: *repeat, alpha: *alpha, clip_texture: clip }
                 };
 
                 // Add to the updates
@@ -402,6 +441,7 @@ impl RenderCore {
         render_state.render_target      = Some(render_target);
         render_state.clip_mask          = Maybe::None;
         render_state.clip_buffers       = Some(vec![]);
+        render_state.stencil_clipping   = core.stencil_clipping;
         render_state.shader_modifier    = Some(ShaderModifier::Simple);
         render_state.is_clear           = Some(false);
 
@@ -444,8 +484,20 @@ impl RenderCore {
                 },
 
                 DrawIndexed(vertex_buffer, index_buffer, num_items) => {
+                    // Entities that are entirely outside of the viewport or the damaged region don't need to be drawn
+                    let is_visible = match layer.entity_bounds.get(&render_idx) {
+                        Some(bounds)    => bounds.transform(&viewport_transform).clip(&render_state.visible_region()).is_some(),
+                        None            => true
+                    };
+
                     // Draw the triangles
-                    render_order.push(render::RenderAction::DrawIndexedTriangles(*vertex_buffer, *index_buffer, *num_items));
+                    if is_visible {
+                        render_order.push(render::RenderAction::DrawIndexedTriangles(*vertex_buffer, *index_buffer, *num_items));
+                    }
+                },
+
+                Batched => {
+                    // Drawn along with an earlier entity
                 },
 
                 RenderSprite(namespace_id, sprite_id, sprite_transform) => { 
@@ -453,18 +505,27 @@ impl RenderCore {
                     let sprite_transform    = *sprite_transform;
                     let namespace_id        = *namespace_id;
 
-                    if let Some(sprite_layer_handle) = core.sprites.get(&(namespace_id, sprite_id)) {
-                        let sprite_layer_handle = *sprite_layer_handle;
+                    let sprite_layer_handle = core.sprites.get(&(namespace_id, sprite_id)).copied();
+                    let sprite_layer_handle = sprite_layer_handle.filter(|_| core.sprite_render_depth < MAX_SPRITE_DEPTH);
 
+                    if let Some(sprite_layer_handle) = sprite_layer_handle {
                         // The sprite transform is appended to the viewport transform
                         let combined_transform      = &viewport_transform * &active_transform;
                         let combined_transform      = combined_transform * sprite_transform;
 
+                        // Sprites that are entirely outside of the viewport or the damaged region don't need to be drawn
+                        if core.layer(sprite_layer_handle).bounds.transform(&combined_transform).clip(&render_state.visible_region()).is_none() {
+                            layer = core.layer(layer_handle);
+                            continue;
+                        }
+
                         // The items from before the sprite should be rendered using the current state
                         let old_state               = render_state.clone();
 
                         // Render the layer associated with the sprite
+                        core.sprite_render_depth    += 1;
                         let render_sprite           = core.render_layer(combined_transform, sprite_layer_handle, render_target, render_state);
+                        core.sprite_render_depth    -= 1;
 
                         // Render the sprite
                         render_order.extend(render_sprite);
@@ -489,9 +550,10 @@ impl RenderCore {
                     let namespace_id        = *namespace_id;
                     let filters             = filters.clone();
 
-                    if let Some(sprite_layer_handle) = core.sprites.get(&(namespace_id, sprite_id)) {
-                        let sprite_layer_handle     = *sprite_layer_handle;
+                    let sprite_layer_handle = core.sprites.get(&(namespace_id, sprite_id)).copied();
+                    let sprite_layer_handle = sprite_layer_handle.filter(|_| core.sprite_render_depth < MAX_SPRITE_DEPTH);
 
+                    if let Some(sprite_layer_handle) = sprite_layer_handle {
                         // Figure out the sprite size in pixels
                         let transform               = active_transform * sprite_transform;
                         let sprite_layer            = core.layer(sprite_layer_handle);
@@ -507,7 +569,7 @@ impl RenderCore {
 
                         if let Some(texture_bounds_pixels) = texture_bounds_pixels {
                             use render::RenderAction::*;
-                            use render::{VertexBufferId, ShaderType, Vertex2D};
+                            use render::{VertexBufferId, ShaderType, TextureWrapMode, Vertex2D};
 
                             // Calculate the radius needed by the filters (we use the maximum of all the filters here, which is simpler but not always correct)
                             let filter_radius           = filters.iter()
@@ -535,8 +597,16 @@ impl RenderCore {
                             let render_transform        = viewport_transform * (active_transform * sprite_transform);
                             let render_bounds           = texture_bounds_pixels.to_viewport_coordinates(&render_state.viewport_size);
 
-                            // Render the sprite to the texture
-                            render_order.extend(core.render_layer_to_texture(temp_texture, sprite_layer_handle, render_transform, render_bounds.to_sprite_bounds()));
+                            // Render the sprite to the texture (the scissor region only applies to the layer render target)
+                            core.sprite_render_depth += 1;
+                            if let Some(damage_region) = render_state.damage_region {
+                                render_order.push(ClearScissor);
+                                render_order.extend(core.render_layer_to_texture(temp_texture, sprite_layer_handle, render_transform, render_bounds.to_sprite_bounds()));
+                                render_order.push(SetScissor(damage_region.into()));
+                            } else {
+                                render_order.extend(core.render_layer_to_texture(temp_texture, sprite_layer_handle, render_transform, render_bounds.to_sprite_bounds()));
+                            }
+                            core.sprite_render_depth -= 1;
 
                             let last_transform      = render_state.transform.unwrap_or_else(|| &viewport_transform * &active_transform);
 
@@ -568,7 +638,7 @@ impl RenderCore {
                                 UseShader(ShaderType::Texture { 
                                     texture:            temp_texture, 
                                     texture_transform:  transform_to_matrix(&texture_transform),
-                                    repeat:             false,
+                                    wrap_mode:          TextureWrapMode::Clamp,
                                     alpha:              1.0,
                                     clip_texture:       None,
                                 }),
@@ -665,10 +735,10 @@ impl RenderCore {
                     render_order.extend(render_state.update_from_state(&old_state));
                 }
 
-                SetFillTexture(texture_id, matrix, repeat, alpha) => {
+                SetFillTexture(texture_id, matrix, wrap_mode, alpha) => {
                     // Set the shader modifier to use the fill texture (overriding any other shader modifier)
                     let old_state               = render_state.clone();
-                    render_state.shader_modifier = Some(ShaderModifier::Texture(*texture_id, *matrix, *repeat, *alpha));
+                    render_state.shader_modifier = Some(ShaderModifier::Texture(*texture_id, *matrix, *wrap_mode, *alpha));
 
                     // Update to the new state
                     render_order.extend(render_state.update_from_state(&old_state));
@@ -1081,20 +1151,15 @@ impl<'a> RenderStream<'a> {
     /// Modifies any 'draw framebuffer' operations in the pending list so that they render only the invalid region
     ///
     fn clip_draw_framebuffer(&self, instructions: Vec<render::RenderAction>) -> Vec<render::RenderAction> {
-        if self.invalid_bounds.is_undefined() {
-            // Remove any 'draw frame buffer' as there's nothing to draw
-            let mut instructions = instructions;
-            instructions.retain(|item| {
-                match item {
-                    render::RenderAction::DrawFrameBuffer(_, _, _)  => false,
-                    _                                               => true
-                }
-            });
+        // Only the part of the invalid region that's inside the damaged region needs to be drawn
+        let draw_bounds = match self.damage_region {
+            _ if self.invalid_bounds.is_undefined() => None,
+            Some(damage_region)                     => self.invalid_bounds.clip(&damage_region),
+            None                                    => Some(self.invalid_bounds),
+        };
 
-            instructions
-        } else {
+        if let Some(new_bounds) = draw_bounds {
             // Convert the bounds for any 'draw frame buffer' instruction to affect only the invalid bounds
-            let new_bounds          = self.invalid_bounds;
             let mut instructions    = instructions;
 
             instructions.iter_mut()
@@ -1111,6 +1176,17 @@ impl<'a> RenderStream<'a> {
                     }
                 });
 
+            instructions
+        } else {
+            // Remove any 'draw frame buffer' as there's nothing to draw
+            let mut instructions = instructions;
+            instructions.retain(|item| {
+                match item {
+                    render::RenderAction::DrawFrameBuffer(_, _, _)  => false,
+                    _                                               => true
+                }
+            });
+
             instructions
         }
     }
@@ -1138,6 +1214,10 @@ impl<'a> RenderStream<'a> {
                 render_actions.push(render::RenderAction::CreateTextureBgra(*texture_id, render::Size2D(*w as _, *h as _)));
             }
 
+            CreateBlankTexture(texture_id, canvas::TextureSize(w, h), canvas::TextureFormat::RgbaPremultiplied) => {
+                render_actions.push(render::RenderAction::CreateTextureBgraPremultiplied(*texture_id, render::Size2D(*w as _, *h as _)));
+            }
+
             SetBytes(texture_id, canvas::TexturePosition(x, y), canvas::TextureSize(w, h), bytes) => {
                 render_actions.push(render::RenderAction::WriteTextureData(*texture_id, render::Position2D(*x as _, *y as _), render::Position2D((x+w) as _, (y+h) as _), Arc::clone(bytes)));
             }
@@ -1203,7 +1283,7 @@ impl<'a> Stream for RenderStream<'a> {
             } else {
                 // Finished processing the rendering: can send the actual rendering commands to the hardware layer
                 self.processing_future  = None;
-                self.layer_count        = self.core.sync(|core| core.layers.len());
+                self.layer_handles      = self.core.sync(|core| core.layers.values().cloned().collect());
                 self.render_index       = 0;
 
                 // Perform any setup actions that might exist or have been generated before proceeding
@@ -1255,7 +1335,7 @@ impl<'a> Stream for RenderStream<'a> {
         let mut layer_id        = self.layer_id;
         let viewport_transform  = self.viewport_transform;
 
-        let result              = if layer_id >= self.layer_count {
+        let result              = if layer_id >= self.layer_handles.len() {
             // Stop if we've processed all the layers
             None
         } else {
@@ -1263,17 +1343,24 @@ impl<'a> Stream for RenderStream<'a> {
             let mut layer_buffer_is_clear   = self.layer_buffer_is_clear;
             let mut invalid_bounds          = self.invalid_bounds;
             let viewport_size               = self.viewport_size;
+            let damage_region               = self.damage_region;
+            let layer_handle                = self.layer_handles[layer_id];
 
             let result                  = core.sync(|core| {
                 // Send any pending vertex buffers, then render the layer
-                let layer_handle            = core.layers[layer_id];
                 let send_vertex_buffers     = core.send_vertex_buffers(layer_handle);
                 let mut render_state        = RenderStreamState::new(viewport_size);
                 render_state.is_clear       = Some(layer_buffer_is_clear);
                 render_state.invalid_bounds = invalid_bounds;
+                render_state.damage_region  = damage_region;
 
                 let mut render_layer        = VecDeque::new();
 
+                // Restrict drawing to the damaged region (this stays set for the rest of the frame)
+                if let (0, Some(damage_region)) = (layer_id, damage_region) {
+                    render_layer.push_back(render::RenderAction::SetScissor(damage_region.into()));
+                }
+
                 render_layer.extend(send_vertex_buffers);
                 render_layer.extend(core.render_layer(viewport_transform, layer_handle, MAIN_RENDER_TARGET, &mut render_state));
                 render_layer.extend(RenderStreamState::new(viewport_size).update_from_state(&render_state));
@@ -111,14 +111,19 @@ impl CanvasWorker {
         fill_options.tolerance  = f32::max(MIN_TOLERANCE, fill_options.tolerance);
 
         // Tessellate the current path
-        tessellator.tessellate_path(&path, &fill_options,
+        let result = tessellator.tessellate_path(&path, &fill_options,
             &mut BuffersBuilder::new(&mut geometry, move |vertex: FillVertex| {
                 render::Vertex2D {
                     pos:        vertex.position().to_array(),
                     tex_coord:  [0.0, 0.0],
                     color:      color
                 }
-            })).unwrap();
+            }));
+
+        // Paths that can't be tessellated (eg, because they have NaN coordinates) are left empty
+        if result.is_err() {
+            return VertexBuffers::new();
+        }
 
         geometry
     }
@@ -183,7 +188,7 @@ impl CanvasWorker {
 
         // Stroke the path
         // TODO: 'TooManyVertices'
-        tessellator.tessellate_path(&path, &stroke_options,
+        let result = tessellator.tessellate_path(&path, &stroke_options,
             &mut BuffersBuilder::new(&mut geometry, move |point: StrokeVertex| {
                 let advancement = point.advancement();
                 let side        = match point.side() { Side::Negative => 0.0, Side::Positive => 1.0 };
@@ -193,7 +198,12 @@ impl CanvasWorker {
                     tex_coord:  [advancement, side],
                     color:      color
                 }
-            })).unwrap();
+            }));
+
+        // Paths that can't be tessellated (eg, because they have NaN coordinates) are left empty
+        if result.is_err() {
+            return VertexBuffers::new();
+        }
 
         geometry
     }
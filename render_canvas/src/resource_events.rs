@@ -0,0 +1,120 @@
+use flo_render::*;
+
+use std::collections::{HashMap};
+
+///
+/// Describes a change to the GPU resources used by a canvas renderer
+///
+/// These are generated from the render actions as they are produced by `CanvasRenderer::draw()`, so they describe the
+/// resources that the renderer is asking for rather than the actual memory used by the GPU driver.
+///
+#[derive(Clone, PartialEq, Debug)]
+pub enum RenderResourceEvent {
+    /// A texture (or the texture for a render target) was created with the specified size in pixels and in bytes
+    TextureCreated { texture_id: TextureId, width: usize, height: usize, bytes: usize },
+
+    /// A texture was released, freeing the specified number of bytes (0 if the texture was created before the events were subscribed to)
+    TextureReleased { texture_id: TextureId, bytes: usize },
+
+    /// A vertex buffer was created with the specified number of vertices
+    VertexBufferCreated { buffer_id: VertexBufferId, vertex_count: usize },
+
+    /// A vertex buffer was released (the vertex count is 0 if the buffer was created before the events were subscribed to)
+    VertexBufferReleased { buffer_id: VertexBufferId, vertex_count: usize },
+}
+
+///
+/// Tracks the sizes of the resources created by a canvas renderer, so that the events for when they are released can report them
+///
+#[derive(Default)]
+pub (crate) struct RenderResourceTracker {
+    /// The width, height and size in bytes of each texture that has been created
+    texture_sizes: HashMap<TextureId, (usize, usize, usize)>,
+
+    /// The number of vertices in each vertex buffer that has been created
+    vertex_counts: HashMap<VertexBufferId, usize>,
+}
+
+impl RenderResourceTracker {
+    ///
+    /// Updates the tracked resources for a render action, returning the events that it generates
+    ///
+    pub fn events_for_action(&mut self, action: &RenderAction) -> Vec<RenderResourceEvent> {
+        use self::RenderAction::*;
+
+        match action {
+            CreateTextureBgra(texture_id, Size2D(width, height))            => self.create_texture(*texture_id, *width, *height, 4),
+            CreateTextureBgraPremultiplied(texture_id, Size2D(width, height)) => self.create_texture(*texture_id, *width, *height, 4),
+            CreateTextureMono(texture_id, Size2D(width, height))            => self.create_texture(*texture_id, *width, *height, 1),
+            Create1DTextureBgra(texture_id, Size1D(width))                  => self.create_texture(*texture_id, *width, 1, 4),
+            Create1DTextureMono(texture_id, Size1D(width))                  => self.create_texture(*texture_id, *width, 1, 1),
+            FreeTexture(texture_id)                                         => {
+                let (_, _, bytes) = self.texture_sizes.remove(texture_id).unwrap_or((0, 0, 0));
+                vec![RenderResourceEvent::TextureReleased { texture_id: *texture_id, bytes }]
+            }
+
+            CreateRenderTarget(_, texture_id, Size2D(width, height), target_type) => {
+                let bytes_per_pixel = match target_type {
+                    RenderTargetType::Monochrome | RenderTargetType::MonochromeMultisampledTexture  => 1,
+                    _                                                                               => 4,
+                };
+
+                self.create_texture(*texture_id, *width, *height, bytes_per_pixel)
+            }
+
+            CopyTexture(source_texture_id, target_texture_id) => {
+                // The copy has the same size as the source texture
+                let (width, height, bytes)  = self.texture_sizes.get(source_texture_id).copied().unwrap_or((0, 0, 0));
+                let mut events              = self.release_texture(*target_texture_id).into_iter().collect::<Vec<_>>();
+
+                self.texture_sizes.insert(*target_texture_id, (width, height, bytes));
+                events.push(RenderResourceEvent::TextureCreated { texture_id: *target_texture_id, width, height, bytes });
+                events
+            }
+
+            CreateVertex2DBuffer(buffer_id, vertices) => {
+                let mut events = self.release_vertex_buffer(*buffer_id).into_iter().collect::<Vec<_>>();
+
+                self.vertex_counts.insert(*buffer_id, vertices.len());
+                events.push(RenderResourceEvent::VertexBufferCreated { buffer_id: *buffer_id, vertex_count: vertices.len() });
+                events
+            }
+
+            FreeVertexBuffer(buffer_id)                                     => {
+                let vertex_count = self.vertex_counts.remove(buffer_id).unwrap_or(0);
+                vec![RenderResourceEvent::VertexBufferReleased { buffer_id: *buffer_id, vertex_count }]
+            }
+
+            _                                                               => vec![],
+        }
+    }
+
+    ///
+    /// Generates the events for creating a texture (which replaces any existing texture with the same ID)
+    ///
+    fn create_texture(&mut self, texture_id: TextureId, width: usize, height: usize, bytes_per_pixel: usize) -> Vec<RenderResourceEvent> {
+        let mut events  = self.release_texture(texture_id).into_iter().collect::<Vec<_>>();
+        let bytes       = width * height * bytes_per_pixel;
+
+        self.texture_sizes.insert(texture_id, (width, height, bytes));
+        events.push(RenderResourceEvent::TextureCreated { texture_id, width, height, bytes });
+
+        events
+    }
+
+    ///
+    /// Generates the event for releasing a texture that is being replaced, if it has been created
+    ///
+    fn release_texture(&mut self, texture_id: TextureId) -> Option<RenderResourceEvent> {
+        self.texture_sizes.remove(&texture_id)
+            .map(|(_, _, bytes)| RenderResourceEvent::TextureReleased { texture_id, bytes })
+    }
+
+    ///
+    /// Generates the event for releasing a vertex buffer that is being replaced, if it has been created
+    ///
+    fn release_vertex_buffer(&mut self, buffer_id: VertexBufferId) -> Option<RenderResourceEvent> {
+        self.vertex_counts.remove(&buffer_id)
+            .map(|vertex_count| RenderResourceEvent::VertexBufferReleased { buffer_id, vertex_count })
+    }
+}
@@ -93,7 +93,7 @@ fn fill_two_circles() {
         // Get the upates for a drawing operation
         let mut draw_stream = renderer.draw(draw_circle.into_iter());
 
-        // Should be a 'clear', an 'upload vertex buffer', an 'upload index buffer' and two 'draw indexed' instructions
+        // Should be a 'clear', an 'upload vertex buffer', an 'upload index buffer' and a single 'draw indexed' instruction (the two fills are batched together)
         loop {
             let next = draw_stream.next().await;
             assert!(next.is_some());
@@ -107,7 +107,7 @@ fn fill_two_circles() {
         assert!(set_transform.is_some());
         assert!(match set_transform { Some(RenderAction::SetTransform(_)) => true, _ => false });
 
-        // First we upload the vertex buffers...
+        // First we upload the combined vertex buffer...
         let upload_vertices = draw_stream.next().await;
         assert!(upload_vertices.is_some());
         assert!(match upload_vertices { Some(RenderAction::CreateVertex2DBuffer(_, _)) => true, _ => false });
@@ -116,14 +116,6 @@ fn fill_two_circles() {
         assert!(upload_indices.is_some());
         assert!(match upload_indices { Some(RenderAction::CreateIndexBuffer(_, _)) => true, _ => false });
 
-        let upload_vertices_2 = draw_stream.next().await;
-        assert!(upload_vertices_2.is_some());
-        assert!(match upload_vertices_2 { Some(RenderAction::CreateVertex2DBuffer(_, _)) => true, _ => false });
-
-        let upload_indices_2 = draw_stream.next().await;
-        assert!(upload_indices_2.is_some());
-        assert!(match upload_indices_2 { Some(RenderAction::CreateIndexBuffer(_, _)) => true, _ => false });
-
         // Layer preamble occurs after uploading the buffers
         check_layer_preamble(&mut draw_stream).await;
 
@@ -132,9 +124,36 @@ fn fill_two_circles() {
         assert!(draw_vertices.is_some());
         assert!(match draw_vertices { Some(RenderAction::DrawIndexedTriangles(_, _, _)) => true, _ => false });
 
-        let draw_vertices_2  = draw_stream.next().await;
-        assert!(draw_vertices_2.is_some());
-        assert!(match draw_vertices_2 { Some(RenderAction::DrawIndexedTriangles(_, _, _)) => true, _ => false });
+        let after_draw      = draw_stream.next().await;
+        assert!(!match after_draw { Some(RenderAction::DrawIndexedTriangles(_, _, _)) => true, _ => false });
+    })
+}
+
+#[test]
+fn batch_many_circles() {
+    // Draw a lot of small circles with the same colour
+    let mut draw_circles = vec![];
+    draw_circles.fill_color(Color::Rgba(0.0, 0.0, 0.0, 1.0));
+    for idx in 0..1000 {
+        let x = (idx % 40) as f32 * 10.0;
+        let y = (idx / 40) as f32 * 10.0;
+
+        draw_circles.new_path();
+        draw_circles.circle(x, y, 2.0);
+        draw_circles.fill();
+    }
+
+    executor::block_on(async {
+        // Create the renderer
+        let mut renderer    = CanvasRenderer::new();
+
+        // Count the draw calls generated for the circles
+        let rendering       = renderer.draw(draw_circles.into_iter()).collect::<Vec<_>>().await;
+        let num_draws       = rendering.iter()
+            .filter(|action| match action { RenderAction::DrawIndexedTriangles(_, _, _) => true, _ => false })
+            .count();
+
+        assert!(num_draws > 0 && num_draws < 10, "{} draw calls", num_draws);
     })
 }
 
@@ -194,6 +213,74 @@ fn draw_twice() {
     })
 }
 
+#[test]
+fn clear_canvas_frees_buffers() {
+    // Draw a simple circle
+    let mut draw_circle = vec![];
+    draw_circle.circle(0.0,0.0, 100.0);
+    draw_circle.fill();
+
+    // Clear the canvas and draw it again
+    let mut redraw_circle = vec![];
+    redraw_circle.clear_canvas(Color::Rgba(0.0, 0.0, 0.0, 0.0));
+    redraw_circle.circle(0.0,0.0, 100.0);
+    redraw_circle.fill();
+
+    executor::block_on(async {
+        // Create the renderer
+        let mut renderer    = CanvasRenderer::new();
+
+        // The first drawing creates a vertex buffer
+        let first_drawing   = renderer.draw(draw_circle.into_iter()).collect::<Vec<_>>().await;
+        let buffer_id       = first_drawing.iter()
+            .filter_map(|action| match action { RenderAction::CreateVertex2DBuffer(buffer_id, _) => Some(*buffer_id), _ => None })
+            .next()
+            .unwrap();
+
+        // Clearing the canvas should free the buffer before it's re-used for the new drawing
+        let second_drawing  = renderer.draw(redraw_circle.into_iter()).collect::<Vec<_>>().await;
+        let free_idx        = second_drawing.iter().position(|action| action == &RenderAction::FreeVertexBuffer(buffer_id));
+        let create_idx      = second_drawing.iter().position(|action| match action { RenderAction::CreateVertex2DBuffer(id, _) => id == &buffer_id, _ => false });
+
+        println!("{:?}", second_drawing);
+        assert!(free_idx.is_some());
+        assert!(second_drawing.contains(&RenderAction::FreeIndexBuffer(render::IndexBufferId(buffer_id.0))));
+        assert!(create_idx.is_none() || create_idx > free_idx);
+    })
+}
+
+#[test]
+fn clear_layer_frees_buffers() {
+    // Draw a simple circle
+    let mut draw_circle = vec![];
+    draw_circle.circle(0.0,0.0, 100.0);
+    draw_circle.fill();
+
+    // Clear the layer without drawing anything else
+    let mut clear_layer = vec![];
+    clear_layer.clear_layer();
+
+    executor::block_on(async {
+        // Create the renderer
+        let mut renderer    = CanvasRenderer::new();
+
+        // The first drawing creates a vertex buffer
+        let first_drawing   = renderer.draw(draw_circle.into_iter()).collect::<Vec<_>>().await;
+        let buffer_id       = first_drawing.iter()
+            .filter_map(|action| match action { RenderAction::CreateVertex2DBuffer(buffer_id, _) => Some(*buffer_id), _ => None })
+            .next()
+            .unwrap();
+
+        // Clearing the layer should free the buffer even though nothing replaces it
+        let second_drawing  = renderer.draw(clear_layer.into_iter()).collect::<Vec<_>>().await;
+
+        println!("{:?}", second_drawing);
+        assert!(second_drawing.contains(&RenderAction::FreeVertexBuffer(buffer_id)));
+        assert!(second_drawing.contains(&RenderAction::FreeIndexBuffer(render::IndexBufferId(buffer_id.0))));
+        assert!(!second_drawing.iter().any(|action| match action { RenderAction::DrawIndexedTriangles(vertex_buffer, _, _) => vertex_buffer == &buffer_id, _ => false }));
+    })
+}
+
 #[test]
 fn clip_rect() {
     // Draw a simple rectabgle
@@ -258,3 +345,1145 @@ fn clip_rect() {
         // Remaining instructions finish the render
     })
 }
+
+#[test]
+fn overlapping_clips_intersect_with_stencil() {
+    fn draw_overlapping_clips() -> Vec<Draw> {
+        let mut drawing = vec![];
+        drawing.new_path();
+        drawing.rect(0.0, 0.0, 100.0, 100.0);
+        drawing.clip();
+        drawing.new_path();
+        drawing.rect(50.0, 50.0, 150.0, 150.0);
+        drawing.clip();
+        drawing.new_path();
+        drawing.rect(0.0, 0.0, 150.0, 150.0);
+        drawing.fill();
+
+        drawing
+    }
+
+    let is_stencil_mode = |action: &RenderAction| match action { RenderAction::StencilMode(_) => true, _ => false };
+
+    executor::block_on(async {
+        // Clip paths are combined without the stencil buffer by default
+        let mut renderer    = CanvasRenderer::new();
+        let actions         = renderer.draw(draw_overlapping_clips().into_iter()).collect::<Vec<_>>().await;
+
+        assert!(!actions.iter().any(is_stencil_mode));
+
+        // With stencil clipping on, the first clip path is counted in the stencil buffer and the second is only drawn where it overlaps the first
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_stencil_clipping(true);
+        let actions         = renderer.draw(draw_overlapping_clips().into_iter()).collect::<Vec<_>>().await;
+
+        let stencil_actions = actions.iter().enumerate().filter(|(_, action)| is_stencil_mode(*action)).collect::<Vec<_>>();
+        assert!(stencil_actions.len() == 3);
+
+        let (increment, _)  = stencil_actions[0];
+        let (equal, _)      = stencil_actions[1];
+        let (disabled, _)   = stencil_actions[2];
+
+        assert!(match stencil_actions[0].1 { RenderAction::StencilMode(render::StencilMode::Increment) => true, _ => false });
+        assert!(match stencil_actions[1].1 { RenderAction::StencilMode(render::StencilMode::Equal(1)) => true, _ => false });
+        assert!(match stencil_actions[2].1 { RenderAction::StencilMode(render::StencilMode::Disabled) => true, _ => false });
+
+        assert!(match actions[increment+1] { RenderAction::DrawIndexedTriangles(_, _, _) => true, _ => false });
+        assert!(equal == increment+2);
+        assert!(match actions[equal+1] { RenderAction::DrawIndexedTriangles(_, _, _) => true, _ => false });
+        assert!(disabled == equal+2);
+    })
+}
+
+#[test]
+fn damage_region_skips_hidden_entities() {
+    // Draw a circle on the left-hand side of the canvas
+    let mut draw_circle = vec![];
+    draw_circle.canvas_height(1000.0);
+    draw_circle.center_region(0.0, 0.0, 1000.0, 1000.0);
+    draw_circle.circle(200.0, 500.0, 50.0);
+    draw_circle.fill();
+
+    executor::block_on(async {
+        // Create the renderer and only redraw the right-hand side of the viewport
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+        renderer.set_damage_region(Some((600.0..1000.0, 0.0..1000.0)));
+
+        let actions         = renderer.draw(draw_circle.into_iter()).collect::<Vec<_>>().await;
+
+        // The scissor region should be set for the frame and cleared before it's shown, and the circle should not be drawn
+        let set_scissor     = actions.iter().position(|action| match action { RenderAction::SetScissor(_) => true, _ => false });
+        let clear_scissor   = actions.iter().position(|action| match action { RenderAction::ClearScissor => true, _ => false });
+        let show_frame      = actions.iter().position(|action| match action { RenderAction::ShowFrameBuffer => true, _ => false });
+
+        assert!(set_scissor.is_some());
+        assert!(clear_scissor.is_some());
+        assert!(set_scissor < clear_scissor && clear_scissor < show_frame);
+        assert!(!actions.iter().any(|action| match action { RenderAction::DrawIndexedTriangles(_, _, _) => true, _ => false }));
+
+        // The damage region only applies to a single frame
+        let mut draw_circle = vec![];
+        draw_circle.circle(200.0, 500.0, 50.0);
+        draw_circle.fill();
+
+        let actions         = renderer.draw(draw_circle.into_iter()).collect::<Vec<_>>().await;
+
+        assert!(!actions.iter().any(|action| match action { RenderAction::SetScissor(_) => true, _ => false }));
+        assert!(actions.iter().any(|action| match action { RenderAction::DrawIndexedTriangles(_, _, _) => true, _ => false }));
+    })
+}
+
+#[test]
+fn pathological_drawing_does_not_panic() {
+    // Drawing that collapses the transform, pops more states than it pushes and has a sprite that draws itself
+    let drawing = vec![
+        Draw::ClearCanvas(Color::Rgba(0.0, 0.0, 0.0, 0.0)),
+        Draw::PopState,
+        Draw::PopState,
+        Draw::CanvasHeight(f32::INFINITY),
+        Draw::CenterRegion((0.0, 0.0), (1000.0, 1000.0)),
+        Draw::CanvasHeight(1000.0),
+        Draw::Sprite(SpriteId(0)),
+        Draw::ClearSprite,
+        Draw::Path(PathOp::NewPath),
+        Draw::Path(PathOp::Move(0.0, 0.0)),
+        Draw::Path(PathOp::Line(100.0, 0.0)),
+        Draw::Path(PathOp::Line(100.0, 100.0)),
+        Draw::Fill,
+        Draw::DrawSprite(SpriteId(0)),
+        Draw::Layer(LayerId(0)),
+        Draw::DrawSprite(SpriteId(0)),
+        Draw::DrawSpriteWithFilters(SpriteId(0), vec![TextureFilter::GaussianBlur(4.0)]),
+    ];
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+
+        let actions         = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+
+        assert!(actions.iter().any(|action| match action { RenderAction::ShowFrameBuffer => true, _ => false }));
+    })
+}
+
+#[test]
+fn offscreen_shapes_are_drawn_once_scrolled_into_view() {
+    // A rectangle well to the right of the viewport
+    let mut drawing = vec![];
+    drawing.canvas_height(1000.0);
+    drawing.center_region(-500.0, -500.0, 500.0, 500.0);
+    drawing.rect(1950.0, -50.0, 2050.0, 50.0);
+    drawing.fill();
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+
+        // The rectangle is outside of the viewport, so it's not drawn
+        let actions         = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+        let draws           = actions.iter().filter(|action| matches!(action, RenderAction::DrawIndexedTriangles(_, _, _))).count();
+        assert!(draws == 0, "{} draws", draws);
+
+        // Scrolling the view so the rectangle is in the center should draw it without needing to send the drawing again
+        renderer.set_view_transform(Transform2D::translate(-4.0, 0.0));
+
+        let actions         = renderer.draw(vec![].into_iter()).collect::<Vec<_>>().await;
+        let draws           = actions.iter().filter(|action| matches!(action, RenderAction::DrawIndexedTriangles(_, _, _))).count();
+        assert!(draws == 1, "{} draws", draws);
+    })
+}
+
+#[test]
+fn offscreen_sprites_are_not_drawn() {
+    // A small square sprite
+    let mut drawing = vec![];
+    drawing.canvas_height(1000.0);
+    drawing.center_region(-500.0, -500.0, 500.0, 500.0);
+
+    drawing.sprite(SpriteId(0));
+    drawing.clear_sprite();
+    drawing.rect(-5.0, -5.0, 5.0, 5.0);
+    drawing.fill();
+
+    // 1000 instances off to the side of the viewport, and 10 that are visible
+    drawing.layer(LayerId(0));
+    let offscreen   = (0..1000).map(|idx| Transform2D::translate(2000.0 + (idx as f32) * 20.0, 0.0));
+    let visible     = (0..10).map(|idx| Transform2D::translate((idx as f32) * 20.0 - 100.0, 0.0));
+    for transform in offscreen.chain(visible) {
+        drawing.sprite_transform(SpriteTransform::Identity);
+        drawing.sprite_transform(SpriteTransform::Transform2D(transform));
+        drawing.draw_sprite(SpriteId(0));
+    }
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+
+        let actions         = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+        let draws           = actions.iter().filter(|action| matches!(action, RenderAction::DrawIndexedTriangles(_, _, _))).count();
+
+        assert!(draws == 10, "{} draws", draws);
+    })
+}
+
+#[test]
+fn clear_all_layers_keeps_sprites() {
+    fn count_draws(actions: &Vec<RenderAction>) -> usize {
+        actions.iter().filter(|action| matches!(action, RenderAction::DrawIndexedTriangles(_, _, _))).count()
+    }
+
+    // Define a sprite, then draw it alongside a shape on layer 0
+    let mut first_frame = vec![];
+    first_frame.canvas_height(1000.0);
+    first_frame.center_region(-500.0, -500.0, 500.0, 500.0);
+
+    first_frame.sprite(SpriteId(0));
+    first_frame.clear_sprite();
+    first_frame.rect(-50.0, -50.0, 50.0, 50.0);
+    first_frame.fill();
+
+    first_frame.layer(LayerId(0));
+    first_frame.rect(100.0, 100.0, 200.0, 200.0);
+    first_frame.fill();
+    first_frame.draw_sprite(SpriteId(0));
+
+    // Clearing all the layers removes the shape, but the sprite can still be drawn
+    let mut second_frame = vec![];
+    second_frame.clear_all_layers();
+    second_frame.draw_sprite(SpriteId(0));
+
+    // Clearing all the layers while a sprite is selected still clears the normal layers
+    let mut third_frame = vec![];
+    third_frame.sprite(SpriteId(1));
+    third_frame.clear_all_layers();
+    third_frame.layer(LayerId(0));
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+
+        let actions = renderer.draw(first_frame.into_iter()).collect::<Vec<_>>().await;
+        assert!(count_draws(&actions) == 2, "{:?}", actions);
+
+        let actions = renderer.draw(second_frame.into_iter()).collect::<Vec<_>>().await;
+        assert!(count_draws(&actions) == 1, "{:?}", actions);
+
+        let actions = renderer.draw(third_frame.into_iter()).collect::<Vec<_>>().await;
+        assert!(count_draws(&actions) == 0, "{:?}", actions);
+    })
+}
+
+#[test]
+fn move_sprite_from_transfers_geometry() {
+    fn count_indices(actions: &Vec<RenderAction>) -> usize {
+        actions.iter().map(|action| match action { RenderAction::DrawIndexedTriangles(_, _, num_indices) => *num_indices, _ => 0 }).sum()
+    }
+
+    // Define sprite 0 and draw it
+    let mut define_sprite = vec![];
+    define_sprite.canvas_height(1000.0);
+    define_sprite.center_region(-500.0, -500.0, 500.0, 500.0);
+
+    define_sprite.sprite(SpriteId(0));
+    define_sprite.clear_sprite();
+    define_sprite.rect(-50.0, -50.0, 50.0, 50.0);
+    define_sprite.fill();
+
+    define_sprite.layer(LayerId(0));
+    define_sprite.draw_sprite(SpriteId(0));
+
+    // Move it to sprite 1 and add another shape to it
+    let mut move_sprite = vec![];
+    move_sprite.clear_layer();
+    move_sprite.sprite(SpriteId(1));
+    move_sprite.move_sprite_from(SpriteId(0));
+    move_sprite.line_width(4.0);
+    move_sprite.rect(-100.0, -100.0, 100.0, 100.0);
+    move_sprite.stroke();
+
+    move_sprite.layer(LayerId(0));
+    move_sprite.draw_sprite(SpriteId(1));
+
+    // Moving from a sprite that doesn't exist (sprite 0 has been moved, and sprite 3 was never defined) leaves the current sprite empty
+    let mut move_missing = vec![];
+    move_missing.clear_layer();
+    move_missing.sprite(SpriteId(2));
+    move_missing.rect(-50.0, -50.0, 50.0, 50.0);
+    move_missing.fill();
+    move_missing.move_sprite_from(SpriteId(3));
+
+    move_missing.layer(LayerId(0));
+    move_missing.draw_sprite(SpriteId(0));
+    move_missing.draw_sprite(SpriteId(2));
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+
+        let actions         = renderer.draw(define_sprite.into_iter()).collect::<Vec<_>>().await;
+        let sprite_indices  = count_indices(&actions);
+        assert!(sprite_indices > 0);
+
+        // Sprite 1 has the fill from sprite 0 as well as the stroke that was added after the move
+        let actions         = renderer.draw(move_sprite.into_iter()).collect::<Vec<_>>().await;
+        assert!(count_indices(&actions) > sprite_indices, "{:?}", actions);
+
+        let actions         = renderer.draw(move_missing.into_iter()).collect::<Vec<_>>().await;
+        assert!(count_indices(&actions) == 0, "{:?}", actions);
+    })
+}
+
+#[test]
+fn texture_mipmaps_can_be_disabled() {
+    fn draw_texture(mipmaps: bool) -> Vec<Draw> {
+        let mut drawing = vec![];
+        drawing.canvas_height(1000.0);
+        drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+        drawing.create_texture(TextureId(0), 4, 4, TextureFormat::Rgba);
+        drawing.set_texture_mipmaps(TextureId(0), mipmaps);
+        drawing.set_texture_bytes(TextureId(0), 0, 0, 4, 4, std::sync::Arc::new(vec![255; 4*4*4]));
+        drawing.rect(0.0, 0.0, 100.0, 100.0);
+        drawing.fill_texture(TextureId(0), 0.0, 0.0, 100.0, 100.0);
+        drawing.fill();
+
+        drawing
+    }
+
+    executor::block_on(async {
+        // Mipmaps are generated for textures by default
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+        let actions         = renderer.draw(draw_texture(true).into_iter()).collect::<Vec<_>>().await;
+
+        assert!(actions.iter().any(|action| match action { RenderAction::CreateMipMaps(_) => true, _ => false }));
+
+        // Turning them off should skip generating them
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+        let actions         = renderer.draw(draw_texture(false).into_iter()).collect::<Vec<_>>().await;
+
+        assert!(!actions.iter().any(|action| match action { RenderAction::CreateMipMaps(_) => true, _ => false }));
+    })
+}
+
+///
+/// Draws a 4x1 texture created with the specified format, and returns the actions that create and write to the texture
+///
+fn texture_upload_actions(format: TextureFormat, pixels: Vec<u8>) -> Vec<RenderAction> {
+    let mut drawing = vec![];
+    drawing.canvas_height(1000.0);
+    drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+    drawing.create_texture(TextureId(0), 4, 1, format);
+    drawing.set_texture_bytes(TextureId(0), 0, 0, 4, 1, std::sync::Arc::new(pixels));
+    drawing.rect(0.0, 0.0, 100.0, 100.0);
+    drawing.fill_texture(TextureId(0), 0.0, 0.0, 100.0, 100.0);
+    drawing.fill();
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+        renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await
+    })
+}
+
+#[test]
+fn straight_alpha_texture_is_premultiplied_on_upload() {
+    // Gradient of straight alpha red that fades to a transparent edge
+    let actions = texture_upload_actions(TextureFormat::Rgba, vec![
+        255, 0, 0, 255,
+        255, 0, 0, 128,
+        255, 0, 0, 32,
+        255, 0, 0, 0,
+    ]);
+
+    let bytes = actions.iter()
+        .filter_map(|action| match action { RenderAction::WriteTextureData(_, _, _, bytes) => Some(bytes.clone()), _ => None })
+        .next()
+        .unwrap();
+
+    // The texture is stored premultiplied, so the color fades out with the alpha, and the transparent edge has no color left to bleed into its neighbours
+    assert!(*bytes == vec![
+        255, 0, 0, 255,
+        128, 0, 0, 128,
+        32, 0, 0, 32,
+        0, 0, 0, 0,
+    ]);
+    assert!(actions.iter().any(|action| matches!(action, RenderAction::CreateTextureBgraPremultiplied(_, _))));
+    assert!(!actions.iter().any(|action| matches!(action, RenderAction::CreateTextureBgra(_, _))));
+}
+
+#[test]
+fn premultiplied_texture_is_uploaded_unchanged() {
+    let gradient = vec![
+        255, 0, 0, 255,
+        128, 0, 0, 128,
+        32, 0, 0, 32,
+        0, 0, 0, 0,
+    ];
+    let actions = texture_upload_actions(TextureFormat::RgbaPremultiplied, gradient.clone());
+
+    let bytes = actions.iter()
+        .filter_map(|action| match action { RenderAction::WriteTextureData(_, _, _, bytes) => Some(bytes.clone()), _ => None })
+        .next()
+        .unwrap();
+
+    assert!(*bytes == gradient);
+    assert!(actions.iter().any(|action| matches!(action, RenderAction::CreateTextureBgraPremultiplied(_, _))));
+}
+
+#[cfg(feature = "image-formats")]
+#[test]
+fn encoded_png_is_loaded_into_texture() {
+    // 2x1 opaque image (so the pixels are uploaded unchanged)
+    let pixels      = vec![255, 0, 0, 255,  10, 20, 30, 255];
+    let mut png     = vec![];
+    {
+        let mut png_encoder = png::Encoder::new(&mut png, 2, 1);
+        png_encoder.set_color(png::ColorType::Rgba);
+        png_encoder.set_depth(png::BitDepth::Eight);
+
+        let mut png_writer = png_encoder.write_header().unwrap();
+        png_writer.write_image_data(&pixels).unwrap();
+    }
+
+    let mut drawing = vec![];
+    drawing.canvas_height(1000.0);
+    drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+    drawing.set_texture_from_encoded_image(TextureId(0), std::sync::Arc::new(png));
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+        let actions         = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+
+        let bytes = actions.iter()
+            .filter_map(|action| match action { RenderAction::WriteTextureData(_, _, _, bytes) => Some(bytes.clone()), _ => None })
+            .next();
+
+        assert!(renderer.get_texture_size(TextureId(0)) == Some((2, 1)));
+        assert!(bytes.map(|bytes| *bytes == pixels) == Some(true));
+        assert!(renderer.take_texture_errors().is_empty());
+    })
+}
+
+#[test]
+fn corrupt_encoded_image_is_reported_as_texture_error() {
+    let mut drawing = vec![];
+    drawing.canvas_height(1000.0);
+    drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+    drawing.create_texture(TextureId(0), 16, 8, TextureFormat::Rgba);
+    drawing.set_texture_from_encoded_image(TextureId(0), std::sync::Arc::new(vec![0x89, b'P', b'N', b'G', 1, 2, 3, 4]));
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+        renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+
+        // The texture is freed rather than being left with its old contents, and the error is only reported once
+        let errors = renderer.take_texture_errors();
+
+        assert!(renderer.get_texture_size(TextureId(0)) == None);
+        assert!(errors.len() == 1, "{:?}", errors);
+        assert!(errors[0].0 == TextureId(0));
+        assert!(renderer.take_texture_errors().is_empty());
+    })
+}
+
+#[test]
+fn texture_size_can_be_queried() {
+    let mut drawing = vec![];
+    drawing.canvas_height(1000.0);
+    drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+    drawing.create_texture(TextureId(0), 16, 8, TextureFormat::Rgba);
+    drawing.set_texture_bytes(TextureId(0), 0, 0, 16, 8, std::sync::Arc::new(vec![255; 16*8*4]));
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+        renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+
+        assert!(renderer.get_texture_size(TextureId(0)) == Some((16, 8)));
+        assert!(renderer.get_texture_size(TextureId(1)) == None);
+    })
+}
+
+#[test]
+fn texture_bytes_round_trip_through_readback() {
+    // Opaque and transparent pixels come back exactly, and partially transparent ones are rounded by the conversion to premultiplied alpha
+    let pixels = vec![
+        255, 0, 0, 255,     0, 255, 0, 255,     10, 20, 30, 255,
+        200, 100, 50, 128,  1, 2, 3, 0,         255, 255, 255, 64,
+    ];
+
+    let mut drawing = vec![];
+    drawing.canvas_height(1000.0);
+    drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+    drawing.create_texture(TextureId(0), 3, 2, TextureFormat::Rgba);
+    drawing.set_texture_bytes(TextureId(0), 0, 0, 3, 2, std::sync::Arc::new(pixels));
+
+    // Leaving a different namespace selected shouldn't stop the texture from being found
+    drawing.push(Draw::Namespace(NamespaceId::new()));
+
+    executor::block_on(async {
+        let mut context = match initialize_offscreen_rendering() {
+            Ok(context) => context,
+            Err(_)      => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        let texture = render_texture_offscreen(&mut context, 100, 100, 1.0, &drawing, TextureId(0)).await;
+
+        assert!(texture == Some((3, 2, vec![
+            255, 0, 0, 255,     0, 255, 0, 255,     10, 20, 30, 255,
+            199, 100, 50, 128,  0, 0, 0, 0,         255, 255, 255, 64,
+        ])), "{:?}", texture);
+
+        assert!(render_texture_offscreen(&mut context, 100, 100, 1.0, &drawing, TextureId(1)).await.is_none());
+    })
+}
+
+#[test]
+fn sprite_bounds_can_be_queried() {
+    let mut drawing = vec![];
+    drawing.canvas_height(1000.0);
+    drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+
+    drawing.sprite(SpriteId(0));
+    drawing.clear_sprite();
+
+    drawing.sprite(SpriteId(1));
+    drawing.clear_sprite();
+    drawing.new_path();
+    drawing.rect(10.0, 20.0, 30.0, 50.0);
+    drawing.fill();
+
+    drawing.layer(LayerId(0));
+
+    let mut more_drawing = vec![];
+    more_drawing.sprite(SpriteId(1));
+    more_drawing.new_path();
+    more_drawing.rect(100.0, 0.0, 110.0, 10.0);
+    more_drawing.fill();
+    more_drawing.layer(LayerId(0));
+
+    let is_near = |bounds: Option<((f64, f64), (f64, f64))>, expected: ((f64, f64), (f64, f64))| {
+        let ((min_x, min_y), (max_x, max_y)) = bounds.unwrap();
+        let ((exp_min_x, exp_min_y), (exp_max_x, exp_max_y)) = expected;
+
+        (min_x-exp_min_x).abs() < 0.1 && (min_y-exp_min_y).abs() < 0.1 && (max_x-exp_max_x).abs() < 0.1 && (max_y-exp_max_y).abs() < 0.1
+    };
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+        renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+
+        // Empty and undefined sprites have no bounds
+        assert!(renderer.get_sprite_bounds(NamespaceId::default(), SpriteId(0)) == None);
+        assert!(renderer.get_sprite_bounds(NamespaceId::default(), SpriteId(2)) == None);
+
+        let bounds = renderer.get_sprite_bounds(NamespaceId::default(), SpriteId(1));
+        assert!(is_near(bounds, ((10.0, 20.0), (30.0, 50.0))), "{:?}", bounds);
+
+        // Drawing more into the sprite updates the bounds
+        renderer.draw(more_drawing.into_iter()).collect::<Vec<_>>().await;
+
+        let bounds = renderer.get_sprite_bounds(NamespaceId::default(), SpriteId(1));
+        assert!(is_near(bounds, ((10.0, 0.0), (110.0, 50.0))), "{:?}", bounds);
+    })
+}
+
+#[test]
+fn layer_bounds_are_in_canvas_coordinates() {
+    // A layer with a rectangle and a sprite drawn on it
+    let mut drawing = vec![];
+    drawing.canvas_height(1000.0);
+    drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+
+    drawing.sprite(SpriteId(0));
+    drawing.clear_sprite();
+    drawing.new_path();
+    drawing.rect(0.0, 0.0, 10.0, 10.0);
+    drawing.fill();
+
+    drawing.layer(LayerId(0));
+    drawing.new_path();
+    drawing.rect(100.0, 200.0, 300.0, 400.0);
+    drawing.fill();
+
+    drawing.sprite_transform(SpriteTransform::Identity);
+    drawing.sprite_transform(SpriteTransform::Translate(500.0, 600.0));
+    drawing.draw_sprite(SpriteId(0));
+
+    drawing.layer(LayerId(1));
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+        renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+
+        // Empty and undefined layers have no bounds
+        assert!(renderer.get_layer_bounds(LayerId(1)) == None);
+        assert!(renderer.get_layer_bounds(LayerId(2)) == None);
+
+        // The bounds of layer 0 cover both the rectangle and the sprite
+        let bounds                              = renderer.get_layer_bounds(LayerId(0));
+        let ((min_x, min_y), (max_x, max_y))    = bounds.unwrap();
+
+        assert!((min_x-100.0).abs() < 0.1 && (min_y-200.0).abs() < 0.1 && (max_x-510.0).abs() < 0.1 && (max_y-610.0).abs() < 0.1, "{:?}", bounds);
+    })
+}
+
+#[test]
+fn sprite_bounds_do_not_include_clip_paths() {
+    // A sprite clipped to a large rectangle, with a small rectangle drawn inside it
+    let mut drawing = vec![];
+    drawing.canvas_height(1000.0);
+    drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+
+    drawing.sprite(SpriteId(0));
+    drawing.clear_sprite();
+    drawing.new_path();
+    drawing.rect(0.0, 0.0, 500.0, 500.0);
+    drawing.clip();
+    drawing.new_path();
+    drawing.rect(10.0, 20.0, 30.0, 50.0);
+    drawing.fill();
+
+    drawing.layer(LayerId(0));
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+        renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+
+        // Only the filled rectangle counts as drawing
+        let bounds                              = renderer.get_sprite_bounds(NamespaceId::default(), SpriteId(0));
+        let ((min_x, min_y), (max_x, max_y))    = bounds.unwrap();
+
+        assert!((min_x-10.0).abs() < 0.1 && (min_y-20.0).abs() < 0.1 && (max_x-30.0).abs() < 0.1 && (max_y-50.0).abs() < 0.1, "{:?}", bounds);
+    })
+}
+
+#[test]
+fn sprites_in_different_namespaces_are_independent() {
+    let namespace_1 = NamespaceId::new();
+    let namespace_2 = NamespaceId::new();
+
+    // Sprite 0 is defined differently in each namespace, then each is drawn
+    let mut drawing = vec![];
+    drawing.canvas_height(1000.0);
+    drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+
+    drawing.push(Draw::Namespace(namespace_1));
+    drawing.sprite(SpriteId(0));
+    drawing.clear_sprite();
+    drawing.new_path();
+    drawing.rect(10.0, 10.0, 20.0, 20.0);
+    drawing.fill();
+
+    drawing.push(Draw::Namespace(namespace_2));
+    drawing.sprite(SpriteId(0));
+    drawing.clear_sprite();
+    drawing.new_path();
+    drawing.rect(100.0, 100.0, 300.0, 300.0);
+    drawing.fill();
+
+    drawing.layer(LayerId(0));
+    drawing.push(Draw::Namespace(namespace_1));
+    drawing.draw_sprite(SpriteId(0));
+    drawing.push(Draw::Namespace(namespace_2));
+    drawing.draw_sprite(SpriteId(0));
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+
+        let actions         = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+
+        // Both sprites are uploaded and drawn
+        let vertex_buffers  = actions.iter().filter(|action| matches!(action, RenderAction::CreateVertex2DBuffer(_, _))).count();
+        assert!(vertex_buffers == 2, "{} vertex buffers", vertex_buffers);
+
+        // Sprite 0 refers to a different sprite depending on the namespace
+        let bounds_1 = renderer.get_sprite_bounds(namespace_1, SpriteId(0));
+        let bounds_2 = renderer.get_sprite_bounds(namespace_2, SpriteId(0));
+
+        let ((min_x, _), (max_x, _)) = bounds_1.unwrap();
+        assert!((min_x-10.0).abs() < 0.1 && (max_x-20.0).abs() < 0.1, "{:?}", bounds_1);
+
+        let ((min_x, _), (max_x, _)) = bounds_2.unwrap();
+        assert!((min_x-100.0).abs() < 0.1 && (max_x-300.0).abs() < 0.1, "{:?}", bounds_2);
+    })
+}
+
+#[test]
+fn editing_one_layer_does_not_reupload_others() {
+    // Two layers with a circle on each
+    let mut drawing = vec![];
+    drawing.layer(LayerId(0));
+    drawing.circle(0.0, 0.0, 100.0);
+    drawing.fill();
+    drawing.layer(LayerId(1));
+    drawing.circle(0.0, 0.0, 50.0);
+    drawing.fill();
+
+    // Replace the contents of the second layer only
+    let mut edit_layer = vec![];
+    edit_layer.layer(LayerId(1));
+    edit_layer.clear_layer();
+    edit_layer.rect(0.0, 0.0, 50.0, 50.0);
+    edit_layer.fill();
+
+    let created_buffers = |actions: &[RenderAction]| actions.iter()
+        .filter_map(|action| match action { RenderAction::CreateVertex2DBuffer(buffer_id, _) => Some(*buffer_id), _ => None })
+        .collect::<Vec<_>>();
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+
+        let first_frame     = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+        let second_frame    = renderer.draw(edit_layer.into_iter()).collect::<Vec<_>>().await;
+
+        // Only the new rectangle is uploaded, and the circle on the first layer is drawn from its existing buffer
+        assert!(created_buffers(&first_frame).len() == 2);
+        assert!(created_buffers(&second_frame).len() == 1);
+
+        let draws_in_second_frame = second_frame.iter().filter(|action| matches!(action, RenderAction::DrawIndexedTriangles(_, _, _))).count();
+        assert!(draws_in_second_frame == 2);
+    })
+}
+
+#[test]
+fn unchanged_layers_and_sprites_are_not_reuploaded_in_later_frames() {
+    // Two layers with a circle on each, and a sprite drawn on a third layer
+    let mut drawing = vec![];
+    drawing.sprite(SpriteId(0));
+    drawing.clear_sprite();
+    drawing.rect(0.0, 0.0, 10.0, 10.0);
+    drawing.fill();
+
+    drawing.layer(LayerId(0));
+    drawing.circle(0.0, 0.0, 100.0);
+    drawing.fill();
+    drawing.layer(LayerId(1));
+    drawing.circle(0.0, 0.0, 50.0);
+    drawing.fill();
+    drawing.layer(LayerId(2));
+    drawing.draw_sprite(SpriteId(0));
+
+    // Replace the contents of the second layer in a new frame
+    let mut edit_layer = vec![];
+    edit_layer.start_frame();
+    edit_layer.layer(LayerId(1));
+    edit_layer.clear_layer();
+    edit_layer.rect(0.0, 0.0, 50.0, 50.0);
+    edit_layer.fill();
+    edit_layer.show_frame();
+
+    let created_buffers = |actions: &[RenderAction]| actions.iter()
+        .filter_map(|action| match action { RenderAction::CreateVertex2DBuffer(buffer_id, _) => Some(*buffer_id), _ => None })
+        .collect::<Vec<_>>();
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+
+        let first_frame     = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+        let second_frame    = renderer.draw(edit_layer.into_iter()).collect::<Vec<_>>().await;
+        let third_frame     = renderer.draw(vec![Draw::StartFrame, Draw::ShowFrame].into_iter()).collect::<Vec<_>>().await;
+
+        // Only the drawing that changed is uploaded: the other layers and the sprite are drawn from the buffers created for the first frame
+        let first_buffers   = created_buffers(&first_frame);
+        let second_buffers  = created_buffers(&second_frame);
+
+        assert!(first_buffers.len() == 3, "{:?}", first_buffers);
+        assert!(second_buffers.len() == 1, "{:?}", second_buffers);
+        assert!(created_buffers(&third_frame).is_empty());
+
+        // The frames still draw everything
+        let draws_in_third_frame = third_frame.iter().filter(|action| matches!(action, RenderAction::DrawIndexedTriangles(_, _, _))).count();
+        assert!(draws_in_third_frame == 3, "{:?}", third_frame);
+    })
+}
+
+#[test]
+fn free_resources_releases_everything_still_allocated() {
+    // Two layers of shapes and a textured rectangle
+    let mut drawing = vec![];
+    drawing.canvas_height(1000.0);
+    drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+    drawing.circle(0.0, 0.0, 100.0);
+    drawing.fill();
+    drawing.layer(LayerId(1));
+    drawing.create_texture(TextureId(0), 4, 4, TextureFormat::Rgba);
+    drawing.set_texture_bytes(TextureId(0), 0, 0, 4, 4, std::sync::Arc::new(vec![255; 4*4*4]));
+    drawing.rect(0.0, 0.0, 100.0, 100.0);
+    drawing.fill_texture(TextureId(0), 0.0, 0.0, 100.0, 100.0);
+    drawing.fill();
+
+    ///
+    /// Updates the list of resources that are allocated after a set of render actions
+    ///
+    fn track_resources(allocated: &mut Vec<String>, actions: &[RenderAction]) {
+        for action in actions.iter() {
+            match action {
+                RenderAction::CreateVertex2DBuffer(VertexBufferId(id), _)                   => { allocated.push(format!("v{}", id)); }
+                RenderAction::CreateIndexBuffer(IndexBufferId(id), _)                       => { allocated.push(format!("i{}", id)); }
+                RenderAction::CreateRenderTarget(RenderTargetId(id), render::TextureId(texture_id), _, _) => { allocated.push(format!("r{}", id)); allocated.push(format!("t{}", texture_id)); }
+                RenderAction::CreateTextureBgra(render::TextureId(id), _)                   |
+                RenderAction::CreateTextureBgraPremultiplied(render::TextureId(id), _)      |
+                RenderAction::CreateTextureMono(render::TextureId(id), _)                   |
+                RenderAction::Create1DTextureBgra(render::TextureId(id), _)                 |
+                RenderAction::Create1DTextureMono(render::TextureId(id), _)                 => { allocated.push(format!("t{}", id)); }
+
+                RenderAction::FreeVertexBuffer(VertexBufferId(id))                          => { allocated.retain(|resource| resource != &format!("v{}", id)); }
+                RenderAction::FreeIndexBuffer(IndexBufferId(id))                            => { allocated.retain(|resource| resource != &format!("i{}", id)); }
+                RenderAction::FreeRenderTarget(RenderTargetId(id))                          => { allocated.retain(|resource| resource != &format!("r{}", id)); }
+                RenderAction::FreeTexture(render::TextureId(id))                            => { allocated.retain(|resource| resource != &format!("t{}", id)); }
+
+                _ => { }
+            }
+        }
+    }
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+
+        let frame           = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+        let mut allocated   = vec![];
+        track_resources(&mut allocated, &frame);
+
+        // The buffers for the shapes and the texture stay allocated after the frame
+        assert!(allocated.iter().any(|resource| resource.starts_with('v')), "{:?}", allocated);
+        assert!(allocated.iter().any(|resource| resource.starts_with('t')), "{:?}", allocated);
+
+        // Freeing the resources should release all of them
+        let free_actions    = renderer.free_resources();
+        track_resources(&mut allocated, &free_actions);
+
+        assert!(allocated.is_empty(), "{:?}", allocated);
+    })
+}
+
+#[test]
+fn resource_events_track_buffers_and_textures() {
+    // Draw a simple circle
+    let mut draw_circle = vec![];
+    draw_circle.circle(0.0,0.0, 100.0);
+    draw_circle.fill();
+
+    executor::block_on(async {
+        // Create the renderer and subscribe to its resource events
+        let mut renderer        = CanvasRenderer::new();
+        let resource_events     = renderer.subscribe_resource_events();
+
+        renderer.set_viewport(0.0..256.0, 0.0..128.0, 256.0, 128.0, 1.0);
+        let drawing             = renderer.draw(draw_circle.into_iter()).collect::<Vec<_>>().await;
+
+        // Dropping the renderer finishes the stream of events
+        drop(renderer);
+        let events              = resource_events.collect::<Vec<_>>().await;
+        println!("{:?}", events);
+
+        // Every vertex buffer in the drawing should generate an event with the same number of vertices
+        for action in drawing.iter() {
+            if let RenderAction::CreateVertex2DBuffer(buffer_id, vertices) = action {
+                assert!(events.contains(&RenderResourceEvent::VertexBufferCreated { buffer_id: *buffer_id, vertex_count: vertices.len() }));
+            }
+        }
+
+        // The main render target (texture 0) is created for the frame and released once it's finished
+        assert!(events.contains(&RenderResourceEvent::TextureCreated { texture_id: render::TextureId(0), width: 256, height: 128, bytes: 256*128*4 }));
+        assert!(events.contains(&RenderResourceEvent::TextureReleased { texture_id: render::TextureId(0), bytes: 256*128*4 }));
+    })
+}
+
+#[test]
+fn unread_resource_events_do_not_block_drawing() {
+    let mut draw_circle = vec![];
+    draw_circle.circle(0.0,0.0, 100.0);
+    draw_circle.fill();
+
+    executor::block_on(async {
+        let mut renderer        = CanvasRenderer::new();
+        let _resource_events    = renderer.subscribe_resource_events();
+
+        // Every frame creates and releases the render targets, so this generates more events than the subscriber can buffer
+        renderer.set_viewport(0.0..256.0, 0.0..128.0, 256.0, 128.0, 1.0);
+        for _ in 0..500 {
+            renderer.draw(draw_circle.clone().into_iter()).collect::<Vec<_>>().await;
+        }
+    })
+}
+
+#[test]
+fn resource_tracking_stops_when_events_are_dropped() {
+    let mut draw_circle = vec![];
+    draw_circle.circle(0.0,0.0, 100.0);
+    draw_circle.fill();
+
+    let mut clear = vec![];
+    clear.clear_canvas(Color::Rgba(0.0, 0.0, 0.0, 0.0));
+
+    executor::block_on(async {
+        let mut renderer        = CanvasRenderer::new();
+        renderer.set_viewport(0.0..256.0, 0.0..128.0, 256.0, 128.0, 1.0);
+
+        // Draw the circle while subscribed, then stop listening
+        let resource_events     = renderer.subscribe_resource_events();
+        renderer.draw(draw_circle.into_iter()).collect::<Vec<_>>().await;
+        drop(resource_events);
+
+        // The next frame forgets the tracked buffers, so when they're released after subscribing again, their sizes are unknown
+        renderer.draw(vec![].into_iter()).collect::<Vec<_>>().await;
+
+        let resource_events     = renderer.subscribe_resource_events();
+        renderer.draw(clear.into_iter()).collect::<Vec<_>>().await;
+        drop(renderer);
+
+        let events              = resource_events.collect::<Vec<_>>().await;
+        let released_buffers    = events.iter().filter(|event| matches!(event, RenderResourceEvent::VertexBufferReleased { .. })).collect::<Vec<_>>();
+
+        assert!(!released_buffers.is_empty(), "{:?}", events);
+        assert!(released_buffers.iter().all(|event| matches!(event, RenderResourceEvent::VertexBufferReleased { vertex_count: 0, .. })), "{:?}", events);
+    })
+}
+
+#[test]
+fn clearing_canvas_trims_buffer_pools_after_freeing_buffers() {
+    let mut drawing = vec![];
+    drawing.circle(0.0, 0.0, 100.0);
+    drawing.fill();
+
+    let mut clear = vec![];
+    clear.clear_canvas(Color::Rgba(0.0, 0.0, 0.0, 0.0));
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+
+        let _first_frame    = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+        let clear_frame     = renderer.draw(clear.into_iter()).collect::<Vec<_>>().await;
+
+        // The buffers for the circle are freed, then the renderer is told it can release the memory it was keeping for re-use
+        let last_free   = clear_frame.iter().rposition(|action| matches!(action, RenderAction::FreeVertexBuffer(_)));
+        let trim        = clear_frame.iter().position(|action| matches!(action, RenderAction::TrimBufferPools));
+
+        assert!(last_free.is_some());
+        assert!(trim.is_some());
+        assert!(trim > last_free, "{:?}", clear_frame);
+    })
+}
+
+#[test]
+fn fill_and_stroke_path_with_nan_coordinates() {
+    // A path with NaN coordinates can't be tessellated, followed by a path that can
+    let mut drawing = vec![];
+    drawing.new_path();
+    drawing.move_to(0.0, 0.0);
+    drawing.line_to(f32::NAN, 100.0);
+    drawing.line_to(100.0, f32::NAN);
+    drawing.close_path();
+    drawing.fill();
+    drawing.line_width(2.0);
+    drawing.stroke();
+
+    drawing.new_path();
+    drawing.circle(0.0, 0.0, 100.0);
+    drawing.fill();
+
+    executor::block_on(async {
+        let mut renderer    = CanvasRenderer::new();
+        let actions         = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+
+        // The renderer doesn't panic, and the circle is still drawn
+        let num_indices = actions.iter()
+            .filter_map(|action| match action { RenderAction::DrawIndexedTriangles(_, _, num_indices) => Some(*num_indices), _ => None })
+            .sum::<usize>();
+
+        assert!(num_indices > 0, "{:?}", actions);
+    })
+}
+
+#[test]
+fn texture_mipmaps_are_reenabled_when_texture_is_freed_or_canvas_is_cleared() {
+    fn draw_texture(drawing: &mut Vec<Draw>) {
+        drawing.create_texture(TextureId(0), 4, 4, TextureFormat::Rgba);
+        drawing.set_texture_bytes(TextureId(0), 0, 0, 4, 4, std::sync::Arc::new(vec![255; 4*4*4]));
+        drawing.new_path();
+        drawing.rect(0.0, 0.0, 100.0, 100.0);
+        drawing.fill_texture(TextureId(0), 0.0, 0.0, 100.0, 100.0);
+        drawing.fill();
+    }
+
+    let creates_mipmaps = |actions: &[RenderAction]| actions.iter().any(|action| matches!(action, RenderAction::CreateMipMaps(_)));
+
+    executor::block_on(async {
+        let mut renderer = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+
+        // Texture without mipmaps
+        let mut drawing = vec![];
+        drawing.canvas_height(1000.0);
+        drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+        drawing.set_texture_mipmaps(TextureId(0), false);
+        draw_texture(&mut drawing);
+        let actions = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+        assert!(!creates_mipmaps(&actions));
+
+        // Freeing the texture forgets the setting
+        let mut drawing = vec![];
+        drawing.free_texture(TextureId(0));
+        draw_texture(&mut drawing);
+        let actions = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+        assert!(creates_mipmaps(&actions));
+
+        // Clearing the canvas forgets the setting too
+        let mut drawing = vec![];
+        drawing.set_texture_mipmaps(TextureId(0), false);
+        drawing.clear_canvas(Color::Rgba(0.0, 0.0, 0.0, 0.0));
+        drawing.canvas_height(1000.0);
+        drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+        draw_texture(&mut drawing);
+        let actions = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+        assert!(creates_mipmaps(&actions));
+    })
+}
+
+#[test]
+fn texture_wrap_mode_is_reset_when_texture_is_freed_or_canvas_is_cleared() {
+    fn draw_texture(drawing: &mut Vec<Draw>) {
+        drawing.create_texture(TextureId(0), 4, 4, TextureFormat::Rgba);
+        drawing.set_texture_bytes(TextureId(0), 0, 0, 4, 4, std::sync::Arc::new(vec![255; 4*4*4]));
+        drawing.new_path();
+        drawing.rect(0.0, 0.0, 100.0, 100.0);
+        drawing.fill_texture(TextureId(0), 0.0, 0.0, 100.0, 100.0);
+        drawing.fill();
+    }
+
+    let wrap_modes = |actions: &[RenderAction]| actions.iter()
+        .filter_map(|action| match action { RenderAction::UseShader(ShaderType::Texture { wrap_mode, .. }) => Some(*wrap_mode), _ => None })
+        .collect::<Vec<_>>();
+
+    executor::block_on(async {
+        let mut renderer = CanvasRenderer::new();
+        renderer.set_viewport(0.0..1000.0, 0.0..1000.0, 1000.0, 1000.0, 1.0);
+
+        // Clamped texture
+        let mut drawing = vec![];
+        drawing.canvas_height(1000.0);
+        drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+        drawing.set_texture_wrap_mode(TextureId(0), flo_canvas::TextureWrapMode::Clamp);
+        draw_texture(&mut drawing);
+        let actions = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+        assert!(!wrap_modes(&actions).is_empty() && wrap_modes(&actions).iter().all(|wrap_mode| *wrap_mode == render::TextureWrapMode::Clamp), "{:?}", wrap_modes(&actions));
+
+        // Freeing the texture forgets the setting
+        let mut drawing = vec![];
+        drawing.free_texture(TextureId(0));
+        draw_texture(&mut drawing);
+        let actions = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+        assert!(wrap_modes(&actions).contains(&render::TextureWrapMode::Repeat), "{:?}", wrap_modes(&actions));
+
+        // Clearing the canvas forgets the setting too
+        let mut drawing = vec![];
+        drawing.set_texture_wrap_mode(TextureId(0), flo_canvas::TextureWrapMode::Clamp);
+        drawing.clear_canvas(Color::Rgba(0.0, 0.0, 0.0, 0.0));
+        drawing.canvas_height(1000.0);
+        drawing.center_region(0.0, 0.0, 1000.0, 1000.0);
+        draw_texture(&mut drawing);
+        let actions = renderer.draw(drawing.into_iter()).collect::<Vec<_>>().await;
+        assert!(!wrap_modes(&actions).is_empty() && wrap_modes(&actions).iter().all(|wrap_mode| *wrap_mode == render::TextureWrapMode::Repeat), "{:?}", wrap_modes(&actions));
+    })
+}
+
+#[test]
+fn downscaled_checkerboard_does_not_shimmer() {
+    // A 64x64 checkerboard of single pixels
+    let checkerboard = (0..64).flat_map(|y| (0..64).map(move |x| (x, y)))
+        .flat_map(|(x, y)| if (x + y) % 2 == 0 { vec![255, 255, 255, 255] } else { vec![0, 0, 0, 255] })
+        .collect::<Vec<u8>>();
+    let checkerboard = std::sync::Arc::new(checkerboard);
+
+    // Draws the checkerboard scaled down to 8x8 pixels, with a sub-pixel offset
+    let draw_checkerboard = |offset: f32| {
+        let mut drawing = vec![];
+        drawing.clear_canvas(Color::Rgba(0.0, 0.0, 0.0, 1.0));
+        drawing.canvas_height(64.0);
+        drawing.center_region(0.0, 0.0, 64.0, 64.0);
+        drawing.create_texture(TextureId(0), 64, 64, TextureFormat::Rgba);
+        drawing.set_texture_bytes(TextureId(0), 0, 0, 64, 64, std::sync::Arc::clone(&checkerboard));
+        drawing.new_path();
+        drawing.rect(28.0 + offset, 28.0 + offset, 36.0 + offset, 36.0 + offset);
+        drawing.fill_texture(TextureId(0), 28.0 + offset, 28.0 + offset, 36.0 + offset, 36.0 + offset);
+        drawing.fill();
+
+        drawing
+    };
+
+    executor::block_on(async {
+        let mut context = match initialize_offscreen_rendering() {
+            Ok(context) => context,
+            Err(_)      => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        let image           = render_canvas_offscreen(&mut context, 64, 64, 1.0, stream::iter(draw_checkerboard(0.0))).await;
+        let shifted_image   = render_canvas_offscreen(&mut context, 64, 64, 1.0, stream::iter(draw_checkerboard(0.5))).await;
+        let pixel           = |image: &[u8], x: usize, y: usize| image[(y*64 + x)*4] as i32;
+
+        // The mipmaps average the checkerboard to grey, so each pixel is close to mid-grey and stays that way when the texture moves
+        for y in 29..35 {
+            for x in 29..35 {
+                assert!((pixel(&image, x, y) - 128).abs() < 40, "Pixel at {}, {} is {}", x, y, pixel(&image, x, y));
+                assert!((pixel(&image, x, y) - pixel(&shifted_image, x, y)).abs() < 32, "Pixel at {}, {} changes from {} to {}", x, y, pixel(&image, x, y), pixel(&shifted_image, x, y));
+            }
+        }
+    })
+}
+
+#[test]
+fn stitched_tiles_match_whole_image() {
+    // Some shapes that cross the tile boundaries
+    let mut drawing = vec![];
+    drawing.clear_canvas(Color::Rgba(1.0, 1.0, 1.0, 1.0));
+    drawing.canvas_height(80.0);
+    drawing.center_region(0.0, 0.0, 100.0, 80.0);
+    drawing.new_path();
+    drawing.circle(40.0, 40.0, 30.0);
+    drawing.fill_color(Color::Rgba(0.8, 0.2, 0.1, 1.0));
+    drawing.fill();
+    drawing.new_path();
+    drawing.rect(50.0, 10.0, 95.0, 60.0);
+    drawing.fill_color(Color::Rgba(0.1, 0.3, 0.9, 0.5));
+    drawing.fill();
+
+    executor::block_on(async {
+        let mut context = match initialize_offscreen_rendering() {
+            Ok(context) => context,
+            Err(_)      => { println!("Test not run: graphics device unavailable"); return; }
+        };
+
+        let image = render_canvas_offscreen(&mut context, 100, 80, 1.0, stream::iter(drawing.clone())).await;
+
+        // Tile sizes that don't divide the image size, so the tiles along the right and bottom edges are smaller
+        let mut stitched    = vec![0u8; 100*80*4];
+        let mut num_tiles   = 0;
+        render_canvas_offscreen_tiles(&mut context, 100, 80, 32, 24, 1.0, &drawing, |tile, pixels| {
+            assert!(pixels.len() == tile.width*tile.height*4);
+            num_tiles += 1;
+
+            for (row, row_pixels) in pixels.chunks_exact(tile.width*4).enumerate() {
+                let start = ((tile.y + row)*100 + tile.x)*4;
+                stitched[start..(start + tile.width*4)].copy_from_slice(row_pixels);
+            }
+        }).await;
+
+        assert!(num_tiles == 4*4);
+
+        // Allow for rounding differences in the transform for each tile
+        for (idx, (stitched_component, image_component)) in stitched.iter().zip(image.iter()).enumerate() {
+            let pixel = idx/4;
+            assert!((*stitched_component as i32 - *image_component as i32).abs() <= 2, "Pixel at {}, {} differs: {:?} vs {:?}", pixel%100, pixel/100, &stitched[pixel*4..pixel*4+4], &image[pixel*4..pixel*4+4]);
+        }
+    })
+}